@@ -91,13 +91,16 @@ contract Summa is Ownable {
             );
         }
 
-        uint256[] memory inputs = new uint256[](balancesToProve.length + 1);
+        uint256[] memory inputs = new uint256[](balancesToProve.length + 2);
         inputs[0] = mstRoot;
 
         for (uint i = 0; i < balancesToProve.length; i++) {
             inputs[i + 1] = balancesToProve[i];
         }
 
+        // The last public input is the number of assets covered by the proof
+        inputs[balancesToProve.length + 1] = balancesToProve.length;
+
         // Verify ZK proof
         require(verifyZkProof(proof, inputs), "Invalid zk proof");
 
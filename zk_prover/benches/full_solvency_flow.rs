@@ -1,17 +1,21 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use halo2_proofs::{
     halo2curves::bn256::{Bn256, Fr as Fp},
     plonk::{keygen_pk, keygen_vk},
     poly::kzg::commitment::ParamsKZG,
 };
+use num_bigint::BigInt;
 use snark_verifier_sdk::CircuitExt;
 use summa_solvency::{
     circuits::merkle_sum_tree::MstInclusionCircuit,
     circuits::{
         solvency::SolvencyCircuit,
-        utils::{full_prover, full_verifier, generate_setup_params},
+        utils::{full_prover, full_verifier, generate_setup_params, min_k},
+    },
+    merkle_sum_tree::{
+        build_merkle_sum_tree_streaming, create_middle_node, create_middle_nodes, Entry,
+        MerkleSumTree, Node, MOD_BITS,
     },
-    merkle_sum_tree::{MerkleSumTree, MOD_BITS},
 };
 
 const SAMPLE_SIZE: usize = 10;
@@ -41,6 +45,96 @@ fn build_mstree(_c: &mut Criterion) {
     });
 }
 
+fn build_mstree_eager_vs_streaming(_c: &mut Criterion) {
+    let mut criterion = Criterion::default().sample_size(SAMPLE_SIZE);
+
+    const STREAMING_LEVELS: usize = 16;
+    let entries = || {
+        (0..1usize << STREAMING_LEVELS).map(|i| {
+            Entry::<N_ASSETS>::new(format!("user{}", i), [BigInt::from(i), BigInt::from(i)])
+                .unwrap()
+        })
+    };
+
+    let bench_name = format!(
+        "eager build of a merkle sum tree for 2 power of {} entries",
+        STREAMING_LEVELS
+    );
+    criterion.bench_function(&bench_name, |b| {
+        b.iter(|| MerkleSumTree::<N_ASSETS>::from_entries(entries().collect()).unwrap())
+    });
+
+    let bench_name = format!(
+        "streaming build of a merkle sum tree for 2 power of {} entries",
+        STREAMING_LEVELS
+    );
+    criterion.bench_function(&bench_name, |b| {
+        b.iter(|| {
+            let mut nodes: Vec<Vec<Node<N_ASSETS>>> = vec![];
+            build_merkle_sum_tree_streaming(entries(), &mut nodes).unwrap()
+        })
+    });
+}
+
+fn build_mstree_sequential_vs_parallel(_c: &mut Criterion) {
+    let mut criterion = Criterion::default().sample_size(SAMPLE_SIZE);
+
+    let entries = (0..1usize << LEVELS)
+        .map(|i| {
+            Entry::<N_ASSETS>::new(format!("user{}", i), [BigInt::from(i), BigInt::from(i)])
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let bench_name = format!(
+        "sequential build of a merkle sum tree for 2 power of {} entries",
+        LEVELS
+    );
+    criterion.bench_function(&bench_name, |b| {
+        b.iter(|| MerkleSumTree::<N_ASSETS>::from_entries(entries.clone()).unwrap())
+    });
+
+    // run with `cargo bench --features rayon`
+    #[cfg(feature = "rayon")]
+    {
+        let bench_name = format!(
+            "parallel build of a merkle sum tree for 2 power of {} entries",
+            LEVELS
+        );
+        criterion.bench_function(&bench_name, |b| {
+            b.iter(|| MerkleSumTree::<N_ASSETS>::from_entries_parallel(entries.clone()).unwrap())
+        });
+    }
+}
+
+fn middle_level_per_node_vs_batched(_c: &mut Criterion) {
+    let mut criterion = Criterion::default().sample_size(SAMPLE_SIZE);
+
+    // a level as wide as the leaves of the tree
+    let level = (0..(1u64 << LEVELS))
+        .map(|i| Node {
+            hash: Fp::from(i),
+            balances: [Fp::from(i); N_ASSETS],
+        })
+        .collect::<Vec<_>>();
+    let padding = level[0].clone();
+
+    let bench_name = format!("per-node hashing of a level of 2 power of {} nodes", LEVELS);
+    criterion.bench_function(&bench_name, |b| {
+        b.iter(|| {
+            level
+                .chunks(2)
+                .map(|pair| create_middle_node(&pair[0], &pair[1]))
+                .collect::<Vec<_>>()
+        })
+    });
+
+    let bench_name = format!("batched hashing of a level of 2 power of {} nodes", LEVELS);
+    criterion.bench_function(&bench_name, |b| {
+        b.iter(|| create_middle_nodes(&level, &padding))
+    });
+}
+
 fn verification_key_gen_mst_inclusion_circuit(_c: &mut Criterion) {
     let mut criterion = Criterion::default().sample_size(SAMPLE_SIZE);
 
@@ -144,6 +238,64 @@ fn verify_zk_proof_mst_inclusion_circuit(_c: &mut Criterion) {
     });
 }
 
+// Generates and verifies a zk proof of the mst inclusion circuit for a tree of 2 power of LEVELS entries, with the smallest k that fits the circuit
+fn bench_mst_inclusion_circuit_at_depth<const LEVELS: usize>(criterion: &mut Criterion) {
+    let entries = (0..1usize << LEVELS)
+        .map(|i| {
+            Entry::<N_ASSETS>::new(format!("user{}", i), [BigInt::from(i), BigInt::from(i)])
+                .unwrap()
+        })
+        .collect();
+    let merkle_sum_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries).unwrap();
+
+    let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+    let k = min_k(&circuit).expect("the circuit should fit in the max k");
+    let params: ParamsKZG<Bn256> = generate_setup_params(k);
+
+    let empty_circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+    let vk = keygen_vk(&params, &empty_circuit).expect("vk generation should not fail");
+    let pk = keygen_pk(&params, vk.clone(), &empty_circuit).expect("pk generation should not fail");
+
+    let mut group = criterion.benchmark_group(format!(
+        "mst inclusion circuit with {} assets by tree depth",
+        N_ASSETS
+    ));
+
+    group.bench_with_input(
+        BenchmarkId::new("generate zk proof", LEVELS),
+        &circuit,
+        |b, circuit| {
+            b.iter(|| {
+                full_prover(&params, &pk, circuit.clone(), circuit.instances());
+            })
+        },
+    );
+
+    let proof = full_prover(&params, &pk, circuit.clone(), circuit.instances());
+
+    group.bench_with_input(
+        BenchmarkId::new("verify zk proof", LEVELS),
+        &proof,
+        |b, proof| {
+            b.iter(|| {
+                full_verifier(&params, &vk, proof.clone(), circuit.instances());
+            })
+        },
+    );
+
+    group.finish();
+}
+
+fn mst_inclusion_circuit_by_depth(_c: &mut Criterion) {
+    let mut criterion = Criterion::default().sample_size(SAMPLE_SIZE);
+
+    bench_mst_inclusion_circuit_at_depth::<4>(&mut criterion);
+    bench_mst_inclusion_circuit_at_depth::<8>(&mut criterion);
+    bench_mst_inclusion_circuit_at_depth::<12>(&mut criterion);
+    bench_mst_inclusion_circuit_at_depth::<16>(&mut criterion);
+}
+
 fn verification_key_gen_solvency_circuit(_c: &mut Criterion) {
     let mut criterion = Criterion::default().sample_size(SAMPLE_SIZE);
 
@@ -254,10 +406,14 @@ fn verify_zk_proof_solvency_circuit(_c: &mut Criterion) {
 criterion_group!(
     benches,
     build_mstree,
+    build_mstree_eager_vs_streaming,
+    build_mstree_sequential_vs_parallel,
+    middle_level_per_node_vs_batched,
     verification_key_gen_mst_inclusion_circuit,
     proving_key_gen_mst_inclusion_circuit,
     generate_zk_proof_mst_inclusion_circuit,
     verify_zk_proof_mst_inclusion_circuit,
+    mst_inclusion_circuit_by_depth,
     verification_key_gen_solvency_circuit,
     proving_key_gen_solvency_circuit,
     generate_zk_proof_solvency_circuit,
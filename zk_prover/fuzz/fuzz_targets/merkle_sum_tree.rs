@@ -0,0 +1,63 @@
+//! Builds a merkle sum tree from arbitrary entries and checks that the builder never panics and that every proof it generates is accepted by the native verifier.
+//!
+//! Run it with `cargo +nightly fuzz run merkle_sum_tree` from the `zk_prover` directory.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use num_bigint::BigInt;
+use summa_solvency::merkle_sum_tree::{Entry, MerkleSumTree};
+
+const N_ASSETS: usize = 2;
+
+/// Number of bytes of each balance, enough to exceed the modulus of the field
+const BALANCE_BYTES: usize = 33;
+
+/// Maximum number of entries of a tree, to keep each run fast
+const MAX_ENTRIES: usize = 64;
+
+/// Decodes the entries as a sequence of records, each made of a length byte, the username and N_ASSETS balances of BALANCE_BYTES bytes encoded as signed big endian integers, so that negative and out of range balances are generated as well.
+/// Returns None as soon as an entry is rejected
+fn decode_entries(mut data: &[u8]) -> Option<Vec<Entry<N_ASSETS>>> {
+    let mut entries = vec![];
+
+    while let Some((&username_len, rest)) = data.split_first() {
+        let username_len = username_len as usize % 32;
+        if rest.len() < username_len + N_ASSETS * BALANCE_BYTES || entries.len() == MAX_ENTRIES {
+            break;
+        }
+
+        let (username, rest) = rest.split_at(username_len);
+        let (balances, rest) = rest.split_at(N_ASSETS * BALANCE_BYTES);
+        data = rest;
+
+        let balances: [BigInt; N_ASSETS] = std::array::from_fn(|asset| {
+            BigInt::from_signed_bytes_be(
+                &balances[asset * BALANCE_BYTES..(asset + 1) * BALANCE_BYTES],
+            )
+        });
+
+        let username = String::from_utf8_lossy(username).into_owned();
+        entries.push(Entry::new(username, balances).ok()?);
+    }
+
+    Some(entries)
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some(entries) = decode_entries(data) else {
+        return;
+    };
+
+    // the builder must return an error, for example for an empty tree, instead of panicking
+    let Ok(tree) = MerkleSumTree::<N_ASSETS>::from_entries(entries) else {
+        return;
+    };
+
+    for index in 0..tree.entries().len() {
+        let proof = tree.generate_proof(index).unwrap();
+        assert!(tree.verify_proof(&proof));
+        assert!(proof.verify(tree.root()));
+    }
+
+    assert!(tree.generate_proof(tree.entries().len()).is_err());
+});
@@ -0,0 +1,218 @@
+use crate::chips::overflow::overflow_check::{OverflowCheckConfig, OverflowChip};
+use crate::merkle_sum_tree::LIMB_BITS;
+use gadgets::util::pow_of_two;
+use halo2_proofs::circuit::{AssignedCell, Layouter, Value};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector};
+use halo2_proofs::poly::Rotation;
+
+/// Number of bits of each chunk in which the limbs are decomposed to be range checked
+const LIMB_CHUNK_BITS: u8 = 8;
+
+/// Configuration for the Limb Sum Chip
+///
+/// # Fields
+///
+/// * `advice`: advice columns to fit the witness values.
+/// * `sum_selector`: Selector to toggle the limb sum constraints.
+/// * `range_check_config`: Configuration of the range check of the limbs of the sum to [LIMB_BITS] bits.
+#[derive(Debug, Clone)]
+pub struct LimbSumConfig {
+    pub advice: [Column<Advice>; 3],
+    pub sum_selector: Selector,
+    pub range_check_config: OverflowCheckConfig<LIMB_CHUNK_BITS, LIMB_BITS>,
+}
+
+/// Chip that sums two unsigned balances of up to 256 bits, each represented as a low and a high limb of [LIMB_BITS] bits, so that balances that don't fit in a single field element with room for summation can be added without overflow.
+///
+/// The sum of two balances is assigned following this layout on 3 advice columns:
+///
+/// | a          | b           | c        |
+/// | ---------- | ----------- | -------- |
+/// | `left_lo`  | `right_lo`  | `sum_lo` |
+/// | `left_hi`  | `right_hi`  | `sum_hi` |
+/// | `carry`    | -           | -        |
+///
+/// It contains the following constraints (if `sum_selector` is toggled at the first row):
+///
+/// * `s * carry * (1 - carry) = 0`. It enforces the carry to be either 0 or 1.
+/// * `s * (left_lo + right_lo - sum_lo - carry * 2^128) = 0`. It propagates the carry out of the low limbs.
+/// * `s * (left_hi + right_hi + carry - sum_hi) = 0`. It adds the carry to the high limbs.
+///
+/// Both limbs of the sum are range checked to [LIMB_BITS] bits, so that the low limb and the carry are uniquely determined and a total that doesn't fit in 256 bits is rejected.
+/// The limbs of the operands must be range checked as well, for example by being the sum of a previous addition.
+///
+/// The chip sums the balances of the nodes of a [crate::merkle_sum_tree::LimbMerkleSumTree], see [crate::chips::merkle_sum_tree::MerkleSumTreeChip::assign_nodes_limbs_per_asset].
+#[derive(Debug, Clone)]
+pub struct LimbSumChip {
+    config: LimbSumConfig,
+}
+
+impl LimbSumChip {
+    pub fn construct(config: LimbSumConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advice: [Column<Advice>; 3],
+        range: Column<Fixed>,
+    ) -> LimbSumConfig {
+        for column in advice {
+            meta.enable_equality(column);
+        }
+
+        let sum_selector = meta.selector();
+
+        meta.create_gate("limb sum constraint", |meta| {
+            let s = meta.query_selector(sum_selector);
+
+            let left_lo = meta.query_advice(advice[0], Rotation::cur());
+            let right_lo = meta.query_advice(advice[1], Rotation::cur());
+            let sum_lo = meta.query_advice(advice[2], Rotation::cur());
+            let left_hi = meta.query_advice(advice[0], Rotation::next());
+            let right_hi = meta.query_advice(advice[1], Rotation::next());
+            let sum_hi = meta.query_advice(advice[2], Rotation::next());
+            let carry = meta.query_advice(advice[0], Rotation(2));
+
+            let limb_base = Expression::Constant(pow_of_two::<Fp>(LIMB_BITS));
+
+            vec![
+                s.clone() * carry.clone() * (Expression::Constant(Fp::one()) - carry.clone()),
+                s.clone() * (left_lo + right_lo - sum_lo - carry.clone() * limb_base),
+                s * (left_hi + right_hi + carry - sum_hi),
+            ]
+        });
+
+        let toggle_decomposed_value_check = meta.selector();
+        let toggle_lookup_check = meta.complex_selector();
+
+        let range_check_config = OverflowChip::<LIMB_CHUNK_BITS, LIMB_BITS>::configure(
+            meta,
+            advice[0],
+            advice[1],
+            range,
+            toggle_decomposed_value_check,
+            toggle_lookup_check,
+        );
+
+        LimbSumConfig {
+            advice,
+            sum_selector,
+            range_check_config,
+        }
+    }
+
+    /// Loads the lookup table of the range check of the limbs. It must be called once per circuit
+    pub fn load(&self, layouter: &mut impl Layouter<Fp>) -> Result<(), Error> {
+        OverflowChip::construct(self.config.range_check_config.clone()).load(layouter)
+    }
+
+    /// Assigns the limbs of a balance following this layout on a single column and returns the assigned cells:
+    ///
+    /// | a    |
+    /// | ---- |
+    /// | `lo` |
+    /// | `hi` |
+    ///
+    /// Both limbs are range checked to [LIMB_BITS] bits.
+    pub fn assign_limbs(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        limbs: [Fp; 2],
+    ) -> Result<[AssignedCell<Fp, Fp>; 2], Error> {
+        let limbs = layouter.assign_region(
+            || "assign balance limbs",
+            |mut region| {
+                let lo = region.assign_advice(
+                    || "low limb",
+                    self.config.advice[0],
+                    0,
+                    || Value::known(limbs[0]),
+                )?;
+                let hi = region.assign_advice(
+                    || "high limb",
+                    self.config.advice[0],
+                    1,
+                    || Value::known(limbs[1]),
+                )?;
+
+                Ok([lo, hi])
+            },
+        )?;
+
+        self.range_check_limbs(layouter.namespace(|| "range check limbs"), &limbs)?;
+
+        Ok(limbs)
+    }
+
+    /// Sums two balances given as their low and high limbs and returns the limbs of the sum.
+    /// The limbs of the sum are range checked to [LIMB_BITS] bits, so an overflow of 256 bits makes the circuit unsatisfiable.
+    pub fn assign_limb_sum(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        left: &[AssignedCell<Fp, Fp>; 2],
+        right: &[AssignedCell<Fp, Fp>; 2],
+    ) -> Result<[AssignedCell<Fp, Fp>; 2], Error> {
+        let sum = layouter.assign_region(
+            || "assign limb sum",
+            |mut region| {
+                self.config.sum_selector.enable(&mut region, 0)?;
+
+                for (row, (left, right)) in left.iter().zip(right.iter()).enumerate() {
+                    left.copy_advice(|| "left limb", &mut region, self.config.advice[0], row)?;
+                    right.copy_advice(|| "right limb", &mut region, self.config.advice[1], row)?;
+                }
+
+                let limb_base = pow_of_two::<Fp>(LIMB_BITS);
+
+                // the low limbs are less than 2^128, so their sum is less than 2^129 and the carry is its bit of index 128
+                let low_sum = left[0].value().copied() + right[0].value().copied();
+                let carry = low_sum.map(|low_sum| {
+                    if low_sum.to_bytes()[LIMB_BITS / 8] & 1 == 1 {
+                        Fp::one()
+                    } else {
+                        Fp::zero()
+                    }
+                });
+
+                let sum_lo = region.assign_advice(
+                    || "low limb of the sum",
+                    self.config.advice[2],
+                    0,
+                    || low_sum - carry * Value::known(limb_base),
+                )?;
+
+                let sum_hi = region.assign_advice(
+                    || "high limb of the sum",
+                    self.config.advice[2],
+                    1,
+                    || left[1].value().copied() + right[1].value().copied() + carry,
+                )?;
+
+                region.assign_advice(|| "carry", self.config.advice[0], 2, || carry)?;
+
+                Ok([sum_lo, sum_hi])
+            },
+        )?;
+
+        self.range_check_limbs(layouter.namespace(|| "range check sum limbs"), &sum)?;
+
+        Ok(sum)
+    }
+
+    /// Range checks both `limbs` to [LIMB_BITS] bits, for example the limbs of a balance that is not the output of [Self::assign_limbs] or [Self::assign_limb_sum]
+    pub fn range_check_limbs(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        limbs: &[AssignedCell<Fp, Fp>; 2],
+    ) -> Result<(), Error> {
+        let overflow_chip = OverflowChip::construct(self.config.range_check_config.clone());
+
+        for limb in limbs {
+            overflow_chip.assign(layouter.namespace(|| "range check limb"), limb)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,2 @@
+pub mod limb_sum;
+mod tests;
@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod test {
+    use crate::chips::limb_sum::limb_sum::{LimbSumChip, LimbSumConfig};
+    use crate::merkle_sum_tree::{big_uint_to_limbs, limbs_to_big_uint};
+    use gadgets::util::pow_of_two;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance},
+    };
+    use num_bigint::BigUint;
+
+    #[derive(Clone)]
+    struct LimbSumTestConfig {
+        limb_sum_config: LimbSumConfig,
+        instance: Column<Instance>,
+    }
+
+    /// Sums all the balances, given as their limbs, and exposes the limbs of the total as public inputs
+    #[derive(Default)]
+    struct LimbSumTestCircuit {
+        balances: Vec<[Fp; 2]>,
+    }
+
+    impl Circuit<Fp> for LimbSumTestCircuit {
+        type Config = LimbSumTestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                balances: vec![[Fp::zero(); 2]; self.balances.len()],
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advice: [Column<Advice>; 3] = std::array::from_fn(|_| meta.advice_column());
+            let range = meta.fixed_column();
+
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+
+            LimbSumTestConfig {
+                limb_sum_config: LimbSumChip::configure(meta, advice, range),
+                instance,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let chip = LimbSumChip::construct(config.limb_sum_config);
+            chip.load(&mut layouter)?;
+
+            let mut total =
+                chip.assign_limbs(layouter.namespace(|| "balance 0"), self.balances[0])?;
+
+            for (i, balance) in self.balances.iter().enumerate().skip(1) {
+                let balance =
+                    chip.assign_limbs(layouter.namespace(|| format!("balance {}", i)), *balance)?;
+                total = chip.assign_limb_sum(
+                    layouter.namespace(|| format!("sum {}", i)),
+                    &total,
+                    &balance,
+                )?;
+            }
+
+            layouter.constrain_instance(total[0].cell(), config.instance, 0)?;
+            layouter.constrain_instance(total[1].cell(), config.instance, 1)
+        }
+    }
+
+    fn limbs(value: &BigUint) -> [Fp; 2] {
+        big_uint_to_limbs(value).unwrap()
+    }
+
+    #[test]
+    fn test_limb_conversion() {
+        let value = (BigUint::from(3u8) << 160) + BigUint::from(7u8);
+        let [lo, hi] = limbs(&value);
+        assert_eq!(lo, Fp::from(7));
+        assert_eq!(hi, Fp::from(3u64 << 32));
+        assert_eq!(limbs_to_big_uint(&[lo, hi]), value);
+
+        let max = (BigUint::from(1u8) << 256) - 1u8;
+        assert_eq!(limbs_to_big_uint(&limbs(&max)), max);
+
+        assert!(big_uint_to_limbs(&(BigUint::from(1u8) << 256)).is_err());
+    }
+
+    #[test]
+    fn test_limb_sum_near_2_128() {
+        let k = 9;
+
+        let near_2_128 = (BigUint::from(1u8) << 128) - 1u8;
+        let balances = vec![
+            near_2_128.clone(),
+            near_2_128,
+            BigUint::from(5u8),
+            (BigUint::from(1u8) << 128) + 1u8,
+        ];
+        let total = balances.iter().sum::<BigUint>();
+
+        let circuit = LimbSumTestCircuit {
+            balances: balances.iter().map(limbs).collect(),
+        };
+
+        // the low limbs overflow 2^128 and carry into the high limbs
+        let [total_lo, total_hi] = limbs(&total);
+        assert_eq!(total_hi, Fp::from(3));
+
+        let valid_prover = MockProver::run(k, &circuit, vec![vec![total_lo, total_hi]]).unwrap();
+        valid_prover.assert_satisfied();
+
+        // the sum without the carry is rejected
+        let invalid_prover =
+            MockProver::run(k, &circuit, vec![vec![total_lo, total_hi - Fp::one()]]).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_limb_sum_overflow_of_256_bits() {
+        let k = 9;
+
+        // the high limb of the total is 2^128, which doesn't fit in 128 bits
+        let max = (BigUint::from(1u8) << 256) - 1u8;
+        let circuit = LimbSumTestCircuit {
+            balances: vec![limbs(&max), limbs(&BigUint::from(1u8))],
+        };
+
+        let invalid_prover =
+            MockProver::run(k, &circuit, vec![vec![Fp::zero(), pow_of_two(128)]]).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+}
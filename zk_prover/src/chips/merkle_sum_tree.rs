@@ -1,3 +1,4 @@
+use crate::chips::limb_sum::limb_sum::LimbSumChip;
 use halo2_proofs::circuit::{AssignedCell, Layouter, Value};
 use halo2_proofs::halo2curves::bn256::Fr as Fp;
 use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector};
@@ -22,12 +23,34 @@ pub struct MerkleSumTreeConfig {
 /// * `s * swap_bit * (1 - swap_bit) = 0` (if `bool_and_swap_selector` is toggled). It basically enforces that swap_bit is either a 0 or 1.
 /// * `s * swap_bit * ((elelment_l_next - elelment_l_cur) - (elelment_r_cur - elelment_r_next))`. Enforces that if the swap_bit is equal to 1, the values will be swapped on the next row (if `bool_and_swap_selector` is toggled).
 /// * `s * (left_balance + right_balance - computed_sum)`. It constraints the computed sum to be equal to the sum of the left and right balances (if `sum_selector` is toggled).
+///
+/// The balances are assigned one asset at a time, so the same gates prove a separate `computed_sum` for each of the `N_ASSETS` assets of a node. A single-asset tree is the special case `N_ASSETS = 1`.
 #[derive(Debug, Clone)]
 pub struct MerkleSumTreeChip<const N_ASSETS: usize> {
     config: MerkleSumTreeConfig,
 }
 
 impl<const N_ASSETS: usize> MerkleSumTreeChip<N_ASSETS> {
+    /// Number of rows consumed by [Self::assign_entry_hash_and_balances], namely the entry hash followed by the N_ASSETS balances
+    pub const ENTRY_ROWS: usize = 1 + N_ASSETS;
+
+    /// Number of rows consumed by [Self::assing_swap_bit]
+    pub const SWAP_BIT_ROWS: usize = 1;
+
+    /// Number of rows consumed by [Self::assign_nodes_hashes_per_level]
+    pub const NODES_HASHES_ROWS: usize = 2;
+
+    /// Number of rows consumed by [Self::assign_nodes_balance_per_asset], once per asset
+    pub const NODES_BALANCE_ROWS: usize = 2;
+
+    /// Number of rows consumed on the advice columns of the chip by [Self::assign_nodes_limbs_per_asset], once per asset. The sum of the limbs is assigned by [LimbSumChip]
+    pub const NODES_LIMBS_ROWS: usize = 4;
+
+    /// Number of rows consumed on the advice columns of the chip by a level of the merkle path, namely the swap bit, the nodes hashes and the nodes balances of each asset.
+    /// The poseidon hash of the level and the range checks of the balances are assigned by other chips, see [crate::chips::poseidon::hash::PoseidonChip::rows_used]
+    pub const LEVEL_ROWS: usize =
+        Self::SWAP_BIT_ROWS + Self::NODES_HASHES_ROWS + N_ASSETS * Self::NODES_BALANCE_ROWS;
+
     pub fn construct(config: MerkleSumTreeConfig) -> Self {
         Self { config }
     }
@@ -127,8 +150,17 @@ impl<const N_ASSETS: usize> MerkleSumTreeChip<N_ASSETS> {
         Ok((entry_hash_cell, entry_balance_cells))
     }
 
-    // Assigns the swap bit to a cell and returns it
+    // Assigns the swap bit to a cell and returns it. The swap bit is a bool so that a non binary value can't be passed by mistake, the bool constraint still enforces it in-circuit
     pub fn assing_swap_bit(
+        &self,
+        layouter: impl Layouter<Fp>,
+        swap_bit: bool,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        self.assign_swap_bit_value(layouter, Fp::from(u64::from(swap_bit)))
+    }
+
+    // Assigns any value as swap bit, only meant to test that the bool constraint rejects a non binary swap bit
+    pub(crate) fn assign_swap_bit_value(
         &self,
         mut layouter: impl Layouter<Fp>,
         swap_bit: Fp,
@@ -322,4 +354,112 @@ impl<const N_ASSETS: usize> MerkleSumTreeChip<N_ASSETS> {
             },
         )
     }
+
+    /// Assign the nodes balance for a single asset, split into its low and high limbs, in a region following this layout on 3 advice columns:
+    ///
+    /// | a            | b            | c          |
+    /// | ------------ | ------------ | ---------- |
+    /// | `current_lo` | `element_lo` | `swap_bit` |
+    /// | `left_lo`    | `right_lo`   | -          |
+    /// | `current_hi` | `element_hi` | `swap_bit` |
+    /// | `left_hi`    | `right_hi`   | -          |
+    ///
+    /// At rows 0 and 2 bool_and_swap_selector is enabled, so that both limbs are swapped according to the same swap bit.
+    /// The limbs of the left and right balances are then summed with the carry propagated from the low limbs to the high limbs by `limb_sum_chip`, see [LimbSumChip::assign_limb_sum], instead of the sum constraint of this chip.
+    /// Returns the limbs of the left balance, of the right balance and of their sum
+    pub fn assign_nodes_limbs_per_asset(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        limb_sum_chip: &LimbSumChip,
+        current_limbs: &[AssignedCell<Fp, Fp>; 2],
+        element_limbs: [Fp; 2],
+        swap_bit_assigned: AssignedCell<Fp, Fp>,
+    ) -> Result<
+        (
+            [AssignedCell<Fp, Fp>; 2],
+            [AssignedCell<Fp, Fp>; 2],
+            [AssignedCell<Fp, Fp>; 2],
+        ),
+        Error,
+    > {
+        let (left_limbs, right_limbs) = layouter.assign_region(
+            || "assign nodes limbs per asset",
+            |mut region| {
+                let mut left_limbs = vec![];
+                let mut right_limbs = vec![];
+
+                for (limb, (current_limb, element_limb)) in
+                    current_limbs.iter().zip(element_limbs).enumerate()
+                {
+                    let offset = 2 * limb;
+
+                    // enable the bool_and_swap_selector at the first row of each limb
+                    self.config
+                        .bool_and_swap_selector
+                        .enable(&mut region, offset)?;
+
+                    let l1 = current_limb.copy_advice(
+                        || "copy current limb from prev level",
+                        &mut region,
+                        self.config.advice[0],
+                        offset,
+                    )?;
+
+                    let r1 = region.assign_advice(
+                        || "element limb",
+                        self.config.advice[1],
+                        offset,
+                        || Value::known(element_limb),
+                    )?;
+
+                    let swap_bit = swap_bit_assigned.copy_advice(
+                        || "swap bit",
+                        &mut region,
+                        self.config.advice[2],
+                        offset,
+                    )?;
+
+                    let mut l1_val = l1.value().copied();
+                    let mut r1_val = r1.value().copied();
+
+                    // if swap_bit is 0 return (l1, r1) else return (r1, l1)
+                    swap_bit.value().copied().map(|x| {
+                        (l1_val, r1_val) = if x == Fp::zero() {
+                            (l1_val, r1_val)
+                        } else {
+                            (r1_val, l1_val)
+                        };
+                    });
+
+                    left_limbs.push(region.assign_advice(
+                        || "assign left limb after swap",
+                        self.config.advice[0],
+                        offset + 1,
+                        || l1_val,
+                    )?);
+
+                    right_limbs.push(region.assign_advice(
+                        || "assign right limb after swap",
+                        self.config.advice[1],
+                        offset + 1,
+                        || r1_val,
+                    )?);
+                }
+
+                let to_array = |limbs: Vec<AssignedCell<Fp, Fp>>| -> [AssignedCell<Fp, Fp>; 2] {
+                    limbs.try_into().unwrap()
+                };
+
+                Ok((to_array(left_limbs), to_array(right_limbs)))
+            },
+        )?;
+
+        let sum_limbs = limb_sum_chip.assign_limb_sum(
+            layouter.namespace(|| "sum limbs with carry"),
+            &left_limbs,
+            &right_limbs,
+        )?;
+
+        Ok((left_limbs, right_limbs, sum_limbs))
+    }
 }
@@ -0,0 +1,338 @@
+use halo2_proofs::circuit::{AssignedCell, Layouter, Region, Value};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector};
+use halo2_proofs::poly::Rotation;
+
+/// Configuration for the N-ary Merkle Sum Tree Chip
+///
+/// # Type Parameters
+///
+/// * `ARITY`: The number of children of each middle node
+///
+/// # Fields
+///
+/// * `advice`: advice columns to fit the witness values, one per child.
+/// * `position_selector`: Selector to toggle the position constraints.
+/// * `sum_selector`: Selector to toggle the sum constraint.
+#[derive(Debug, Clone)]
+pub struct NaryMerkleSumTreeConfig<const ARITY: usize> {
+    pub advice: [Column<Advice>; ARITY],
+    pub position_selector: Selector,
+    pub sum_selector: Selector,
+}
+
+/// Chip that performs the constraints of a Merkle Sum Tree in which each middle node has `ARITY` children, so that the tree of a large number of users is shallower than a binary one at the cost of a wider hash per level.
+///
+/// The position of the current node among its siblings is witnessed as `ARITY` one-hot bits `b_j`, and `p_j = b_0 + ... + b_(j-1)` is 1 for the slots after the current node. The chip contains the following constraints:
+///
+/// * `s * b_j * (1 - b_j) = 0` and `s * (b_0 + ... + b_(ARITY-1) - 1) = 0` (if `position_selector` is toggled). They enforce the position bits to be one-hot.
+/// * `s * (child_j - (b_j * current + (1 - b_j - p_j) * sibling_j + p_j * sibling_(j-1))) = 0` (if `position_selector` is toggled). It enforces the current node to be placed at the witnessed position and the siblings to keep their order around it.
+/// * `s * (child_0 + ... + child_(ARITY-1) - computed_sum) = 0` (if `sum_selector` is toggled). It constraints the computed sum to be equal to the sum of the balances of the children.
+///
+/// With `ARITY = 2` the constraints are equivalent to the ones of [crate::chips::merkle_sum_tree::MerkleSumTreeChip], where the swap bit is `b_1`.
+#[derive(Debug, Clone)]
+pub struct NaryMerkleSumTreeChip<const N_ASSETS: usize, const ARITY: usize> {
+    config: NaryMerkleSumTreeConfig<ARITY>,
+}
+
+impl<const N_ASSETS: usize, const ARITY: usize> NaryMerkleSumTreeChip<N_ASSETS, ARITY> {
+    pub fn construct(config: NaryMerkleSumTreeConfig<ARITY>) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advice: [Column<Advice>; ARITY],
+        selectors: [Selector; 2],
+    ) -> NaryMerkleSumTreeConfig<ARITY> {
+        assert!(ARITY >= 2, "The arity of the tree must be at least 2");
+
+        let position_selector = selectors[0];
+        let sum_selector = selectors[1];
+
+        meta.create_gate("one-hot position constraint", |meta| {
+            let s = meta.query_selector(position_selector);
+            let bits = advice
+                .iter()
+                .map(|column| meta.query_advice(*column, Rotation::next()))
+                .collect::<Vec<_>>();
+
+            let mut constraints = bits
+                .iter()
+                .map(|bit| {
+                    s.clone() * bit.clone() * (Expression::Constant(Fp::from(1)) - bit.clone())
+                })
+                .collect::<Vec<_>>();
+
+            let bits_sum = bits
+                .into_iter()
+                .fold(Expression::Constant(Fp::zero()), |acc, bit| acc + bit);
+            constraints.push(s * (bits_sum - Expression::Constant(Fp::from(1))));
+
+            constraints
+        });
+
+        meta.create_gate("position constraint", |meta| {
+            let s = meta.query_selector(position_selector);
+            let current = meta.query_advice(advice[0], Rotation::cur());
+            let siblings = advice[1..]
+                .iter()
+                .map(|column| meta.query_advice(*column, Rotation::cur()))
+                .collect::<Vec<_>>();
+
+            let mut after_current = Expression::Constant(Fp::zero());
+            let mut constraints = vec![];
+
+            for (j, column) in advice.iter().enumerate() {
+                let bit = meta.query_advice(*column, Rotation::next());
+                let child = meta.query_advice(*column, Rotation(2));
+
+                // the slot is filled by the current node, by the sibling with the same index if it comes before the current node, or by the previous sibling otherwise
+                let mut expected = bit.clone() * current.clone();
+                if j < ARITY - 1 {
+                    expected = expected
+                        + (Expression::Constant(Fp::from(1)) - bit.clone() - after_current.clone())
+                            * siblings[j].clone();
+                }
+                if j > 0 {
+                    expected = expected + after_current.clone() * siblings[j - 1].clone();
+                }
+
+                constraints.push(s.clone() * (child - expected));
+
+                after_current = after_current + bit;
+            }
+
+            constraints
+        });
+
+        meta.create_gate("sum constraint", |meta| {
+            let s = meta.query_selector(sum_selector);
+            let children_sum = advice
+                .iter()
+                .fold(Expression::Constant(Fp::zero()), |acc, column| {
+                    acc + meta.query_advice(*column, Rotation::cur())
+                });
+            let computed_sum = meta.query_advice(advice[0], Rotation::next());
+            vec![s * (children_sum - computed_sum)]
+        });
+
+        NaryMerkleSumTreeConfig {
+            advice,
+            position_selector,
+            sum_selector,
+        }
+    }
+
+    /// Assigns the entry hash and balances to the tree following this layout on a single column and returns the assigned cells:
+    ///
+    /// | a |
+    /// | -- |
+    /// | entry hash |
+    /// | entry_balance_0 |
+    /// | entry_balance_1 |
+    /// | ... |
+    /// | entry_balance_N |
+    pub fn assign_entry_hash_and_balances(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        entry_hash: Fp,
+        entry_balances: &[Fp],
+    ) -> Result<(AssignedCell<Fp, Fp>, Vec<AssignedCell<Fp, Fp>>), Error> {
+        layouter.assign_region(
+            || "assign entry hash",
+            |mut region| {
+                let hash = region.assign_advice(
+                    || "entry hash",
+                    self.config.advice[0],
+                    0,
+                    || Value::known(entry_hash),
+                )?;
+
+                let balances = (0..N_ASSETS)
+                    .map(|i| {
+                        region.assign_advice(
+                            || "entry balances",
+                            self.config.advice[0],
+                            i + 1,
+                            || Value::known(entry_balances[i]),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok((hash, balances))
+            },
+        )
+    }
+
+    /// Assigns the one-hot bits of `position`, namely the index of the current node among its siblings, and returns them.
+    /// The bits are constrained to be one-hot when they are copied in the regions of the nodes of the level.
+    pub fn assign_position_bits(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        position: usize,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        layouter.assign_region(
+            || "assign position bits",
+            |mut region| {
+                self.config
+                    .advice
+                    .iter()
+                    .enumerate()
+                    .map(|(j, column)| {
+                        region.assign_advice(
+                            || format!("position bit {}", j),
+                            *column,
+                            0,
+                            || Value::known(Fp::from((j == position) as u64)),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )
+    }
+
+    /// Assign the hashes of the children of a node in a region following this layout on ARITY advice columns:
+    ///
+    /// | a              | b                 | ... | ARITY              |
+    /// | ------------   | -------------     | --- | ------------------ |
+    /// | `current_hash` | `sibling_hash_0`  | ... | `sibling_hash_A-2` |
+    /// | `bit_0`        | `bit_1`           | ... | `bit_A-1`          |
+    /// | `child_hash_0` | `child_hash_1`    | ... | `child_hash_A-1`   |
+    ///
+    /// At row 0 position_selector is enabled. Returns the hashes of the children in order
+    pub fn assign_nodes_hashes_per_level(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        current_hash: &AssignedCell<Fp, Fp>,
+        sibling_hashes: &[Fp],
+        position_bits: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        layouter.assign_region(
+            || "assign nodes hashes per merkle tree level",
+            |mut region| {
+                self.assign_children(&mut region, current_hash, sibling_hashes, position_bits)
+            },
+        )
+    }
+
+    /// Assign the balances of the children of a node for a single asset in a region following this layout on ARITY advice columns:
+    ///
+    /// | a                 | b                   | ... | ARITY                 |
+    /// | ------------      | -------------       | --- | --------------------- |
+    /// | `current_balance` | `sibling_balance_0` | ... | `sibling_balance_A-2` |
+    /// | `bit_0`           | `bit_1`             | ... | `bit_A-1`             |
+    /// | `child_balance_0` | `child_balance_1`   | ... | `child_balance_A-1`   |
+    /// | `sum`             | -                   | ... | -                     |
+    ///
+    /// At row 0 position_selector is enabled.
+    /// At row 2 sum_selector is enabled. Returns the balances of the children in order and their sum
+    pub fn assign_nodes_balance_per_asset(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        current_balance: &AssignedCell<Fp, Fp>,
+        sibling_balances: &[Fp],
+        position_bits: &[AssignedCell<Fp, Fp>],
+    ) -> Result<(Vec<AssignedCell<Fp, Fp>>, AssignedCell<Fp, Fp>), Error> {
+        layouter.assign_region(
+            || "assign nodes balances per asset",
+            |mut region| {
+                let children = self.assign_children(
+                    &mut region,
+                    current_balance,
+                    sibling_balances,
+                    position_bits,
+                )?;
+
+                // enable the sum_selector at offset 2
+                self.config.sum_selector.enable(&mut region, 2)?;
+
+                // compute the sum of the balances of the children and assign it to the column self.config.advice[0] at offset 3
+                let sum = children
+                    .iter()
+                    .fold(Value::known(Fp::zero()), |acc, child| {
+                        acc + child.value().copied()
+                    });
+                let sum_cell =
+                    region.assign_advice(|| "sum of balances", self.config.advice[0], 3, || sum)?;
+
+                Ok((children, sum_cell))
+            },
+        )
+    }
+
+    /// Assigns the current value, the siblings and the position bits, and places the current value among the siblings at the position given by the bits
+    fn assign_children(
+        &self,
+        region: &mut Region<'_, Fp>,
+        current: &AssignedCell<Fp, Fp>,
+        siblings: &[Fp],
+        position_bits: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        assert_eq!(siblings.len(), ARITY - 1);
+        assert_eq!(position_bits.len(), ARITY);
+
+        // enable the position_selector at row 0
+        self.config.position_selector.enable(region, 0)?;
+
+        // copy the current value to the column self.config.advice[0] at offset 0
+        let current = current.copy_advice(
+            || "copy current value from previous level",
+            region,
+            self.config.advice[0],
+            0,
+        )?;
+
+        // assign the siblings to the other columns at offset 0
+        for (j, sibling) in siblings.iter().enumerate() {
+            region.assign_advice(
+                || format!("sibling {}", j),
+                self.config.advice[j + 1],
+                0,
+                || Value::known(*sibling),
+            )?;
+        }
+
+        // copy the position bits at offset 1
+        let bits = position_bits
+            .iter()
+            .enumerate()
+            .map(|(j, bit)| {
+                bit.copy_advice(
+                    || format!("position bit {}", j),
+                    region,
+                    self.config.advice[j],
+                    1,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // place the current value among the siblings according to the position bits and assign the children at offset 2
+        let mut after_current = Value::known(Fp::zero());
+        let mut children = vec![];
+
+        for (j, bit) in bits.iter().enumerate() {
+            let bit = bit.value().copied();
+
+            let mut child = bit * current.value().copied();
+            if j < ARITY - 1 {
+                child = child
+                    + (Value::known(Fp::one()) - bit - after_current) * Value::known(siblings[j]);
+            }
+            if j > 0 {
+                child = child + after_current * Value::known(siblings[j - 1]);
+            }
+
+            children.push(region.assign_advice(
+                || format!("child {}", j),
+                self.config.advice[j],
+                2,
+                || child,
+            )?);
+
+            after_current = after_current + bit;
+        }
+
+        Ok(children)
+    }
+}
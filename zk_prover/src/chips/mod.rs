@@ -1,6 +1,10 @@
 pub mod aggregation;
 pub mod ecdsa;
 pub mod less_than;
+pub mod limb_sum;
 pub mod merkle_sum_tree;
+pub mod merkle_sum_tree_nary;
 pub mod overflow;
+pub mod pedersen;
 pub mod poseidon;
+pub mod sha256;
@@ -0,0 +1,203 @@
+use ecc::integer::{IntegerInstructions, Range};
+use ecc::maingate::{big_to_fe, fe_to_big, MainGate, RangeChip, RangeInstructions, RegionCtx};
+use ecc::{AssignedPoint, EccConfig, GeneralEccChip};
+use ecdsa::ecdsa::EcdsaConfig;
+use halo2_proofs::circuit::{AssignedCell, Layouter, Value};
+use halo2_proofs::halo2curves::{
+    bn256::Fr as Fp,
+    ff::Field,
+    group::Curve,
+    secp256k1::{Fp as Secp256k1Base, Secp256k1Affine as Secp256k1},
+    CurveAffine,
+};
+use halo2_proofs::plonk::{ConstraintSystem, Error};
+
+pub const BIT_LEN_LIMB: usize = 68;
+pub const NUMBER_OF_LIMBS: usize = 4;
+
+/// Scalar field of the curve on which the commitment is computed
+pub type CommitmentScalar = <Secp256k1 as CurveAffine>::ScalarExt;
+
+/// Configuration for the Pedersen Commitment Chip
+///
+/// The commitment is computed on the secp256k1 curve, whose arithmetic is emulated inside the BN256 scalar field.
+/// It reuses the same main gate and range configuration of the ECDSA chip.
+///
+/// # Fields
+///
+/// * `ecdsa_config`: The main gate and range configuration from which the inner [ecc::GeneralEccChip] is built
+#[derive(Debug, Clone)]
+pub struct PedersenCommitmentConfig {
+    pub ecdsa_config: EcdsaConfig,
+}
+
+/// Chip that computes a vector Pedersen commitment `C = r * H + balance_0 * G_0 + ... + balance_N * G_N` to the balances of an entry.
+///
+/// The generators `H` and `G_i` are derived in a nothing-up-my-sleeve fashion by [pedersen_generators] and are assigned as constants.
+/// Each balance cell is copy constrained to the native value of the scalar used in the multiplication, so that the commitment is bound to the actual balances used elsewhere in the circuit.
+#[derive(Debug, Clone)]
+pub struct PedersenCommitmentChip<const N_ASSETS: usize> {
+    config: PedersenCommitmentConfig,
+}
+
+impl<const N_ASSETS: usize> PedersenCommitmentChip<N_ASSETS> {
+    /// Constructs a new Pedersen Commitment Chip given a PedersenCommitmentConfig
+    pub fn construct(config: PedersenCommitmentConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configures the Pedersen Commitment Chip
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> PedersenCommitmentConfig {
+        let (rns_base, rns_scalar) =
+            GeneralEccChip::<Secp256k1, Fp, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::rns();
+        let main_gate_config = MainGate::<Fp>::configure(meta);
+        let mut overflow_bit_lens: Vec<usize> = vec![];
+        overflow_bit_lens.extend(rns_base.overflow_lengths());
+        overflow_bit_lens.extend(rns_scalar.overflow_lengths());
+        let composition_bit_lens = vec![BIT_LEN_LIMB / NUMBER_OF_LIMBS];
+
+        let range_config = RangeChip::<Fp>::configure(
+            meta,
+            &main_gate_config,
+            composition_bit_lens,
+            overflow_bit_lens,
+        );
+
+        PedersenCommitmentConfig {
+            ecdsa_config: EcdsaConfig::new(range_config, main_gate_config),
+        }
+    }
+
+    /// Returns the configuration of the inner [ecc::GeneralEccChip]
+    pub fn ecc_chip_config(&self) -> EccConfig {
+        self.config.ecdsa_config.ecc_chip_config()
+    }
+
+    /// Commits to the balances passed as assigned cells using the `blinding` factor.
+    /// Returns the limbs of the x and y coordinates of the commitment point.
+    pub fn commit(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        balances: &[AssignedCell<Fp, Fp>],
+        blinding: Value<CommitmentScalar>,
+        aux_generator: Secp256k1,
+        window_size: usize,
+    ) -> Result<(Vec<AssignedCell<Fp, Fp>>, Vec<AssignedCell<Fp, Fp>>), Error> {
+        assert_eq!(balances.len(), N_ASSETS);
+
+        let mut ecc_chip = GeneralEccChip::<Secp256k1, Fp, NUMBER_OF_LIMBS, BIT_LEN_LIMB>::new(
+            self.ecc_chip_config(),
+        );
+
+        layouter.assign_region(
+            || "assign aux values",
+            |region| {
+                let offset = 0;
+                let ctx = &mut RegionCtx::new(region, offset);
+
+                ecc_chip.assign_aux_generator(ctx, Value::known(aux_generator))?;
+                ecc_chip.assign_aux(ctx, window_size, 1)?;
+                Ok(())
+            },
+        )?;
+
+        let scalar_chip = ecc_chip.scalar_field_chip();
+        let (h, g) = pedersen_generators::<N_ASSETS>();
+
+        let commitment = layouter.assign_region(
+            || "pedersen commitment",
+            |region| {
+                let offset = 0;
+                let ctx = &mut RegionCtx::new(region, offset);
+
+                // blinding term r * H
+                let h_assigned = ecc_chip.assign_constant(ctx, h)?;
+                let blinding_integer = ecc_chip.new_unassigned_scalar(blinding);
+                let blinding_assigned =
+                    scalar_chip.assign_integer(ctx, blinding_integer, Range::Remainder)?;
+                let mut acc = ecc_chip.mul(ctx, &h_assigned, &blinding_assigned, window_size)?;
+
+                // balance terms balance_i * G_i
+                for (balance, generator) in balances.iter().zip(g.iter()) {
+                    let balance_scalar = balance
+                        .value()
+                        .map(|balance| big_to_fe::<CommitmentScalar>(fe_to_big(*balance)));
+                    let balance_integer = ecc_chip.new_unassigned_scalar(balance_scalar);
+                    let balance_assigned =
+                        scalar_chip.assign_integer(ctx, balance_integer, Range::Remainder)?;
+
+                    // bind the scalar to the balance used in the rest of the circuit
+                    ctx.constrain_equal(balance_assigned.native().cell(), balance.cell())?;
+
+                    let generator_assigned = ecc_chip.assign_constant(ctx, *generator)?;
+                    let term =
+                        ecc_chip.mul(ctx, &generator_assigned, &balance_assigned, window_size)?;
+                    acc = ecc_chip.add(ctx, &acc, &term)?;
+                }
+
+                ecc_chip.normalize(ctx, &acc)
+            },
+        )?;
+
+        let range_chip = RangeChip::<Fp>::new(self.config.ecdsa_config.range_config.clone());
+        range_chip.load_table(&mut layouter)?;
+
+        Ok(point_limbs(&commitment))
+    }
+}
+
+/// Extracts the limbs of the x and y coordinates of an assigned point
+fn point_limbs(
+    point: &AssignedPoint<Secp256k1Base, Fp, NUMBER_OF_LIMBS, BIT_LEN_LIMB>,
+) -> (Vec<AssignedCell<Fp, Fp>>, Vec<AssignedCell<Fp, Fp>>) {
+    let x_limbs = point
+        .x()
+        .limbs()
+        .iter()
+        .map(|limb| limb.as_ref().clone())
+        .collect();
+    let y_limbs = point
+        .y()
+        .limbs()
+        .iter()
+        .map(|limb| limb.as_ref().clone())
+        .collect();
+    (x_limbs, y_limbs)
+}
+
+/// Derives the generators `(H, [G_0, ..., G_N])` used by the Pedersen commitment.
+///
+/// Each generator is obtained by try-and-increment: starting from a fixed counter, the first x-coordinate for which `x^3 + 7` is a square
+/// is taken as a point of secp256k1. Nobody knows the discrete log relation between these points.
+pub fn pedersen_generators<const N_ASSETS: usize>() -> (Secp256k1, [Secp256k1; N_ASSETS]) {
+    let mut counter = 0u64;
+    let mut next_generator = || loop {
+        counter += 1;
+        let x = Secp256k1Base::from(counter);
+        let y2 = x.square() * x + Secp256k1Base::from(7);
+        if let Some(y) = Option::<Secp256k1Base>::from(y2.sqrt()) {
+            return Option::<Secp256k1>::from(Secp256k1::from_xy(x, y))
+                .expect("the point should be on the curve");
+        }
+    };
+
+    let h = next_generator();
+    let g = [(); N_ASSETS].map(|_| next_generator());
+    (h, g)
+}
+
+/// Computes natively the Pedersen commitment to `balances` with the given `blinding` factor.
+pub fn pedersen_commitment<const N_ASSETS: usize>(
+    balances: &[Fp; N_ASSETS],
+    blinding: CommitmentScalar,
+) -> Secp256k1 {
+    let (h, g) = pedersen_generators::<N_ASSETS>();
+
+    let mut commitment = h * blinding;
+    for (balance, generator) in balances.iter().zip(g.iter()) {
+        let balance_scalar = big_to_fe::<CommitmentScalar>(fe_to_big(*balance));
+        commitment = commitment + (*generator * balance_scalar);
+    }
+
+    commitment.to_affine()
+}
@@ -1,5 +1,7 @@
 //! An easy-to-use implementation of the Poseidon Hash in the form of a Halo2 Chip. While the Poseidon Hash function
 //! is already implemented in halo2_gadgets, there is no wrapper chip that makes it easy to use in other circuits.
+use crate::merkle_sum_tree::HashDomain;
+use crate::timing;
 use halo2_gadgets::poseidon::{primitives::*, Hash, Pow5Chip, Pow5Config};
 use halo2_proofs::{
     circuit::{AssignedCell, Layouter},
@@ -23,8 +25,10 @@ use std::marker::PhantomData;
 /// # Fields
 ///
 /// * `pow5_config`: The configuration for the inner [halo2_gadgets::poseidon::Pow5Config]
+/// * `tag`: The advice column to which the domain tag of [PoseidonChip::hash_with_domain] is assigned, namely the first state column
 pub struct PoseidonConfig<const WIDTH: usize, const RATE: usize, const L: usize> {
     pow5_config: Pow5Config<Fp, WIDTH, RATE>,
+    tag: Column<Advice>,
 }
 
 #[derive(Debug, Clone)]
@@ -69,7 +73,18 @@ impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize, const L: u
         meta.enable_constant(rc_b[0]);
         let pow5_config = Pow5Chip::configure::<S>(meta, state, partial_sbox, rc_a, rc_b);
 
-        PoseidonConfig { pow5_config }
+        PoseidonConfig {
+            pow5_config,
+            tag: state[0],
+        }
+    }
+
+    /// Returns the number of rows consumed by [Self::hash] on the columns of the chip: 1 row to load the initial state, then for each chunk of RATE inputs 3 rows to add the chunk to the state and `R_F + R_P / 2 + 1` rows for the permutation, as the partial rounds are assigned two per row.
+    /// For example, 127 rows for L = 6 with 8 full rounds and 60 partial rounds. The WIDTH constants of the initial state are assigned in the constants column after all the regions of the circuit.
+    pub fn rows_used() -> usize {
+        let permutation_rows = S::full_rounds() + S::partial_rounds() / 2 + 1;
+
+        1 + L.div_ceil(RATE) * (3 + permutation_rows)
     }
 
     /// Performs poseidon hash on the given input cells. Returns the output cell.
@@ -78,12 +93,53 @@ impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize, const L: u
         mut layouter: impl Layouter<Fp>,
         input_cells: [AssignedCell<Fp, Fp>; L],
     ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let _timer = timing::start(timing::POSEIDON_HASH);
+        let _span = tracing::debug_span!("poseidon hash", inputs = L).entered();
+
         let pow5_chip = Pow5Chip::construct(self.config.pow5_config.clone());
 
         let hasher = Hash::<_, _, S, ConstantLength<L>, WIDTH, RATE>::init(
             pow5_chip,
             layouter.namespace(|| "hasher"),
         )?;
-        hasher.hash(layouter.namespace(|| "hash"), input_cells)
+        let output = hasher.hash(layouter.namespace(|| "hash"), input_cells)?;
+        tracing::debug!(output = ?output.value(), "assigned poseidon hash");
+
+        Ok(output)
+    }
+
+    /// Performs poseidon hash on the tag of `domain` followed by the L - 1 given input cells, see [crate::merkle_sum_tree::poseidon_tagged]. Returns the output cell.
+    /// The tag is assigned as a constant, so that a hash computed in one domain can't be passed off as a hash computed in another one.
+    pub fn hash_with_domain(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        domain: HashDomain,
+        input_cells: &[AssignedCell<Fp, Fp>],
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        assert_eq!(
+            input_cells.len() + 1,
+            L,
+            "The domain tag and the inputs must be L cells"
+        );
+
+        let tag = layouter.assign_region(
+            || "assign domain tag",
+            |mut region| {
+                region.assign_advice_from_constant(
+                    || "domain tag",
+                    self.config.tag,
+                    0,
+                    domain.tag(),
+                )
+            },
+        )?;
+
+        let hash_input: [AssignedCell<Fp, Fp>; L] = std::iter::once(tag)
+            .chain(input_cells.iter().cloned())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+
+        self.hash(layouter, hash_input)
     }
 }
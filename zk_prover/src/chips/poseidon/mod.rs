@@ -1,3 +1,7 @@
 pub mod hash;
 pub mod poseidon_params;
 pub mod poseidon_spec;
+pub mod spec_params;
+pub mod sponge;
+pub mod test_vectors;
+mod tests;
@@ -3,6 +3,7 @@
 //! This file was generated by running `generate_params.py`.
 //! Patterned after [halo2_gadgets::poseidon::primitives::P128Pow5T3]
 use crate::chips::poseidon::poseidon_params;
+use crate::chips::poseidon::spec_params::PoseidonSpecParams;
 use halo2_gadgets::poseidon::primitives::*;
 use halo2_proofs::arithmetic::Field;
 use halo2_proofs::halo2curves::bn256::Fr as Fp;
@@ -30,10 +31,14 @@ impl Spec<Fp, 3, 2> for PoseidonSpec {
     }
 
     fn constants() -> (Vec<[Fp; 3]>, Mds<Fp, 3>, Mds<Fp, 3>) {
-        (
+        PoseidonSpecParams::new(
+            Self::full_rounds(),
+            Self::partial_rounds(),
             poseidon_params::ROUND_CONSTANTS[..].to_vec(),
             poseidon_params::MDS,
             poseidon_params::MDS_INV,
         )
+        .expect("The round constants should match the number of rounds")
+        .constants()
     }
 }
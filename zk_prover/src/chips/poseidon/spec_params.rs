@@ -0,0 +1,217 @@
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use std::fmt;
+
+/// Errors that can occur while validating the parameters of a Poseidon spec
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoseidonSpecError {
+    /// The number of full rounds must be even, as half of them are performed before the partial rounds and half after
+    OddFullRounds(usize),
+    /// The number of round constants doesn't match `(full_rounds + partial_rounds) * WIDTH`
+    RoundConstantsLength { expected: usize, found: usize },
+    /// The number of rounds is less than the one required for 128 bits of security, see [secure_round_numbers]
+    InsecureRoundNumbers {
+        full_rounds: usize,
+        partial_rounds: usize,
+        min_full_rounds: usize,
+        min_partial_rounds: usize,
+    },
+}
+
+impl fmt::Display for PoseidonSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoseidonSpecError::OddFullRounds(full_rounds) => {
+                write!(f, "The number of full rounds must be even, found {}", full_rounds)
+            }
+            PoseidonSpecError::RoundConstantsLength { expected, found } => write!(
+                f,
+                "Expected {} round constants, namely (full_rounds + partial_rounds) * WIDTH, but found {}",
+                expected, found
+            ),
+            PoseidonSpecError::InsecureRoundNumbers {
+                full_rounds,
+                partial_rounds,
+                min_full_rounds,
+                min_partial_rounds,
+            } => write!(
+                f,
+                "{} full rounds and {} partial rounds are insecure, at least {} full rounds and {} partial rounds are required",
+                full_rounds, partial_rounds, min_full_rounds, min_partial_rounds
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PoseidonSpecError {}
+
+/// Security level of the Poseidon instances, in bits
+const SECURITY_BITS: f64 = 128.0;
+/// Base 2 logarithm of the modulus of the BN256 scalar field
+const LOG2_MODULUS: f64 = 253.59669135500215;
+/// Exponent of the s-box, namely `x^5`
+const ALPHA: f64 = 5.0;
+
+/// Returns whether `full_rounds` and `partial_rounds`, without security margin, resist the statistical, interpolation and Gröbner basis attacks on a Poseidon permutation of `width` elements over the BN256 scalar field.
+/// It follows `sat_inequiv_alpha` of the reference `calc_round_numbers.py` script, including the bound of <https://eprint.iacr.org/2023/537>.
+fn resists_known_attacks(width: usize, full_rounds: usize, partial_rounds: usize) -> bool {
+    let t = width as f64;
+    let r_f = full_rounds as f64;
+    let r_p = partial_rounds as f64;
+    let log_alpha = |x: f64| x.ln() / ALPHA.ln();
+
+    let statistical = if SECURITY_BITS <= (LOG2_MODULUS - (ALPHA - 1.0) / 2.0).floor() * (t + 1.0) {
+        6.0
+    } else {
+        10.0
+    };
+    let interpolation = 1.0
+        + (log_alpha(2.0) * SECURITY_BITS.min(LOG2_MODULUS.ceil())).ceil()
+        + log_alpha(t).ceil()
+        - r_p;
+    let groebner_1 = log_alpha(2.0) * SECURITY_BITS.min(LOG2_MODULUS) - r_p;
+    let groebner_2 =
+        t - 1.0 + log_alpha(2.0) * (SECURITY_BITS / (t + 1.0)).min(LOG2_MODULUS / 2.0) - r_p;
+    let groebner_3 = (t - 2.0 + SECURITY_BITS / (2.0 * ALPHA.log2()) - r_p) / (t - 1.0);
+
+    let min_full_rounds = [
+        statistical,
+        interpolation,
+        groebner_1,
+        groebner_2,
+        groebner_3,
+    ]
+    .iter()
+    .map(|bound| bound.ceil())
+    .fold(f64::MIN, f64::max);
+
+    // the cost of the Gröbner basis attack of https://eprint.iacr.org/2023/537, namely twice the base 2 logarithm of binomial(over, under)
+    let r = (width / 3) as f64;
+    let over = (r_f - 1.0) * t + r_p + r + r * (r_f / 2.0) + r_p + ALPHA;
+    let under = r * (r_f / 2.0) + r_p + ALPHA;
+    let log2_binomial = (1..=under as usize)
+        .map(|i| ((over - under + i as f64) / i as f64).log2())
+        .sum::<f64>();
+
+    r_f >= min_full_rounds && (2.0 * log2_binomial).ceil() >= SECURITY_BITS
+}
+
+/// Returns the number of full rounds and partial rounds required for 128 bits of security by a Poseidon permutation of `width` elements with the `x^5` s-box over the BN256 scalar field, as computed by the reference `calc_round_numbers.py` script.
+/// Among the round numbers resisting the known attacks, the ones with the fewest s-boxes are picked, then a security margin of 2 full rounds and 7.5% partial rounds is added.
+pub fn secure_round_numbers(width: usize) -> (usize, usize) {
+    let mut best: Option<(usize, usize, usize)> = None;
+
+    for partial_rounds in 1..500 {
+        for full_rounds in (4..100).step_by(2) {
+            if !resists_known_attacks(width, full_rounds, partial_rounds) {
+                continue;
+            }
+
+            let full_rounds = full_rounds + 2;
+            let partial_rounds = (partial_rounds as f64 * 1.075).ceil() as usize;
+            let cost = width * full_rounds + partial_rounds;
+
+            let is_cheaper = match best {
+                Some((min_cost, min_full_rounds, _)) => {
+                    cost < min_cost || (cost == min_cost && full_rounds < min_full_rounds)
+                }
+                None => true,
+            };
+            if is_cheaper {
+                best = Some((cost, full_rounds, partial_rounds));
+            }
+        }
+    }
+
+    let (_, full_rounds, partial_rounds) = best.expect("There are secure round numbers");
+    (full_rounds, partial_rounds)
+}
+
+/// Checks that a Poseidon permutation of `width` elements has at least as many full rounds and partial rounds as the ones returned by [secure_round_numbers].
+/// Returns [PoseidonSpecError::InsecureRoundNumbers] otherwise, so that an insecure hash configuration is not used silently.
+pub fn validate_round_numbers(
+    width: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+) -> Result<(), PoseidonSpecError> {
+    let (min_full_rounds, min_partial_rounds) = secure_round_numbers(width);
+
+    if full_rounds < min_full_rounds || partial_rounds < min_partial_rounds {
+        return Err(PoseidonSpecError::InsecureRoundNumbers {
+            full_rounds,
+            partial_rounds,
+            min_full_rounds,
+            min_partial_rounds,
+        });
+    }
+
+    Ok(())
+}
+
+/// Round counts and constants of a Poseidon spec, validated at construction so that a mismatch is reported upfront rather than while hashing.
+///
+/// # Type Parameters
+///
+/// * `WIDTH`: The width of the Poseidon permutation
+///
+/// # Fields
+///
+/// * `full_rounds`: The number of full rounds
+/// * `partial_rounds`: The number of partial rounds
+/// * `round_constants`: The round constants, one array of WIDTH constants per round
+/// * `mds`: The MDS matrix
+/// * `mds_inv`: The inverse of the MDS matrix
+#[derive(Debug, Clone)]
+pub struct PoseidonSpecParams<const WIDTH: usize> {
+    full_rounds: usize,
+    partial_rounds: usize,
+    round_constants: Vec<[Fp; WIDTH]>,
+    mds: [[Fp; WIDTH]; WIDTH],
+    mds_inv: [[Fp; WIDTH]; WIDTH],
+}
+
+impl<const WIDTH: usize> PoseidonSpecParams<WIDTH> {
+    pub fn new(
+        full_rounds: usize,
+        partial_rounds: usize,
+        round_constants: Vec<[Fp; WIDTH]>,
+        mds: [[Fp; WIDTH]; WIDTH],
+        mds_inv: [[Fp; WIDTH]; WIDTH],
+    ) -> Result<Self, PoseidonSpecError> {
+        if full_rounds % 2 != 0 {
+            return Err(PoseidonSpecError::OddFullRounds(full_rounds));
+        }
+
+        let expected = (full_rounds + partial_rounds) * WIDTH;
+        let found = round_constants.len() * WIDTH;
+        if found != expected {
+            return Err(PoseidonSpecError::RoundConstantsLength { expected, found });
+        }
+
+        Ok(Self {
+            full_rounds,
+            partial_rounds,
+            round_constants,
+            mds,
+            mds_inv,
+        })
+    }
+
+    pub fn full_rounds(&self) -> usize {
+        self.full_rounds
+    }
+
+    pub fn partial_rounds(&self) -> usize {
+        self.partial_rounds
+    }
+
+    /// Checks that the round numbers are secure for WIDTH, see [validate_round_numbers]
+    pub fn validate_round_numbers(&self) -> Result<(), PoseidonSpecError> {
+        validate_round_numbers(WIDTH, self.full_rounds, self.partial_rounds)
+    }
+
+    /// Returns the round constants, the MDS matrix and its inverse in the format of [halo2_gadgets::poseidon::primitives::Spec::constants]
+    #[allow(clippy::type_complexity)]
+    pub fn constants(&self) -> (Vec<[Fp; WIDTH]>, [[Fp; WIDTH]; WIDTH], [[Fp; WIDTH]; WIDTH]) {
+        (self.round_constants.clone(), self.mds, self.mds_inv)
+    }
+}
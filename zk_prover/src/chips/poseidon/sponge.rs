@@ -0,0 +1,123 @@
+//! A Poseidon sponge chip for hashing inputs whose length is only known when the circuit is synthesized, such as concatenated account identifiers.
+//! It wraps the [halo2_gadgets::poseidon::Sponge] API with a [VariableLength] domain.
+use halo2_gadgets::poseidon::{
+    primitives::{Absorbing, Domain, Spec},
+    PaddedWord, Pow5Chip, Pow5Config, Sponge,
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter},
+    halo2curves::bn256::Fr as Fp,
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed},
+};
+use std::marker::PhantomData;
+
+/// Domain of the Poseidon sponge for inputs of variable length.
+///
+/// The input is padded with a single 1 followed by as many 0s as needed to fill the last rate-sized chunk, so that inputs of different lengths can't collide.
+/// The initial capacity element is 1, which is different from the one of every [halo2_gadgets::poseidon::primitives::ConstantLength] domain, namely `L * 2^64`.
+#[derive(Debug, Clone, Copy)]
+pub struct VariableLength;
+
+impl<const RATE: usize> Domain<Fp, RATE> for VariableLength {
+    type Padding = Vec<Fp>;
+
+    fn name() -> String {
+        "VariableLength".to_string()
+    }
+
+    fn initial_capacity_element() -> Fp {
+        Fp::one()
+    }
+
+    fn padding(input_len: usize) -> Self::Padding {
+        let zeros = (RATE - (input_len + 1) % RATE) % RATE;
+        std::iter::once(Fp::one())
+            .chain(std::iter::repeat(Fp::zero()).take(zeros))
+            .collect()
+    }
+}
+
+/// Configuration of the Poseidon sponge chip
+///
+/// # Type Parameters
+///
+/// * `WIDTH`: The width of the Poseidon permutation,
+/// * `RATE`: The rate of the Poseidon permutation, typically WIDTH - 1.
+///
+/// # Fields
+///
+/// * `pow5_config`: The configuration for the inner [halo2_gadgets::poseidon::Pow5Config]
+#[derive(Debug, Clone)]
+pub struct PoseidonSpongeConfig<const WIDTH: usize, const RATE: usize> {
+    pow5_config: Pow5Config<Fp, WIDTH, RATE>,
+}
+
+/// Chip that performs the Poseidon Hash of a variable number of input cells
+///
+/// # Type Parameters
+///
+/// * `S`: The specification for the Poseidon hash function,
+/// * `WIDTH`: The width of the Poseidon permutation,
+/// * `RATE`: The rate of the Poseidon permutation, typically WIDTH - 1.
+#[derive(Debug, Clone)]
+pub struct PoseidonSpongeChip<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize> {
+    config: PoseidonSpongeConfig<WIDTH, RATE>,
+    _marker: PhantomData<S>,
+}
+
+impl<S: Spec<Fp, WIDTH, RATE>, const WIDTH: usize, const RATE: usize>
+    PoseidonSpongeChip<S, WIDTH, RATE>
+{
+    /// Constructs a new Poseidon Sponge Chip given a PoseidonSpongeConfig
+    pub fn construct(config: PoseidonSpongeConfig<WIDTH, RATE>) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Configures the Poseidon Sponge Chip. The columns are the same as the ones of [crate::chips::poseidon::hash::PoseidonChip]
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        state: [Column<Advice>; WIDTH],
+        partial_sbox: Column<Advice>,
+        rc_a: [Column<Fixed>; WIDTH],
+        rc_b: [Column<Fixed>; WIDTH],
+    ) -> PoseidonSpongeConfig<WIDTH, RATE> {
+        meta.enable_constant(rc_b[0]);
+        let pow5_config = Pow5Chip::configure::<S>(meta, state, partial_sbox, rc_a, rc_b);
+
+        PoseidonSpongeConfig { pow5_config }
+    }
+
+    /// Absorbs the given input cells followed by the [VariableLength] padding, which is assigned as constants, and squeezes a single output cell.
+    pub fn hash_variable(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        inputs: &[AssignedCell<Fp, Fp>],
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let pow5_chip = Pow5Chip::construct(self.config.pow5_config.clone());
+
+        let mut sponge =
+            Sponge::<_, _, S, Absorbing<PaddedWord<Fp>, RATE>, VariableLength, WIDTH, RATE>::new(
+                pow5_chip,
+                layouter.namespace(|| "sponge"),
+            )?;
+
+        let padding = <VariableLength as Domain<Fp, RATE>>::padding(inputs.len());
+
+        for (i, word) in inputs
+            .iter()
+            .cloned()
+            .map(PaddedWord::Message)
+            .chain(padding.into_iter().map(PaddedWord::Padding))
+            .enumerate()
+        {
+            sponge.absorb(layouter.namespace(|| format!("absorb {}", i)), word)?;
+        }
+
+        sponge
+            .finish_absorbing(layouter.namespace(|| "finish absorbing"))?
+            .squeeze(layouter.namespace(|| "squeeze"))
+    }
+}
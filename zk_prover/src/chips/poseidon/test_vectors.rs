@@ -0,0 +1,37 @@
+//! Deterministic input-output vectors of the Poseidon hash with [PoseidonSpec], to cross-check the hash against external implementations.
+use crate::chips::poseidon::poseidon_spec::PoseidonSpec;
+use halo2_gadgets::poseidon::primitives::{self as poseidon, ConstantLength};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+
+/// Performs the off-circuit Poseidon hash of 4 inputs with [PoseidonSpec], with the same domain as [crate::chips::poseidon::hash::PoseidonChip::hash] for `L = 4`
+pub fn poseidon_hash_4(inputs: [Fp; 4]) -> Fp {
+    poseidon::Hash::<Fp, PoseidonSpec, ConstantLength<4>, 3, 2>::init().hash(inputs)
+}
+
+/// Returns `n` deterministic test vectors, namely pairs of inputs and their hash.
+/// The first vectors cover the edge cases of all zero inputs and of all inputs equal to the modulus minus 1, the following ones have consecutive small inputs.
+pub fn poseidon_test_vectors(n: usize) -> Vec<([Fp; 4], Fp)> {
+    let edge_cases = [[Fp::zero(); 4], [-Fp::one(); 4]];
+
+    edge_cases
+        .into_iter()
+        .chain((0u64..).map(|i| std::array::from_fn(|j| Fp::from(4 * i + j as u64 + 1))))
+        .take(n)
+        .map(|inputs| (inputs, poseidon_hash_4(inputs)))
+        .collect()
+}
+
+/// Dumps `n` test vectors as text, one vector per line in the form `in_0,in_1,in_2,in_3 -> out`, where each field element is a `0x`-prefixed big endian hex string
+pub fn dump_poseidon_test_vectors(n: usize) -> String {
+    poseidon_test_vectors(n)
+        .iter()
+        .map(|(inputs, output)| {
+            let inputs = inputs
+                .iter()
+                .map(|input| format!("{:?}", input))
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("{} -> {:?}\n", inputs, output)
+        })
+        .collect()
+}
@@ -0,0 +1,537 @@
+#[cfg(test)]
+mod test {
+    use crate::chips::poseidon::hash::{PoseidonChip, PoseidonConfig};
+    use crate::chips::poseidon::poseidon_params::{MDS, MDS_INV, ROUND_CONSTANTS};
+    use crate::chips::poseidon::poseidon_spec::PoseidonSpec;
+    use crate::chips::poseidon::spec_params::{
+        secure_round_numbers, validate_round_numbers, PoseidonSpecError, PoseidonSpecParams,
+    };
+    use crate::chips::poseidon::sponge::{PoseidonSpongeChip, PoseidonSpongeConfig};
+    use crate::chips::poseidon::test_vectors::{
+        dump_poseidon_test_vectors, poseidon_hash_4, poseidon_test_vectors,
+    };
+    use crate::circuits::utils::probe_free_rows;
+    use crate::merkle_sum_tree::{poseidon_tagged, poseidon_variable_length, HashDomain};
+    use halo2_gadgets::poseidon::primitives::{self as poseidon, ConstantLength};
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Instance},
+    };
+
+    #[derive(Debug, Clone)]
+    struct SpongeTestConfig {
+        sponge_config: PoseidonSpongeConfig<3, 2>,
+        advice: Column<Advice>,
+        instance: Column<Instance>,
+    }
+
+    #[derive(Clone)]
+    struct SpongeTestCircuit {
+        inputs: Vec<Fp>,
+    }
+
+    impl Circuit<Fp> for SpongeTestCircuit {
+        type Config = SpongeTestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                inputs: vec![Fp::zero(); self.inputs.len()],
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices: [Column<Advice>; 4] = std::array::from_fn(|_| meta.advice_column());
+            let fixed_columns: [Column<Fixed>; 6] = std::array::from_fn(|_| meta.fixed_column());
+
+            for col in &advices {
+                meta.enable_equality(*col);
+            }
+
+            let sponge_config = PoseidonSpongeChip::<PoseidonSpec, 3, 2>::configure(
+                meta,
+                advices[0..3].try_into().unwrap(),
+                advices[3],
+                fixed_columns[0..3].try_into().unwrap(),
+                fixed_columns[3..6].try_into().unwrap(),
+            );
+
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+
+            SpongeTestConfig {
+                sponge_config,
+                advice: advices[0],
+                instance,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let inputs = layouter.assign_region(
+                || "assign inputs",
+                |mut region| {
+                    self.inputs
+                        .iter()
+                        .enumerate()
+                        .map(|(i, input)| {
+                            region.assign_advice(
+                                || format!("input {}", i),
+                                config.advice,
+                                i,
+                                || Value::known(*input),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                },
+            )?;
+
+            let sponge_chip =
+                PoseidonSpongeChip::<PoseidonSpec, 3, 2>::construct(config.sponge_config);
+            let digest =
+                sponge_chip.hash_variable(layouter.namespace(|| "hash inputs"), &inputs)?;
+
+            layouter.constrain_instance(digest.cell(), config.instance, 0)
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct HashTestConfig {
+        hash_config: PoseidonConfig<3, 2, 4>,
+        advice: Column<Advice>,
+        instance: Column<Instance>,
+    }
+
+    #[derive(Clone)]
+    struct HashTestCircuit {
+        inputs: [Fp; 4],
+    }
+
+    impl Circuit<Fp> for HashTestCircuit {
+        type Config = HashTestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                inputs: [Fp::zero(); 4],
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices: [Column<Advice>; 4] = std::array::from_fn(|_| meta.advice_column());
+            let fixed_columns: [Column<Fixed>; 6] = std::array::from_fn(|_| meta.fixed_column());
+
+            for col in &advices {
+                meta.enable_equality(*col);
+            }
+
+            let hash_config = PoseidonChip::<PoseidonSpec, 3, 2, 4>::configure(
+                meta,
+                advices[0..3].try_into().unwrap(),
+                advices[3],
+                fixed_columns[0..3].try_into().unwrap(),
+                fixed_columns[3..6].try_into().unwrap(),
+            );
+
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+
+            HashTestConfig {
+                hash_config,
+                advice: advices[0],
+                instance,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let inputs = layouter.assign_region(
+                || "assign inputs",
+                |mut region| {
+                    self.inputs
+                        .iter()
+                        .enumerate()
+                        .map(|(i, input)| {
+                            region.assign_advice(
+                                || format!("input {}", i),
+                                config.advice,
+                                i,
+                                || Value::known(*input),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                },
+            )?;
+
+            let chip = PoseidonChip::<PoseidonSpec, 3, 2, 4>::construct(config.hash_config);
+            let digest = chip.hash(
+                layouter.namespace(|| "hash inputs"),
+                inputs.try_into().unwrap(),
+            )?;
+
+            layouter.constrain_instance(digest.cell(), config.instance, 0)
+        }
+    }
+
+    /// Hashes the tag of `domain` followed by 2 inputs and exposes the hash as public input
+    #[derive(Clone)]
+    struct TaggedHashTestCircuit {
+        domain: HashDomain,
+        inputs: [Fp; 2],
+    }
+
+    impl Circuit<Fp> for TaggedHashTestCircuit {
+        type Config = (PoseidonConfig<3, 2, 3>, Column<Advice>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices: [Column<Advice>; 4] = std::array::from_fn(|_| meta.advice_column());
+            let fixed_columns: [Column<Fixed>; 6] = std::array::from_fn(|_| meta.fixed_column());
+
+            for col in &advices {
+                meta.enable_equality(*col);
+            }
+
+            let hash_config = PoseidonChip::<PoseidonSpec, 3, 2, 3>::configure(
+                meta,
+                advices[0..3].try_into().unwrap(),
+                advices[3],
+                fixed_columns[0..3].try_into().unwrap(),
+                fixed_columns[3..6].try_into().unwrap(),
+            );
+
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+
+            (hash_config, advices[0], instance)
+        }
+
+        fn synthesize(
+            &self,
+            (hash_config, advice, instance): Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let inputs = layouter.assign_region(
+                || "assign inputs",
+                |mut region| {
+                    self.inputs
+                        .iter()
+                        .enumerate()
+                        .map(|(i, input)| {
+                            region.assign_advice(
+                                || format!("input {}", i),
+                                advice,
+                                i,
+                                || Value::known(*input),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                },
+            )?;
+
+            let chip = PoseidonChip::<PoseidonSpec, 3, 2, 3>::construct(hash_config);
+            let digest = chip.hash_with_domain(
+                layouter.namespace(|| "hash inputs with domain"),
+                self.domain,
+                &inputs,
+            )?;
+
+            layouter.constrain_instance(digest.cell(), instance, 0)
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct RowsTestConfig<const L: usize> {
+        hash_config: PoseidonConfig<3, 2, L>,
+        advice: Column<Advice>,
+    }
+
+    /// Hashes L inputs if `hash` is set, then assigns `probe_rows` rows on the first state column of the chip, to measure the rows consumed by the hash
+    #[derive(Clone)]
+    struct RowsTestCircuit<const L: usize> {
+        hash: bool,
+        probe_rows: usize,
+    }
+
+    impl<const L: usize> Circuit<Fp> for RowsTestCircuit<L> {
+        type Config = RowsTestConfig<L>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices: [Column<Advice>; 4] = std::array::from_fn(|_| meta.advice_column());
+            let fixed_columns: [Column<Fixed>; 6] = std::array::from_fn(|_| meta.fixed_column());
+
+            for col in &advices {
+                meta.enable_equality(*col);
+            }
+
+            let hash_config = PoseidonChip::<PoseidonSpec, 3, 2, L>::configure(
+                meta,
+                advices[0..3].try_into().unwrap(),
+                advices[3],
+                fixed_columns[0..3].try_into().unwrap(),
+                fixed_columns[3..6].try_into().unwrap(),
+            );
+
+            RowsTestConfig {
+                hash_config,
+                advice: advices[0],
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            if self.hash {
+                let inputs = layouter.assign_region(
+                    || "assign inputs",
+                    |mut region| {
+                        (0..L)
+                            .map(|i| {
+                                region.assign_advice(
+                                    || format!("input {}", i),
+                                    config.advice,
+                                    i,
+                                    || Value::known(Fp::from(i as u64)),
+                                )
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                    },
+                )?;
+
+                let chip = PoseidonChip::<PoseidonSpec, 3, 2, L>::construct(config.hash_config);
+                chip.hash(
+                    layouter.namespace(|| "hash inputs"),
+                    inputs.try_into().unwrap(),
+                )?;
+            }
+
+            layouter.assign_region(
+                || "probe",
+                |mut region| {
+                    for row in 0..self.probe_rows {
+                        region.assign_advice(
+                            || "probe",
+                            config.advice,
+                            row,
+                            || Value::known(Fp::zero()),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    /// Returns the rows consumed by the inputs and the poseidon hash of L inputs, measured with the mock prover
+    fn measured_rows<const L: usize>() -> usize {
+        let k = 9;
+        probe_free_rows(k, |probe_rows| RowsTestCircuit::<L> {
+            hash: false,
+            probe_rows,
+        }) - probe_free_rows(k, |probe_rows| RowsTestCircuit::<L> {
+            hash: true,
+            probe_rows,
+        })
+    }
+
+    #[test]
+    fn test_poseidon_rows_used() {
+        // 8 full rounds and 60 partial rounds take 39 rows per permutation, plus 3 rows to add each chunk of inputs
+        assert_eq!(PoseidonChip::<PoseidonSpec, 3, 2, 6>::rows_used(), 127);
+
+        // the inputs take L rows on the probed column, before the regions of the hash
+        assert_eq!(
+            measured_rows::<2>(),
+            2 + PoseidonChip::<PoseidonSpec, 3, 2, 2>::rows_used()
+        );
+        assert_eq!(
+            measured_rows::<3>(),
+            3 + PoseidonChip::<PoseidonSpec, 3, 2, 3>::rows_used()
+        );
+        assert_eq!(
+            measured_rows::<6>(),
+            6 + PoseidonChip::<PoseidonSpec, 3, 2, 6>::rows_used()
+        );
+    }
+
+    #[test]
+    fn test_poseidon_hash_with_domain() {
+        let inputs = [Fp::from(1), Fp::from(2)];
+        let domains = [HashDomain::Leaf, HashDomain::Node, HashDomain::Root];
+
+        let untagged_hash =
+            poseidon::Hash::<Fp, PoseidonSpec, ConstantLength<2>, 3, 2>::init().hash(inputs);
+        let tagged_hashes = domains.map(|domain| poseidon_tagged(domain, &inputs));
+
+        // the tagged hashes differ from the untagged one and from each other
+        for (i, tagged_hash) in tagged_hashes.iter().enumerate() {
+            assert_ne!(*tagged_hash, untagged_hash);
+            for other_hash in tagged_hashes.iter().skip(i + 1) {
+                assert_ne!(tagged_hash, other_hash);
+            }
+        }
+
+        // the tag is prepended to the inputs
+        assert_eq!(
+            tagged_hashes[1],
+            poseidon::Hash::<Fp, PoseidonSpec, ConstantLength<3>, 3, 2>::init().hash([
+                HashDomain::Node.tag(),
+                inputs[0],
+                inputs[1]
+            ])
+        );
+
+        // the chip matches the native hash of its domain only
+        for (domain, tagged_hash) in domains.into_iter().zip(tagged_hashes) {
+            let circuit = TaggedHashTestCircuit { domain, inputs };
+
+            let valid_prover = MockProver::run(9, &circuit, vec![vec![tagged_hash]]).unwrap();
+            valid_prover.assert_satisfied();
+
+            for other_hash in [untagged_hash].into_iter().chain(
+                tagged_hashes
+                    .into_iter()
+                    .filter(|hash| *hash != tagged_hash),
+            ) {
+                let invalid_prover = MockProver::run(9, &circuit, vec![vec![other_hash]]).unwrap();
+                assert!(invalid_prover.verify().is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_spec_params_validation() {
+        // the compiled-in constants match 8 full rounds and 60 partial rounds
+        let params =
+            PoseidonSpecParams::new(8, 60, ROUND_CONSTANTS.to_vec(), MDS, MDS_INV).unwrap();
+        assert_eq!(params.full_rounds(), 8);
+        assert_eq!(params.partial_rounds(), 60);
+
+        // a short constant vector is rejected with a clear error
+        let error = PoseidonSpecParams::new(8, 60, ROUND_CONSTANTS[..60].to_vec(), MDS, MDS_INV)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            PoseidonSpecError::RoundConstantsLength {
+                expected: 204,
+                found: 180
+            }
+        );
+
+        // the full rounds are split in two halves around the partial rounds
+        let error =
+            PoseidonSpecParams::new(7, 61, ROUND_CONSTANTS.to_vec(), MDS, MDS_INV).unwrap_err();
+        assert_eq!(error, PoseidonSpecError::OddFullRounds(7));
+    }
+
+    #[test]
+    fn test_secure_round_numbers() {
+        // the same round numbers as the width 3 spec of halo2_gadgets, computed with the same script
+        assert_eq!(secure_round_numbers(3), (8, 56));
+
+        // the compiled-in spec has 4 extra partial rounds
+        let params =
+            PoseidonSpecParams::new(8, 60, ROUND_CONSTANTS.to_vec(), MDS, MDS_INV).unwrap();
+        assert_eq!(params.validate_round_numbers(), Ok(()));
+
+        assert_eq!(
+            validate_round_numbers(3, 8, 50),
+            Err(PoseidonSpecError::InsecureRoundNumbers {
+                full_rounds: 8,
+                partial_rounds: 50,
+                min_full_rounds: 8,
+                min_partial_rounds: 56
+            })
+        );
+        assert!(validate_round_numbers(3, 6, 60).is_err());
+    }
+
+    #[test]
+    fn test_poseidon_width_3_rate_2_reference_vector() {
+        // H(1, 2) computed with an independent implementation of the permutation over the constants of poseidon_params.rs,
+        // starting from the state [1, 2, 2 * 2^64], with 4 + 60 + 4 rounds and the x^5 s-box
+        let expected = Fp::from_raw([
+            0xdc4a9704f78c1917,
+            0x54ee074254281310,
+            0x4b2cdf4855250e8a,
+            0x152e960b5c9c8a62,
+        ]);
+
+        let digest = poseidon::Hash::<Fp, PoseidonSpec, ConstantLength<2>, 3, 2>::init()
+            .hash([Fp::from(1), Fp::from(2)]);
+        assert_eq!(digest, expected);
+    }
+
+    #[test]
+    fn test_poseidon_sponge_variable_length() {
+        for len in [1, 2, 3, 5] {
+            let inputs = (1..=len).map(|i| Fp::from(i as u64)).collect::<Vec<_>>();
+            let circuit = SpongeTestCircuit {
+                inputs: inputs.clone(),
+            };
+
+            // the circuit matches the off-circuit hash
+            let digest = poseidon_variable_length(&inputs);
+            let valid_prover = MockProver::run(9, &circuit, vec![vec![digest]]).unwrap();
+            valid_prover.assert_satisfied();
+
+            let invalid_prover =
+                MockProver::run(9, &circuit, vec![vec![digest + Fp::one()]]).unwrap();
+            assert!(invalid_prover.verify().is_err());
+        }
+
+        // the padding separates inputs that differ only by trailing zeros
+        assert_ne!(
+            poseidon_variable_length(&[Fp::from(1)]),
+            poseidon_variable_length(&[Fp::from(1), Fp::zero()])
+        );
+    }
+
+    #[test]
+    fn test_poseidon_test_vectors_match_chip() {
+        let vectors = poseidon_test_vectors(6);
+        assert_eq!(vectors.len(), 6);
+
+        // the vectors are deterministic
+        assert_eq!(vectors, poseidon_test_vectors(6));
+        assert_eq!(vectors[0].1, poseidon_hash_4([Fp::zero(); 4]));
+
+        let dump = dump_poseidon_test_vectors(6);
+        assert_eq!(dump.lines().count(), 6);
+        assert_eq!(
+            dump.lines().next().unwrap(),
+            format!("{0:?},{0:?},{0:?},{0:?} -> {1:?}", Fp::zero(), vectors[0].1)
+        );
+
+        // the in-circuit hash matches every vector, so that a drift of the spec parameters is caught
+        for (inputs, output) in vectors {
+            let circuit = HashTestCircuit { inputs };
+
+            let valid_prover = MockProver::run(9, &circuit, vec![vec![output]]).unwrap();
+            valid_prover.assert_satisfied();
+
+            let invalid_prover =
+                MockProver::run(9, &circuit, vec![vec![output + Fp::one()]]).unwrap();
+            assert!(invalid_prover.verify().is_err());
+        }
+    }
+}
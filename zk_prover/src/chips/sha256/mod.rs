@@ -0,0 +1,2 @@
+pub mod sha256_chip;
+mod tests;
@@ -0,0 +1,648 @@
+//! A SHA-256 chip that hashes a list of field elements, so that the leaves of a Merkle Sum Tree hashed with [crate::merkle_sum_tree::LeafHasher::Sha256] can be recomputed in-circuit.
+//!
+//! Every row holds a 32 bits word decomposed into 32 boolean advice cells, so that the bitwise operations of SHA-256 are low degree polynomials of the bits.
+//! The words of each compression block are laid out 3 rows per round, namely the message schedule word `W[t]` followed by the new `a` and the new `e` of the state, see [Sha256Chip].
+
+use gadgets::util::pow_of_two;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    halo2curves::bn256::Fr as Fp,
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector, VirtualCells},
+    poly::Rotation,
+};
+
+/// The round constants of SHA-256
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// The initial state of SHA-256, namely `a, b, c, d, e, f, g, h`
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Number of rows of a compression block. The 12 rows following a block hold the state at its end, and are the first rows of the following block
+const BLOCK_ROWS: usize = 203;
+/// Offset of the first round within a block, after the rows of the initial state
+const ROUNDS_OFFSET: usize = 12;
+/// Number of bits of the field elements hashed by the chip, so that their 32 bytes representation is unique
+const INPUT_BITS: usize = 248;
+
+/// Offset, within a block, of the row holding the word `j` of the initial state of the block, namely `a, b, c, d, e, f, g, h`.
+/// The initial `a, b, c, d` are laid out as the `a` of the rounds -1 to -4, and the initial `e, f, g, h` as the `e` of the rounds -1 to -4
+fn state_offset(j: usize) -> usize {
+    if j < 4 {
+        ROUNDS_OFFSET + 1 - 3 * (j + 1)
+    } else {
+        ROUNDS_OFFSET + 2 - 3 * (j - 3)
+    }
+}
+
+/// Offset, within a block, of the row holding the message schedule word of round `t`. The new `a` and the new `e` of the round are in the two following rows
+fn round_offset(t: usize) -> usize {
+    ROUNDS_OFFSET + 3 * t
+}
+
+/// Number of compression blocks needed to hash `n_inputs` field elements of 32 bytes, including the padding of the message
+fn n_blocks(n_inputs: usize) -> usize {
+    (32 * n_inputs + 9 + 63) / 64
+}
+
+/// Number of rows of the region assigned by the chip, namely the rows of the blocks, the rows of the final state and the row of the digest
+fn region_rows(n_inputs: usize) -> usize {
+    n_blocks(n_inputs) * BLOCK_ROWS + ROUNDS_OFFSET + 1
+}
+
+/// Returns the message words appended to `n_inputs` field elements by the SHA-256 padding, namely the `1` bit, the zeros and the length of the message in bits
+fn padding_words(n_inputs: usize) -> Vec<u32> {
+    let mut words = vec![0u32; 16 * n_blocks(n_inputs) - 8 * n_inputs];
+    let bit_len = (256 * n_inputs) as u64;
+    let len = words.len();
+
+    words[0] = 0x8000_0000;
+    words[len - 2] = (bit_len >> 32) as u32;
+    words[len - 1] = bit_len as u32;
+    words
+}
+
+/// Splits the big endian representation of a field element into 8 message words
+fn fp_to_words(fp: &Fp) -> Vec<u32> {
+    let mut bytes = fp.to_bytes();
+    bytes.reverse();
+    bytes
+        .chunks(4)
+        .map(|chunk| u32::from_be_bytes(chunk.try_into().unwrap()))
+        .collect()
+}
+
+/// The words and the carries assigned to each row of the region, together with the truncated digest
+struct Sha256Trace {
+    words: Vec<u32>,
+    carries: Vec<u64>,
+    digest: Fp,
+}
+
+/// Runs SHA-256 over the padded `message`, recording the word and the carry of the modular addition of each row
+fn compute_trace(message: &[u32], n_rows: usize) -> Sha256Trace {
+    let mut words = vec![0u32; n_rows];
+    let mut carries = vec![0u64; n_rows];
+
+    let mut state = INITIAL_STATE;
+    for (j, word) in state.iter().enumerate() {
+        words[state_offset(j)] = *word;
+    }
+
+    let mut record = |row: usize, sum: u64| {
+        words[row] = sum as u32;
+        carries[row] = sum >> 32;
+        sum as u32
+    };
+
+    for (block, chunk) in message.chunks(16).enumerate() {
+        let base = block * BLOCK_ROWS;
+
+        let mut w = [0u32; 64];
+        for (t, word) in chunk.iter().enumerate() {
+            w[t] = record(base + round_offset(t), *word as u64);
+        }
+        for t in 16..64 {
+            w[t] = record(
+                base + round_offset(t),
+                small_sigma1(w[t - 2]) as u64
+                    + w[t - 7] as u64
+                    + small_sigma0(w[t - 15]) as u64
+                    + w[t - 16] as u64,
+            );
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (t, (k, w_t)) in ROUND_CONSTANTS.iter().zip(w.iter()).enumerate() {
+            let t1 = h as u64 + big_sigma1(e) as u64 + ch(e, f, g) as u64 + *k as u64 + *w_t as u64;
+            let t2 = big_sigma0(a) as u64 + maj(a, b, c) as u64;
+
+            h = g;
+            g = f;
+            f = e;
+            e = record(base + round_offset(t) + 2, d as u64 + t1);
+            d = c;
+            c = b;
+            b = a;
+            a = record(base + round_offset(t) + 1, t1 + t2);
+        }
+
+        for (j, (word, new_word)) in state.iter_mut().zip([a, b, c, d, e, f, g, h]).enumerate() {
+            *word = record(
+                base + BLOCK_ROWS + state_offset(j),
+                *word as u64 + new_word as u64,
+            );
+        }
+    }
+
+    // the most significant byte of the digest is dropped
+    let digest = state
+        .iter()
+        .enumerate()
+        .map(|(j, word)| {
+            let word = if j == 0 { word & 0x00ff_ffff } else { *word };
+            Fp::from(word as u64) * pow_of_two::<Fp>(32 * (7 - j))
+        })
+        .fold(Fp::zero(), |acc, term| acc + term);
+
+    Sha256Trace {
+        words,
+        carries,
+        digest,
+    }
+}
+
+fn big_sigma0(x: u32) -> u32 {
+    x.rotate_right(2) ^ x.rotate_right(13) ^ x.rotate_right(22)
+}
+
+fn big_sigma1(x: u32) -> u32 {
+    x.rotate_right(6) ^ x.rotate_right(11) ^ x.rotate_right(25)
+}
+
+fn small_sigma0(x: u32) -> u32 {
+    x.rotate_right(7) ^ x.rotate_right(18) ^ (x >> 3)
+}
+
+fn small_sigma1(x: u32) -> u32 {
+    x.rotate_right(17) ^ x.rotate_right(19) ^ (x >> 10)
+}
+
+fn ch(e: u32, f: u32, g: u32) -> u32 {
+    (e & f) ^ (!e & g)
+}
+
+fn maj(a: u32, b: u32, c: u32) -> u32 {
+    (a & b) ^ (a & c) ^ (b & c)
+}
+
+/// Configuration for the SHA-256 Chip
+///
+/// # Fields
+///
+/// * `bits`: Advice columns holding the bits of the word of each row, least significant first
+/// * `carry`: Advice column holding the carry of the modular addition that computes the word of the row
+/// * `value`: Advice column holding the hashed field elements and the digest
+/// * `fixed`: Fixed column holding the round constants, the initial state and the padding words
+/// * `q_bits`: Selector to toggle the boolean constraint of the bits
+/// * `q_schedule`: Selector to toggle the message schedule constraint, from round 16 onwards
+/// * `q_round_a`: Selector to toggle the constraint of the new `a` of a round
+/// * `q_round_e`: Selector to toggle the constraint of the new `e` of a round
+/// * `q_output`: Selector to toggle the addition of the state at the end of a block to the state at its start
+/// * `q_fixed_word`: Selector to toggle the equality between the word of the row and the fixed column
+/// * `q_field_element`: Selector to toggle the composition of a field element from 8 message words
+/// * `q_digest`: Selector to toggle the composition of the truncated digest from the final state
+#[derive(Debug, Clone)]
+pub struct Sha256Config {
+    pub bits: [Column<Advice>; 32],
+    pub carry: Column<Advice>,
+    pub value: Column<Advice>,
+    pub fixed: Column<Fixed>,
+    pub q_bits: Selector,
+    pub q_schedule: Selector,
+    pub q_round_a: Selector,
+    pub q_round_e: Selector,
+    pub q_output: Selector,
+    pub q_fixed_word: Selector,
+    pub q_field_element: Selector,
+    pub q_digest: Selector,
+}
+
+/// Chip that computes the SHA-256 hash of a list of field elements, each encoded as its 32 bytes big endian representation, and truncates the digest to 248 bits.
+///
+/// The region of a message of `B` blocks spans `203 * B + 13` rows. The row `12 + 3 * t` of a block holds `W[t]`, the following rows hold the new `a` and the new `e` of round `t`, so that the rest of the state is found at fixed rotations.
+/// The chip performs the following constraints:
+///
+/// * `bit * (1 - bit) = 0` for every bit of every row (if `q_bits` is toggled).
+/// * `W[t] + carry * 2^32 = σ1(W[t - 2]) + W[t - 7] + σ0(W[t - 15]) + W[t - 16]` with `carry` in `[0, 3]` (if `q_schedule` is toggled).
+/// * `a + carry * 2^32 = h + Σ1(e) + Ch(e, f, g) + K[t] + W[t] + Σ0(a) + Maj(a, b, c)` with `carry` in `[0, 6]` (if `q_round_a` is toggled).
+/// * `e + carry * 2^32 = d + h + Σ1(e) + Ch(e, f, g) + K[t] + W[t]` with `carry` in `[0, 5]` (if `q_round_e` is toggled).
+/// * `H + carry * 2^32 = final + initial` with `carry` in `[0, 1]` for each word of the state (if `q_output` is toggled).
+/// * `word = fixed` (if `q_fixed_word` is toggled).
+/// * `value = W[0] * 2^224 + W[1] * 2^192 + ... + W[7]` with the 8 most significant bits of `W[0]` equal to 0 (if `q_field_element` is toggled).
+/// * `value = (H[0] mod 2^24) * 2^224 + H[1] * 2^192 + ... + H[7]` (if `q_digest` is toggled).
+///
+/// As the words are exactly determined by their bits and the carries are range checked, every sum holds over the integers as well as in the field.
+/// The hashed field elements must be less than 2^248, so that their 32 bytes representation is unique.
+#[derive(Debug, Clone)]
+pub struct Sha256Chip {
+    config: Sha256Config,
+}
+
+/// Returns the bits of the word at `rotation`, least significant first
+fn query_word(
+    meta: &mut VirtualCells<Fp>,
+    bits: &[Column<Advice>; 32],
+    rotation: i32,
+) -> Vec<Expression<Fp>> {
+    bits.iter()
+        .map(|bit| meta.query_advice(*bit, Rotation(rotation)))
+        .collect()
+}
+
+/// Returns the word composed of `bits`, least significant first
+fn pack(bits: &[Expression<Fp>]) -> Expression<Fp> {
+    bits.iter()
+        .enumerate()
+        .fold(Expression::Constant(Fp::zero()), |acc, (i, bit)| {
+            acc + bit.clone() * Expression::Constant(pow_of_two(i))
+        })
+}
+
+fn rotate_right(bits: &[Expression<Fp>], n: usize) -> Vec<Expression<Fp>> {
+    (0..32).map(|i| bits[(i + n) % 32].clone()).collect()
+}
+
+fn shift_right(bits: &[Expression<Fp>], n: usize) -> Vec<Expression<Fp>> {
+    (0..32)
+        .map(|i| {
+            bits.get(i + n)
+                .cloned()
+                .unwrap_or_else(|| Expression::Constant(Fp::zero()))
+        })
+        .collect()
+}
+
+/// Returns the bitwise xor of the three words, namely `x + y + z - 2 * (xy + xz + yz) + 4 * xyz` for each bit
+fn xor3(x: &[Expression<Fp>], y: &[Expression<Fp>], z: &[Expression<Fp>]) -> Vec<Expression<Fp>> {
+    let xor = |a: Expression<Fp>, b: Expression<Fp>| {
+        a.clone() + b.clone() - a * b * Expression::Constant(Fp::from(2))
+    };
+
+    (0..32)
+        .map(|i| xor(xor(x[i].clone(), y[i].clone()), z[i].clone()))
+        .collect()
+}
+
+/// Returns `Σ0(a) + Maj(a, b, c)`
+fn big_sigma0_plus_maj(
+    a: &[Expression<Fp>],
+    b: &[Expression<Fp>],
+    c: &[Expression<Fp>],
+) -> Expression<Fp> {
+    let maj = (0..32)
+        .map(|i| {
+            a[i].clone() * b[i].clone() + a[i].clone() * c[i].clone() + b[i].clone() * c[i].clone()
+                - a[i].clone() * b[i].clone() * c[i].clone() * Expression::Constant(Fp::from(2))
+        })
+        .collect::<Vec<_>>();
+
+    pack(&xor3(
+        &rotate_right(a, 2),
+        &rotate_right(a, 13),
+        &rotate_right(a, 22),
+    )) + pack(&maj)
+}
+
+/// Returns `Σ1(e) + Ch(e, f, g)`
+fn big_sigma1_plus_ch(
+    e: &[Expression<Fp>],
+    f: &[Expression<Fp>],
+    g: &[Expression<Fp>],
+) -> Expression<Fp> {
+    let ch = (0..32)
+        .map(|i| {
+            e[i].clone() * f[i].clone()
+                + (Expression::Constant(Fp::one()) - e[i].clone()) * g[i].clone()
+        })
+        .collect::<Vec<_>>();
+
+    pack(&xor3(
+        &rotate_right(e, 6),
+        &rotate_right(e, 11),
+        &rotate_right(e, 25),
+    )) + pack(&ch)
+}
+
+/// Returns an expression that is 0 only if `value` lies in `[0, max]`
+fn range_check(value: Expression<Fp>, max: u64) -> Expression<Fp> {
+    (1..=max).fold(value.clone(), |acc, i| {
+        acc * (value.clone() - Expression::Constant(Fp::from(i)))
+    })
+}
+
+impl Sha256Chip {
+    pub fn construct(config: Sha256Config) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> Sha256Config {
+        let bits: [Column<Advice>; 32] = std::array::from_fn(|_| meta.advice_column());
+        let carry = meta.advice_column();
+        let value = meta.advice_column();
+        let fixed = meta.fixed_column();
+
+        meta.enable_equality(value);
+
+        let q_bits = meta.selector();
+        let q_schedule = meta.selector();
+        let q_round_a = meta.selector();
+        let q_round_e = meta.selector();
+        let q_output = meta.selector();
+        let q_fixed_word = meta.selector();
+        let q_field_element = meta.selector();
+        let q_digest = meta.selector();
+
+        let two_pow_32 = Expression::Constant(pow_of_two(32));
+
+        meta.create_gate("sha256: bits are boolean", |meta| {
+            let s = meta.query_selector(q_bits);
+            query_word(meta, &bits, 0)
+                .into_iter()
+                .map(|bit| s.clone() * bit.clone() * (Expression::Constant(Fp::one()) - bit))
+                .collect::<Vec<_>>()
+        });
+
+        meta.create_gate("sha256: message schedule", |meta| {
+            let s = meta.query_selector(q_schedule);
+            let w = pack(&query_word(meta, &bits, 0));
+            let carry = meta.query_advice(carry, Rotation::cur());
+
+            let w_2 = query_word(meta, &bits, -6);
+            let w_7 = pack(&query_word(meta, &bits, -21));
+            let w_15 = query_word(meta, &bits, -45);
+            let w_16 = pack(&query_word(meta, &bits, -48));
+
+            let sigma1 = pack(&xor3(
+                &rotate_right(&w_2, 17),
+                &rotate_right(&w_2, 19),
+                &shift_right(&w_2, 10),
+            ));
+            let sigma0 = pack(&xor3(
+                &rotate_right(&w_15, 7),
+                &rotate_right(&w_15, 18),
+                &shift_right(&w_15, 3),
+            ));
+
+            vec![
+                s.clone() * (sigma1 + w_7 + sigma0 + w_16 - w - carry.clone() * two_pow_32.clone()),
+                s * range_check(carry, 3),
+            ]
+        });
+
+        meta.create_gate("sha256: round a", |meta| {
+            let s = meta.query_selector(q_round_a);
+            let new_a = pack(&query_word(meta, &bits, 0));
+            let carry = meta.query_advice(carry, Rotation::cur());
+            let k = meta.query_fixed(fixed, Rotation::cur());
+
+            let w = pack(&query_word(meta, &bits, -1));
+            let a = query_word(meta, &bits, -3);
+            let b = query_word(meta, &bits, -6);
+            let c = query_word(meta, &bits, -9);
+            let e = query_word(meta, &bits, -2);
+            let f = query_word(meta, &bits, -5);
+            let g = query_word(meta, &bits, -8);
+            let h = pack(&query_word(meta, &bits, -11));
+
+            let sum = h + big_sigma1_plus_ch(&e, &f, &g) + k + w + big_sigma0_plus_maj(&a, &b, &c);
+
+            vec![
+                s.clone() * (sum - new_a - carry.clone() * two_pow_32.clone()),
+                s * range_check(carry, 6),
+            ]
+        });
+
+        meta.create_gate("sha256: round e", |meta| {
+            let s = meta.query_selector(q_round_e);
+            let new_e = pack(&query_word(meta, &bits, 0));
+            let carry = meta.query_advice(carry, Rotation::cur());
+            let k = meta.query_fixed(fixed, Rotation::cur());
+
+            let w = pack(&query_word(meta, &bits, -2));
+            let d = pack(&query_word(meta, &bits, -13));
+            let e = query_word(meta, &bits, -3);
+            let f = query_word(meta, &bits, -6);
+            let g = query_word(meta, &bits, -9);
+            let h = pack(&query_word(meta, &bits, -12));
+
+            let sum = d + h + big_sigma1_plus_ch(&e, &f, &g) + k + w;
+
+            vec![
+                s.clone() * (sum - new_e - carry.clone() * two_pow_32.clone()),
+                s * range_check(carry, 5),
+            ]
+        });
+
+        meta.create_gate("sha256: block output", |meta| {
+            let s = meta.query_selector(q_output);
+            let output = pack(&query_word(meta, &bits, 0));
+            let carry = meta.query_advice(carry, Rotation::cur());
+
+            let last = pack(&query_word(meta, &bits, -11));
+            let initial = pack(&query_word(meta, &bits, -(BLOCK_ROWS as i32)));
+
+            vec![
+                s.clone() * (last + initial - output - carry.clone() * two_pow_32.clone()),
+                s * range_check(carry, 1),
+            ]
+        });
+
+        meta.create_gate("sha256: fixed word", |meta| {
+            let s = meta.query_selector(q_fixed_word);
+            let word = pack(&query_word(meta, &bits, 0));
+            let fixed = meta.query_fixed(fixed, Rotation::cur());
+
+            vec![s * (word - fixed)]
+        });
+
+        meta.create_gate("sha256: field element", |meta| {
+            let s = meta.query_selector(q_field_element);
+            let value = meta.query_advice(value, Rotation::cur());
+
+            // the 8 words of the field element are the message words of 8 consecutive rounds, the most significant first
+            let words = (0..8)
+                .map(|k| query_word(meta, &bits, -3 * (7 - k)))
+                .collect::<Vec<_>>();
+
+            let composed = words
+                .iter()
+                .enumerate()
+                .fold(Expression::Constant(Fp::zero()), |acc, (k, word)| {
+                    acc + pack(word) * Expression::Constant(pow_of_two(32 * (7 - k)))
+                });
+
+            std::iter::once(s.clone() * (composed - value))
+                .chain(
+                    words[0][INPUT_BITS - 224..]
+                        .iter()
+                        .map(|bit| s.clone() * bit.clone()),
+                )
+                .collect::<Vec<_>>()
+        });
+
+        meta.create_gate("sha256: digest", |meta| {
+            let s = meta.query_selector(q_digest);
+            let value = meta.query_advice(value, Rotation::cur());
+
+            let composed = (0..8).fold(Expression::Constant(Fp::zero()), |acc, j| {
+                let word = query_word(meta, &bits, state_offset(j) as i32 - ROUNDS_OFFSET as i32);
+                // the most significant byte of the digest is dropped
+                let word = if j == 0 {
+                    pack(&word[..INPUT_BITS - 224])
+                } else {
+                    pack(&word)
+                };
+                acc + word * Expression::Constant(pow_of_two(32 * (7 - j)))
+            });
+
+            vec![s * (composed - value)]
+        });
+
+        Sha256Config {
+            bits,
+            carry,
+            value,
+            fixed,
+            q_bits,
+            q_schedule,
+            q_round_a,
+            q_round_e,
+            q_output,
+            q_fixed_word,
+            q_field_element,
+            q_digest,
+        }
+    }
+
+    /// Hashes the field elements of `inputs`, each of which must be less than 2^248, and returns the digest truncated to 248 bits.
+    /// The inputs are copied into the region of the chip, so they are constrained to be the ones hashed.
+    pub fn hash_field_elements(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        inputs: &[AssignedCell<Fp, Fp>],
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let config = &self.config;
+
+        let n_inputs = inputs.len();
+        let n_blocks = n_blocks(n_inputs);
+        let n_rows = region_rows(n_inputs);
+        let padding = padding_words(n_inputs);
+
+        let trace = inputs
+            .iter()
+            .map(|input| input.value().copied())
+            .collect::<Value<Vec<Fp>>>()
+            .map(|inputs| {
+                let message = inputs
+                    .iter()
+                    .flat_map(fp_to_words)
+                    .chain(padding.iter().copied())
+                    .collect::<Vec<_>>();
+                compute_trace(&message, n_rows)
+            });
+
+        // the row of the message word with index `m`, namely the word of round `m % 16` of block `m / 16`
+        let message_row = |m: usize| (m / 16) * BLOCK_ROWS + round_offset(m % 16);
+
+        layouter.assign_region(
+            || "sha256",
+            |mut region| {
+                for row in 0..n_rows {
+                    config.q_bits.enable(&mut region, row)?;
+
+                    for (i, bit) in config.bits.iter().enumerate() {
+                        region.assign_advice(
+                            || format!("row {}: bit {}", row, i),
+                            *bit,
+                            row,
+                            || {
+                                trace
+                                    .as_ref()
+                                    .map(|trace| Fp::from(((trace.words[row] >> i) & 1) as u64))
+                            },
+                        )?;
+                    }
+
+                    region.assign_advice(
+                        || format!("row {}: carry", row),
+                        config.carry,
+                        row,
+                        || trace.as_ref().map(|trace| Fp::from(trace.carries[row])),
+                    )?;
+                }
+
+                // the initial state of the first block is fixed
+                for (j, word) in INITIAL_STATE.iter().enumerate() {
+                    let row = state_offset(j);
+                    region.assign_fixed(
+                        || format!("initial state {}", j),
+                        config.fixed,
+                        row,
+                        || Value::known(Fp::from(*word as u64)),
+                    )?;
+                    config.q_fixed_word.enable(&mut region, row)?;
+                }
+
+                for block in 0..n_blocks {
+                    let base = block * BLOCK_ROWS;
+
+                    for (t, k) in ROUND_CONSTANTS.iter().enumerate() {
+                        let row = base + round_offset(t);
+
+                        if t >= 16 {
+                            config.q_schedule.enable(&mut region, row)?;
+                        }
+
+                        config.q_round_a.enable(&mut region, row + 1)?;
+                        config.q_round_e.enable(&mut region, row + 2)?;
+
+                        for offset in [1, 2] {
+                            region.assign_fixed(
+                                || format!("block {}: round {}: constant", block, t),
+                                config.fixed,
+                                row + offset,
+                                || Value::known(Fp::from(*k as u64)),
+                            )?;
+                        }
+                    }
+
+                    // the state at the end of the block is the initial state of the following block
+                    for j in 0..8 {
+                        config
+                            .q_output
+                            .enable(&mut region, base + BLOCK_ROWS + state_offset(j))?;
+                    }
+                }
+
+                // each input is composed from 8 message words at the row of its last word
+                for (k, input) in inputs.iter().enumerate() {
+                    let row = message_row(8 * k + 7);
+                    input.copy_advice(|| format!("input {}", k), &mut region, config.value, row)?;
+                    config.q_field_element.enable(&mut region, row)?;
+                }
+
+                for (i, word) in padding.iter().enumerate() {
+                    let row = message_row(8 * n_inputs + i);
+                    region.assign_fixed(
+                        || format!("padding word {}", i),
+                        config.fixed,
+                        row,
+                        || Value::known(Fp::from(*word as u64)),
+                    )?;
+                    config.q_fixed_word.enable(&mut region, row)?;
+                }
+
+                let digest_row = n_blocks * BLOCK_ROWS + ROUNDS_OFFSET;
+                config.q_digest.enable(&mut region, digest_row)?;
+
+                region.assign_advice(
+                    || "digest",
+                    config.value,
+                    digest_row,
+                    || trace.as_ref().map(|trace| trace.digest),
+                )
+            },
+        )
+    }
+}
@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod test {
+    use crate::chips::sha256::sha256_chip::{Sha256Chip, Sha256Config};
+    use crate::merkle_sum_tree::sha256_field_elements;
+    use gadgets::util::pow_of_two;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance},
+    };
+
+    #[derive(Clone)]
+    struct Sha256TestConfig {
+        input: Column<Advice>,
+        instance: Column<Instance>,
+        sha256_config: Sha256Config,
+    }
+
+    #[derive(Default)]
+    struct Sha256TestCircuit {
+        inputs: Vec<Fp>,
+    }
+
+    impl Circuit<Fp> for Sha256TestCircuit {
+        type Config = Sha256TestConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                inputs: vec![Fp::zero(); self.inputs.len()],
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let input = meta.advice_column();
+            meta.enable_equality(input);
+
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+
+            Sha256TestConfig {
+                input,
+                instance,
+                sha256_config: Sha256Chip::configure(meta),
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let inputs = layouter.assign_region(
+                || "assign inputs",
+                |mut region| {
+                    self.inputs
+                        .iter()
+                        .enumerate()
+                        .map(|(i, input)| {
+                            region.assign_advice(
+                                || format!("input {}", i),
+                                config.input,
+                                i,
+                                || Value::known(*input),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                },
+            )?;
+
+            let chip = Sha256Chip::construct(config.sha256_config);
+            let digest = chip.hash_field_elements(layouter.namespace(|| "sha256"), &inputs)?;
+
+            layouter.constrain_instance(digest.cell(), config.instance, 0)
+        }
+    }
+
+    #[test]
+    fn test_sha256_chip() {
+        let k = 10;
+
+        // 1 input fits a single block, 2 and 3 inputs need two blocks and 4 inputs need three blocks
+        for n_inputs in 1..=4 {
+            let inputs = (0..n_inputs)
+                .map(|i| Fp::from(0x1234_5678_9abc_def0u64) * Fp::from(i as u64 + 1))
+                .collect::<Vec<_>>();
+            let expected = sha256_field_elements(&inputs);
+
+            let circuit = Sha256TestCircuit {
+                inputs: inputs.clone(),
+            };
+
+            let valid_prover = MockProver::run(k, &circuit, vec![vec![expected]]).unwrap();
+            assert_eq!(valid_prover.verify(), Ok(()));
+
+            let invalid_prover =
+                MockProver::run(k, &circuit, vec![vec![expected + Fp::one()]]).unwrap();
+            assert!(invalid_prover.verify().is_err());
+        }
+    }
+
+    #[test]
+    fn test_sha256_chip_rejects_input_out_of_range() {
+        let k = 10;
+
+        // an input of 2^248 doesn't fit in 248 bits, so its most significant byte is not zero
+        let inputs = vec![pow_of_two::<Fp>(248), Fp::one()];
+        let expected = sha256_field_elements(&inputs);
+
+        let circuit = Sha256TestCircuit { inputs };
+
+        let invalid_prover = MockProver::run(k, &circuit, vec![vec![expected]]).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+}
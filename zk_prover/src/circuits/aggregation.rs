@@ -1,9 +1,11 @@
 use crate::chips::aggregation::WrappedAggregationConfig;
+use crate::circuits::merkle_sum_tree::ROOT_HASH_INDEX;
+use crate::circuits::utils::{full_prover, full_verifier};
 use ecc::integer::rns::Rns;
 use halo2_proofs::{
-    circuit::{Layouter, SimpleFloorPlanner},
-    halo2curves::bn256::{Bn256, Fq, Fr as Fp},
-    plonk::{Circuit, ConstraintSystem, Error},
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner},
+    halo2curves::bn256::{Bn256, Fq, Fr as Fp, G1Affine},
+    plonk::{Circuit, ConstraintSystem, Error, ProvingKey, VerifyingKey},
     poly::kzg::commitment::ParamsKZG,
 };
 use itertools::Itertools;
@@ -79,13 +81,27 @@ impl<const N_SNARK: usize> Circuit<Fp> for WrappedAggregationCircuit<N_SNARK> {
         config: Self::Config,
         mut layouter: impl Layouter<Fp>,
     ) -> Result<(), Error> {
+        self.assign_aggregation(&config, &mut layouter)?;
+
+        Ok(())
+    }
+}
+
+impl<const N_SNARK: usize> WrappedAggregationCircuit<N_SNARK> {
+    /// Verifies the input snarks, exposing the accumulator limbs and the instances of the input snarks as public inputs.
+    /// Returns the assigned instances of the input snarks
+    fn assign_aggregation(
+        &self,
+        config: &WrappedAggregationConfig<N_SNARK>,
+        layouter: &mut impl Layouter<Fp>,
+    ) -> Result<Vec<Vec<AssignedCell<Fp, Fp>>>, Error> {
         let main_gate = config.aggregation_config.main_gate();
         let range_chip = config.aggregation_config.range_chip();
-        range_chip.load_table(&mut layouter)?;
+        range_chip.load_table(layouter)?;
 
         let (accumulator_limbs, prev_instances) = self
             .aggregation_circuit
-            .aggregation_region(config.clone().aggregation_config, &mut layouter)?;
+            .aggregation_region(config.clone().aggregation_config, layouter)?;
 
         for (row, limb) in accumulator_limbs.into_iter().enumerate() {
             main_gate.expose_public(layouter.namespace(|| ""), limb, row)?;
@@ -98,7 +114,7 @@ impl<const N_SNARK: usize> Circuit<Fp> for WrappedAggregationCircuit<N_SNARK> {
             }
         }
 
-        Ok(())
+        Ok(prev_instances)
     }
 }
 
@@ -123,3 +139,135 @@ impl<const N_SNARK: usize> CircuitExt<Fp> for WrappedAggregationCircuit<N_SNARK>
         instances
     }
 }
+
+/// Circuit aggregating `N_SNARK` inclusion proofs of users of the same merkle sum tree, see [aggregate].
+///
+/// It verifies the input snarks as [WrappedAggregationCircuit], with the same public inputs, and additionally constrains the root hash of every input snark to be equal to the root hash of the first one, so that the aggregated proof commits to a single root.
+#[derive(Clone)]
+pub struct InclusionAggregationCircuit<const N_SNARK: usize> {
+    inner: WrappedAggregationCircuit<N_SNARK>,
+}
+
+impl<const N_SNARK: usize> InclusionAggregationCircuit<N_SNARK> {
+    /// Creates a new InclusionAggregationCircuit from the snarks of [crate::circuits::merkle_sum_tree::MstInclusionCircuit]
+    pub fn new(params: &ParamsKZG<Bn256>, snarks: impl IntoIterator<Item = Snark>) -> Self {
+        Self {
+            inner: WrappedAggregationCircuit::new(params, snarks),
+        }
+    }
+}
+
+impl<const N_SNARK: usize> Circuit<Fp> for InclusionAggregationCircuit<N_SNARK> {
+    type Config = WrappedAggregationConfig<N_SNARK>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            inner: self.inner.without_witnesses(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        WrappedAggregationCircuit::<N_SNARK>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let prev_instances = self.inner.assign_aggregation(&config, &mut layouter)?;
+
+        // the root hash of every input snark must be the root hash of the first one
+        layouter.assign_region(
+            || "enforce common root hash",
+            |mut region| {
+                for prev_instance in prev_instances.iter().skip(1) {
+                    region.constrain_equal(
+                        prev_instances[0][ROOT_HASH_INDEX].cell(),
+                        prev_instance[ROOT_HASH_INDEX].cell(),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+impl<const N_SNARK: usize> CircuitExt<Fp> for InclusionAggregationCircuit<N_SNARK> {
+    /// Returns the number of instances of the circuit, the same as [WrappedAggregationCircuit]
+    fn num_instance(&self) -> Vec<usize> {
+        self.inner.num_instance()
+    }
+
+    /// Returns the instances of the circuit, the same as [WrappedAggregationCircuit]
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        self.inner.instances()
+    }
+}
+
+/// A single proof of the inclusion of many users in the same merkle sum tree, as returned by [aggregate]
+///
+/// # Fields
+///
+/// * `root_hash`: The root hash of the merkle sum tree, common to all the aggregated inclusion proofs
+/// * `instances`: The public inputs of the aggregated proof, namely the public inputs of each inclusion proof followed by the accumulator limbs
+/// * `proof`: The aggregated proof
+pub struct AggregatedProof {
+    pub root_hash: Fp,
+    pub instances: Vec<Vec<Fp>>,
+    pub proof: Vec<u8>,
+}
+
+impl AggregatedProof {
+    /// Verifies the aggregated proof with the params and the verifying key of the aggregation circuit.
+    /// The verifying key must come from a trusted source, such as the key published by the CEX, rather than from the prover of the proof.
+    pub fn verify(&self, params: &ParamsKZG<Bn256>, vk: &VerifyingKey<G1Affine>) -> bool {
+        full_verifier(params, vk, self.proof.clone(), self.instances.clone())
+    }
+}
+
+/// Aggregates `N_SNARK` snarks of [crate::circuits::merkle_sum_tree::MstInclusionCircuit] into a single proof, with the params and the proving key `pk` of the [InclusionAggregationCircuit].
+/// The proving key is generated once, for example with [snark_verifier_sdk::gen_pk] on the circuit returned by [Circuit::without_witnesses], and reused for every aggregation.
+/// The snarks are generated for example with [snark_verifier_sdk::halo2::gen_snark_shplonk] using params downsized from `params`.
+/// Returns an error if there are no snarks, if the number of snarks is not `N_SNARK` or if the snarks don't share the same root hash, as the aggregated proof must commit to a single root.
+pub fn aggregate<const N_SNARK: usize>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    proofs: Vec<Snark>,
+) -> Result<AggregatedProof, &'static str> {
+    if proofs.is_empty() {
+        return Err("At least one proof is required to be aggregated");
+    }
+
+    if proofs.len() != N_SNARK {
+        return Err("The number of proofs doesn't match the number of aggregated snarks");
+    }
+
+    let root_hashes = proofs
+        .iter()
+        .map(|snark| {
+            snark
+                .instances
+                .first()
+                .and_then(|instance| instance.get(ROOT_HASH_INDEX))
+                .copied()
+                .ok_or("The proof doesn't expose a root hash")
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let root_hash = root_hashes[0];
+    if root_hashes.iter().any(|hash| *hash != root_hash) {
+        return Err("The proofs don't share the same root hash");
+    }
+
+    let circuit = InclusionAggregationCircuit::<N_SNARK>::new(params, proofs);
+    let instances = circuit.instances();
+    let proof = full_prover(params, pk, circuit, instances.clone());
+
+    Ok(AggregatedProof {
+        root_hash,
+        instances,
+        proof,
+    })
+}
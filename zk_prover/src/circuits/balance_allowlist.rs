@@ -0,0 +1,140 @@
+use crate::circuits::merkle_sum_tree::MstInclusionCircuit;
+use crate::circuits::merkle_sum_tree_any_root::MstInclusionAnyRootConfig;
+use crate::merkle_sum_tree::MerkleSumTree;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for verifying inclusion of a leaf_hash inside a merkle sum tree with a given root, together with the balance of the leaf for a given asset being one of a set of allowed values, for example staking tiers.
+///
+/// The balance is kept private. The circuit enforces `(balance - allowed_0) * (balance - allowed_1) * ... * (balance - allowed_N) = 0`, namely that the balance is equal to at least one of the allowed values.
+///
+/// The asset is not a public input: its index selects at synthesis time which balance cell of the leaf is wired to the allowlist check, so it is part of the circuit layout rather than of the witness.
+/// As a consequence the proving and verifying keys are specific to the asset: a key must be generated for each asset, with a circuit initialized with that asset index, and a verifier must pick the verifying key of the asset whose balance is checked.
+/// A proof generated for an asset doesn't verify against the verifying key of another asset.
+///
+/// # Type Parameters
+///
+/// * `LEVELS`: The number of levels of the merkle sum tree
+/// * `L`: The length of the hasher input, namely 2 + (2 * N_ASSETS)
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+/// * `N_ALLOWED`: The number of allowed values
+///
+/// # Fields
+///
+/// * `inclusion`: The inclusion circuit witness, namely the leaf and the merkle path
+/// * `asset`: The index of the asset whose balance must be one of the allowed values. It determines the verifying key of the circuit, see above
+/// * `allowed_values`: The committed set of allowed values
+#[derive(Clone)]
+pub struct BalanceAllowlistCircuit<
+    const LEVELS: usize,
+    const L: usize,
+    const N_ASSETS: usize,
+    const N_ALLOWED: usize,
+> {
+    pub inclusion: MstInclusionCircuit<LEVELS, L, N_ASSETS>,
+    pub asset: usize,
+    pub allowed_values: [Fp; N_ALLOWED],
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize, const N_ALLOWED: usize>
+    CircuitExt<Fp> for BalanceAllowlistCircuit<LEVELS, L, N_ASSETS, N_ALLOWED>
+{
+    /// Returns the number of public inputs of the circuit. It is 2 + N_ALLOWED, namely the leaf hash, the root hash and the allowed values
+    fn num_instance(&self) -> Vec<usize> {
+        vec![2 + N_ALLOWED]
+    }
+
+    /// Returns the values of the public inputs of the circuit. The first values are the leaf hash to be verified inclusion of and the root hash of the merkle sum tree, followed by the allowed values
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        let mut instance = vec![self.inclusion.leaf_hash, self.inclusion.root_hash];
+        instance.extend(self.allowed_values);
+        vec![instance]
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize, const N_ALLOWED: usize>
+    BalanceAllowlistCircuit<LEVELS, L, N_ASSETS, N_ALLOWED>
+{
+    pub fn init_empty() -> Self {
+        Self {
+            inclusion: MstInclusionCircuit::init_empty(),
+            asset: 0,
+            allowed_values: [Fp::zero(); N_ALLOWED],
+        }
+    }
+
+    /// Initializes the circuit with the merkle sum tree, the index of the user of which the inclusion is to be verified, the index of the asset and the set of allowed values.
+    /// The keys used to prove and verify the circuit must be generated for the same `asset`.
+    pub fn init(
+        merkle_sum_tree: MerkleSumTree<N_ASSETS>,
+        user_index: usize,
+        asset: usize,
+        allowed_values: [Fp; N_ALLOWED],
+    ) -> Self {
+        assert!(asset < N_ASSETS, "The asset index is out of range");
+
+        Self {
+            inclusion: MstInclusionCircuit::init(merkle_sum_tree, user_index),
+            asset,
+            allowed_values,
+        }
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize, const N_ALLOWED: usize> Circuit<Fp>
+    for BalanceAllowlistCircuit<LEVELS, L, N_ASSETS, N_ALLOWED>
+{
+    type Config = MstInclusionAnyRootConfig<L, N_ASSETS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        // the asset determines the layout of the circuit, so it is preserved
+        Self {
+            asset: self.asset,
+            ..Self::init_empty()
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MstInclusionAnyRootConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        assert!(
+            N_ALLOWED > 0,
+            "The circuit requires at least one allowed value"
+        );
+
+        let (leaf_hash, leaf_balances, root_hash) = self
+            .inclusion
+            .assign_inclusion_path(&config.mst_inclusion_config, &mut layouter)?;
+
+        // expose the leaf hash and the root hash as public input
+        config.mst_inclusion_config.expose_public(
+            layouter.namespace(|| "public leaf hash"),
+            &leaf_hash,
+            0,
+        )?;
+        config.mst_inclusion_config.expose_public(
+            layouter.namespace(|| "public root hash"),
+            &root_hash,
+            1,
+        )?;
+
+        // the balance is kept private, it is only enforced to be one of the allowed values exposed right after the root hash
+        config.enforce_root_in_candidates(
+            layouter.namespace(|| "enforce balance in allowed values"),
+            &leaf_balances[self.asset],
+            2,
+            N_ALLOWED,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use crate::chips::less_than::less_than_vertical::{LtVerticalChip, LtVerticalInstruction};
+use crate::circuits::merkle_sum_tree::{MstEntryConfig, MstInclusionCircuit, MstInclusionConfig};
+use crate::circuits::solvency::SolvencyConfig;
+use crate::merkle_sum_tree::{big_int_to_fp, MerkleSumTree};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for proving that the balances of a user didn't decrease between two snapshots of the merkle sum tree, namely `balance_B >= balance_A` for each asset.
+///
+/// The leaf hashes of both snapshots are recomputed in-circuit from the public username and the private balances, so that the two inclusion proofs are bound to the same user.
+/// Only the username and the two roots are exposed as public inputs, the balances are kept private.
+///
+/// # Type Parameters
+///
+/// * `LEVELS`: The number of levels of the merkle sum trees
+/// * `L`: The length of the hasher input of the middle nodes, namely 2 + (2 * N_ASSETS)
+/// * `L_ENTRY`: The length of the hasher input of the leaves, namely 1 + N_ASSETS
+/// * `N_ASSETS`: The number of assets of each entry
+/// * `N_BYTES`: Range in which the balances should lie
+///
+/// # Fields
+///
+/// * `username`: The username of the user, converted to a field element
+/// * `snapshot_a`: The inclusion witness of the user in the earlier snapshot
+/// * `snapshot_b`: The inclusion witness of the user in the later snapshot
+#[derive(Clone)]
+pub struct BalanceIncreaseCircuit<
+    const LEVELS: usize,
+    const L: usize,
+    const L_ENTRY: usize,
+    const N_ASSETS: usize,
+    const N_BYTES: usize,
+> {
+    pub username: Fp,
+    pub snapshot_a: MstInclusionCircuit<LEVELS, L, N_ASSETS>,
+    pub snapshot_b: MstInclusionCircuit<LEVELS, L, N_ASSETS>,
+}
+
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const L_ENTRY: usize,
+        const N_ASSETS: usize,
+        const N_BYTES: usize,
+    > CircuitExt<Fp> for BalanceIncreaseCircuit<LEVELS, L, L_ENTRY, N_ASSETS, N_BYTES>
+{
+    /// Returns the number of public inputs of the circuit. It is 3, namely the username and the roots of the two snapshots
+    fn num_instance(&self) -> Vec<usize> {
+        vec![3]
+    }
+
+    /// Returns the values of the public inputs of the circuit, namely the username, the root of the snapshot A and the root of the snapshot B
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        vec![vec![
+            self.username,
+            self.snapshot_a.root_hash,
+            self.snapshot_b.root_hash,
+        ]]
+    }
+}
+
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const L_ENTRY: usize,
+        const N_ASSETS: usize,
+        const N_BYTES: usize,
+    > BalanceIncreaseCircuit<LEVELS, L, L_ENTRY, N_ASSETS, N_BYTES>
+{
+    pub fn init_empty() -> Self {
+        assert_eq!(N_ASSETS + 1, L_ENTRY);
+
+        Self {
+            username: Fp::zero(),
+            snapshot_a: MstInclusionCircuit::init_empty(),
+            snapshot_b: MstInclusionCircuit::init_empty(),
+        }
+    }
+
+    /// Initializes the circuit with the two snapshots and the index of the user in each of them
+    pub fn init(
+        snapshot_a: MerkleSumTree<N_ASSETS>,
+        user_index_a: usize,
+        snapshot_b: MerkleSumTree<N_ASSETS>,
+        user_index_b: usize,
+    ) -> Self {
+        assert_eq!(N_ASSETS + 1, L_ENTRY);
+
+        let username = big_int_to_fp(snapshot_a.entries()[user_index_a].username_to_big_int());
+        assert_eq!(
+            username,
+            big_int_to_fp(snapshot_b.entries()[user_index_b].username_to_big_int()),
+            "The user must be the same in both snapshots"
+        );
+
+        Self {
+            username,
+            snapshot_a: MstInclusionCircuit::init(snapshot_a, user_index_a),
+            snapshot_b: MstInclusionCircuit::init(snapshot_b, user_index_b),
+        }
+    }
+}
+
+/// Configuration for the balance increase circuit
+///
+/// # Fields
+///
+/// * `mst_inclusion_config`: Configuration for the inclusion paths of both snapshots. Its instance column is used to store the public inputs
+/// * `entry_config`: Configuration for recomputing the leaf hashes. Its advice columns are used to assign the username
+/// * `solvency_config`: Configuration for the less than or equal comparison between the balances of the two snapshots, sharing the instance column of `mst_inclusion_config`
+#[derive(Debug, Clone)]
+pub struct BalanceIncreaseConfig<
+    const L: usize,
+    const L_ENTRY: usize,
+    const N_ASSETS: usize,
+    const N_BYTES: usize,
+> {
+    pub mst_inclusion_config: MstInclusionConfig<L, N_ASSETS>,
+    pub entry_config: MstEntryConfig<L_ENTRY>,
+    pub solvency_config: SolvencyConfig<L, N_ASSETS, N_BYTES>,
+}
+
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const L_ENTRY: usize,
+        const N_ASSETS: usize,
+        const N_BYTES: usize,
+    > Circuit<Fp> for BalanceIncreaseCircuit<LEVELS, L, L_ENTRY, N_ASSETS, N_BYTES>
+{
+    type Config = BalanceIncreaseConfig<L, L_ENTRY, N_ASSETS, N_BYTES>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let mst_inclusion_config = MstInclusionConfig::<L, N_ASSETS>::configure(meta);
+
+        let entry_config = MstEntryConfig::<L_ENTRY>::configure(meta);
+
+        let solvency_config = SolvencyConfig::<L, N_ASSETS, N_BYTES>::configure_with_instance(
+            meta,
+            mst_inclusion_config.instances[0],
+        );
+
+        BalanceIncreaseConfig {
+            mst_inclusion_config,
+            entry_config,
+            solvency_config,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let (leaf_hash_a, balances_a, root_hash_a) = self
+            .snapshot_a
+            .assign_inclusion_path(&config.mst_inclusion_config, &mut layouter)?;
+
+        let (leaf_hash_b, balances_b, root_hash_b) = self
+            .snapshot_b
+            .assign_inclusion_path(&config.mst_inclusion_config, &mut layouter)?;
+
+        // the username is public, the same cell is used to recompute the leaf hash of both snapshots
+        let username = layouter.assign_region(
+            || "assign username",
+            |mut region| {
+                region.assign_advice_from_instance(
+                    || "copy username from instance column",
+                    config.mst_inclusion_config.instances[0],
+                    0,
+                    config.entry_config.advice[0],
+                    0,
+                )
+            },
+        )?;
+
+        config.entry_config.enforce_leaf_hash(
+            layouter.namespace(|| "snapshot A: enforce leaf hash"),
+            &username,
+            &balances_a,
+            &leaf_hash_a,
+        )?;
+
+        config.entry_config.enforce_leaf_hash(
+            layouter.namespace(|| "snapshot B: enforce leaf hash"),
+            &username,
+            &balances_b,
+            &leaf_hash_b,
+        )?;
+
+        let lt_chip = LtVerticalChip::<N_BYTES>::construct(config.solvency_config.lt_config);
+        lt_chip.load(&mut layouter)?;
+
+        for asset in 0..N_ASSETS {
+            config.solvency_config.enforce_cell_less_than_or_equal(
+                layouter.namespace(|| format!("asset {}: enforce balance increase", asset)),
+                &balances_a[asset],
+                &balances_b[asset],
+                &lt_chip,
+            )?;
+        }
+
+        // expose the roots of the two snapshots as public input
+        config.mst_inclusion_config.expose_public(
+            layouter.namespace(|| "public root hash of snapshot A"),
+            &root_hash_a,
+            1,
+        )?;
+        config.mst_inclusion_config.expose_public(
+            layouter.namespace(|| "public root hash of snapshot B"),
+            &root_hash_b,
+            2,
+        )?;
+
+        Ok(())
+    }
+}
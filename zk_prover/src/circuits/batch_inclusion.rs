@@ -0,0 +1,111 @@
+use crate::circuits::merkle_sum_tree::{MstInclusionCircuit, MstInclusionConfig};
+use crate::merkle_sum_tree::MerkleSumTree;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for verifying the inclusion of `N_PROOFS` users inside merkle sum trees in a single proof, so that the proving key setup and the fixed columns are amortized across the users.
+///
+/// The inclusion paths are assigned one after the other with the same configuration as [MstInclusionCircuit]. The public inputs are laid out in a single instance column as follows:
+///
+/// | row | public input |
+/// | --- | --- |
+/// | 2 * i | leaf hash of the i-th user |
+/// | 2 * i + 1 | root hash of the tree of the i-th user |
+///
+/// # Type Parameters
+///
+/// * `LEVELS`: The number of levels of the merkle sum tree
+/// * `L`: The length of the hasher input, namely 2 + (2 * N_ASSETS)
+/// * `N_ASSETS`: The number of assets of each entry
+/// * `N_PROOFS`: The number of inclusion paths verified by the circuit
+///
+/// # Fields
+///
+/// * `inclusions`: The inclusion witnesses of the users, in the order of their public inputs
+#[derive(Clone)]
+pub struct BatchMstInclusionCircuit<
+    const LEVELS: usize,
+    const L: usize,
+    const N_ASSETS: usize,
+    const N_PROOFS: usize,
+> {
+    pub inclusions: [MstInclusionCircuit<LEVELS, L, N_ASSETS>; N_PROOFS],
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize, const N_PROOFS: usize>
+    CircuitExt<Fp> for BatchMstInclusionCircuit<LEVELS, L, N_ASSETS, N_PROOFS>
+{
+    /// Returns the number of public inputs of the circuit. It is 2 * N_PROOFS, namely a leaf hash and a root hash for each user
+    fn num_instance(&self) -> Vec<usize> {
+        vec![2 * N_PROOFS]
+    }
+
+    /// Returns the values of the public inputs of the circuit, namely the leaf hash and the root hash of each user in order
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        vec![self
+            .inclusions
+            .iter()
+            .flat_map(|inclusion| [inclusion.leaf_hash, inclusion.root_hash])
+            .collect()]
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize, const N_PROOFS: usize>
+    BatchMstInclusionCircuit<LEVELS, L, N_ASSETS, N_PROOFS>
+{
+    pub fn init_empty() -> Self {
+        Self {
+            inclusions: std::array::from_fn(|_| MstInclusionCircuit::init_empty()),
+        }
+    }
+
+    /// Initializes the circuit with the merkle sum tree and the indexes of the users of which the inclusion is to be verified
+    pub fn init(merkle_sum_tree: MerkleSumTree<N_ASSETS>, user_indexes: [usize; N_PROOFS]) -> Self {
+        Self {
+            inclusions: user_indexes
+                .map(|user_index| MstInclusionCircuit::init(merkle_sum_tree.clone(), user_index)),
+        }
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize, const N_PROOFS: usize> Circuit<Fp>
+    for BatchMstInclusionCircuit<LEVELS, L, N_ASSETS, N_PROOFS>
+{
+    type Config = MstInclusionConfig<L, N_ASSETS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MstInclusionConfig::<L, N_ASSETS>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        for (proof_index, inclusion) in self.inclusions.iter().enumerate() {
+            let (leaf_hash, _, root_hash) =
+                inclusion.assign_inclusion_path(&config, &mut layouter)?;
+
+            // expose the leaf hash and the root hash of the user at the rows of the proof, as public input
+            config.expose_public(
+                layouter.namespace(|| format!("proof {}: public leaf hash", proof_index)),
+                &leaf_hash,
+                2 * proof_index,
+            )?;
+            config.expose_public(
+                layouter.namespace(|| format!("proof {}: public root hash", proof_index)),
+                &root_hash,
+                2 * proof_index + 1,
+            )?;
+        }
+
+        Ok(())
+    }
+}
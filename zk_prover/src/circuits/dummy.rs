@@ -0,0 +1,67 @@
+//! Dummy prover and verifier with the same signatures as [crate::circuits::utils::full_prover] and [crate::circuits::utils::full_verifier], compiled only with the `dummy-prover` feature, so that integration tests can exercise the plumbing around the proofs, such as their serialization and storage, without paying the cost of real proving.
+//! The dummy prover checks the circuit with the [MockProver] and returns a sentinel proof, which is only accepted by the dummy verifier and never by [crate::circuits::utils::full_verifier].
+//! The feature can't be enabled in release builds, namely without debug assertions.
+#[cfg(not(debug_assertions))]
+compile_error!(
+    "The `dummy-prover` feature is meant for testing only and can't be enabled in release builds"
+);
+
+use crate::circuits::utils::verifying_key_fingerprint;
+use halo2_proofs::{
+    dev::MockProver,
+    halo2curves::bn256::{Bn256, Fr as Fp, G1Affine},
+    plonk::{Circuit, ProvingKey, VerifyingKey},
+    poly::{commitment::Params, kzg::commitment::ParamsKZG},
+};
+use sha2::{Digest, Sha256};
+use snark_verifier_sdk::CircuitExt;
+
+/// Prefix of every dummy proof, so that it can't be mistaken for a real one
+pub const DUMMY_PROOF_TAG: &[u8] = b"summa-solvency dummy proof";
+
+/// Checks that `circuit` is satisfied by `public_inputs` with the [MockProver] on `2^k` rows, where `k` is the one of `params`, and returns a sentinel proof bound to the verifying key of `pk` and to `public_inputs`.
+/// As for a real proof, a circuit that is not satisfied doesn't make the prover fail but yields a proof that the verifier rejects.
+pub fn full_prover<C: Circuit<Fp> + CircuitExt<Fp>>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    public_inputs: Vec<Vec<Fp>>,
+) -> Vec<u8> {
+    let is_satisfied = MockProver::run(params.k(), &circuit, public_inputs.clone())
+        .expect("prover should not fail")
+        .verify()
+        .is_ok();
+
+    sentinel_proof(pk.get_vk(), &public_inputs, is_satisfied)
+}
+
+/// Verifies a proof generated by [full_prover], namely checks that the circuit was satisfied and that the proof is bound to `vk` and to `public_inputs`
+pub fn full_verifier(
+    _params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    proof: Vec<u8>,
+    public_inputs: Vec<Vec<Fp>>,
+) -> bool {
+    proof == sentinel_proof(vk, &public_inputs, true)
+}
+
+/// Returns the tag of the dummy proofs followed by whether the circuit is satisfied, the fingerprint of `vk` and the SHA-256 hash of `public_inputs`
+fn sentinel_proof(
+    vk: &VerifyingKey<G1Affine>,
+    public_inputs: &[Vec<Fp>],
+    is_satisfied: bool,
+) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    for column in public_inputs {
+        hasher.update((column.len() as u64).to_le_bytes());
+        for value in column {
+            hasher.update(value.to_bytes());
+        }
+    }
+
+    let mut proof = DUMMY_PROOF_TAG.to_vec();
+    proof.push(is_satisfied as u8);
+    proof.extend_from_slice(verifying_key_fingerprint(vk).as_bytes());
+    proof.extend_from_slice(&hasher.finalize());
+    proof
+}
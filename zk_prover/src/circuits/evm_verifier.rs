@@ -0,0 +1,26 @@
+use crate::circuits::solvency::SolvencyCircuit;
+use halo2_proofs::halo2curves::bn256::{Bn256, G1Affine};
+use halo2_proofs::plonk::VerifyingKey;
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use snark_verifier_sdk::evm::gen_evm_verifier_shplonk;
+use snark_verifier_sdk::CircuitExt;
+use std::path::Path;
+
+/// Generates the EVM verifier of the proofs of the [SolvencyCircuit] created with `params` and `vk`, for example by [snark_verifier_sdk::evm::gen_evm_proof_shplonk].
+///
+/// The verifier is generated by snark-verifier as Yul source, which is written to `yul_path` if given, and compiled with `solc`. Returns the deployment bytecode of the verifier contract.
+/// The deployed contract is called with the public inputs of the circuit, namely the root hash, the sum of the assets of the CEX for each asset and the number of assets, each encoded as a 32 bytes word, followed by the proof. The call reverts if the proof is invalid.
+pub fn gen_solvency_evm_verifier<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    yul_path: Option<&Path>,
+) -> Vec<u8> {
+    let num_instance = SolvencyCircuit::<L, N_ASSETS, N_BYTES>::init_empty().num_instance();
+
+    gen_evm_verifier_shplonk::<SolvencyCircuit<L, N_ASSETS, N_BYTES>>(
+        params,
+        vk,
+        num_instance,
+        yul_path,
+    )
+}
@@ -0,0 +1,125 @@
+use halo2_proofs::arithmetic::eval_polynomial;
+use halo2_proofs::halo2curves::{
+    bn256::{Bn256, Fr as Fp, G1Affine},
+    ff::Field,
+    group::Curve,
+};
+use halo2_proofs::poly::{
+    commitment::{Blind, Params, ParamsProver, Prover, Verifier},
+    kzg::{
+        commitment::ParamsKZG,
+        multiopen::{ProverSHPLONK, VerifierSHPLONK},
+        strategy::SingleStrategy,
+    },
+    Coeff, EvaluationDomain, Polynomial, ProverQuery, VerificationStrategy, VerifierQuery,
+};
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, Transcript, TranscriptReadBuffer,
+    TranscriptWriterBuffer,
+};
+use rand::rngs::OsRng;
+
+/// KZG vector commitment to the balances of the users, alternative to the Merkle Sum Tree as inclusion backend.
+///
+/// The balance of the user at index `i` is the evaluation of the committed polynomial at `omega^i`, where `omega` is the generator of the evaluation domain of size 2^k.
+/// The commitment is a single curve point and the opening proof of a balance has constant size, no matter the number of users.
+/// The same params used for the circuits, see [crate::circuits::utils::generate_setup_params], are reused for the commitment.
+///
+/// # Fields
+///
+/// * `domain`: The evaluation domain of size 2^k over which the balances are interpolated
+/// * `polynomial`: The polynomial interpolating the balances, in coefficient form
+/// * `blind`: The blinding factor of the commitment, so that the commitment doesn't leak the balances
+/// * `commitment`: The KZG commitment to the polynomial
+#[derive(Debug)]
+pub struct KzgBalanceCommitment {
+    domain: EvaluationDomain<Fp>,
+    polynomial: Polynomial<Fp, Coeff>,
+    blind: Blind<Fp>,
+    commitment: G1Affine,
+}
+
+impl KzgBalanceCommitment {
+    /// Commits to `balances` with the blinding factor `blind`. The number of balances must not exceed the size of the evaluation domain, namely 2^k.
+    /// The blind must be sampled at random by the caller, for example with `Blind::new(&mut OsRng)`: the constant `Blind::default()` is rejected since the commitment would be a deterministic function of the balances.
+    pub fn commit(
+        params: &ParamsKZG<Bn256>,
+        balances: &[Fp],
+        blind: Blind<Fp>,
+    ) -> Result<Self, &'static str> {
+        if blind == Blind::default() {
+            return Err("The blind of the commitment must be random, not the default one");
+        }
+
+        let domain = EvaluationDomain::<Fp>::new(1, params.k());
+
+        let mut lagrange = domain.empty_lagrange();
+        if balances.len() > lagrange.len() {
+            return Err("The number of balances exceeds the size of the evaluation domain");
+        }
+        for (value, balance) in lagrange.iter_mut().zip(balances.iter()) {
+            *value = *balance;
+        }
+
+        let commitment = params.commit_lagrange(&lagrange, blind).to_affine();
+        let polynomial = domain.lagrange_to_coeff(lagrange);
+
+        Ok(Self {
+            domain,
+            polynomial,
+            blind,
+            commitment,
+        })
+    }
+
+    pub fn commitment(&self) -> &G1Affine {
+        &self.commitment
+    }
+
+    /// Returns the point of the evaluation domain at which the balance of the user at `index` is committed, namely `omega^index`
+    pub fn point(&self, index: usize) -> Fp {
+        self.domain.get_omega().pow_vartime([index as u64])
+    }
+
+    /// Opens the commitment at the balance of the user at `index`. Returns the balance together with the opening proof
+    pub fn open(&self, params: &ParamsKZG<Bn256>, index: usize) -> (Fp, Vec<u8>) {
+        let point = self.point(index);
+        let balance = eval_polynomial(&self.polynomial, point);
+
+        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+        transcript
+            .common_point(self.commitment)
+            .expect("the commitment should be added to the transcript");
+
+        let queries = [ProverQuery::new(point, &self.polynomial, self.blind)];
+
+        ProverSHPLONK::<Bn256>::new(params)
+            .create_proof(OsRng, &mut transcript, queries)
+            .expect("the opening proof should be created");
+
+        (balance, transcript.finalize())
+    }
+}
+
+/// Verifies that `balance` is the balance committed in `commitment` at the evaluation point `point`, namely [KzgBalanceCommitment::point] of the index of the user.
+pub fn verify_balance_opening(
+    params: &ParamsKZG<Bn256>,
+    commitment: &G1Affine,
+    point: Fp,
+    balance: Fp,
+    proof: &[u8],
+) -> bool {
+    let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof);
+    if transcript.common_point(*commitment).is_err() {
+        return false;
+    }
+
+    let queries = [VerifierQuery::new_commitment(commitment, point, balance)];
+
+    let verifier = VerifierSHPLONK::<Bn256>::new(params);
+    let strategy = SingleStrategy::new(params);
+
+    strategy
+        .process(|msm| verifier.verify_proof(&mut transcript, queries, msm))
+        .is_ok()
+}
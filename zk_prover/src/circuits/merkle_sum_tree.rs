@@ -2,13 +2,15 @@ use crate::chips::merkle_sum_tree::{MerkleSumTreeChip, MerkleSumTreeConfig};
 use crate::chips::overflow::overflow_check::{OverflowCheckConfig, OverflowChip};
 use crate::chips::poseidon::hash::{PoseidonChip, PoseidonConfig};
 use crate::chips::poseidon::poseidon_spec::PoseidonSpec;
-use crate::merkle_sum_tree::{big_int_to_fp, MerkleSumTree, MOD_BITS};
-use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner};
+use crate::merkle_sum_tree::{big_int_to_fp, MerkleProof, MerkleSumTree, MOD_BITS};
+use crate::timing;
+use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value};
 use halo2_proofs::halo2curves::bn256::Fr as Fp;
 use halo2_proofs::plonk::{
     Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Instance, Selector,
 };
 use snark_verifier_sdk::CircuitExt;
+use zeroize::Zeroize;
 
 const MAX_BITS: u8 = 8;
 
@@ -19,6 +21,9 @@ const MAX_BITS: u8 = 8;
 /// * `LEVELS`: The number of levels of the merkle sum tree
 /// * `L`: The length of the hasher input, namely 2 + (2 * N_ASSETS)
 /// * `N_ASSETS`: The number of assets for which the solvency is verified.
+/// * `N_INSTANCE_COLUMNS`: The number of instance columns across which the public inputs are laid out. Defaults to 1
+/// * `SUM_BITS`: Range in which every computed sum, up to the root balances, is constrained to lie, for example 64 or 84. Must be a multiple of 8. Defaults to MOD_BITS
+/// * `LEAF_BITS`: Range in which each balance of the leaf should lie when the leaf range check is enabled, for example 64. Must be a multiple of 8. Defaults to MOD_BITS
 ///
 /// # Fields
 ///
@@ -26,32 +31,62 @@ const MAX_BITS: u8 = 8;
 /// * `leaf_balances`: The balances of the leaf to be verified inclusion of. The length of this vector is N_ASSETS
 /// * `path_element_hashes`: The hashes of the path elements from the leaf to root. The length of this vector is LEVELS
 /// * `path_element_balances`: The balances of the path elements from the leaf to the root. The length of this vector is LEVELS
-/// * `path_indices`: The boolean indices of the path elements from the leaf to the root. 0 indicates that the element is on the right to the path, 1 indicates that the element is on the left to the path. The length of this vector is LEVELS
+/// * `path_indices`: The boolean indices of the path elements from the leaf to the root. false indicates that the element is on the right to the path, true indicates that the element is on the left to the path. The length of this vector is LEVELS
+/// * `root_hash`: The root hash of the merkle sum tree
+/// * `extra_public_inputs`: Application-specific values, such as a request nonce, bound into the proof as trailing public inputs. They don't take part in the inclusion logic
+/// * `root_balances_public`: Whether the balances of the root, namely the total liabilities of the CEX per asset, are exposed as public inputs right after the root hash, see [PublicInputs]
+/// * `leaf_range_check`: Whether the balances of the leaf are explicitly constrained to be less than 2^LEAF_BITS, so that a near-modulus balance acting as a negative one is rejected even if the tree has no levels
+/// * `sum_range_check`: Whether the computed sum of each level, up to the root balances, is constrained to be less than 2^SUM_BITS. Enabled by default, it can only be disabled explicitly with [MstInclusionCircuit::without_sum_range_check]
 #[derive(Clone)]
-pub struct MstInclusionCircuit<const LEVELS: usize, const L: usize, const N_ASSETS: usize> {
+pub struct MstInclusionCircuit<
+    const LEVELS: usize,
+    const L: usize,
+    const N_ASSETS: usize,
+    const N_INSTANCE_COLUMNS: usize = 1,
+    const SUM_BITS: usize = MOD_BITS,
+    const LEAF_BITS: usize = MOD_BITS,
+> {
     pub leaf_hash: Fp,
     pub leaf_balances: Vec<Fp>,
     pub path_element_hashes: Vec<Fp>,
     pub path_element_balances: Vec<[Fp; N_ASSETS]>,
-    pub path_indices: Vec<Fp>,
+    pub path_indices: Vec<bool>,
     pub root_hash: Fp,
+    pub extra_public_inputs: Vec<Fp>,
+    pub root_balances_public: bool,
+    pub leaf_range_check: bool,
+    pub sum_range_check: bool,
 }
 
-impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize> CircuitExt<Fp>
-    for MstInclusionCircuit<LEVELS, L, N_ASSETS>
-{   
-    /// Returns the number of public inputs of the circuit. It is 2, namely the laef hash to be verified inclusion of and the root hash of the merkle sum tree.
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const N_ASSETS: usize,
+        const N_INSTANCE_COLUMNS: usize,
+        const SUM_BITS: usize,
+        const LEAF_BITS: usize,
+    > CircuitExt<Fp>
+    for MstInclusionCircuit<LEVELS, L, N_ASSETS, N_INSTANCE_COLUMNS, SUM_BITS, LEAF_BITS>
+{
+    /// Returns the number of public inputs of the circuit per instance column. There are 2 public inputs in total, namely the laef hash to be verified inclusion of and the root hash of the merkle sum tree, plus the root balances if they are public and the extra public inputs.
     fn num_instance(&self) -> Vec<usize> {
-        vec![2]
+        self.instances().iter().map(|column| column.len()).collect()
     }
-    /// Returns the values of the public inputs of the circuit. Namely the leaf hash to be verified inclusion of and the root hash of the merkle sum tree.
+    /// Returns the values of the public inputs of the circuit, see [PublicInputs] for their order.
+    /// The public inputs are laid out across the instance columns in row-major order, see [MstInclusionConfig::expose_public].
     fn instances(&self) -> Vec<Vec<Fp>> {
-        vec![vec![self.leaf_hash, self.root_hash]]
+        self.public_inputs().to_instances(N_INSTANCE_COLUMNS)
     }
 }
 
-impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize>
-    MstInclusionCircuit<LEVELS, L, N_ASSETS>
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const N_ASSETS: usize,
+        const N_INSTANCE_COLUMNS: usize,
+        const SUM_BITS: usize,
+        const LEAF_BITS: usize,
+    > MstInclusionCircuit<LEVELS, L, N_ASSETS, N_INSTANCE_COLUMNS, SUM_BITS, LEAF_BITS>
 {
     pub fn init_empty() -> Self {
         assert_eq!((N_ASSETS * 2) + 2, L);
@@ -61,23 +96,32 @@ impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize>
             leaf_balances: vec![Fp::zero(); N_ASSETS],
             path_element_hashes: vec![Fp::zero(); LEVELS],
             path_element_balances: vec![[Fp::zero(); N_ASSETS]; LEVELS],
-            path_indices: vec![Fp::zero(); LEVELS],
+            path_indices: vec![false; LEVELS],
             root_hash: Fp::zero(),
+            extra_public_inputs: vec![],
+            root_balances_public: false,
+            leaf_range_check: false,
+            sum_range_check: true,
         }
     }
 
 /// Initializes the circuit with the merkle sum tree and the index of the user of which the inclusion is to be verified.
     pub fn init(merkle_sum_tree: MerkleSumTree<N_ASSETS>, user_index: usize) -> Self {
-        assert_eq!((N_ASSETS * 2) + 2, L);
-
         let proof = merkle_sum_tree.generate_proof(user_index).unwrap();
 
+        Self::init_from_proof(proof)
+    }
+
+    /// Initializes the circuit with a merkle proof, for example one spanning the two levels of a [crate::merkle_sum_tree::HierarchicalMerkleSumTree].
+    pub fn init_from_proof(proof: MerkleProof<N_ASSETS>) -> Self {
+        assert_eq!((N_ASSETS * 2) + 2, L);
+
         assert_eq!(proof.path_indices.len(), LEVELS);
         assert_eq!(proof.sibling_hashes.len(), LEVELS);
         assert_eq!(proof.sibling_sums.len(), LEVELS);
 
         Self {
-            leaf_hash: proof.entry.compute_leaf().hash,
+            leaf_hash: proof.entry.compute_leaf_with(proof.leaf_hasher).hash,
             leaf_balances: proof
                 .entry
                 .balances()
@@ -88,117 +132,131 @@ impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize>
             path_element_balances: proof.sibling_sums,
             path_indices: proof.path_indices,
             root_hash: proof.root_hash,
+            extra_public_inputs: vec![],
+            root_balances_public: false,
+            leaf_range_check: false,
+            sum_range_check: true,
         }
     }
-}
 
-/// Configuration for the Mst Inclusion circuit
-/// # Type Parameters
-///
-/// * `L`: The length of the hasher input, namely 2 + (2 * N_ASSETS)
-/// * `N_ASSETS`: The number of assets for which the solvency is verified.
-///
-/// # Fields
-///
-/// * `merkle_sum_tree_config`: Configuration for the merkle sum tree
-/// * `poseidon_config`: Configuration for the poseidon hash function with WIDTH = 3 and RATE = 2
-/// * `overflow_check_config`: Configuration for the overflow check chip
-/// * `instance`: Instance column used to store the public inputs
-
-#[derive(Debug, Clone)]
-pub struct MstInclusionConfig<const L: usize, const N_ASSETS: usize> {
-    pub merkle_sum_tree_config: MerkleSumTreeConfig,
-    pub poseidon_config: PoseidonConfig<3, 2, L>,
-    pub overflow_check_config: OverflowCheckConfig<MAX_BITS, MOD_BITS>,
-    pub instance: Column<Instance>,
-}
-
-impl<const L: usize, const N_ASSETS: usize> MstInclusionConfig<L, N_ASSETS> {
-    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> Self {
-        // the max number of advices columns needed is WIDTH + 1 given requirement of the poseidon config with WIDTH 3
-        let advices: [Column<Advice>; 4] = std::array::from_fn(|_| meta.advice_column());
-
-        // we need 2 * WIDTH fixed columns for poseidon config with WIDTH 3 + 1 for the overflow check chip
-        let fixed_columns: [Column<Fixed>; 7] = std::array::from_fn(|_| meta.fixed_column());
-
-        // we also need 2 selectors for the MerkleSumTreeChip and 1 for the overflow check chip
-        let selectors: [Selector; 3] = std::array::from_fn(|_| meta.selector());
-
-        // we need 1 complex selector for the lookup check
-        let toggle_lookup_check = meta.complex_selector();
-
-        // in fact, the poseidon config requires #WIDTH advice columns for state and 1 for partial_sbox, 3 fixed columns for rc_a and 3 for rc_b
-        let poseidon_config = PoseidonChip::<PoseidonSpec, 3, 2, L>::configure(
-            meta,
-            advices[0..3].try_into().unwrap(),
-            advices[3],
-            fixed_columns[0..3].try_into().unwrap(),
-            fixed_columns[3..6].try_into().unwrap(),
-        );
-
-        // enable permutation for all the advice columns
-        for col in &advices {
-            meta.enable_equality(*col);
-        }
+    /// Replaces the witness of the circuit with `proof`, for example the proof regenerated after the tree has been updated.
+    /// The extra public inputs, the public root balances and the range checks are kept, so the circuit keeps the same layout and the same proving key can be reused.
+    pub fn update_witness(&mut self, proof: MerkleProof<N_ASSETS>) {
+        // the balances of the previous witness are cleared before it is replaced, see the Zeroize implementation for the limitations
+        self.zeroize();
+
+        *self = Self {
+            extra_public_inputs: std::mem::take(&mut self.extra_public_inputs),
+            root_balances_public: self.root_balances_public,
+            leaf_range_check: self.leaf_range_check,
+            sum_range_check: self.sum_range_check,
+            ..Self::init_from_proof(proof)
+        };
+    }
 
-        // the configuration of merkle_sum_tree will always require 3 advices, no matter the number of assets
-        let merkle_sum_tree_config = MerkleSumTreeChip::<N_ASSETS>::configure(
-            meta,
-            advices[0..3].try_into().unwrap(),
-            selectors[0..2].try_into().unwrap(),
-        );
+    /// Binds `extra_public_inputs` into the proof. They are exposed as the last public inputs, see [PublicInputs].
+    pub fn with_extra_public_inputs(mut self, extra_public_inputs: Vec<Fp>) -> Self {
+        self.extra_public_inputs = extra_public_inputs;
+        self
+    }
 
-        let overflow_check_config = OverflowChip::<MAX_BITS, MOD_BITS>::configure(
-            meta,
-            advices[0],
-            advices[1],
-            fixed_columns[6],
-            selectors[2],
-            toggle_lookup_check,
-        );
+    /// Exposes the balances of the root, namely the total liabilities of the CEX per asset, as public inputs, see the `root_balances_public` field
+    pub fn with_public_root_balances(mut self) -> Self {
+        self.root_balances_public = true;
+        self
+    }
 
-        let instance = meta.instance_column();
-        meta.enable_equality(instance);
+    /// Returns the balances of the root, computed off-circuit as the sum of the leaf balances and the balances of the path elements
+    pub fn root_balances(&self) -> [Fp; N_ASSETS] {
+        std::array::from_fn(|asset| {
+            self.path_element_balances
+                .iter()
+                .fold(self.leaf_balances[asset], |sum, balances| {
+                    sum + balances[asset]
+                })
+        })
+    }
 
-        Self {
-            merkle_sum_tree_config,
-            poseidon_config,
-            overflow_check_config,
-            instance,
-        }
+    /// Returns the public inputs of the circuit
+    pub fn public_inputs(&self) -> PublicInputs<N_ASSETS> {
+        PublicInputs::new(
+            self.leaf_hash,
+            self.root_hash,
+            self.root_balances_public.then(|| self.root_balances()),
+            self.extra_public_inputs.clone(),
+        )
     }
 
-    /// Enforce copy constraint check between input cell and instance column at row passed as input
-    pub fn expose_public(
-        &self,
-        mut layouter: impl Layouter<Fp>,
-        cell: &AssignedCell<Fp, Fp>,
-        row: usize,
-    ) -> Result<(), Error> {
-        layouter.constrain_instance(cell.cell(), self.instance, row)
+    /// Enables the range check of the balances of the leaf, see the `leaf_range_check` field
+    pub fn with_leaf_range_check(mut self) -> Self {
+        self.leaf_range_check = true;
+        self
     }
-}
 
-impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize> Circuit<Fp>
-    for MstInclusionCircuit<LEVELS, L, N_ASSETS>
-{
-    type Config = MstInclusionConfig<L, N_ASSETS>;
-    type FloorPlanner = SimpleFloorPlanner;
+    /// Disables the range check of the computed sums, see the `sum_range_check` field.
+    /// The computed sums are then only bounded by the range check of the balances of the next level, and the root balances are not bounded at all.
+    pub fn without_sum_range_check(mut self) -> Self {
+        self.sum_range_check = false;
+        self
+    }
 
-    fn without_witnesses(&self) -> Self {
-        Self::init_empty()
+    /// Assigns the leaf and walks the merkle path up to the root, hashing and summing the balances at each level.
+    /// Returns the assigned leaf hash, the assigned leaf balances and the computed root hash so that the caller can decide what to expose as public input.
+    pub fn assign_inclusion_path(
+        &self,
+        config: &MstInclusionConfig<L, N_ASSETS, N_INSTANCE_COLUMNS, SUM_BITS, LEAF_BITS>,
+        layouter: &mut impl Layouter<Fp>,
+    ) -> Result<
+        (
+            AssignedCell<Fp, Fp>,
+            Vec<AssignedCell<Fp, Fp>>,
+            AssignedCell<Fp, Fp>,
+        ),
+        Error,
+    > {
+        let (leaf_hash, leaf_balances, root_hash, _) =
+            self.assign_inclusion_path_with_root_balances(config, layouter)?;
+
+        Ok((leaf_hash, leaf_balances, root_hash))
     }
 
-    /// Configures the circuit
-    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
-        MstInclusionConfig::<L, N_ASSETS>::configure(meta)
+    /// Same as [Self::assign_inclusion_path], but also returns the computed root balances
+    #[allow(clippy::type_complexity)]
+    pub fn assign_inclusion_path_with_root_balances(
+        &self,
+        config: &MstInclusionConfig<L, N_ASSETS, N_INSTANCE_COLUMNS, SUM_BITS, LEAF_BITS>,
+        layouter: &mut impl Layouter<Fp>,
+    ) -> Result<
+        (
+            AssignedCell<Fp, Fp>,
+            Vec<AssignedCell<Fp, Fp>>,
+            AssignedCell<Fp, Fp>,
+            Vec<AssignedCell<Fp, Fp>>,
+        ),
+        Error,
+    > {
+        let (leaf_hash, leaf_balances, root_hash, root_balances, _) =
+            self.assign_inclusion_path_with_swap_bits(config, layouter)?;
+
+        Ok((leaf_hash, leaf_balances, root_hash, root_balances))
     }
 
-    fn synthesize(
+    /// Same as [Self::assign_inclusion_path_with_root_balances], but also returns the assigned swap bits from the leaf level to the root level, namely the bits of the index of the leaf from the least significant one
+    #[allow(clippy::type_complexity)]
+    pub fn assign_inclusion_path_with_swap_bits(
         &self,
-        config: Self::Config,
-        mut layouter: impl Layouter<Fp>,
-    ) -> Result<(), Error> {
+        config: &MstInclusionConfig<L, N_ASSETS, N_INSTANCE_COLUMNS, SUM_BITS, LEAF_BITS>,
+        layouter: &mut impl Layouter<Fp>,
+    ) -> Result<
+        (
+            AssignedCell<Fp, Fp>,
+            Vec<AssignedCell<Fp, Fp>>,
+            AssignedCell<Fp, Fp>,
+            Vec<AssignedCell<Fp, Fp>>,
+            Vec<AssignedCell<Fp, Fp>>,
+        ),
+        Error,
+    > {
         // build auxiliary chips
         let merkle_sum_tree_chip =
             MerkleSumTreeChip::<N_ASSETS>::construct(config.merkle_sum_tree_config.clone());
@@ -206,22 +264,45 @@ impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize> Circuit<Fp>
             PoseidonChip::<PoseidonSpec, 3, 2, L>::construct(config.poseidon_config.clone());
         let overflow_check_chip =
             OverflowChip::<MAX_BITS, MOD_BITS>::construct(config.overflow_check_config.clone());
+        let sum_overflow_check_chip =
+            OverflowChip::<MAX_BITS, SUM_BITS>::construct(config.sum_overflow_check_config.clone());
 
         // Assign the leaf hash and the leaf balances
-        let (mut current_hash, mut current_balances) = merkle_sum_tree_chip
-            .assign_entry_hash_and_balances(
-                layouter.namespace(|| "assign leaf hash and balances"),
-                self.leaf_hash,
-                &self.leaf_balances,
-            )?;
+        let (leaf_hash, leaf_balances) = merkle_sum_tree_chip.assign_entry_hash_and_balances(
+            layouter.namespace(|| "assign leaf hash and balances"),
+            self.leaf_hash,
+            &self.leaf_balances,
+        )?;
 
-        // expose the first current hash, namely the leaf hash, as public input
-        config.expose_public(layouter.namespace(|| "public leaf hash"), &current_hash, 0)?;
+        let mut current_hash = leaf_hash.clone();
+        let mut current_balances = leaf_balances.clone();
 
         // load overflow check chip
-        overflow_check_chip.load(&mut layouter)?;
+        overflow_check_chip.load(layouter)?;
+
+        // The balances of the leaf are constrained to be less than 2^LEAF_BITS before entering any sum
+        if self.leaf_range_check {
+            for (asset, balance) in leaf_balances.iter().enumerate() {
+                let namespace =
+                    layouter.namespace(|| format!("asset {}: range check leaf balance", asset));
+                match &config.leaf_overflow_check_config {
+                    Some(leaf_overflow_check_config) => {
+                        OverflowChip::<MAX_BITS, LEAF_BITS>::construct(
+                            leaf_overflow_check_config.clone(),
+                        )
+                        .assign(namespace, balance)?;
+                    }
+                    // with LEAF_BITS = MOD_BITS the range of the leaf is the one of the overflow check chip
+                    None => overflow_check_chip.assign(namespace, balance)?,
+                }
+            }
+        }
+
+        let mut swap_bits = vec![];
 
         for level in 0..LEVELS {
+            let _timer = timing::start(timing::MERKLE_LEVEL);
+            let _span = tracing::debug_span!("merkle prove layer", level).entered();
             let namespace_prefix = format!("level {}", level);
 
             // For each level assign the index to the circuit
@@ -278,6 +359,19 @@ impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize> Circuit<Fp>
                     &right_balance,
                 )?;
 
+                // The computed sum is constrained to be less than 2^SUM_BITS
+                if self.sum_range_check {
+                    sum_overflow_check_chip.assign(
+                        layouter.namespace(|| {
+                            format!(
+                                "{}: asset {}: range check computed sum",
+                                namespace_prefix, asset
+                            )
+                        }),
+                        &next_balance,
+                    )?;
+                }
+
                 next_balances.push(next_balance);
                 left_balances.push(left_balance);
                 right_balances.push(right_balance);
@@ -302,13 +396,481 @@ impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize> Circuit<Fp>
                 layouter.namespace(|| format!("{}: perform poseidon hash", namespace_prefix)),
                 hash_input,
             )?;
+            tracing::debug!(hash = ?computed_hash.value(), "assigned merkle prove layer");
 
             current_balances = next_balances;
             current_hash = computed_hash;
+            swap_bits.push(swap_bit_level);
+        }
+
+        Ok((
+            leaf_hash,
+            leaf_balances,
+            current_hash,
+            current_balances,
+            swap_bits,
+        ))
+    }
+}
+
+/// The balances of the leaf and of the path elements are the balances of the users of the tree, so they can be cleared once the proof is generated.
+///
+/// This is a best effort: [Fp] doesn't implement [Zeroize] and is `Copy`, so the balances are overwritten with plain writes, which the compiler may elide if the circuit is not read afterwards, and the copies made while synthesizing the circuit or generating the proof are not reached.
+/// The circuit is not zeroized on drop, so that it can still be built with the struct update syntax: it must be zeroized explicitly. Use [crate::circuits::secret::SecretBalances] to hold balances that must be wiped.
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const N_ASSETS: usize,
+        const N_INSTANCE_COLUMNS: usize,
+        const SUM_BITS: usize,
+        const LEAF_BITS: usize,
+    > Zeroize
+    for MstInclusionCircuit<LEVELS, L, N_ASSETS, N_INSTANCE_COLUMNS, SUM_BITS, LEAF_BITS>
+{
+    fn zeroize(&mut self) {
+        for balance in self
+            .leaf_balances
+            .iter_mut()
+            .chain(self.path_element_balances.iter_mut().flatten())
+        {
+            *balance = Fp::zero();
+        }
+    }
+}
+
+/// Position of the leaf hash among the public inputs of the [MstInclusionCircuit], whatever the number of assets
+pub const LEAF_HASH_INDEX: usize = 0;
+/// Position of the root hash among the public inputs of the [MstInclusionCircuit], whatever the number of assets
+pub const ROOT_HASH_INDEX: usize = 1;
+
+/// The public inputs of the [MstInclusionCircuit], with named accessors so that a verifier doesn't need to know their positions.
+///
+/// They are exposed in the following order:
+/// * the leaf hash, at position [PublicInputs::LEAF_HASH_INDEX]
+/// * the root hash, at position [PublicInputs::ROOT_HASH_INDEX]
+/// * if the root balances are public, the root balance of each asset, namely the total liabilities of the CEX for that asset
+/// * the extra public inputs
+///
+/// The positions are laid out across the instance columns in row-major order, see [MstInclusionConfig::expose_public].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicInputs<const N_ASSETS: usize> {
+    leaf_hash: Fp,
+    root_hash: Fp,
+    root_balances: Option<[Fp; N_ASSETS]>,
+    extra_public_inputs: Vec<Fp>,
+}
+
+impl<const N_ASSETS: usize> PublicInputs<N_ASSETS> {
+    pub const LEAF_HASH_INDEX: usize = LEAF_HASH_INDEX;
+    pub const ROOT_HASH_INDEX: usize = ROOT_HASH_INDEX;
+
+    pub fn new(
+        leaf_hash: Fp,
+        root_hash: Fp,
+        root_balances: Option<[Fp; N_ASSETS]>,
+        extra_public_inputs: Vec<Fp>,
+    ) -> Self {
+        Self {
+            leaf_hash,
+            root_hash,
+            root_balances,
+            extra_public_inputs,
+        }
+    }
+
+    /// Parses the public inputs of a proof, laid out across the instance columns in row-major order.
+    /// `root_balances_public` must match the `root_balances_public` field of the circuit that generated the proof
+    pub fn from_instances(
+        instances: &[Vec<Fp>],
+        root_balances_public: bool,
+    ) -> Result<Self, &'static str> {
+        let n_values = instances.iter().map(|column| column.len()).sum::<usize>();
+        let values = (0..n_values)
+            .map(|index| {
+                instances
+                    .get(index % instances.len())
+                    .and_then(|column| column.get(index / instances.len()))
+                    .copied()
+                    .ok_or("The public inputs are not laid out in row-major order")
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let n_root_balances = if root_balances_public { N_ASSETS } else { 0 };
+        if values.len() < 2 + n_root_balances {
+            return Err("Too few public inputs");
+        }
+
+        let root_balances =
+            root_balances_public.then(|| std::array::from_fn(|asset| values[2 + asset]));
+
+        Ok(Self::new(
+            values[Self::LEAF_HASH_INDEX],
+            values[Self::ROOT_HASH_INDEX],
+            root_balances,
+            values[2 + n_root_balances..].to_vec(),
+        ))
+    }
+
+    /// Returns the public inputs laid out across `n_instance_columns` instance columns in row-major order
+    pub fn to_instances(&self, n_instance_columns: usize) -> Vec<Vec<Fp>> {
+        let mut instances = vec![vec![]; n_instance_columns];
+        for (index, value) in self.to_vec().into_iter().enumerate() {
+            instances[index % n_instance_columns].push(value);
         }
+        instances
+    }
+
+    /// Returns the public inputs in the order in which they are exposed
+    pub fn to_vec(&self) -> Vec<Fp> {
+        [self.leaf_hash, self.root_hash]
+            .into_iter()
+            .chain(self.root_balances.iter().flatten().copied())
+            .chain(self.extra_public_inputs.iter().copied())
+            .collect()
+    }
+
+    pub fn leaf_hash(&self) -> Fp {
+        self.leaf_hash
+    }
+
+    pub fn root_hash(&self) -> Fp {
+        self.root_hash
+    }
+
+    /// Returns the balances of the root, namely the total liabilities of the CEX per asset, if they are public
+    pub fn root_balances(&self) -> Option<&[Fp; N_ASSETS]> {
+        self.root_balances.as_ref()
+    }
+
+    pub fn extra_public_inputs(&self) -> &[Fp] {
+        &self.extra_public_inputs
+    }
+
+    /// Returns the position of the root balance of `asset`, if the root balances are public
+    pub fn root_balance_index(&self, asset: usize) -> Option<usize> {
+        assert!(asset < N_ASSETS);
+        self.root_balances.map(|_| 2 + asset)
+    }
+
+    /// Returns the position of the extra public input at `index`
+    pub fn extra_public_input_index(&self, index: usize) -> usize {
+        let n_root_balances = if self.root_balances.is_some() {
+            N_ASSETS
+        } else {
+            0
+        };
+        2 + n_root_balances + index
+    }
+}
+
+/// Configuration for the Mst Inclusion circuit
+/// # Type Parameters
+///
+/// * `L`: The length of the hasher input, namely 2 + (2 * N_ASSETS)
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+/// * `N_INSTANCE_COLUMNS`: The number of instance columns used to store the public inputs. Defaults to 1
+/// * `SUM_BITS`: Range in which every computed sum, up to the root balances, is constrained to lie. Defaults to MOD_BITS
+/// * `LEAF_BITS`: Range in which each balance of the leaf should lie when the leaf range check is enabled. Defaults to MOD_BITS
+///
+/// # Fields
+///
+/// * `merkle_sum_tree_config`: Configuration for the merkle sum tree
+/// * `poseidon_config`: Configuration for the poseidon hash function with WIDTH = 3 and RATE = 2
+/// * `overflow_check_config`: Configuration for the overflow check chip
+/// * `sum_overflow_check_config`: Configuration for the overflow check chip of the computed sums
+/// * `leaf_overflow_check_config`: Configuration for the overflow check chip of the balances of the leaf. It is only configured when LEAF_BITS differs from MOD_BITS, since it costs 1 selector and 1 complex selector, otherwise the balances of the leaf are checked by the overflow check chip
+/// * `instances`: Instance columns used to store the public inputs
+
+#[derive(Debug, Clone)]
+pub struct MstInclusionConfig<
+    const L: usize,
+    const N_ASSETS: usize,
+    const N_INSTANCE_COLUMNS: usize = 1,
+    const SUM_BITS: usize = MOD_BITS,
+    const LEAF_BITS: usize = MOD_BITS,
+> {
+    pub merkle_sum_tree_config: MerkleSumTreeConfig,
+    pub poseidon_config: PoseidonConfig<3, 2, L>,
+    pub overflow_check_config: OverflowCheckConfig<MAX_BITS, MOD_BITS>,
+    pub sum_overflow_check_config: OverflowCheckConfig<MAX_BITS, SUM_BITS>,
+    pub leaf_overflow_check_config: Option<OverflowCheckConfig<MAX_BITS, LEAF_BITS>>,
+    pub instances: [Column<Instance>; N_INSTANCE_COLUMNS],
+}
+
+impl<
+        const L: usize,
+        const N_ASSETS: usize,
+        const N_INSTANCE_COLUMNS: usize,
+        const SUM_BITS: usize,
+        const LEAF_BITS: usize,
+    > MstInclusionConfig<L, N_ASSETS, N_INSTANCE_COLUMNS, SUM_BITS, LEAF_BITS>
+{
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> Self {
+        // the max number of advices columns needed is WIDTH + 1 given requirement of the poseidon config with WIDTH 3
+        let advices: [Column<Advice>; 4] = std::array::from_fn(|_| meta.advice_column());
+
+        // we need 2 * WIDTH fixed columns for poseidon config with WIDTH 3 + 1 for the overflow check chip
+        let fixed_columns: [Column<Fixed>; 7] = std::array::from_fn(|_| meta.fixed_column());
+
+        // we also need 2 selectors for the MerkleSumTreeChip and 1 for each of the two overflow check chips
+        let selectors: [Selector; 4] = std::array::from_fn(|_| meta.selector());
+
+        // we need 1 complex selector for the lookup check of each of the two overflow check chips
+        let toggle_lookup_check = meta.complex_selector();
+        let toggle_sum_lookup_check = meta.complex_selector();
+
+        // in fact, the poseidon config requires #WIDTH advice columns for state and 1 for partial_sbox, 3 fixed columns for rc_a and 3 for rc_b
+        let poseidon_config = PoseidonChip::<PoseidonSpec, 3, 2, L>::configure(
+            meta,
+            advices[0..3].try_into().unwrap(),
+            advices[3],
+            fixed_columns[0..3].try_into().unwrap(),
+            fixed_columns[3..6].try_into().unwrap(),
+        );
+
+        // enable permutation for all the advice columns
+        for col in &advices {
+            meta.enable_equality(*col);
+        }
+
+        // the configuration of merkle_sum_tree will always require 3 advices, no matter the number of assets
+        let merkle_sum_tree_config = MerkleSumTreeChip::<N_ASSETS>::configure(
+            meta,
+            advices[0..3].try_into().unwrap(),
+            selectors[0..2].try_into().unwrap(),
+        );
+
+        let overflow_check_config = OverflowChip::<MAX_BITS, MOD_BITS>::configure(
+            meta,
+            advices[0],
+            advices[1],
+            fixed_columns[6],
+            selectors[2],
+            toggle_lookup_check,
+        );
+
+        // the computed sums are decomposed against the same range table, which is loaded only once by the overflow check chip
+        let sum_overflow_check_config = OverflowChip::<MAX_BITS, SUM_BITS>::configure(
+            meta,
+            advices[0],
+            advices[1],
+            fixed_columns[6],
+            selectors[3],
+            toggle_sum_lookup_check,
+        );
+
+        // the balances of the leaf are decomposed into bytes against the same range table, with their own selectors so that their width can differ from MOD_BITS.
+        // When LEAF_BITS = MOD_BITS the overflow check chip already enforces that range, so the extra selectors are not allocated
+        let leaf_overflow_check_config = (LEAF_BITS != MOD_BITS).then(|| {
+            let toggle_leaf_decomposed_value_check = meta.selector();
+            let toggle_leaf_lookup_check = meta.complex_selector();
+            OverflowChip::<MAX_BITS, LEAF_BITS>::configure(
+                meta,
+                advices[0],
+                advices[1],
+                fixed_columns[6],
+                toggle_leaf_decomposed_value_check,
+                toggle_leaf_lookup_check,
+            )
+        });
+
+        assert!(
+            N_INSTANCE_COLUMNS > 0,
+            "The circuit requires at least one instance column"
+        );
+
+        let instances = [(); N_INSTANCE_COLUMNS].map(|_| meta.instance_column());
+        for instance in instances.iter() {
+            meta.enable_equality(*instance);
+        }
+
+        Self {
+            merkle_sum_tree_config,
+            poseidon_config,
+            overflow_check_config,
+            sum_overflow_check_config,
+            leaf_overflow_check_config,
+            instances,
+        }
+    }
+
+    /// Enforce copy constraint check between input cell and the public input at position `index`.
+    /// The public inputs are laid out across the instance columns in row-major order, namely the public input at position `index` is stored in the instance column `index % N_INSTANCE_COLUMNS` at row `index / N_INSTANCE_COLUMNS`.
+    /// With a single instance column, `index` is simply the row of the instance column.
+    pub fn expose_public(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        cell: &AssignedCell<Fp, Fp>,
+        index: usize,
+    ) -> Result<(), Error> {
+        layouter.constrain_instance(
+            cell.cell(),
+            self.instances[index % N_INSTANCE_COLUMNS],
+            index / N_INSTANCE_COLUMNS,
+        )
+    }
+}
+
+/// Configuration for recomputing the hash of a leaf from its entry, namely `H(username, balances[0], ..., balances[N_ASSETS - 1])`, so that a circuit built on top of [MstInclusionConfig] can bind the balances of the leaf to a username.
+///
+/// # Type Parameters
+///
+/// * `L_ENTRY`: The length of the hasher input of the leaves, namely 1 + N_ASSETS
+///
+/// # Fields
+///
+/// * `entry_poseidon_config`: Configuration for the poseidon hash function used to recompute the leaf hash, with WIDTH = 3 and RATE = 2
+/// * `advice`: Advice columns used by the entry hasher, which are also available to assign the usernames
+#[derive(Debug, Clone)]
+pub struct MstEntryConfig<const L_ENTRY: usize> {
+    pub entry_poseidon_config: PoseidonConfig<3, 2, L_ENTRY>,
+    pub advice: [Column<Advice>; 4],
+}
+
+impl<const L_ENTRY: usize> MstEntryConfig<L_ENTRY> {
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> Self {
+        // the entry hasher requires #WIDTH advice columns for state and 1 for partial_sbox, 3 fixed columns for rc_a and 3 for rc_b
+        let advice: [Column<Advice>; 4] = std::array::from_fn(|_| meta.advice_column());
+        let fixed_columns: [Column<Fixed>; 6] = std::array::from_fn(|_| meta.fixed_column());
+
+        for col in &advice {
+            meta.enable_equality(*col);
+        }
+
+        let entry_poseidon_config = PoseidonChip::<PoseidonSpec, 3, 2, L_ENTRY>::configure(
+            meta,
+            advice[0..3].try_into().unwrap(),
+            advice[3],
+            fixed_columns[0..3].try_into().unwrap(),
+            fixed_columns[3..6].try_into().unwrap(),
+        );
+
+        Self {
+            entry_poseidon_config,
+            advice,
+        }
+    }
+
+    /// Recomputes the leaf hash `H(username, balances[0], ..., balances[N_ASSETS - 1])` and constrains it to be equal to `leaf_hash`
+    pub fn enforce_leaf_hash(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        username: &AssignedCell<Fp, Fp>,
+        balances: &[AssignedCell<Fp, Fp>],
+        leaf_hash: &AssignedCell<Fp, Fp>,
+    ) -> Result<(), Error> {
+        let poseidon_chip = PoseidonChip::<PoseidonSpec, 3, 2, L_ENTRY>::construct(
+            self.entry_poseidon_config.clone(),
+        );
+
+        let hash_input: [AssignedCell<Fp, Fp>; L_ENTRY] = [username]
+            .into_iter()
+            .chain(balances.iter())
+            .cloned()
+            .collect::<Vec<_>>()
+            .try_into()
+            .expect("the hash input must contain the username and N_ASSETS balances");
+
+        let computed_leaf_hash =
+            poseidon_chip.hash(layouter.namespace(|| "perform leaf hash"), hash_input)?;
+
+        layouter.assign_region(
+            || "constrain leaf hash",
+            |mut region| region.constrain_equal(computed_leaf_hash.cell(), leaf_hash.cell()),
+        )
+    }
+}
+
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const N_ASSETS: usize,
+        const N_INSTANCE_COLUMNS: usize,
+        const SUM_BITS: usize,
+        const LEAF_BITS: usize,
+    > Circuit<Fp>
+    for MstInclusionCircuit<LEVELS, L, N_ASSETS, N_INSTANCE_COLUMNS, SUM_BITS, LEAF_BITS>
+{
+    type Config = MstInclusionConfig<L, N_ASSETS, N_INSTANCE_COLUMNS, SUM_BITS, LEAF_BITS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        // the number of extra public inputs, the public root balances and the range checks determine the layout of the circuit, so they are preserved
+        Self {
+            root_balances_public: self.root_balances_public,
+            leaf_range_check: self.leaf_range_check,
+            sum_range_check: self.sum_range_check,
+            ..Self::init_empty()
+                .with_extra_public_inputs(vec![Fp::zero(); self.extra_public_inputs.len()])
+        }
+    }
+
+    /// Configures the circuit
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MstInclusionConfig::<L, N_ASSETS, N_INSTANCE_COLUMNS, SUM_BITS, LEAF_BITS>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let (leaf_hash, leaf_balances, root_hash, root_balances) =
+            self.assign_inclusion_path_with_root_balances(&config, &mut layouter)?;
+
+        let public_inputs = self.public_inputs();
+
+        // expose the first current hash, namely the leaf hash, as public input
+        config.expose_public(
+            layouter.namespace(|| "public leaf hash"),
+            &leaf_hash,
+            PublicInputs::<N_ASSETS>::LEAF_HASH_INDEX,
+        )?;
 
         // expose the last current hash, namely the root hash, as public input
-        config.expose_public(layouter.namespace(|| "public root hash"), &current_hash, 1)?;
+        config.expose_public(
+            layouter.namespace(|| "public root hash"),
+            &root_hash,
+            PublicInputs::<N_ASSETS>::ROOT_HASH_INDEX,
+        )?;
+
+        // expose the last current balances, namely the root balances, as public input if requested
+        for (asset, balance) in root_balances.iter().enumerate() {
+            if let Some(index) = public_inputs.root_balance_index(asset) {
+                config.expose_public(
+                    layouter.namespace(|| format!("asset {}: public root balance", asset)),
+                    balance,
+                    index,
+                )?;
+            }
+        }
+
+        // assign the extra public inputs and expose them in the trailing public input positions
+        let extra_public_inputs = layouter.assign_region(
+            || "assign extra public inputs",
+            |mut region| {
+                self.extra_public_inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| {
+                        region.assign_advice(
+                            || format!("extra public input {}", i),
+                            config.merkle_sum_tree_config.advice[0],
+                            i,
+                            || Value::known(*value),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            },
+        )?;
+
+        for (i, cell) in extra_public_inputs.iter().enumerate() {
+            config.expose_public(
+                layouter.namespace(|| format!("public extra input {}", i)),
+                cell,
+                public_inputs.extra_public_input_index(i),
+            )?;
+        }
 
         // don't need to perform further range check on the balances of the root node as their addends are already constrained to be less than 2^MOD_BITS
         Ok(())
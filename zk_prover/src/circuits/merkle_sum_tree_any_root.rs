@@ -0,0 +1,310 @@
+use crate::circuits::merkle_sum_tree::{MstEntryConfig, MstInclusionCircuit, MstInclusionConfig};
+use crate::merkle_sum_tree::{big_int_to_fp, MerkleSumTree};
+use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error, Selector};
+use halo2_proofs::poly::Rotation;
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for verifying inclusion of an entry inside a merkle sum tree whose root is one of a set of candidate roots, without revealing which one.
+///
+/// The root computed from the merkle path is kept private. The circuit enforces `(root - candidate_0) * (root - candidate_1) * ... * (root - candidate_N) = 0`,
+/// namely that the root is equal to at least one of the candidates, so that the public inputs are the same no matter which root has been used.
+/// The leaf hash is kept private as well and recomputed from the private username and balances: a public leaf hash would reveal which tree contains it, and would link the proofs of a user whose balances are unchanged across snapshots.
+/// The public inputs are therefore the candidate roots only.
+///
+/// # Type Parameters
+///
+/// * `LEVELS`: The number of levels of the merkle sum tree
+/// * `L`: The length of the hasher input, namely 2 + (2 * N_ASSETS)
+/// * `L_ENTRY`: The length of the hasher input of the leaves, namely 1 + N_ASSETS
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+/// * `N_ROOTS`: The number of candidate roots
+///
+/// # Fields
+///
+/// * `username`: The username of the entry, converted to a field element, private
+/// * `inclusion`: The inclusion circuit witness, namely the leaf and the merkle path to the (private) root
+/// * `candidate_roots`: The set of published roots among which the root of the inclusion proof is hidden
+#[derive(Clone)]
+pub struct MstInclusionAnyRootCircuit<
+    const LEVELS: usize,
+    const L: usize,
+    const L_ENTRY: usize,
+    const N_ASSETS: usize,
+    const N_ROOTS: usize,
+> {
+    pub username: Fp,
+    pub inclusion: MstInclusionCircuit<LEVELS, L, N_ASSETS>,
+    pub candidate_roots: [Fp; N_ROOTS],
+}
+
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const L_ENTRY: usize,
+        const N_ASSETS: usize,
+        const N_ROOTS: usize,
+    > CircuitExt<Fp> for MstInclusionAnyRootCircuit<LEVELS, L, L_ENTRY, N_ASSETS, N_ROOTS>
+{
+    /// Returns the number of public inputs of the circuit. It is N_ROOTS, namely the candidate roots
+    fn num_instance(&self) -> Vec<usize> {
+        vec![N_ROOTS]
+    }
+
+    /// Returns the values of the public inputs of the circuit, namely the candidate roots
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        vec![self.candidate_roots.to_vec()]
+    }
+}
+
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const L_ENTRY: usize,
+        const N_ASSETS: usize,
+        const N_ROOTS: usize,
+    > MstInclusionAnyRootCircuit<LEVELS, L, L_ENTRY, N_ASSETS, N_ROOTS>
+{
+    pub fn init_empty() -> Self {
+        assert_eq!(N_ASSETS + 1, L_ENTRY);
+
+        Self {
+            username: Fp::zero(),
+            inclusion: MstInclusionCircuit::init_empty(),
+            candidate_roots: [Fp::zero(); N_ROOTS],
+        }
+    }
+
+    /// Initializes the circuit with the merkle sum tree, the index of the user of which the inclusion is to be verified and the set of candidate roots.
+    /// The root of `merkle_sum_tree` must be one of the candidates.
+    pub fn init(
+        merkle_sum_tree: MerkleSumTree<N_ASSETS>,
+        user_index: usize,
+        candidate_roots: [Fp; N_ROOTS],
+    ) -> Self {
+        assert_eq!(N_ASSETS + 1, L_ENTRY);
+        assert!(
+            candidate_roots.contains(&merkle_sum_tree.root().hash),
+            "The root of the merkle sum tree must be one of the candidate roots"
+        );
+
+        let username = big_int_to_fp(merkle_sum_tree.entries()[user_index].username_to_big_int());
+
+        Self {
+            username,
+            inclusion: MstInclusionCircuit::init(merkle_sum_tree, user_index),
+            candidate_roots,
+        }
+    }
+}
+
+/// Configuration for the inclusion path to one of a set of candidate roots, shared by [MstInclusionAnyRootCircuit] and [crate::circuits::balance_allowlist::BalanceAllowlistCircuit]
+///
+/// # Fields
+///
+/// * `mst_inclusion_config`: Configuration for the inclusion path. Its instance column is used to store the public inputs
+/// * `first_selector`: Selector to activate the constraint on the first factor of the product
+/// * `product_selector`: Selector to activate the constraint on the following factors of the product
+/// * `zero_selector`: Selector to activate the constraint on the final product to be zero
+///
+/// The product is computed on the 3 advice columns of the merkle sum tree chip following this layout:
+///
+/// | a      | b             | c         |
+/// | ------ | ------------- | --------- |
+/// | `root` | `candidate_0` | `acc_0`   |
+/// | `root` | `candidate_1` | `acc_1`   |
+/// | ...    | ...           | ...       |
+/// | `root` | `candidate_N` | `acc_N`   |
+///
+/// * `acc_0 = root - candidate_0` (if `first_selector` is toggled)
+/// * `acc_i = acc_(i-1) * (root - candidate_i)` (if `product_selector` is toggled)
+/// * `acc_N = 0` (if `zero_selector` is toggled)
+#[derive(Debug, Clone)]
+pub struct MstInclusionAnyRootConfig<const L: usize, const N_ASSETS: usize> {
+    pub mst_inclusion_config: MstInclusionConfig<L, N_ASSETS>,
+    pub first_selector: Selector,
+    pub product_selector: Selector,
+    pub zero_selector: Selector,
+}
+
+impl<const L: usize, const N_ASSETS: usize> MstInclusionAnyRootConfig<L, N_ASSETS> {
+    /// Configures the inclusion path and the gates of the product of the differences
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> Self {
+        let mst_inclusion_config = MstInclusionConfig::<L, N_ASSETS>::configure(meta);
+
+        let first_selector = meta.selector();
+        let product_selector = meta.selector();
+        let zero_selector = meta.selector();
+
+        let [col_a, col_b, col_c] = mst_inclusion_config.merkle_sum_tree_config.advice;
+
+        meta.create_gate("first root factor", |meta| {
+            let s = meta.query_selector(first_selector);
+            let root = meta.query_advice(col_a, Rotation::cur());
+            let candidate = meta.query_advice(col_b, Rotation::cur());
+            let acc = meta.query_advice(col_c, Rotation::cur());
+            vec![s * (acc - (root - candidate))]
+        });
+
+        meta.create_gate("root factors product", |meta| {
+            let s = meta.query_selector(product_selector);
+            let root = meta.query_advice(col_a, Rotation::cur());
+            let candidate = meta.query_advice(col_b, Rotation::cur());
+            let acc = meta.query_advice(col_c, Rotation::cur());
+            let acc_prev = meta.query_advice(col_c, Rotation::prev());
+            vec![s * (acc - acc_prev * (root - candidate))]
+        });
+
+        meta.create_gate("root factors product is zero", |meta| {
+            let s = meta.query_selector(zero_selector);
+            let acc = meta.query_advice(col_c, Rotation::cur());
+            vec![s * acc]
+        });
+
+        Self {
+            mst_inclusion_config,
+            first_selector,
+            product_selector,
+            zero_selector,
+        }
+    }
+
+    /// Enforces the value in the `root` cell to be equal to one of the first `n_roots` values of the instance column, starting at row `offset`.
+    /// The same constraint applies to any other cell, for example a balance to be one of a set of allowed values.
+    pub fn enforce_root_in_candidates(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        root: &AssignedCell<Fp, Fp>,
+        offset: usize,
+        n_roots: usize,
+    ) -> Result<(), Error> {
+        let [col_a, col_b, col_c] = self.mst_inclusion_config.merkle_sum_tree_config.advice;
+
+        layouter.assign_region(
+            || "enforce value to be one of the candidates",
+            |mut region| {
+                let mut acc = None;
+
+                for i in 0..n_roots {
+                    let root = root.copy_advice(|| "copy value", &mut region, col_a, i)?;
+
+                    let candidate = region.assign_advice_from_instance(
+                        || "copy candidate from instance column",
+                        self.mst_inclusion_config.instances[0],
+                        offset + i,
+                        col_b,
+                        i,
+                    )?;
+
+                    let factor = root.value().copied() - candidate.value().copied();
+
+                    let acc_val = match acc {
+                        None => {
+                            self.first_selector.enable(&mut region, i)?;
+                            factor
+                        }
+                        Some(prev) => {
+                            self.product_selector.enable(&mut region, i)?;
+                            factor * prev
+                        }
+                    };
+
+                    region.assign_advice(|| "product accumulator", col_c, i, || acc_val)?;
+                    acc = Some(acc_val);
+                }
+
+                self.zero_selector.enable(&mut region, n_roots - 1)?;
+
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Configuration for the [MstInclusionAnyRootCircuit]
+///
+/// # Fields
+///
+/// * `any_root_config`: Configuration for the inclusion path and the product of the differences between the root and the candidates. Its instance column is used to store the public inputs
+/// * `entry_config`: Configuration for recomputing the leaf hash. Its advice columns are used to assign the username
+#[derive(Debug, Clone)]
+pub struct MstInclusionAnyRootCircuitConfig<
+    const L: usize,
+    const L_ENTRY: usize,
+    const N_ASSETS: usize,
+> {
+    pub any_root_config: MstInclusionAnyRootConfig<L, N_ASSETS>,
+    pub entry_config: MstEntryConfig<L_ENTRY>,
+}
+
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const L_ENTRY: usize,
+        const N_ASSETS: usize,
+        const N_ROOTS: usize,
+    > Circuit<Fp> for MstInclusionAnyRootCircuit<LEVELS, L, L_ENTRY, N_ASSETS, N_ROOTS>
+{
+    type Config = MstInclusionAnyRootCircuitConfig<L, L_ENTRY, N_ASSETS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let any_root_config = MstInclusionAnyRootConfig::configure(meta);
+        let entry_config = MstEntryConfig::<L_ENTRY>::configure(meta);
+
+        MstInclusionAnyRootCircuitConfig {
+            any_root_config,
+            entry_config,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        assert!(
+            N_ROOTS > 0,
+            "The circuit requires at least one candidate root"
+        );
+
+        let (leaf_hash, leaf_balances, root_hash) = self
+            .inclusion
+            .assign_inclusion_path(&config.any_root_config.mst_inclusion_config, &mut layouter)?;
+
+        let username = layouter.assign_region(
+            || "assign username",
+            |mut region| {
+                region.assign_advice(
+                    || "username",
+                    config.entry_config.advice[0],
+                    0,
+                    || Value::known(self.username),
+                )
+            },
+        )?;
+
+        // the leaf hash is kept private, it is recomputed from the private username and balances
+        config.entry_config.enforce_leaf_hash(
+            layouter.namespace(|| "enforce leaf hash"),
+            &username,
+            &leaf_balances,
+            &leaf_hash,
+        )?;
+
+        // the computed root is kept private, it is only enforced to be one of the candidate roots exposed as public input
+        config.any_root_config.enforce_root_in_candidates(
+            layouter.namespace(|| "enforce root in candidates"),
+            &root_hash,
+            0,
+            N_ROOTS,
+        )?;
+
+        Ok(())
+    }
+}
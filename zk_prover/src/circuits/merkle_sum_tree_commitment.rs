@@ -0,0 +1,226 @@
+use crate::chips::pedersen::{
+    pedersen_commitment, CommitmentScalar, PedersenCommitmentChip, PedersenCommitmentConfig,
+    BIT_LEN_LIMB, NUMBER_OF_LIMBS,
+};
+use crate::circuits::merkle_sum_tree::{MstEntryConfig, MstInclusionCircuit, MstInclusionConfig};
+use crate::merkle_sum_tree::{big_int_to_fp, MerkleSumTree};
+use ecc::maingate::{big_to_fe, decompose, fe_to_big};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::{
+    bn256::Fr as Fp,
+    group::{Curve, Group},
+    secp256k1::Secp256k1Affine as Secp256k1,
+    CurveAffine,
+};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use rand::{CryptoRng, RngCore};
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for verifying inclusion of an entry inside a merkle sum tree with a given root, exposing a Pedersen commitment to the entry balances.
+///
+/// The balances of the entry are used in the sum constraints of the inclusion path and are committed to without being revealed. The public inputs are the root hash and the limbs of the commitment point.
+/// The leaf hash is not public, since anyone who knows the username could brute-force a realistic balance from it: it is recomputed from the private username and balances instead.
+/// The balances of the leaf are range checked to be less than 2^MOD_BITS, see [MstInclusionCircuit::with_leaf_range_check], so that the committed balances can't wrap around the modulus of the field.
+///
+/// # Type Parameters
+///
+/// * `LEVELS`: The number of levels of the merkle sum tree
+/// * `L`: The length of the hasher input, namely 2 + (2 * N_ASSETS)
+/// * `L_ENTRY`: The length of the hasher input of the leaves, namely 1 + N_ASSETS
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+///
+/// # Fields
+///
+/// * `username`: The username of the entry, converted to a field element, private
+/// * `inclusion`: The inclusion circuit witness, namely the leaf and the merkle path. Its leaf range check is always enabled
+/// * `blinding`: The blinding factor of the commitment
+/// * `aux_generator`: The auxiliary generator point used by the ecc chip
+/// * `window_size`: The window size used by the ecc chip for scalar multiplication
+#[derive(Clone)]
+pub struct MstInclusionCommitmentCircuit<
+    const LEVELS: usize,
+    const L: usize,
+    const L_ENTRY: usize,
+    const N_ASSETS: usize,
+> {
+    pub username: Fp,
+    pub inclusion: MstInclusionCircuit<LEVELS, L, N_ASSETS>,
+    pub blinding: CommitmentScalar,
+    pub aux_generator: Secp256k1,
+    pub window_size: usize,
+}
+
+impl<const LEVELS: usize, const L: usize, const L_ENTRY: usize, const N_ASSETS: usize>
+    CircuitExt<Fp> for MstInclusionCommitmentCircuit<LEVELS, L, L_ENTRY, N_ASSETS>
+{
+    /// Returns the number of public inputs of the circuit. It is 1 + 2 * NUMBER_OF_LIMBS, namely the root hash and the limbs of the x and y coordinates of the commitment.
+    fn num_instance(&self) -> Vec<usize> {
+        vec![1 + 2 * NUMBER_OF_LIMBS]
+    }
+
+    /// Returns the values of the public inputs of the circuit. The first value is the root hash of the merkle sum tree, followed by the limbs of the commitment x and y coordinates.
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        let mut instance = vec![self.inclusion.root_hash];
+        instance.extend(commitment_limbs(&self.commitment()));
+        vec![instance]
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const L_ENTRY: usize, const N_ASSETS: usize>
+    MstInclusionCommitmentCircuit<LEVELS, L, L_ENTRY, N_ASSETS>
+{
+    pub fn init_empty() -> Self {
+        assert_eq!(N_ASSETS + 1, L_ENTRY);
+
+        Self {
+            username: Fp::zero(),
+            inclusion: MstInclusionCircuit::init_empty().with_leaf_range_check(),
+            blinding: CommitmentScalar::zero(),
+            aux_generator: Secp256k1::default(),
+            window_size: 4,
+        }
+    }
+
+    /// Initializes the circuit with the merkle sum tree, the index of the user of which the inclusion is to be verified and the blinding factor of the commitment.
+    /// The auxiliary generator of the ecc chip is drawn from `rng`, so that the circuit can be built deterministically from a seeded rng.
+    pub fn init(
+        merkle_sum_tree: MerkleSumTree<N_ASSETS>,
+        user_index: usize,
+        blinding: CommitmentScalar,
+        rng: impl RngCore + CryptoRng,
+    ) -> Self {
+        assert_eq!(N_ASSETS + 1, L_ENTRY);
+
+        let username = big_int_to_fp(merkle_sum_tree.entries()[user_index].username_to_big_int());
+        let aux_generator = <Secp256k1 as CurveAffine>::CurveExt::random(rng).to_affine();
+
+        Self {
+            username,
+            inclusion: MstInclusionCircuit::init(merkle_sum_tree, user_index)
+                .with_leaf_range_check(),
+            blinding,
+            aux_generator,
+            window_size: 4,
+        }
+    }
+
+    /// Returns the Pedersen commitment to the leaf balances computed natively
+    pub fn commitment(&self) -> Secp256k1 {
+        let balances: [Fp; N_ASSETS] = self.inclusion.leaf_balances.clone().try_into().unwrap();
+        pedersen_commitment(&balances, self.blinding)
+    }
+}
+
+/// Decomposes the coordinates of a commitment point into the limbs exposed as public inputs
+pub fn commitment_limbs(commitment: &Secp256k1) -> Vec<Fp> {
+    let limbs_x = decompose(commitment.x, NUMBER_OF_LIMBS, BIT_LEN_LIMB)
+        .iter()
+        .map(|x| big_to_fe(fe_to_big(*x)))
+        .collect::<Vec<Fp>>();
+
+    let limbs_y = decompose(commitment.y, NUMBER_OF_LIMBS, BIT_LEN_LIMB)
+        .iter()
+        .map(|y| big_to_fe(fe_to_big(*y)))
+        .collect::<Vec<Fp>>();
+
+    let mut limbs = vec![];
+    limbs.extend(limbs_x);
+    limbs.extend(limbs_y);
+    limbs
+}
+
+/// Configuration for the Mst Inclusion Commitment circuit
+///
+/// # Fields
+///
+/// * `mst_inclusion_config`: Configuration for the inclusion path. Its instance column is used to store the public inputs
+/// * `entry_config`: Configuration for recomputing the leaf hash. Its advice columns are used to assign the username
+/// * `pedersen_config`: Configuration for the Pedersen commitment chip
+#[derive(Debug, Clone)]
+pub struct MstInclusionCommitmentConfig<const L: usize, const L_ENTRY: usize, const N_ASSETS: usize>
+{
+    pub mst_inclusion_config: MstInclusionConfig<L, N_ASSETS>,
+    pub entry_config: MstEntryConfig<L_ENTRY>,
+    pub pedersen_config: PedersenCommitmentConfig,
+}
+
+impl<const LEVELS: usize, const L: usize, const L_ENTRY: usize, const N_ASSETS: usize> Circuit<Fp>
+    for MstInclusionCommitmentCircuit<LEVELS, L, L_ENTRY, N_ASSETS>
+{
+    type Config = MstInclusionCommitmentConfig<L, L_ENTRY, N_ASSETS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let mst_inclusion_config = MstInclusionConfig::<L, N_ASSETS>::configure(meta);
+        let entry_config = MstEntryConfig::<L_ENTRY>::configure(meta);
+        let pedersen_config = PedersenCommitmentChip::<N_ASSETS>::configure(meta);
+
+        MstInclusionCommitmentConfig {
+            mst_inclusion_config,
+            entry_config,
+            pedersen_config,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let (leaf_hash, leaf_balances, root_hash) = self
+            .inclusion
+            .assign_inclusion_path(&config.mst_inclusion_config, &mut layouter)?;
+
+        let username = layouter.assign_region(
+            || "assign username",
+            |mut region| {
+                region.assign_advice(
+                    || "username",
+                    config.entry_config.advice[0],
+                    0,
+                    || Value::known(self.username),
+                )
+            },
+        )?;
+
+        // the leaf hash stays private, it is recomputed from the private username and balances
+        config.entry_config.enforce_leaf_hash(
+            layouter.namespace(|| "enforce leaf hash"),
+            &username,
+            &leaf_balances,
+            &leaf_hash,
+        )?;
+
+        // expose the root hash as public input
+        config.mst_inclusion_config.expose_public(
+            layouter.namespace(|| "public root hash"),
+            &root_hash,
+            0,
+        )?;
+
+        // commit to the same balance cells used in the sum constraints of the inclusion path
+        let pedersen_chip = PedersenCommitmentChip::<N_ASSETS>::construct(config.pedersen_config);
+        let (x_limbs, y_limbs) = pedersen_chip.commit(
+            layouter.namespace(|| "commit to leaf balances"),
+            &leaf_balances,
+            Value::known(self.blinding),
+            self.aux_generator,
+            self.window_size,
+        )?;
+
+        // expose the commitment limbs as public input, right after the root hash
+        for (i, limb) in x_limbs.iter().chain(y_limbs.iter()).enumerate() {
+            config.mst_inclusion_config.expose_public(
+                layouter.namespace(|| "public commitment limb"),
+                limb,
+                1 + i,
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use crate::circuits::merkle_sum_tree::{MstEntryConfig, MstInclusionCircuit, MstInclusionConfig};
+use crate::merkle_sum_tree::{
+    big_int_to_fp, poseidon_username, LeafHasher, MerkleProof, MerkleSumTree,
+};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for verifying the inclusion of an entry inside a merkle sum tree whose leaves commit to the hash of the username and the balances, namely a tree built with [LeafHasher::PoseidonUsernameHash].
+///
+/// Unlike [MstInclusionCircuit], which takes the leaf hash and the leaf balances as two independent witnesses, the leaf hash is recomputed in-circuit as `H(username_hash, balance[0], ..., balance[N_ASSETS - 1])`, so that the balances are cryptographically bound to the leaf.
+/// The username hash is kept private. The public inputs are the same as [MstInclusionCircuit], namely the leaf hash and the root hash.
+///
+/// # Type Parameters
+///
+/// * `LEVELS`: The number of levels of the merkle sum tree
+/// * `L`: The length of the hasher input of the middle nodes, namely 2 + (2 * N_ASSETS)
+/// * `L_ENTRY`: The length of the hasher input of the leaves, namely 1 + N_ASSETS
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+///
+/// # Fields
+///
+/// * `username_hash`: The poseidon hash of the username of the entry, see [poseidon_username]
+/// * `inclusion`: The inclusion path of the leaf, see [MstInclusionCircuit]
+#[derive(Clone)]
+pub struct MstInclusionCommittedLeafCircuit<
+    const LEVELS: usize,
+    const L: usize,
+    const L_ENTRY: usize,
+    const N_ASSETS: usize,
+> {
+    pub username_hash: Fp,
+    pub inclusion: MstInclusionCircuit<LEVELS, L, N_ASSETS>,
+}
+
+impl<const LEVELS: usize, const L: usize, const L_ENTRY: usize, const N_ASSETS: usize>
+    CircuitExt<Fp> for MstInclusionCommittedLeafCircuit<LEVELS, L, L_ENTRY, N_ASSETS>
+{
+    /// Returns the number of public inputs of the circuit. It is 2, namely the leaf hash and the root hash
+    fn num_instance(&self) -> Vec<usize> {
+        vec![2]
+    }
+
+    /// Returns the values of the public inputs of the circuit, namely the leaf hash and the root hash
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        vec![vec![self.inclusion.leaf_hash, self.inclusion.root_hash]]
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const L_ENTRY: usize, const N_ASSETS: usize>
+    MstInclusionCommittedLeafCircuit<LEVELS, L, L_ENTRY, N_ASSETS>
+{
+    pub fn init_empty() -> Self {
+        assert_eq!(N_ASSETS + 1, L_ENTRY);
+
+        Self {
+            username_hash: Fp::zero(),
+            inclusion: MstInclusionCircuit::init_empty(),
+        }
+    }
+
+    /// Initializes the circuit with the merkle sum tree and the index of the user of which the inclusion is to be verified.
+    /// The leaves of the tree must be hashed with [LeafHasher::PoseidonUsernameHash]
+    pub fn init(merkle_sum_tree: MerkleSumTree<N_ASSETS>, user_index: usize) -> Self {
+        let proof = merkle_sum_tree.generate_proof(user_index).unwrap();
+
+        Self::init_from_proof(proof)
+    }
+
+    /// Initializes the circuit with a merkle proof of a tree whose leaves are hashed with [LeafHasher::PoseidonUsernameHash]
+    pub fn init_from_proof(proof: MerkleProof<N_ASSETS>) -> Self {
+        assert_eq!(N_ASSETS + 1, L_ENTRY);
+        assert_eq!(proof.leaf_hasher, LeafHasher::PoseidonUsernameHash);
+
+        Self {
+            username_hash: poseidon_username(big_int_to_fp(proof.entry.username_to_big_int())),
+            inclusion: MstInclusionCircuit::init_from_proof(proof),
+        }
+    }
+}
+
+/// Configuration for the Mst Inclusion circuit with committed leaves
+///
+/// # Fields
+///
+/// * `inclusion_config`: Configuration for the inclusion path, see [MstInclusionConfig]
+/// * `entry_config`: Configuration for recomputing the leaf hash. Its advice columns are used to assign the username hash
+#[derive(Debug, Clone)]
+pub struct MstInclusionCommittedLeafConfig<
+    const L: usize,
+    const L_ENTRY: usize,
+    const N_ASSETS: usize,
+> {
+    pub inclusion_config: MstInclusionConfig<L, N_ASSETS>,
+    pub entry_config: MstEntryConfig<L_ENTRY>,
+}
+
+impl<const LEVELS: usize, const L: usize, const L_ENTRY: usize, const N_ASSETS: usize> Circuit<Fp>
+    for MstInclusionCommittedLeafCircuit<LEVELS, L, L_ENTRY, N_ASSETS>
+{
+    type Config = MstInclusionCommittedLeafConfig<L, L_ENTRY, N_ASSETS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    /// Configures the circuit
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let inclusion_config = MstInclusionConfig::<L, N_ASSETS>::configure(meta);
+
+        let entry_config = MstEntryConfig::<L_ENTRY>::configure(meta);
+
+        MstInclusionCommittedLeafConfig {
+            inclusion_config,
+            entry_config,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let (leaf_hash, leaf_balances, root_hash) = self
+            .inclusion
+            .assign_inclusion_path(&config.inclusion_config, &mut layouter)?;
+
+        let username_hash = layouter.assign_region(
+            || "assign username hash",
+            |mut region| {
+                region.assign_advice(
+                    || "username hash",
+                    config.entry_config.advice[0],
+                    0,
+                    || Value::known(self.username_hash),
+                )
+            },
+        )?;
+
+        // recompute the leaf hash from the username hash and the balances of the leaf
+        config.entry_config.enforce_leaf_hash(
+            layouter.namespace(|| "enforce leaf hash"),
+            &username_hash,
+            &leaf_balances,
+            &leaf_hash,
+        )?;
+
+        // expose the leaf hash and the root hash as public input
+        config.inclusion_config.expose_public(
+            layouter.namespace(|| "public leaf hash"),
+            &leaf_hash,
+            0,
+        )?;
+        config.inclusion_config.expose_public(
+            layouter.namespace(|| "public root hash"),
+            &root_hash,
+            1,
+        )?;
+
+        Ok(())
+    }
+}
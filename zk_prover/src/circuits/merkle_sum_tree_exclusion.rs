@@ -0,0 +1,352 @@
+use crate::chips::less_than::less_than_vertical::{LtVerticalChip, LtVerticalInstruction};
+use crate::circuits::merkle_sum_tree::{MstEntryConfig, MstInclusionCircuit, MstInclusionConfig};
+use crate::circuits::solvency::SolvencyConfig;
+use crate::merkle_sum_tree::{big_int_to_fp, big_intify_username, MerkleSumTree};
+use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error, Expression, Selector};
+use halo2_proofs::poly::Rotation;
+use snark_verifier_sdk::CircuitExt;
+
+/// Number of bytes of the less than comparison of the usernames. The usernames of a sorted tree, including the sentinels, are at most 31 bytes long
+pub(crate) const USERNAME_BYTES: usize = 31;
+
+/// Circuit for proving that a username is NOT included in a merkle sum tree built with [MerkleSumTree::from_entries_sorted], namely that it lies strictly between the usernames of two adjacent leaves.
+///
+/// The leaves are sorted by username rather than by leaf hash: the leaf hash depends on balances unknown to the user and spans the whole field, while the usernames fit in the range of the less than chip.
+/// The circuit verifies the inclusion of both leaves in the same tree, recomputes their leaf hashes from the private usernames and balances, enforces their indices to be consecutive and uses the less than chip twice to enforce `left username < username < right username`.
+/// The exclusion is only meaningful if the tree committed by the CEX is sorted, which must be checked by other means.
+///
+/// # Type Parameters
+///
+/// * `LEVELS`: The number of levels of the merkle sum tree
+/// * `L`: The length of the hasher input of the middle nodes, namely 2 + (2 * N_ASSETS)
+/// * `L_ENTRY`: The length of the hasher input of the leaves, namely 1 + N_ASSETS
+/// * `N_ASSETS`: The number of assets of each entry
+///
+/// # Fields
+///
+/// * `username`: The username whose absence is proven, converted to a field element, public
+/// * `left_username`: The username of the left adjacent leaf, converted to a field element
+/// * `right_username`: The username of the right adjacent leaf, converted to a field element
+/// * `left`: The inclusion witness of the left adjacent leaf
+/// * `right`: The inclusion witness of the right adjacent leaf
+#[derive(Clone)]
+pub struct MstExclusionCircuit<
+    const LEVELS: usize,
+    const L: usize,
+    const L_ENTRY: usize,
+    const N_ASSETS: usize,
+> {
+    pub username: Fp,
+    pub left_username: Fp,
+    pub right_username: Fp,
+    pub left: MstInclusionCircuit<LEVELS, L, N_ASSETS>,
+    pub right: MstInclusionCircuit<LEVELS, L, N_ASSETS>,
+}
+
+impl<const LEVELS: usize, const L: usize, const L_ENTRY: usize, const N_ASSETS: usize>
+    CircuitExt<Fp> for MstExclusionCircuit<LEVELS, L, L_ENTRY, N_ASSETS>
+{
+    /// Returns the number of public inputs of the circuit. It is 2, namely the username and the root hash
+    fn num_instance(&self) -> Vec<usize> {
+        vec![2]
+    }
+
+    /// Returns the values of the public inputs of the circuit, namely the username whose absence is proven and the root hash of the merkle sum tree
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        vec![vec![self.username, self.left.root_hash]]
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const L_ENTRY: usize, const N_ASSETS: usize>
+    MstExclusionCircuit<LEVELS, L, L_ENTRY, N_ASSETS>
+{
+    pub fn init_empty() -> Self {
+        assert_eq!(N_ASSETS + 1, L_ENTRY);
+
+        Self {
+            username: Fp::zero(),
+            left_username: Fp::zero(),
+            right_username: Fp::zero(),
+            left: MstInclusionCircuit::init_empty(),
+            right: MstInclusionCircuit::init_empty(),
+        }
+    }
+
+    /// Initializes the circuit with a merkle sum tree built with [MerkleSumTree::from_entries_sorted] and the username whose absence is to be proven.
+    /// Returns an error if the username is included in the tree, see [MerkleSumTree::generate_exclusion_proof]
+    pub fn init(
+        merkle_sum_tree: &MerkleSumTree<N_ASSETS>,
+        username: &str,
+    ) -> Result<Self, &'static str> {
+        assert_eq!(N_ASSETS + 1, L_ENTRY);
+
+        let (left_proof, right_proof) = merkle_sum_tree.generate_exclusion_proof(username)?;
+
+        Ok(Self {
+            username: big_int_to_fp(&big_intify_username(username)),
+            left_username: big_int_to_fp(left_proof.entry.username_to_big_int()),
+            right_username: big_int_to_fp(right_proof.entry.username_to_big_int()),
+            left: MstInclusionCircuit::init_from_proof(left_proof),
+            right: MstInclusionCircuit::init_from_proof(right_proof),
+        })
+    }
+}
+
+/// Configuration for the Mst Exclusion circuit
+///
+/// # Fields
+///
+/// * `mst_inclusion_config`: Configuration for the inclusion paths of both adjacent leaves. Its instance column is used to store the public inputs
+/// * `entry_config`: Configuration for recomputing the leaf hashes. Its advice columns are used to assign the usernames and the difference of the indices
+/// * `solvency_config`: Configuration for the less than comparisons between the usernames, sharing the instance column of `mst_inclusion_config`
+/// * `first_index_selector`: Selector to activate the constraint on the most significant bits of the indices
+/// * `index_selector`: Selector to activate the constraint on the following bits of the indices
+/// * `adjacent_selector`: Selector to activate the constraint on the difference of the indices to be 1
+///
+/// The difference of the indices of the leaves is computed from their swap bits, from the root level to the leaf level, on 3 advice columns following this layout:
+///
+/// | a             | b              | c        |
+/// | ------------- | -------------- | -------- |
+/// | `left_bit_N`  | `right_bit_N`  | `diff_N` |
+/// | ...           | ...            | ...      |
+/// | `left_bit_0`  | `right_bit_0`  | `diff_0` |
+///
+/// * `diff_N = right_bit_N - left_bit_N` (if `first_index_selector` is toggled)
+/// * `diff_i = 2 * diff_(i+1) + right_bit_i - left_bit_i` (if `index_selector` is toggled)
+/// * `diff_0 = 1` (if `adjacent_selector` is toggled)
+#[derive(Debug, Clone)]
+pub struct MstExclusionConfig<const L: usize, const L_ENTRY: usize, const N_ASSETS: usize> {
+    pub mst_inclusion_config: MstInclusionConfig<L, N_ASSETS>,
+    pub entry_config: MstEntryConfig<L_ENTRY>,
+    pub solvency_config: SolvencyConfig<L, N_ASSETS, USERNAME_BYTES>,
+    pub first_index_selector: Selector,
+    pub index_selector: Selector,
+    pub adjacent_selector: Selector,
+}
+
+impl<const L: usize, const L_ENTRY: usize, const N_ASSETS: usize>
+    MstExclusionConfig<L, L_ENTRY, N_ASSETS>
+{
+    /// Configures the inclusion paths, the entry hasher, the less than chip and the gates of the difference of the indices
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> Self {
+        let mst_inclusion_config = MstInclusionConfig::<L, N_ASSETS>::configure(meta);
+
+        let entry_config = MstEntryConfig::<L_ENTRY>::configure(meta);
+
+        let solvency_config =
+            SolvencyConfig::<L, N_ASSETS, USERNAME_BYTES>::configure_with_instance(
+                meta,
+                mst_inclusion_config.instances[0],
+            );
+
+        let first_index_selector = meta.selector();
+        let index_selector = meta.selector();
+        let adjacent_selector = meta.selector();
+
+        let [col_a, col_b, col_c] = [
+            entry_config.advice[0],
+            entry_config.advice[1],
+            entry_config.advice[2],
+        ];
+
+        meta.create_gate("first index difference", |meta| {
+            let s = meta.query_selector(first_index_selector);
+            let left_bit = meta.query_advice(col_a, Rotation::cur());
+            let right_bit = meta.query_advice(col_b, Rotation::cur());
+            let diff = meta.query_advice(col_c, Rotation::cur());
+            vec![s * (diff - (right_bit - left_bit))]
+        });
+
+        meta.create_gate("index difference", |meta| {
+            let s = meta.query_selector(index_selector);
+            let left_bit = meta.query_advice(col_a, Rotation::cur());
+            let right_bit = meta.query_advice(col_b, Rotation::cur());
+            let diff = meta.query_advice(col_c, Rotation::cur());
+            let diff_prev = meta.query_advice(col_c, Rotation::prev());
+            vec![s * (diff - (diff_prev * Fp::from(2) + right_bit - left_bit))]
+        });
+
+        meta.create_gate("index difference is 1", |meta| {
+            let s = meta.query_selector(adjacent_selector);
+            let diff = meta.query_advice(col_c, Rotation::cur());
+            vec![s * (diff - Expression::Constant(Fp::one()))]
+        });
+
+        Self {
+            mst_inclusion_config,
+            entry_config,
+            solvency_config,
+            first_index_selector,
+            index_selector,
+            adjacent_selector,
+        }
+    }
+
+    /// Enforces the index of the leaf of `right_swap_bits` to be equal to the index of the leaf of `left_swap_bits` plus 1, where the swap bits are ordered from the leaf level to the root level
+    pub fn enforce_adjacent(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        left_swap_bits: &[AssignedCell<Fp, Fp>],
+        right_swap_bits: &[AssignedCell<Fp, Fp>],
+    ) -> Result<(), Error> {
+        let [col_a, col_b, col_c] = [
+            self.entry_config.advice[0],
+            self.entry_config.advice[1],
+            self.entry_config.advice[2],
+        ];
+
+        layouter.assign_region(
+            || "enforce leaves to be adjacent",
+            |mut region| {
+                let mut diff = None;
+
+                // the bits are processed from the most significant one, namely from the root level
+                for (row, (left_bit, right_bit)) in left_swap_bits
+                    .iter()
+                    .rev()
+                    .zip(right_swap_bits.iter().rev())
+                    .enumerate()
+                {
+                    let left_bit =
+                        left_bit.copy_advice(|| "copy left swap bit", &mut region, col_a, row)?;
+                    let right_bit =
+                        right_bit.copy_advice(|| "copy right swap bit", &mut region, col_b, row)?;
+
+                    let bit_diff = right_bit.value().copied() - left_bit.value().copied();
+
+                    let diff_val = match diff {
+                        None => {
+                            self.first_index_selector.enable(&mut region, row)?;
+                            bit_diff
+                        }
+                        Some(prev) => {
+                            self.index_selector.enable(&mut region, row)?;
+                            prev * Value::known(Fp::from(2)) + bit_diff
+                        }
+                    };
+
+                    region.assign_advice(|| "index difference", col_c, row, || diff_val)?;
+                    diff = Some(diff_val);
+                }
+
+                self.adjacent_selector
+                    .enable(&mut region, left_swap_bits.len() - 1)?;
+
+                Ok(())
+            },
+        )
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const L_ENTRY: usize, const N_ASSETS: usize> Circuit<Fp>
+    for MstExclusionCircuit<LEVELS, L, L_ENTRY, N_ASSETS>
+{
+    type Config = MstExclusionConfig<L, L_ENTRY, N_ASSETS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MstExclusionConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        assert!(
+            LEVELS > 0,
+            "A sorted tree contains at least the two sentinel entries"
+        );
+
+        let (left_leaf_hash, left_balances, left_root_hash, _, left_swap_bits) = self
+            .left
+            .assign_inclusion_path_with_swap_bits(&config.mst_inclusion_config, &mut layouter)?;
+
+        let (right_leaf_hash, right_balances, right_root_hash, _, right_swap_bits) = self
+            .right
+            .assign_inclusion_path_with_swap_bits(&config.mst_inclusion_config, &mut layouter)?;
+
+        let (username, left_username, right_username) = layouter.assign_region(
+            || "assign usernames",
+            |mut region| {
+                let username = region.assign_advice_from_instance(
+                    || "copy username from instance column",
+                    config.mst_inclusion_config.instances[0],
+                    0,
+                    config.entry_config.advice[0],
+                    0,
+                )?;
+                let left_username = region.assign_advice(
+                    || "left username",
+                    config.entry_config.advice[1],
+                    0,
+                    || Value::known(self.left_username),
+                )?;
+                let right_username = region.assign_advice(
+                    || "right username",
+                    config.entry_config.advice[2],
+                    0,
+                    || Value::known(self.right_username),
+                )?;
+                Ok((username, left_username, right_username))
+            },
+        )?;
+
+        config.entry_config.enforce_leaf_hash(
+            layouter.namespace(|| "left leaf: enforce leaf hash"),
+            &left_username,
+            &left_balances,
+            &left_leaf_hash,
+        )?;
+
+        config.entry_config.enforce_leaf_hash(
+            layouter.namespace(|| "right leaf: enforce leaf hash"),
+            &right_username,
+            &right_balances,
+            &right_leaf_hash,
+        )?;
+
+        config.enforce_adjacent(
+            layouter.namespace(|| "enforce adjacent leaves"),
+            &left_swap_bits,
+            &right_swap_bits,
+        )?;
+
+        let lt_chip = LtVerticalChip::<USERNAME_BYTES>::construct(config.solvency_config.lt_config);
+        lt_chip.load(&mut layouter)?;
+
+        config.solvency_config.enforce_cell_less_than(
+            layouter.namespace(|| "enforce left username to be less than username"),
+            &left_username,
+            &username,
+            &lt_chip,
+        )?;
+
+        config.solvency_config.enforce_cell_less_than(
+            layouter.namespace(|| "enforce username to be less than right username"),
+            &username,
+            &right_username,
+            &lt_chip,
+        )?;
+
+        // both leaves are included in the same tree, whose root is exposed as public input
+        config.mst_inclusion_config.expose_public(
+            layouter.namespace(|| "public root hash of the left leaf"),
+            &left_root_hash,
+            1,
+        )?;
+        config.mst_inclusion_config.expose_public(
+            layouter.namespace(|| "public root hash of the right leaf"),
+            &right_root_hash,
+            1,
+        )?;
+
+        Ok(())
+    }
+}
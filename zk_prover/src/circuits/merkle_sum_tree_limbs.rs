@@ -0,0 +1,335 @@
+use crate::chips::limb_sum::limb_sum::{LimbSumChip, LimbSumConfig};
+use crate::chips::merkle_sum_tree::{MerkleSumTreeChip, MerkleSumTreeConfig};
+use crate::chips::poseidon::hash::{PoseidonChip, PoseidonConfig};
+use crate::chips::poseidon::poseidon_spec::PoseidonSpec;
+use crate::merkle_sum_tree::{LimbMerkleProof, LimbMerkleSumTree};
+use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{
+    Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Instance, Selector,
+};
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for verifying inclusion of a leaf_hash inside a [LimbMerkleSumTree] with a given root, namely a merkle sum tree whose balances are split into a low and a high limb so that totals of up to 256 bits are supported.
+///
+/// At each level the limbs of the balances are swapped together with the hashes and summed with the carry propagated from the low limbs to the high limbs, see [MerkleSumTreeChip::assign_nodes_limbs_per_asset].
+/// Every limb entering a sum, as well as every limb of a sum, is range checked to [crate::merkle_sum_tree::LIMB_BITS] bits, so that the total of each asset up to the root is enforced to fit in 256 bits.
+///
+/// # Type Parameters
+///
+/// * `LEVELS`: The number of levels of the merkle sum tree
+/// * `L`: The length of the hasher input, namely 2 + (4 * N_ASSETS), as the node hash takes the two limbs of each balance of both children
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+///
+/// # Fields
+///
+/// * `leaf_hash`: The hash of the leaf to be verified inclusion of
+/// * `leaf_limbs`: The low and high limbs of the balances of the leaf to be verified inclusion of. The length of this vector is N_ASSETS
+/// * `path_element_hashes`: The hashes of the path elements from the leaf to root. The length of this vector is LEVELS
+/// * `path_element_limbs`: The low and high limbs of the balances of the path elements from the leaf to the root. The length of this vector is LEVELS
+/// * `path_indices`: The boolean indices of the path elements from the leaf to the root. false indicates that the element is on the right to the path, true indicates that the element is on the left to the path. The length of this vector is LEVELS
+/// * `root_hash`: The root hash of the merkle sum tree
+#[derive(Clone)]
+pub struct MstLimbInclusionCircuit<const LEVELS: usize, const L: usize, const N_ASSETS: usize> {
+    pub leaf_hash: Fp,
+    pub leaf_limbs: Vec<[Fp; 2]>,
+    pub path_element_hashes: Vec<Fp>,
+    pub path_element_limbs: Vec<Vec<[Fp; 2]>>,
+    pub path_indices: Vec<bool>,
+    pub root_hash: Fp,
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize> CircuitExt<Fp>
+    for MstLimbInclusionCircuit<LEVELS, L, N_ASSETS>
+{
+    /// Returns the number of public inputs of the circuit. It is 2, namely the leaf hash to be verified inclusion of and the root hash of the merkle sum tree.
+    fn num_instance(&self) -> Vec<usize> {
+        vec![2]
+    }
+    /// Returns the values of the public inputs of the circuit. Namely the leaf hash to be verified inclusion of and the root hash of the merkle sum tree.
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        vec![vec![self.leaf_hash, self.root_hash]]
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize>
+    MstLimbInclusionCircuit<LEVELS, L, N_ASSETS>
+{
+    pub fn init_empty() -> Self {
+        assert_eq!((N_ASSETS * 4) + 2, L);
+
+        Self {
+            leaf_hash: Fp::zero(),
+            leaf_limbs: vec![[Fp::zero(); 2]; N_ASSETS],
+            path_element_hashes: vec![Fp::zero(); LEVELS],
+            path_element_limbs: vec![vec![[Fp::zero(); 2]; N_ASSETS]; LEVELS],
+            path_indices: vec![false; LEVELS],
+            root_hash: Fp::zero(),
+        }
+    }
+
+    /// Initializes the circuit with the limb merkle sum tree and the index of the user of which the inclusion is to be verified.
+    pub fn init<const N_LIMBS: usize>(
+        merkle_sum_tree: LimbMerkleSumTree<N_ASSETS, N_LIMBS>,
+        user_index: usize,
+    ) -> Self {
+        let proof = merkle_sum_tree.generate_proof(user_index).unwrap();
+
+        Self::init_from_proof(proof)
+    }
+
+    /// Initializes the circuit with a limb merkle proof.
+    pub fn init_from_proof<const N_LIMBS: usize>(
+        proof: LimbMerkleProof<N_ASSETS, N_LIMBS>,
+    ) -> Self {
+        assert_eq!((N_ASSETS * 4) + 2, L);
+        assert_eq!(N_ASSETS * 2, N_LIMBS);
+
+        assert_eq!(proof.path_indices.len(), LEVELS);
+        assert_eq!(proof.sibling_hashes.len(), LEVELS);
+        assert_eq!(proof.sibling_limbs.len(), LEVELS);
+
+        let split_limbs = |limbs: [Fp; N_LIMBS]| {
+            limbs
+                .chunks(2)
+                .map(|limbs| [limbs[0], limbs[1]])
+                .collect::<Vec<_>>()
+        };
+
+        let leaf = proof
+            .leaf()
+            .expect("The balances of the entry must fit in 256 bits");
+
+        Self {
+            leaf_hash: leaf.hash,
+            leaf_limbs: split_limbs(leaf.balances),
+            path_element_hashes: proof.sibling_hashes,
+            path_element_limbs: proof.sibling_limbs.into_iter().map(split_limbs).collect(),
+            path_indices: proof.path_indices,
+            root_hash: proof.root_hash,
+        }
+    }
+}
+
+/// Configuration for the Mst Limb Inclusion circuit
+/// # Type Parameters
+///
+/// * `L`: The length of the hasher input, namely 2 + (4 * N_ASSETS)
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+///
+/// # Fields
+///
+/// * `merkle_sum_tree_config`: Configuration for the merkle sum tree
+/// * `poseidon_config`: Configuration for the poseidon hash function with WIDTH = 3 and RATE = 2
+/// * `limb_sum_config`: Configuration for the limb sum chip, which sums the limbs with carry and range checks them
+/// * `instance`: Instance column used to store the public inputs
+#[derive(Debug, Clone)]
+pub struct MstLimbInclusionConfig<const L: usize, const N_ASSETS: usize> {
+    pub merkle_sum_tree_config: MerkleSumTreeConfig,
+    pub poseidon_config: PoseidonConfig<3, 2, L>,
+    pub limb_sum_config: LimbSumConfig,
+    pub instance: Column<Instance>,
+}
+
+impl<const L: usize, const N_ASSETS: usize> MstLimbInclusionConfig<L, N_ASSETS> {
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> Self {
+        // the max number of advices columns needed is WIDTH + 1 given requirement of the poseidon config with WIDTH 3
+        let advices: [Column<Advice>; 4] = std::array::from_fn(|_| meta.advice_column());
+
+        // we need 2 * WIDTH fixed columns for poseidon config with WIDTH 3 + 1 for the range table of the limb sum chip
+        let fixed_columns: [Column<Fixed>; 7] = std::array::from_fn(|_| meta.fixed_column());
+
+        // we also need 2 selectors for the MerkleSumTreeChip
+        let selectors: [Selector; 2] = std::array::from_fn(|_| meta.selector());
+
+        // in fact, the poseidon config requires #WIDTH advice columns for state and 1 for partial_sbox, 3 fixed columns for rc_a and 3 for rc_b
+        let poseidon_config = PoseidonChip::<PoseidonSpec, 3, 2, L>::configure(
+            meta,
+            advices[0..3].try_into().unwrap(),
+            advices[3],
+            fixed_columns[0..3].try_into().unwrap(),
+            fixed_columns[3..6].try_into().unwrap(),
+        );
+
+        // enable permutation for all the advice columns
+        for col in &advices {
+            meta.enable_equality(*col);
+        }
+
+        let merkle_sum_tree_config = MerkleSumTreeChip::<N_ASSETS>::configure(
+            meta,
+            advices[0..3].try_into().unwrap(),
+            selectors,
+        );
+
+        // the limbs are summed and range checked on the same advice columns of the merkle sum tree chip
+        let limb_sum_config =
+            LimbSumChip::configure(meta, advices[0..3].try_into().unwrap(), fixed_columns[6]);
+
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        Self {
+            merkle_sum_tree_config,
+            poseidon_config,
+            limb_sum_config,
+            instance,
+        }
+    }
+
+    /// Enforce copy constraint check between input cell and instance column at row passed as input
+    pub fn expose_public(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        cell: &AssignedCell<Fp, Fp>,
+        row: usize,
+    ) -> Result<(), Error> {
+        layouter.constrain_instance(cell.cell(), self.instance, row)
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize> Circuit<Fp>
+    for MstLimbInclusionCircuit<LEVELS, L, N_ASSETS>
+{
+    type Config = MstLimbInclusionConfig<L, N_ASSETS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    /// Configures the circuit
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MstLimbInclusionConfig::<L, N_ASSETS>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        // build auxiliary chips
+        let merkle_sum_tree_chip =
+            MerkleSumTreeChip::<N_ASSETS>::construct(config.merkle_sum_tree_config.clone());
+        let poseidon_chip =
+            PoseidonChip::<PoseidonSpec, 3, 2, L>::construct(config.poseidon_config.clone());
+        let limb_sum_chip = LimbSumChip::construct(config.limb_sum_config.clone());
+
+        // load the range table of the limbs
+        limb_sum_chip.load(&mut layouter)?;
+
+        // Assign the leaf hash
+        let leaf_hash = layouter.assign_region(
+            || "assign leaf hash",
+            |mut region| {
+                region.assign_advice(
+                    || "leaf hash",
+                    config.merkle_sum_tree_config.advice[0],
+                    0,
+                    || Value::known(self.leaf_hash),
+                )
+            },
+        )?;
+
+        // Assign the limbs of the leaf balances, which are range checked to LIMB_BITS bits
+        let mut current_limbs = self
+            .leaf_limbs
+            .iter()
+            .enumerate()
+            .map(|(asset, limbs)| {
+                limb_sum_chip.assign_limbs(
+                    layouter.namespace(|| format!("asset {}: assign leaf limbs", asset)),
+                    *limbs,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut current_hash = leaf_hash.clone();
+
+        for level in 0..LEVELS {
+            let namespace_prefix = format!("level {}", level);
+
+            // For each level assign the index to the circuit
+            let swap_bit_level = merkle_sum_tree_chip.assing_swap_bit(
+                layouter.namespace(|| format!("{}: assign swap bit", namespace_prefix)),
+                self.path_indices[level],
+            )?;
+
+            // For each level assign the hashes to the circuit
+            let (hash_left_current, hash_right_current) = merkle_sum_tree_chip
+                .assign_nodes_hashes_per_level(
+                    layouter.namespace(|| format!("{}: assign nodes hashes", namespace_prefix)),
+                    &current_hash,
+                    self.path_element_hashes[level],
+                    swap_bit_level.clone(),
+                )?;
+
+            let mut next_limbs = vec![];
+            let mut left_limbs = vec![];
+            let mut right_limbs = vec![];
+
+            // Within each level, assign the limbs of the balances to the circuit per asset and sum them with carry
+            for asset in 0..N_ASSETS {
+                let (left_limbs_asset, right_limbs_asset, next_limbs_asset) = merkle_sum_tree_chip
+                    .assign_nodes_limbs_per_asset(
+                        layouter.namespace(|| {
+                            format!("{}: asset {}: assign nodes limbs", namespace_prefix, asset)
+                        }),
+                        &limb_sum_chip,
+                        &current_limbs[asset],
+                        self.path_element_limbs[level][asset],
+                        swap_bit_level.clone(),
+                    )?;
+
+                // Each limb entering the sum is constrained to be less than 2^LIMB_BITS, so that the carry is uniquely determined
+                for (side, limbs) in [("left", &left_limbs_asset), ("right", &right_limbs_asset)] {
+                    limb_sum_chip.range_check_limbs(
+                        layouter.namespace(|| {
+                            format!(
+                                "{}: asset {}: range check {} limbs",
+                                namespace_prefix, asset, side
+                            )
+                        }),
+                        limbs,
+                    )?;
+                }
+
+                next_limbs.push(next_limbs_asset);
+                left_limbs.push(left_limbs_asset);
+                right_limbs.push(right_limbs_asset);
+            }
+
+            // create an hash_input array of length L that contains the left hash, the left limbs, the right hash and the right limbs
+            let hash_input_vec: Vec<AssignedCell<Fp, Fp>> = [hash_left_current]
+                .iter()
+                .chain(left_limbs.iter().flatten())
+                .chain([hash_right_current].iter())
+                .chain(right_limbs.iter().flatten())
+                .map(|x| x.to_owned())
+                .collect();
+
+            let hash_input: [AssignedCell<Fp, Fp>; L] = match hash_input_vec.try_into() {
+                Ok(arr) => arr,
+                Err(_) => panic!("Failed to convert Vec to Array"),
+            };
+
+            // compute the next hash
+            let computed_hash = poseidon_chip.hash(
+                layouter.namespace(|| format!("{}: perform poseidon hash", namespace_prefix)),
+                hash_input,
+            )?;
+
+            current_limbs = next_limbs;
+            current_hash = computed_hash;
+        }
+
+        // expose the first current hash, namely the leaf hash, as public input
+        config.expose_public(layouter.namespace(|| "public leaf hash"), &leaf_hash, 0)?;
+
+        // expose the last current hash, namely the root hash, as public input
+        config.expose_public(layouter.namespace(|| "public root hash"), &current_hash, 1)?;
+
+        // the limbs of the root balances are the limbs of a sum, so they are already range checked to LIMB_BITS bits
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use crate::chips::sha256::sha256_chip::{Sha256Chip, Sha256Config};
+use crate::circuits::merkle_sum_tree::{MstInclusionCircuit, MstInclusionConfig};
+use crate::merkle_sum_tree::{big_int_to_fp, LeafHasher, MerkleProof, MerkleSumTree};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for verifying the inclusion of an entry inside a merkle sum tree whose leaves are hashed with SHA-256, namely a tree built with [LeafHasher::Sha256].
+///
+/// Unlike [MstInclusionCircuit], which takes the leaf hash as given, the leaf hash is recomputed in-circuit from the username and the balances of the entry with the [Sha256Chip], so that it can be checked by a verifier with standard SHA-256 tooling.
+/// The middle nodes are hashed with Poseidon as in [MstInclusionCircuit]. The public inputs are the same, namely the leaf hash and the root hash.
+///
+/// # Type Parameters
+///
+/// * `LEVELS`: The number of levels of the merkle sum tree
+/// * `L`: The length of the hasher input of the middle nodes, namely 2 + (2 * N_ASSETS)
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+///
+/// # Fields
+///
+/// * `username`: The username of the entry as a field element. It must be less than 2^248, namely at most 31 bytes long
+/// * `inclusion`: The inclusion path of the leaf, see [MstInclusionCircuit]
+#[derive(Clone)]
+pub struct MstInclusionSha256LeafCircuit<const LEVELS: usize, const L: usize, const N_ASSETS: usize>
+{
+    pub username: Fp,
+    pub inclusion: MstInclusionCircuit<LEVELS, L, N_ASSETS>,
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize> CircuitExt<Fp>
+    for MstInclusionSha256LeafCircuit<LEVELS, L, N_ASSETS>
+{
+    /// Returns the number of public inputs of the circuit. It is 2, namely the leaf hash and the root hash
+    fn num_instance(&self) -> Vec<usize> {
+        vec![2]
+    }
+
+    /// Returns the values of the public inputs of the circuit, namely the leaf hash and the root hash
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        vec![vec![self.inclusion.leaf_hash, self.inclusion.root_hash]]
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize>
+    MstInclusionSha256LeafCircuit<LEVELS, L, N_ASSETS>
+{
+    pub fn init_empty() -> Self {
+        Self {
+            username: Fp::zero(),
+            inclusion: MstInclusionCircuit::init_empty(),
+        }
+    }
+
+    /// Initializes the circuit with the merkle sum tree and the index of the user of which the inclusion is to be verified.
+    /// The leaves of the tree must be hashed with [LeafHasher::Sha256]
+    pub fn init(merkle_sum_tree: MerkleSumTree<N_ASSETS>, user_index: usize) -> Self {
+        let proof = merkle_sum_tree.generate_proof(user_index).unwrap();
+
+        Self::init_from_proof(proof)
+    }
+
+    /// Initializes the circuit with a merkle proof of a tree whose leaves are hashed with [LeafHasher::Sha256]
+    pub fn init_from_proof(proof: MerkleProof<N_ASSETS>) -> Self {
+        assert_eq!(proof.leaf_hasher, LeafHasher::Sha256);
+
+        Self {
+            username: big_int_to_fp(proof.entry.username_to_big_int()),
+            inclusion: MstInclusionCircuit::init_from_proof(proof),
+        }
+    }
+}
+
+/// Configuration for the Mst Inclusion circuit with SHA-256 leaves
+///
+/// # Type Parameters
+///
+/// * `L`: The length of the hasher input of the middle nodes, namely 2 + (2 * N_ASSETS)
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+///
+/// # Fields
+///
+/// * `inclusion_config`: Configuration for the inclusion path, see [MstInclusionConfig]
+/// * `sha256_config`: Configuration for the SHA-256 chip
+#[derive(Debug, Clone)]
+pub struct MstInclusionSha256LeafConfig<const L: usize, const N_ASSETS: usize> {
+    pub inclusion_config: MstInclusionConfig<L, N_ASSETS>,
+    pub sha256_config: Sha256Config,
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize> Circuit<Fp>
+    for MstInclusionSha256LeafCircuit<LEVELS, L, N_ASSETS>
+{
+    type Config = MstInclusionSha256LeafConfig<L, N_ASSETS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    /// Configures the circuit
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MstInclusionSha256LeafConfig {
+            inclusion_config: MstInclusionConfig::<L, N_ASSETS>::configure(meta),
+            sha256_config: Sha256Chip::configure(meta),
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let (leaf_hash, leaf_balances, root_hash) = self
+            .inclusion
+            .assign_inclusion_path(&config.inclusion_config, &mut layouter)?;
+
+        let username = layouter.assign_region(
+            || "assign username",
+            |mut region| {
+                region.assign_advice(
+                    || "username",
+                    config.sha256_config.value,
+                    0,
+                    || Value::known(self.username),
+                )
+            },
+        )?;
+
+        // recompute the leaf hash from the username and the balances of the leaf
+        let sha256_chip = Sha256Chip::construct(config.sha256_config.clone());
+        let hash_inputs = std::iter::once(username)
+            .chain(leaf_balances)
+            .collect::<Vec<_>>();
+        let computed_leaf_hash =
+            sha256_chip.hash_field_elements(layouter.namespace(|| "sha256 leaf"), &hash_inputs)?;
+
+        layouter.assign_region(
+            || "constrain leaf hash",
+            |mut region| region.constrain_equal(computed_leaf_hash.cell(), leaf_hash.cell()),
+        )?;
+
+        // expose the leaf hash and the root hash as public input
+        config.inclusion_config.expose_public(
+            layouter.namespace(|| "public leaf hash"),
+            &leaf_hash,
+            0,
+        )?;
+        config.inclusion_config.expose_public(
+            layouter.namespace(|| "public root hash"),
+            &root_hash,
+            1,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use crate::chips::poseidon::poseidon_spec::PoseidonSpec;
+use crate::chips::poseidon::sponge::{PoseidonSpongeChip, PoseidonSpongeConfig};
+use crate::circuits::merkle_sum_tree::{MstInclusionCircuit, MstInclusionConfig};
+use crate::merkle_sum_tree::{poseidon_snapshot_root, MerkleProof, MerkleSumTree};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed};
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for verifying the inclusion of an entry inside a snapshot of a merkle sum tree taken at a given timestamp or block height, so that a published proof is unambiguously tied to the time at which the balances were captured.
+///
+/// The root hash of the tree is computed as in [MstInclusionCircuit], then the snapshot root is computed in-circuit as `H(root_hash, timestamp)`, see [poseidon_snapshot_root].
+/// The root hash is kept private, the public inputs are the leaf hash, the snapshot root and the timestamp, so that the same tree published at another timestamp has a different snapshot root.
+///
+/// # Type Parameters
+///
+/// * `LEVELS`: The number of levels of the merkle sum tree
+/// * `L`: The length of the hasher input of the middle nodes, namely 2 + (2 * N_ASSETS)
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+///
+/// # Fields
+///
+/// * `timestamp`: The timestamp or block height at which the snapshot of the tree was taken
+/// * `inclusion`: The inclusion path of the leaf, see [MstInclusionCircuit]
+#[derive(Clone)]
+pub struct MstInclusionSnapshotCircuit<const LEVELS: usize, const L: usize, const N_ASSETS: usize> {
+    pub timestamp: u64,
+    pub inclusion: MstInclusionCircuit<LEVELS, L, N_ASSETS>,
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize> CircuitExt<Fp>
+    for MstInclusionSnapshotCircuit<LEVELS, L, N_ASSETS>
+{
+    /// Returns the number of public inputs of the circuit. It is 3, namely the leaf hash, the snapshot root and the timestamp
+    fn num_instance(&self) -> Vec<usize> {
+        vec![3]
+    }
+
+    /// Returns the values of the public inputs of the circuit, namely the leaf hash, the snapshot root and the timestamp
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        vec![vec![
+            self.inclusion.leaf_hash,
+            self.snapshot_root(),
+            Fp::from(self.timestamp),
+        ]]
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize>
+    MstInclusionSnapshotCircuit<LEVELS, L, N_ASSETS>
+{
+    pub fn init_empty() -> Self {
+        Self {
+            timestamp: 0,
+            inclusion: MstInclusionCircuit::init_empty(),
+        }
+    }
+
+    /// Initializes the circuit with the merkle sum tree, the index of the user of which the inclusion is to be verified and the timestamp at which the snapshot of the tree was taken
+    pub fn init(
+        merkle_sum_tree: MerkleSumTree<N_ASSETS>,
+        user_index: usize,
+        timestamp: u64,
+    ) -> Self {
+        let proof = merkle_sum_tree.generate_proof(user_index).unwrap();
+
+        Self::init_from_proof(proof, timestamp)
+    }
+
+    /// Initializes the circuit with a merkle proof and the timestamp at which the snapshot of the tree was taken
+    pub fn init_from_proof(proof: MerkleProof<N_ASSETS>, timestamp: u64) -> Self {
+        Self {
+            timestamp,
+            inclusion: MstInclusionCircuit::init_from_proof(proof),
+        }
+    }
+
+    /// Returns the snapshot root, namely the root hash of the tree hashed together with the timestamp
+    pub fn snapshot_root(&self) -> Fp {
+        poseidon_snapshot_root(self.inclusion.root_hash, self.timestamp)
+    }
+}
+
+/// Configuration for the Mst Inclusion circuit of a snapshot
+///
+/// # Fields
+///
+/// * `inclusion_config`: Configuration for the inclusion path, see [MstInclusionConfig]
+/// * `sponge_config`: Configuration for the poseidon sponge used to hash the root hash together with the timestamp, with WIDTH = 3 and RATE = 2
+/// * `advice`: Advice columns used to assign the timestamp and by the sponge
+#[derive(Debug, Clone)]
+pub struct MstInclusionSnapshotConfig<const L: usize, const N_ASSETS: usize> {
+    pub inclusion_config: MstInclusionConfig<L, N_ASSETS>,
+    pub sponge_config: PoseidonSpongeConfig<3, 2>,
+    pub advice: [Column<Advice>; 4],
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize> Circuit<Fp>
+    for MstInclusionSnapshotCircuit<LEVELS, L, N_ASSETS>
+{
+    type Config = MstInclusionSnapshotConfig<L, N_ASSETS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    /// Configures the circuit
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let inclusion_config = MstInclusionConfig::<L, N_ASSETS>::configure(meta);
+
+        // the sponge requires #WIDTH advice columns for state and 1 for partial_sbox, 3 fixed columns for rc_a and 3 for rc_b
+        let advice: [Column<Advice>; 4] = std::array::from_fn(|_| meta.advice_column());
+        let fixed_columns: [Column<Fixed>; 6] = std::array::from_fn(|_| meta.fixed_column());
+
+        for col in &advice {
+            meta.enable_equality(*col);
+        }
+
+        let sponge_config = PoseidonSpongeChip::<PoseidonSpec, 3, 2>::configure(
+            meta,
+            advice[0..3].try_into().unwrap(),
+            advice[3],
+            fixed_columns[0..3].try_into().unwrap(),
+            fixed_columns[3..6].try_into().unwrap(),
+        );
+
+        MstInclusionSnapshotConfig {
+            inclusion_config,
+            sponge_config,
+            advice,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let (leaf_hash, _, root_hash) = self
+            .inclusion
+            .assign_inclusion_path(&config.inclusion_config, &mut layouter)?;
+
+        let timestamp = layouter.assign_region(
+            || "assign timestamp",
+            |mut region| {
+                region.assign_advice(
+                    || "timestamp",
+                    config.advice[0],
+                    0,
+                    || Value::known(Fp::from(self.timestamp)),
+                )
+            },
+        )?;
+
+        // hash the root hash together with the timestamp
+        let sponge_chip = PoseidonSpongeChip::<PoseidonSpec, 3, 2>::construct(config.sponge_config);
+        let snapshot_root = sponge_chip.hash_variable(
+            layouter.namespace(|| "snapshot root"),
+            &[root_hash, timestamp.clone()],
+        )?;
+
+        // expose the leaf hash, the snapshot root and the timestamp as public input
+        config.inclusion_config.expose_public(
+            layouter.namespace(|| "public leaf hash"),
+            &leaf_hash,
+            0,
+        )?;
+        config.inclusion_config.expose_public(
+            layouter.namespace(|| "public snapshot root"),
+            &snapshot_root,
+            1,
+        )?;
+        config.inclusion_config.expose_public(
+            layouter.namespace(|| "public timestamp"),
+            &timestamp,
+            2,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,325 @@
+use crate::chips::less_than::less_than_vertical::{LtVerticalChip, LtVerticalInstruction};
+use crate::chips::merkle_sum_tree::MerkleSumTreeChip;
+use crate::chips::poseidon::hash::PoseidonChip;
+use crate::chips::poseidon::poseidon_spec::PoseidonSpec;
+use crate::circuits::merkle_sum_tree::MstEntryConfig;
+use crate::circuits::merkle_sum_tree_exclusion::USERNAME_BYTES;
+use crate::circuits::solvency::SolvencyConfig;
+use crate::merkle_sum_tree::{big_int_to_fp, KeyedMerkleProof, KeyedMerkleSumTree, MOD_BITS};
+use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for verifying the inclusion of an entry in a [KeyedMerkleSumTree], in which the direction of the path at each level is derived from the usernames committed in the tree rather than supplied as a witness.
+///
+/// Each middle node of a [KeyedMerkleSumTree] commits to the keys of its children, namely the least and the greatest username of their subtrees.
+/// The leaf hash is recomputed from the private username and balances, so that the keys of the leaf are the username of the proven entry, and the keys of the siblings are hashed together with their hashes and balances up to the published root.
+/// At each level, the circuit enforces the greatest username of the left child to be less than the least username of the right child.
+/// Since the subtree of the path contains the username of the entry, a swap bit that puts the sibling on the wrong side contradicts the committed keys of the sibling, so that the path indices are no longer a free witness.
+/// The ordering of the tree outside of the path must be checked by other means, as for [crate::circuits::merkle_sum_tree_exclusion::MstExclusionCircuit].
+///
+/// # Type Parameters
+///
+/// * `LEVELS`: The number of levels of the merkle sum tree
+/// * `L`: The length of the hasher input of the middle nodes, namely 2 * (3 + N_ASSETS)
+/// * `L_ENTRY`: The length of the hasher input of the leaves, namely 1 + N_ASSETS
+/// * `N_ASSETS`: The number of assets of each entry
+///
+/// # Fields
+///
+/// * `username`: The username of the entry, converted to a field element, private
+/// * `leaf_hash`: The hash of the leaf of the entry
+/// * `leaf_balances`: The balances of the entry. The length of this vector is N_ASSETS
+/// * `sibling_hashes`: The hashes of the siblings from the leaf to the root. The length of this vector is LEVELS
+/// * `sibling_balances`: The balances of the siblings from the leaf to the root. The length of this vector is LEVELS
+/// * `sibling_keys`: The least and the greatest username of the subtree of each sibling from the leaf to the root. The length of this vector is LEVELS
+/// * `path_indices`: The direction bits of the path from the leaf to the root. true indicates that the sibling is on the left. The length of this vector is LEVELS
+/// * `root_hash`: The root hash of the tree
+#[derive(Clone)]
+pub struct MstSortedInclusionCircuit<
+    const LEVELS: usize,
+    const L: usize,
+    const L_ENTRY: usize,
+    const N_ASSETS: usize,
+> {
+    pub username: Fp,
+    pub leaf_hash: Fp,
+    pub leaf_balances: Vec<Fp>,
+    pub sibling_hashes: Vec<Fp>,
+    pub sibling_balances: Vec<[Fp; N_ASSETS]>,
+    pub sibling_keys: Vec<[Fp; 2]>,
+    pub path_indices: Vec<bool>,
+    pub root_hash: Fp,
+}
+
+impl<const LEVELS: usize, const L: usize, const L_ENTRY: usize, const N_ASSETS: usize>
+    CircuitExt<Fp> for MstSortedInclusionCircuit<LEVELS, L, L_ENTRY, N_ASSETS>
+{
+    /// Returns the number of public inputs of the circuit. It is 2, namely the leaf hash and the root hash
+    fn num_instance(&self) -> Vec<usize> {
+        vec![2]
+    }
+
+    /// Returns the values of the public inputs of the circuit, namely the leaf hash and the root hash of the merkle sum tree
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        vec![vec![self.leaf_hash, self.root_hash]]
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const L_ENTRY: usize, const N_ASSETS: usize>
+    MstSortedInclusionCircuit<LEVELS, L, L_ENTRY, N_ASSETS>
+{
+    pub fn init_empty() -> Self {
+        assert_eq!(N_ASSETS + 1, L_ENTRY);
+        assert_eq!(2 * (3 + N_ASSETS), L);
+
+        Self {
+            username: Fp::zero(),
+            leaf_hash: Fp::zero(),
+            leaf_balances: vec![Fp::zero(); N_ASSETS],
+            sibling_hashes: vec![Fp::zero(); LEVELS],
+            sibling_balances: vec![[Fp::zero(); N_ASSETS]; LEVELS],
+            sibling_keys: vec![[Fp::zero(); 2]; LEVELS],
+            path_indices: vec![false; LEVELS],
+            root_hash: Fp::zero(),
+        }
+    }
+
+    /// Initializes the circuit with a [KeyedMerkleSumTree] and the username of the entry whose inclusion is to be verified.
+    /// Returns an error if the username is not included in the tree
+    pub fn init(
+        merkle_sum_tree: &KeyedMerkleSumTree<N_ASSETS>,
+        username: &str,
+    ) -> Result<Self, &'static str> {
+        let index = merkle_sum_tree
+            .index_of(username)
+            .ok_or("The username is not included in the tree")?;
+
+        Ok(Self::init_from_proof(
+            merkle_sum_tree.generate_proof(index)?,
+        ))
+    }
+
+    /// Initializes the circuit with an inclusion proof of a [KeyedMerkleSumTree]
+    pub fn init_from_proof(proof: KeyedMerkleProof<N_ASSETS>) -> Self {
+        assert_eq!(N_ASSETS + 1, L_ENTRY);
+        assert_eq!(2 * (3 + N_ASSETS), L);
+        assert_eq!(proof.path_indices.len(), LEVELS);
+
+        Self {
+            username: big_int_to_fp(proof.entry.username_to_big_int()),
+            leaf_hash: proof.entry.compute_leaf().hash,
+            leaf_balances: proof.entry.balances().iter().map(big_int_to_fp).collect(),
+            sibling_hashes: proof.sibling_hashes,
+            sibling_balances: proof.sibling_sums,
+            sibling_keys: proof.sibling_keys,
+            path_indices: proof.path_indices,
+            root_hash: proof.root_hash,
+        }
+    }
+}
+
+/// Configuration for the Mst Sorted Inclusion circuit
+///
+/// # Fields
+///
+/// * `entry_config`: Configuration for recomputing the leaf hash. Its advice columns are used to assign the username
+/// * `solvency_config`: Configuration for the inclusion path, the middle node hasher over the keys and the less than chip that compares the keys and range checks the balances. Its instance column is used to store the public inputs
+#[derive(Debug, Clone)]
+pub struct MstSortedInclusionConfig<const L: usize, const L_ENTRY: usize, const N_ASSETS: usize> {
+    pub entry_config: MstEntryConfig<L_ENTRY>,
+    pub solvency_config: SolvencyConfig<L, N_ASSETS, USERNAME_BYTES>,
+}
+
+impl<const L: usize, const L_ENTRY: usize, const N_ASSETS: usize>
+    MstSortedInclusionConfig<L, L_ENTRY, N_ASSETS>
+{
+    /// Configures the inclusion path, the middle node hasher, the less than chip and the entry hasher
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> Self {
+        let solvency_config = SolvencyConfig::<L, N_ASSETS, USERNAME_BYTES>::configure(meta);
+
+        let entry_config = MstEntryConfig::<L_ENTRY>::configure(meta);
+
+        Self {
+            entry_config,
+            solvency_config,
+        }
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const L_ENTRY: usize, const N_ASSETS: usize> Circuit<Fp>
+    for MstSortedInclusionCircuit<LEVELS, L, L_ENTRY, N_ASSETS>
+{
+    type Config = MstSortedInclusionConfig<L, L_ENTRY, N_ASSETS>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MstSortedInclusionConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let merkle_sum_tree_chip = MerkleSumTreeChip::<N_ASSETS>::construct(
+            config.solvency_config.merkle_sum_tree_config.clone(),
+        );
+        let poseidon_chip = PoseidonChip::<PoseidonSpec, 3, 2, L>::construct(
+            config.solvency_config.poseidon_config.clone(),
+        );
+
+        let lt_chip = LtVerticalChip::<USERNAME_BYTES>::construct(config.solvency_config.lt_config);
+        lt_chip.load(&mut layouter)?;
+
+        let (leaf_hash, leaf_balances) = merkle_sum_tree_chip.assign_entry_hash_and_balances(
+            layouter.namespace(|| "assign leaf hash and balances"),
+            self.leaf_hash,
+            &self.leaf_balances,
+        )?;
+
+        let username = layouter.assign_region(
+            || "assign username",
+            |mut region| {
+                region.assign_advice(
+                    || "username",
+                    config.entry_config.advice[0],
+                    0,
+                    || Value::known(self.username),
+                )
+            },
+        )?;
+
+        config.entry_config.enforce_leaf_hash(
+            layouter.namespace(|| "enforce leaf hash"),
+            &username,
+            &leaf_balances,
+            &leaf_hash,
+        )?;
+
+        // the least and the greatest username of the subtree of a leaf are its username
+        let mut current_hash = leaf_hash.clone();
+        let mut current_balances = leaf_balances;
+        let mut current_keys = [username.clone(), username];
+
+        for level in 0..LEVELS {
+            let namespace_prefix = format!("level {}", level);
+
+            let swap_bit = merkle_sum_tree_chip.assing_swap_bit(
+                layouter.namespace(|| format!("{}: assign swap bit", namespace_prefix)),
+                self.path_indices[level],
+            )?;
+
+            let (left_hash, right_hash) = merkle_sum_tree_chip.assign_nodes_hashes_per_level(
+                layouter.namespace(|| format!("{}: assign nodes hashes", namespace_prefix)),
+                &current_hash,
+                self.sibling_hashes[level],
+                swap_bit.clone(),
+            )?;
+
+            // the keys are swapped by the same swap bit as the hashes
+            let mut left_keys = vec![];
+            let mut right_keys = vec![];
+
+            for (key, current_key) in current_keys.iter().enumerate() {
+                let (left_key, right_key) = merkle_sum_tree_chip.assign_nodes_hashes_per_level(
+                    layouter.namespace(|| {
+                        format!("{}: key {}: assign nodes keys", namespace_prefix, key)
+                    }),
+                    current_key,
+                    self.sibling_keys[level][key],
+                    swap_bit.clone(),
+                )?;
+
+                left_keys.push(left_key);
+                right_keys.push(right_key);
+            }
+
+            let mut next_balances = vec![];
+            let mut left_balances = vec![];
+            let mut right_balances = vec![];
+
+            for asset in 0..N_ASSETS {
+                let (left_balance, right_balance, next_balance) = merkle_sum_tree_chip
+                    .assign_nodes_balance_per_asset(
+                        layouter.namespace(|| {
+                            format!(
+                                "{}: asset {}: assign nodes balance",
+                                namespace_prefix, asset
+                            )
+                        }),
+                        &current_balances[asset],
+                        self.sibling_balances[level][asset],
+                        swap_bit.clone(),
+                    )?;
+
+                // Each balance cell is constrained to be less than the overflow limit
+                for (side, balance) in [("left", &left_balance), ("right", &right_balance)] {
+                    config.solvency_config.enforce_range(
+                        layouter.namespace(|| {
+                            format!(
+                                "{}: asset {}: overflow check {} balance",
+                                namespace_prefix, asset, side
+                            )
+                        }),
+                        balance,
+                        MOD_BITS,
+                        &lt_chip,
+                    )?;
+                }
+
+                next_balances.push(next_balance);
+                left_balances.push(left_balance);
+                right_balances.push(right_balance);
+            }
+
+            // the greatest username of the left child must be less than the least username of the right child
+            config.solvency_config.enforce_cell_less_than(
+                layouter.namespace(|| format!("{}: enforce sorted keys", namespace_prefix)),
+                &left_keys[1],
+                &right_keys[0],
+                &lt_chip,
+            )?;
+
+            let next_keys = [left_keys[0].clone(), right_keys[1].clone()];
+
+            // the hash input is made of the hash, the balances and the keys of the left child followed by the ones of the right child
+            let hash_input: [AssignedCell<Fp, Fp>; L] = [left_hash]
+                .into_iter()
+                .chain(left_balances)
+                .chain(left_keys)
+                .chain([right_hash])
+                .chain(right_balances)
+                .chain(right_keys)
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+
+            current_hash = poseidon_chip.hash(
+                layouter.namespace(|| format!("{}: perform poseidon hash", namespace_prefix)),
+                hash_input,
+            )?;
+            current_balances = next_balances;
+            current_keys = next_keys;
+        }
+
+        // expose the leaf hash and the root hash as public input
+        config.solvency_config.expose_public(
+            layouter.namespace(|| "public leaf hash"),
+            &leaf_hash,
+            0,
+        )?;
+        config.solvency_config.expose_public(
+            layouter.namespace(|| "public root hash"),
+            &current_hash,
+            1,
+        )?;
+
+        Ok(())
+    }
+}
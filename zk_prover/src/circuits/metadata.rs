@@ -0,0 +1,133 @@
+use crate::circuits::merkle_sum_tree::MstInclusionCircuit;
+use crate::circuits::solvency::SolvencyCircuit;
+use halo2_proofs::halo2curves::bn256::{Fr as Fp, G1Affine};
+use halo2_proofs::plonk::VerifyingKey;
+use serde::Serialize;
+use snark_verifier_sdk::CircuitExt;
+
+/// A public input of a circuit
+///
+/// # Fields
+///
+/// * `column`: The index of the instance column in which the public input is stored
+/// * `row`: The row of the instance column in which the public input is stored
+/// * `description`: The semantic meaning of the public input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InstanceSlot {
+    pub column: usize,
+    pub row: usize,
+    pub description: String,
+}
+
+/// Machine-readable metadata about a circuit, meant to be consumed by frontend and contract-generation tooling.
+///
+/// # Fields
+///
+/// * `name`: The name of the circuit
+/// * `k`: The circuit has 2^k rows
+/// * `num_instance_columns`: The number of instance columns
+/// * `num_instance`: The number of public inputs per instance column
+/// * `instance_slots`: The position and the meaning of each public input
+/// * `vk_fingerprint`: The hex encoded transcript representation of the verifying key, which changes with any change to the circuit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CircuitMetadata {
+    pub name: String,
+    pub k: u32,
+    pub num_instance_columns: usize,
+    pub num_instance: Vec<usize>,
+    pub instance_slots: Vec<InstanceSlot>,
+    pub vk_fingerprint: String,
+}
+
+impl CircuitMetadata {
+    /// Serializes the metadata as a pretty printed JSON string
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// Describes the layout of the public inputs of a circuit
+pub trait InstanceLayout {
+    /// Returns the name of the circuit
+    fn name(&self) -> String;
+
+    /// Returns the position and the meaning of each public input of the circuit
+    fn instance_slots(&self) -> Vec<InstanceSlot>;
+}
+
+/// Exports the metadata of `circuit`, whose verifying key is `vk`
+pub fn export_circuit_metadata<C: CircuitExt<Fp> + InstanceLayout>(
+    circuit: &C,
+    vk: &VerifyingKey<G1Affine>,
+) -> CircuitMetadata {
+    let num_instance = circuit.num_instance();
+
+    CircuitMetadata {
+        name: circuit.name(),
+        k: vk.get_domain().k(),
+        num_instance_columns: num_instance.len(),
+        num_instance,
+        instance_slots: circuit.instance_slots(),
+        vk_fingerprint: format!("{:?}", vk.transcript_repr()),
+    }
+}
+
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const N_ASSETS: usize,
+        const N_INSTANCE_COLUMNS: usize,
+        const SUM_BITS: usize,
+        const LEAF_BITS: usize,
+    > InstanceLayout
+    for MstInclusionCircuit<LEVELS, L, N_ASSETS, N_INSTANCE_COLUMNS, SUM_BITS, LEAF_BITS>
+{
+    fn name(&self) -> String {
+        "MstInclusionCircuit".to_string()
+    }
+
+    /// The public inputs are laid out across the instance columns in row-major order, see [crate::circuits::merkle_sum_tree::MstInclusionConfig::expose_public]
+    fn instance_slots(&self) -> Vec<InstanceSlot> {
+        let n_root_balances = if self.root_balances_public {
+            N_ASSETS
+        } else {
+            0
+        };
+
+        ["leaf hash".to_string(), "root hash".to_string()]
+            .into_iter()
+            .chain((0..n_root_balances).map(|asset| format!("root balance of asset {}", asset)))
+            .chain((0..self.extra_public_inputs.len()).map(|i| format!("extra public input {}", i)))
+            .enumerate()
+            .map(|(index, description)| InstanceSlot {
+                column: index % N_INSTANCE_COLUMNS,
+                row: index / N_INSTANCE_COLUMNS,
+                description,
+            })
+            .collect()
+    }
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> InstanceLayout
+    for SolvencyCircuit<L, N_ASSETS, N_BYTES>
+{
+    fn name(&self) -> String {
+        "SolvencyCircuit".to_string()
+    }
+
+    fn instance_slots(&self) -> Vec<InstanceSlot> {
+        let mut descriptions = vec!["root hash".to_string()];
+        descriptions.extend((0..N_ASSETS).map(|asset| format!("assets sum of asset {}", asset)));
+        descriptions.push("number of assets".to_string());
+
+        descriptions
+            .into_iter()
+            .enumerate()
+            .map(|(row, description)| InstanceSlot {
+                column: 0,
+                row,
+                description,
+            })
+            .collect()
+    }
+}
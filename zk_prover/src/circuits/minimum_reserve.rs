@@ -0,0 +1,212 @@
+use crate::chips::less_than::less_than_vertical::{LtVerticalChip, LtVerticalInstruction};
+use crate::circuits::merkle_sum_tree::{MstInclusionCircuit, MstInclusionConfig};
+use crate::circuits::solvency::SolvencyConfig;
+use crate::merkle_sum_tree::MerkleSumTree;
+use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for proving that a sample of users is included in a merkle sum tree and that each sampled user holds at least a committed minimum reserve, namely `balance[i] >= minimum_reserve[i]` for each asset.
+///
+/// The balances of the sampled users are kept private, only their leaf hashes are exposed so that the verifier knows which users have been sampled.
+///
+/// # Type Parameters
+///
+/// * `LEVELS`: The number of levels of the merkle sum tree
+/// * `L`: The length of the hasher input, namely 2 + (2 * N_ASSETS)
+/// * `N_ASSETS`: The number of assets of each entry
+/// * `N_BYTES`: Range in which the balances should lie
+/// * `N_SAMPLES`: The number of sampled users
+///
+/// # Fields
+///
+/// * `samples`: The inclusion witnesses of the sampled users
+/// * `minimum_reserve`: The minimum balance per each asset that every sampled user must hold
+#[derive(Clone)]
+pub struct MinimumReserveCircuit<
+    const LEVELS: usize,
+    const L: usize,
+    const N_ASSETS: usize,
+    const N_BYTES: usize,
+    const N_SAMPLES: usize,
+> {
+    pub samples: [MstInclusionCircuit<LEVELS, L, N_ASSETS>; N_SAMPLES],
+    pub minimum_reserve: [Fp; N_ASSETS],
+}
+
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const N_ASSETS: usize,
+        const N_BYTES: usize,
+        const N_SAMPLES: usize,
+    > CircuitExt<Fp> for MinimumReserveCircuit<LEVELS, L, N_ASSETS, N_BYTES, N_SAMPLES>
+{
+    /// Returns the number of public inputs of the circuit. It is 1 + N_ASSETS + N_SAMPLES, namely the root hash, the minimum reserve for each asset and the leaf hashes of the sampled users
+    fn num_instance(&self) -> Vec<usize> {
+        vec![1 + N_ASSETS + N_SAMPLES]
+    }
+
+    /// Returns the values of the public inputs of the circuit. The first value is the root hash of the merkle sum tree, followed by the minimum reserve for each asset and by the leaf hashes of the sampled users
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        let root_hash = self
+            .samples
+            .first()
+            .map(|sample| sample.root_hash)
+            .unwrap_or(Fp::zero());
+
+        let mut instances = vec![root_hash];
+        instances.extend(self.minimum_reserve);
+        instances.extend(self.samples.iter().map(|sample| sample.leaf_hash));
+        vec![instances]
+    }
+}
+
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const N_ASSETS: usize,
+        const N_BYTES: usize,
+        const N_SAMPLES: usize,
+    > MinimumReserveCircuit<LEVELS, L, N_ASSETS, N_BYTES, N_SAMPLES>
+{
+    pub fn init_empty() -> Self {
+        Self {
+            samples: std::array::from_fn(|_| MstInclusionCircuit::init_empty()),
+            minimum_reserve: [Fp::zero(); N_ASSETS],
+        }
+    }
+
+    /// Initializes the circuit with the merkle sum tree, the indexes of the sampled users and the minimum reserve
+    pub fn init(
+        merkle_sum_tree: MerkleSumTree<N_ASSETS>,
+        user_indexes: [usize; N_SAMPLES],
+        minimum_reserve: [Fp; N_ASSETS],
+    ) -> Self {
+        Self {
+            samples: user_indexes
+                .map(|user_index| MstInclusionCircuit::init(merkle_sum_tree.clone(), user_index)),
+            minimum_reserve,
+        }
+    }
+}
+
+/// Configuration for the minimum reserve circuit
+///
+/// # Fields
+///
+/// * `mst_inclusion_config`: Configuration for the inclusion paths of the sampled users. Its instance column is used to store the public inputs
+/// * `solvency_config`: Configuration for the less than or equal comparison between the minimum reserve and the balances, sharing the instance column of `mst_inclusion_config`
+#[derive(Debug, Clone)]
+pub struct MinimumReserveConfig<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> {
+    pub mst_inclusion_config: MstInclusionConfig<L, N_ASSETS>,
+    pub solvency_config: SolvencyConfig<L, N_ASSETS, N_BYTES>,
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
+    MinimumReserveConfig<L, N_ASSETS, N_BYTES>
+{
+    /// Assigns the minimum reserve stored in the instance column at row `index` and returns the assigned cell
+    pub fn assign_minimum_reserve(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        index: usize,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        layouter.assign_region(
+            || "copy minimum reserve from instance column at row `index`",
+            |mut region| {
+                region.assign_advice_from_instance(
+                    || "copy minimum reserve from instance column",
+                    self.mst_inclusion_config.instances[0],
+                    index,
+                    self.solvency_config.merkle_sum_tree_config.advice[0],
+                    0,
+                )
+            },
+        )
+    }
+}
+
+impl<
+        const LEVELS: usize,
+        const L: usize,
+        const N_ASSETS: usize,
+        const N_BYTES: usize,
+        const N_SAMPLES: usize,
+    > Circuit<Fp> for MinimumReserveCircuit<LEVELS, L, N_ASSETS, N_BYTES, N_SAMPLES>
+{
+    type Config = MinimumReserveConfig<L, N_ASSETS, N_BYTES>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let mst_inclusion_config = MstInclusionConfig::<L, N_ASSETS>::configure(meta);
+
+        let solvency_config = SolvencyConfig::<L, N_ASSETS, N_BYTES>::configure_with_instance(
+            meta,
+            mst_inclusion_config.instances[0],
+        );
+
+        MinimumReserveConfig {
+            mst_inclusion_config,
+            solvency_config,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let lt_chip = LtVerticalChip::<N_BYTES>::construct(config.solvency_config.lt_config);
+        lt_chip.load(&mut layouter)?;
+
+        let minimum_reserve = (0..N_ASSETS)
+            .map(|asset| {
+                config.assign_minimum_reserve(
+                    layouter.namespace(|| format!("asset {}: assign minimum reserve", asset)),
+                    1 + asset,
+                )
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        for (sample_index, sample) in self.samples.iter().enumerate() {
+            let (leaf_hash, leaf_balances, root_hash) =
+                sample.assign_inclusion_path(&config.mst_inclusion_config, &mut layouter)?;
+
+            // every sampled user must be included in the same tree
+            config.mst_inclusion_config.expose_public(
+                layouter.namespace(|| format!("sample {}: public root hash", sample_index)),
+                &root_hash,
+                0,
+            )?;
+
+            // expose the leaf hash of the sampled user, as public input
+            config.mst_inclusion_config.expose_public(
+                layouter.namespace(|| format!("sample {}: public leaf hash", sample_index)),
+                &leaf_hash,
+                1 + N_ASSETS + sample_index,
+            )?;
+
+            for asset in 0..N_ASSETS {
+                config.solvency_config.enforce_cell_less_than_or_equal(
+                    layouter.namespace(|| {
+                        format!(
+                            "sample {} asset {}: enforce minimum reserve",
+                            sample_index, asset
+                        )
+                    }),
+                    &minimum_reserve[asset],
+                    &leaf_balances[asset],
+                    &lt_chip,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,6 +1,31 @@
 pub mod aggregation;
+pub mod balance_allowlist;
+pub mod balance_increase;
+pub mod batch_inclusion;
+#[cfg(feature = "dummy-prover")]
+pub mod dummy;
 pub mod ecdsa;
+pub mod evm_verifier;
+pub mod kzg_balances;
 pub mod merkle_sum_tree;
+pub mod merkle_sum_tree_any_root;
+pub mod merkle_sum_tree_commitment;
+pub mod merkle_sum_tree_committed_leaf;
+pub mod merkle_sum_tree_exclusion;
+pub mod merkle_sum_tree_limbs;
+pub mod merkle_sum_tree_sha256_leaf;
+pub mod merkle_sum_tree_snapshot;
+pub mod merkle_sum_tree_sorted;
+pub mod metadata;
+pub mod minimum_reserve;
+pub mod nary_merkle_sum_tree;
+pub mod partition;
+pub mod proof_bundle;
+pub mod reserves_ratio;
+pub mod secret;
+pub mod serialization;
 pub mod solvency;
+pub mod solvency_commitment;
 pub mod tests;
 pub mod utils;
+pub mod weighted_solvency;
@@ -0,0 +1,334 @@
+use crate::chips::merkle_sum_tree_nary::{NaryMerkleSumTreeChip, NaryMerkleSumTreeConfig};
+use crate::chips::overflow::overflow_check::{OverflowCheckConfig, OverflowChip};
+use crate::chips::poseidon::hash::{PoseidonChip, PoseidonConfig};
+use crate::chips::poseidon::poseidon_spec::PoseidonSpec;
+use crate::merkle_sum_tree::{big_int_to_fp, NaryMerkleProof, NaryMerkleSumTree, MOD_BITS};
+use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{
+    Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Instance, Selector,
+};
+use snark_verifier_sdk::CircuitExt;
+
+const MAX_BITS: u8 = 8;
+
+/// Circuit for verifying inclusion of a leaf_hash inside a N-ary merkle sum tree with a given root.
+///
+/// # Type Parameters
+///
+/// * `LEVELS`: The number of levels of the merkle sum tree
+/// * `L`: The length of the hasher input, namely ARITY * (1 + N_ASSETS)
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+/// * `ARITY`: The number of children of each middle node
+///
+/// # Fields
+///
+/// * `leaf_hash`: The hash of the leaf to be verified inclusion of
+/// * `leaf_balances`: The balances of the leaf to be verified inclusion of. The length of this vector is N_ASSETS
+/// * `path_element_hashes`: The hashes of the ARITY - 1 siblings at each level from the leaf to root. The length of this vector is LEVELS
+/// * `path_element_balances`: The balances of the ARITY - 1 siblings at each level from the leaf to the root. The length of this vector is LEVELS
+/// * `path_indices`: The position of the node of the path among its siblings at each level, in `0..ARITY`. The length of this vector is LEVELS
+/// * `root_hash`: The root hash of the merkle sum tree
+#[derive(Clone)]
+pub struct NaryMstInclusionCircuit<
+    const LEVELS: usize,
+    const L: usize,
+    const N_ASSETS: usize,
+    const ARITY: usize,
+> {
+    pub leaf_hash: Fp,
+    pub leaf_balances: Vec<Fp>,
+    pub path_element_hashes: Vec<Vec<Fp>>,
+    pub path_element_balances: Vec<Vec<[Fp; N_ASSETS]>>,
+    pub path_indices: Vec<usize>,
+    pub root_hash: Fp,
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize, const ARITY: usize> CircuitExt<Fp>
+    for NaryMstInclusionCircuit<LEVELS, L, N_ASSETS, ARITY>
+{
+    /// Returns the number of public inputs of the circuit. It is 2, namely the leaf hash to be verified inclusion of and the root hash of the merkle sum tree.
+    fn num_instance(&self) -> Vec<usize> {
+        vec![2]
+    }
+    /// Returns the values of the public inputs of the circuit. Namely the leaf hash to be verified inclusion of and the root hash of the merkle sum tree.
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        vec![vec![self.leaf_hash, self.root_hash]]
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize, const ARITY: usize>
+    NaryMstInclusionCircuit<LEVELS, L, N_ASSETS, ARITY>
+{
+    pub fn init_empty() -> Self {
+        assert_eq!(ARITY * (1 + N_ASSETS), L);
+
+        Self {
+            leaf_hash: Fp::zero(),
+            leaf_balances: vec![Fp::zero(); N_ASSETS],
+            path_element_hashes: vec![vec![Fp::zero(); ARITY - 1]; LEVELS],
+            path_element_balances: vec![vec![[Fp::zero(); N_ASSETS]; ARITY - 1]; LEVELS],
+            path_indices: vec![0; LEVELS],
+            root_hash: Fp::zero(),
+        }
+    }
+
+    /// Initializes the circuit with the N-ary merkle sum tree and the index of the user of which the inclusion is to be verified.
+    pub fn init(merkle_sum_tree: NaryMerkleSumTree<N_ASSETS, ARITY>, user_index: usize) -> Self {
+        let proof = merkle_sum_tree.generate_proof(user_index).unwrap();
+
+        Self::init_from_proof(proof)
+    }
+
+    /// Initializes the circuit with a N-ary merkle proof.
+    pub fn init_from_proof(proof: NaryMerkleProof<N_ASSETS, ARITY>) -> Self {
+        assert_eq!(ARITY * (1 + N_ASSETS), L);
+
+        assert_eq!(proof.path_indices.len(), LEVELS);
+        assert_eq!(proof.sibling_hashes.len(), LEVELS);
+        assert_eq!(proof.sibling_sums.len(), LEVELS);
+
+        Self {
+            leaf_hash: proof.entry.compute_leaf().hash,
+            leaf_balances: proof
+                .entry
+                .balances()
+                .iter()
+                .map(big_int_to_fp)
+                .collect::<Vec<_>>(),
+            path_element_hashes: proof.sibling_hashes,
+            path_element_balances: proof.sibling_sums,
+            path_indices: proof.path_indices,
+            root_hash: proof.root_hash,
+        }
+    }
+}
+
+/// Configuration for the N-ary Mst Inclusion circuit
+/// # Type Parameters
+///
+/// * `L`: The length of the hasher input, namely ARITY * (1 + N_ASSETS)
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+/// * `ARITY`: The number of children of each middle node
+///
+/// # Fields
+///
+/// * `merkle_sum_tree_config`: Configuration for the N-ary merkle sum tree
+/// * `poseidon_config`: Configuration for the poseidon hash function with WIDTH = 3 and RATE = 2
+/// * `overflow_check_config`: Configuration for the overflow check chip
+/// * `instance`: Instance column used to store the public inputs
+#[derive(Debug, Clone)]
+pub struct NaryMstInclusionConfig<const L: usize, const N_ASSETS: usize, const ARITY: usize> {
+    pub merkle_sum_tree_config: NaryMerkleSumTreeConfig<ARITY>,
+    pub poseidon_config: PoseidonConfig<3, 2, L>,
+    pub overflow_check_config: OverflowCheckConfig<MAX_BITS, MOD_BITS>,
+    pub instance: Column<Instance>,
+}
+
+impl<const L: usize, const N_ASSETS: usize, const ARITY: usize>
+    NaryMstInclusionConfig<L, N_ASSETS, ARITY>
+{
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> Self {
+        // the number of advice columns needed is the max between WIDTH + 1, given requirement of the poseidon config with WIDTH 3, and ARITY, given requirement of the merkle sum tree chip
+        let advices: Vec<Column<Advice>> =
+            (0..ARITY.max(4)).map(|_| meta.advice_column()).collect();
+
+        // we need 2 * WIDTH fixed columns for poseidon config with WIDTH 3 + 1 for the overflow check chip
+        let fixed_columns: [Column<Fixed>; 7] = std::array::from_fn(|_| meta.fixed_column());
+
+        // we also need 2 selectors for the NaryMerkleSumTreeChip and 1 for the overflow check chip
+        let selectors: [Selector; 3] = std::array::from_fn(|_| meta.selector());
+
+        // we need 1 complex selector for the lookup check
+        let toggle_lookup_check = meta.complex_selector();
+
+        // in fact, the poseidon config requires #WIDTH advice columns for state and 1 for partial_sbox, 3 fixed columns for rc_a and 3 for rc_b
+        let poseidon_config = PoseidonChip::<PoseidonSpec, 3, 2, L>::configure(
+            meta,
+            advices[0..3].try_into().unwrap(),
+            advices[3],
+            fixed_columns[0..3].try_into().unwrap(),
+            fixed_columns[3..6].try_into().unwrap(),
+        );
+
+        // enable permutation for all the advice columns
+        for col in &advices {
+            meta.enable_equality(*col);
+        }
+
+        // the configuration of the N-ary merkle_sum_tree requires ARITY advices, no matter the number of assets
+        let merkle_sum_tree_config = NaryMerkleSumTreeChip::<N_ASSETS, ARITY>::configure(
+            meta,
+            advices[0..ARITY].try_into().unwrap(),
+            selectors[0..2].try_into().unwrap(),
+        );
+
+        let overflow_check_config = OverflowChip::<MAX_BITS, MOD_BITS>::configure(
+            meta,
+            advices[0],
+            advices[1],
+            fixed_columns[6],
+            selectors[2],
+            toggle_lookup_check,
+        );
+
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        Self {
+            merkle_sum_tree_config,
+            poseidon_config,
+            overflow_check_config,
+            instance,
+        }
+    }
+
+    /// Enforce copy constraint check between input cell and instance column at row passed as input
+    pub fn expose_public(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        cell: &AssignedCell<Fp, Fp>,
+        row: usize,
+    ) -> Result<(), Error> {
+        layouter.constrain_instance(cell.cell(), self.instance, row)
+    }
+}
+
+impl<const LEVELS: usize, const L: usize, const N_ASSETS: usize, const ARITY: usize> Circuit<Fp>
+    for NaryMstInclusionCircuit<LEVELS, L, N_ASSETS, ARITY>
+{
+    type Config = NaryMstInclusionConfig<L, N_ASSETS, ARITY>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    /// Configures the circuit
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        NaryMstInclusionConfig::<L, N_ASSETS, ARITY>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        // build auxiliary chips
+        let merkle_sum_tree_chip = NaryMerkleSumTreeChip::<N_ASSETS, ARITY>::construct(
+            config.merkle_sum_tree_config.clone(),
+        );
+        let poseidon_chip =
+            PoseidonChip::<PoseidonSpec, 3, 2, L>::construct(config.poseidon_config.clone());
+        let overflow_check_chip =
+            OverflowChip::<MAX_BITS, MOD_BITS>::construct(config.overflow_check_config.clone());
+
+        // Assign the leaf hash and the leaf balances
+        let (leaf_hash, leaf_balances) = merkle_sum_tree_chip.assign_entry_hash_and_balances(
+            layouter.namespace(|| "assign leaf hash and balances"),
+            self.leaf_hash,
+            &self.leaf_balances,
+        )?;
+
+        let mut current_hash = leaf_hash.clone();
+        let mut current_balances = leaf_balances;
+
+        // load overflow check chip
+        overflow_check_chip.load(&mut layouter)?;
+
+        for level in 0..LEVELS {
+            let namespace_prefix = format!("level {}", level);
+
+            // For each level assign the position of the current node among its siblings to the circuit
+            let position_bits = merkle_sum_tree_chip.assign_position_bits(
+                layouter.namespace(|| format!("{}: assign position bits", namespace_prefix)),
+                self.path_indices[level],
+            )?;
+
+            // For each level assign the hashes of the children to the circuit
+            let children_hashes = merkle_sum_tree_chip.assign_nodes_hashes_per_level(
+                layouter.namespace(|| format!("{}: assign nodes hashes", namespace_prefix)),
+                &current_hash,
+                &self.path_element_hashes[level],
+                &position_bits,
+            )?;
+
+            let mut next_balances = vec![];
+            // the balances of the children, indexed by asset and then by child
+            let mut children_balances = vec![];
+
+            // Within each level, assign the balances of the children to the circuit per asset
+            for asset in 0..N_ASSETS {
+                let sibling_balances = self.path_element_balances[level]
+                    .iter()
+                    .map(|balances| balances[asset])
+                    .collect::<Vec<_>>();
+
+                let (children_balance, next_balance) = merkle_sum_tree_chip
+                    .assign_nodes_balance_per_asset(
+                        layouter.namespace(|| {
+                            format!(
+                                "{}: asset {}: assign nodes balance",
+                                namespace_prefix, asset
+                            )
+                        }),
+                        &current_balances[asset],
+                        &sibling_balances,
+                        &position_bits,
+                    )?;
+
+                // Each balance cell is constrained to be less than the overflow limit
+                for (child, balance) in children_balance.iter().enumerate() {
+                    overflow_check_chip.assign(
+                        layouter.namespace(|| {
+                            format!(
+                                "{}: asset {}: overflow check child {} balance",
+                                namespace_prefix, asset, child
+                            )
+                        }),
+                        balance,
+                    )?;
+                }
+
+                next_balances.push(next_balance);
+                children_balances.push(children_balance);
+            }
+
+            // create an hash_input array of length L that contains, for each child in order, its hash followed by its balances
+            let hash_input_vec: Vec<AssignedCell<Fp, Fp>> = children_hashes
+                .iter()
+                .enumerate()
+                .flat_map(|(child, hash)| {
+                    [hash.clone()].into_iter().chain(
+                        children_balances
+                            .iter()
+                            .map(move |balances| balances[child].clone()),
+                    )
+                })
+                .collect();
+
+            let hash_input: [AssignedCell<Fp, Fp>; L] = match hash_input_vec.try_into() {
+                Ok(arr) => arr,
+                Err(_) => panic!("Failed to convert Vec to Array"),
+            };
+
+            // compute the next hash
+            let computed_hash = poseidon_chip.hash(
+                layouter.namespace(|| format!("{}: perform poseidon hash", namespace_prefix)),
+                hash_input,
+            )?;
+
+            current_balances = next_balances;
+            current_hash = computed_hash;
+        }
+
+        // expose the first current hash, namely the leaf hash, as public input
+        config.expose_public(layouter.namespace(|| "public leaf hash"), &leaf_hash, 0)?;
+
+        // expose the last current hash, namely the root hash, as public input
+        config.expose_public(layouter.namespace(|| "public root hash"), &current_hash, 1)?;
+
+        // don't need to perform further range check on the balances of the root node as their addends are already constrained to be less than 2^MOD_BITS
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use crate::chips::less_than::less_than_vertical::{LtVerticalChip, LtVerticalInstruction};
+use crate::circuits::solvency::{assign_penultimate_nodes, SolvencyConfig};
+use crate::merkle_sum_tree::MerkleSumTree;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for verifying that the users of a merkle sum tree are split in two disjoint sets, for example users of two jurisdictions, whose balances add up to the root balances.
+///
+/// The two sets are the two halves of the tree: the first set is made of the users in the left half, namely the first 2^(depth - 1) leaves, and the second set of the users in the right half.
+/// The tree must therefore be built with the users of the first set filling its left half, for example with [MerkleSumTree::build_with_order].
+///
+/// The sum of each set is not a free witness: it is the balances of the penultimate node of its half, which the circuit hashes into the root together with the penultimate node of the other half.
+/// Since each leaf lies in exactly one half, a user counted in both sets, or left out of both, changes one of the sums and makes the proof fail.
+/// Both sums are exposed as public input and range checked to lie in `[0, 2^(8 * N_BYTES))`, so that neither of them can wrap around the modulus while their sum still matches the root balances.
+/// The sums of arbitrary sets can be checked off-circuit with [MerkleSumTree::partition_sums], which checks that the sets are disjoint and cover the whole tree.
+///
+/// # Type Parameters
+///
+/// * `L`: The length of the hasher input, namely 2 + (2 * N_ASSETS)
+/// * `N_ASSETS`: The number of assets for which the partition is verified.
+/// * `N_BYTES`: Range in which the sums of the two sets should lie
+///
+/// # Fields
+///
+/// * `left_node_hash`: The hash of the penultimate left node of the merkle sum tree
+/// * `left_node_balances`: The balances of the penultimate left node of the merkle sum tree
+/// * `right_node_hash`: The hash of the penultimate right node of the merkle sum tree
+/// * `right_node_balances`: The balances of the penultimate right node of the merkle sum tree
+/// * `partition_sums`: The claimed sum of the balances of the users in the first and in the second set for each asset, exposed as public input
+/// * `root_hash`: The root hash of the merkle sum tree
+#[derive(Clone)]
+pub struct PartitionCircuit<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> {
+    pub left_node_hash: Fp,
+    pub left_node_balances: [Fp; N_ASSETS],
+    pub right_node_hash: Fp,
+    pub right_node_balances: [Fp; N_ASSETS],
+    pub partition_sums: [[Fp; N_ASSETS]; 2],
+    pub root_hash: Fp,
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> CircuitExt<Fp>
+    for PartitionCircuit<L, N_ASSETS, N_BYTES>
+{
+    /// Returns the number of public inputs of the circuit. It is 1 + 2 * N_ASSETS, namely the root hash of the merkle sum tree and the sums of the two sets for each asset
+    fn num_instance(&self) -> Vec<usize> {
+        vec![1 + 2 * N_ASSETS]
+    }
+
+    /// Returns the values of the public inputs of the circuit. The first value is the root hash of the merkle sum tree, followed by the sums of the first set and by the sums of the second set for each asset
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        let mut instances = vec![self.root_hash];
+        instances.extend(self.partition_sums[0]);
+        instances.extend(self.partition_sums[1]);
+        vec![instances]
+    }
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
+    PartitionCircuit<L, N_ASSETS, N_BYTES>
+{
+    pub fn init_empty() -> Self {
+        assert_eq!((N_ASSETS * 2) + 2, L);
+        Self {
+            left_node_hash: Fp::zero(),
+            left_node_balances: [Fp::zero(); N_ASSETS],
+            right_node_hash: Fp::zero(),
+            right_node_balances: [Fp::zero(); N_ASSETS],
+            partition_sums: [[Fp::zero(); N_ASSETS]; 2],
+            root_hash: Fp::zero(),
+        }
+    }
+
+    /// Initializes the circuit with the merkle sum tree and the claimed sums of the two halves of the tree, as returned by [MerkleSumTree::partition_sums] for the users of the left and of the right half
+    pub fn init(
+        merkle_sum_tree: MerkleSumTree<N_ASSETS>,
+        partition_sums: [[Fp; N_ASSETS]; 2],
+    ) -> Self {
+        assert_eq!((N_ASSETS * 2) + 2, L);
+
+        let (penultimate_node_left, penultimate_node_right) = merkle_sum_tree
+            .penultimate_level_data()
+            .expect("Failed to retrieve penultimate level data");
+
+        let root_hash = merkle_sum_tree.root().hash;
+
+        Self {
+            left_node_hash: penultimate_node_left.hash,
+            left_node_balances: penultimate_node_left.balances,
+            right_node_hash: penultimate_node_right.hash,
+            right_node_balances: penultimate_node_right.balances,
+            partition_sums,
+            root_hash,
+        }
+    }
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> Circuit<Fp>
+    for PartitionCircuit<L, N_ASSETS, N_BYTES>
+{
+    type Config = SolvencyConfig<L, N_ASSETS, N_BYTES>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        SolvencyConfig::<L, N_ASSETS, N_BYTES>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let lt_chip = LtVerticalChip::<N_BYTES>::construct(config.lt_config);
+
+        // the sums of the two sets are the balances of the penultimate nodes, which are hashed into the root
+        let (root_hash, partition_sums, _) = assign_penultimate_nodes(
+            &config.merkle_sum_tree_config,
+            &config.poseidon_config,
+            &mut layouter,
+            self.left_node_hash,
+            &self.left_node_balances,
+            self.right_node_hash,
+            &self.right_node_balances,
+        )?;
+
+        // expose the root hash, as public input
+        config.expose_public(layouter.namespace(|| "public root hash"), &root_hash, 0)?;
+
+        // load lookup table for lt chip
+        lt_chip.load(&mut layouter)?;
+
+        for (set, sums) in partition_sums.iter().enumerate() {
+            for (asset, sum) in sums.iter().enumerate() {
+                config.enforce_range(
+                    layouter.namespace(|| format!("set {} asset {}: range check sum", set, asset)),
+                    sum,
+                    8 * N_BYTES,
+                    &lt_chip,
+                )?;
+
+                // expose the sum of the set, as public input
+                config.expose_public(
+                    layouter.namespace(|| format!("set {} asset {}: public sum", set, asset)),
+                    sum,
+                    1 + set * N_ASSETS + asset,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
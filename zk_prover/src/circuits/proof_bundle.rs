@@ -0,0 +1,188 @@
+use crate::chips::poseidon::poseidon_spec::PoseidonSpec;
+use crate::circuits::utils::{full_prover, full_verifier};
+use crate::merkle_sum_tree::{ecdsa_sign, ecdsa_verify, AttestationScalar};
+use halo2_gadgets::poseidon::primitives::{self as poseidon, ConstantLength};
+use halo2_proofs::{
+    halo2curves::{
+        bn256::{Bn256, Fr as Fp, G1Affine},
+        secp256k1::Secp256k1Affine as Secp256k1,
+    },
+    plonk::{Circuit, ProvingKey, VerifyingKey},
+    poly::kzg::commitment::ParamsKZG,
+};
+use snark_verifier_sdk::CircuitExt;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Number of bytes of the proof packed in each field element of the signed digest, so that each chunk is less than the modulus
+const PROOF_CHUNK_BYTES: usize = 31;
+
+/// A proof artifact signed by the server together with a nonce and the timestamp at which it was issued, so that a valid proof can't be replayed later to impersonate a fresh attestation.
+///
+/// The signature is an ECDSA signature on secp256k1 over [SignedProofBundle::digest], which commits to the proof, its public inputs, the nonce and the timestamp.
+///
+/// # Fields
+///
+/// * `proof`: The proof, as returned by [full_prover]
+/// * `public_inputs`: The public inputs of the proof, one vector per instance column
+/// * `nonce`: The nonce chosen by the server for this bundle
+/// * `timestamp`: The time at which the bundle was issued, in seconds
+/// * `public_key`: The public key of the server
+/// * `signature`: The ECDSA signature (r, s) over the digest of the bundle
+#[derive(Clone, Debug)]
+pub struct SignedProofBundle {
+    pub proof: Vec<u8>,
+    pub public_inputs: Vec<Vec<Fp>>,
+    pub nonce: u64,
+    pub timestamp: u64,
+    pub public_key: Secp256k1,
+    pub signature: (AttestationScalar, AttestationScalar),
+}
+
+/// Errors that can occur while verifying a [SignedProofBundle]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BundleError {
+    /// The bundle is not signed by the expected server key
+    InvalidSignature,
+    /// The proof is not valid for the public inputs
+    InvalidProof,
+    /// The bundle is older than the allowed maximum age
+    Expired { age: u64, max_age: u64 },
+    /// The bundle is issued after the current time
+    FutureTimestamp { timestamp: u64, now: u64 },
+    /// The nonce of the bundle has already been seen
+    ReplayedNonce(u64),
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::InvalidSignature => write!(f, "The bundle signature is invalid"),
+            BundleError::InvalidProof => write!(f, "The proof is invalid"),
+            BundleError::Expired { age, max_age } => write!(
+                f,
+                "The bundle is {} seconds old but at most {} seconds are allowed",
+                age, max_age
+            ),
+            BundleError::FutureTimestamp { timestamp, now } => write!(
+                f,
+                "The bundle is issued at {} which is after the current time {}",
+                timestamp, now
+            ),
+            BundleError::ReplayedNonce(nonce) => {
+                write!(f, "The nonce {} has already been used", nonce)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+impl SignedProofBundle {
+    /// Generates a proof of `circuit` and signs it together with `nonce` and `timestamp` with the server secret key `sk`
+    pub fn generate<C: Circuit<Fp> + CircuitExt<Fp>>(
+        params: &ParamsKZG<Bn256>,
+        pk: &ProvingKey<G1Affine>,
+        circuit: C,
+        nonce: u64,
+        timestamp: u64,
+        sk: AttestationScalar,
+    ) -> Self {
+        let public_inputs = circuit.instances();
+        let proof = full_prover(params, pk, circuit, public_inputs.clone());
+
+        Self::sign(proof, public_inputs, nonce, timestamp, sk)
+    }
+
+    /// Signs an existing proof and its public inputs together with `nonce` and `timestamp` with the server secret key `sk`
+    pub fn sign(
+        proof: Vec<u8>,
+        public_inputs: Vec<Vec<Fp>>,
+        nonce: u64,
+        timestamp: u64,
+        sk: AttestationScalar,
+    ) -> Self {
+        let digest = bundle_digest(&proof, &public_inputs, nonce, timestamp);
+        let (public_key, signature) = ecdsa_sign(digest, sk);
+
+        Self {
+            proof,
+            public_inputs,
+            nonce,
+            timestamp,
+            public_key,
+            signature,
+        }
+    }
+
+    /// Returns the digest signed by the server, namely the poseidon hash chain over the nonce, the timestamp, the public inputs and the proof bytes
+    pub fn digest(&self) -> Fp {
+        bundle_digest(&self.proof, &self.public_inputs, self.nonce, self.timestamp)
+    }
+
+    /// Verifies that the bundle is signed by `server_key`, that it was issued at most `max_age` seconds before `now`, that its nonce is not in `seen_nonces` and that its proof is valid.
+    /// On success the nonce is added to `seen_nonces`, so that the same bundle is rejected the next time. A rejected bundle leaves `seen_nonces` untouched
+    pub fn verify(
+        &self,
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        server_key: &Secp256k1,
+        now: u64,
+        max_age: u64,
+        seen_nonces: &mut HashSet<u64>,
+    ) -> Result<(), BundleError> {
+        if self.public_key != *server_key
+            || !ecdsa_verify(self.digest(), &self.public_key, &self.signature)
+        {
+            return Err(BundleError::InvalidSignature);
+        }
+
+        if self.timestamp > now {
+            return Err(BundleError::FutureTimestamp {
+                timestamp: self.timestamp,
+                now,
+            });
+        }
+
+        let age = now - self.timestamp;
+        if age > max_age {
+            return Err(BundleError::Expired { age, max_age });
+        }
+
+        if seen_nonces.contains(&self.nonce) {
+            return Err(BundleError::ReplayedNonce(self.nonce));
+        }
+
+        if !full_verifier(params, vk, self.proof.clone(), self.public_inputs.clone()) {
+            return Err(BundleError::InvalidProof);
+        }
+
+        seen_nonces.insert(self.nonce);
+
+        Ok(())
+    }
+}
+
+/// Hashes the nonce, the timestamp, the public inputs and the proof bytes packed in chunks of [PROOF_CHUNK_BYTES] one after the other, starting from the number of hashed elements.
+/// The lengths of the public input columns and of the proof are hashed as well, so that the boundaries between them can't be shifted
+fn bundle_digest(proof: &[u8], public_inputs: &[Vec<Fp>], nonce: u64, timestamp: u64) -> Fp {
+    let mut elements = vec![Fp::from(nonce), Fp::from(timestamp)];
+
+    elements.push(Fp::from(public_inputs.len() as u64));
+    for column in public_inputs {
+        elements.push(Fp::from(column.len() as u64));
+        elements.extend(column);
+    }
+
+    elements.push(Fp::from(proof.len() as u64));
+    elements.extend(proof.chunks(PROOF_CHUNK_BYTES).map(|chunk| {
+        let mut bytes = [0u8; 32];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        Fp::from_bytes(&bytes).unwrap()
+    }));
+
+    let length = Fp::from(elements.len() as u64);
+    elements.into_iter().fold(length, |acc, element| {
+        poseidon::Hash::<Fp, PoseidonSpec, ConstantLength<2>, 3, 2>::init().hash([acc, element])
+    })
+}
@@ -0,0 +1,314 @@
+use crate::chips::less_than::less_than_vertical::LtVerticalChip;
+use crate::circuits::solvency::{SolvencyCircuit, SolvencyConfig};
+use crate::merkle_sum_tree::{big_int_to_fp, fp_to_big_int, MerkleSumTree};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error, Expression, Selector};
+use halo2_proofs::poly::Rotation;
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for verifying solvency and exposing, for each asset, the reserves ratio `floor(assets * SCALE / liabilities)` as a public integer.
+///
+/// The ratio is proven by division-by-multiplication, namely `liabilities * ratio <= assets * SCALE < liabilities * (ratio + 1)`.
+/// The ratio is range checked to [RATIO_BITS] bits and the liabilities to `8 * N_BYTES - RATIO_BITS` bits, so that the products can't wrap around the modulus.
+/// Both sides of the inequalities are then bounded below 2^(8 * N_BYTES), the range of the less than chip, before being compared.
+/// The liabilities must be able to be positive, so `8 * N_BYTES` must be greater than [RATIO_BITS], which is asserted when the circuit is configured.
+///
+/// # Type Parameters
+///
+/// * `L`: The length of the hasher input, namely 2 + (2 * N_ASSETS)
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+/// * `N_BYTES`: Range in which the balances should lie
+/// * `SCALE`: The fixed-point scale of the ratio, for example 10000 to express the ratio with 4 decimals
+///
+/// # Fields
+///
+/// * `solvency`: The solvency circuit witness, namely the penultimate nodes, the assets sum and the root hash
+/// * `ratios`: The reserves ratio for each asset, scaled by SCALE
+#[derive(Clone)]
+pub struct ReservesRatioCircuit<
+    const L: usize,
+    const N_ASSETS: usize,
+    const N_BYTES: usize,
+    const SCALE: u64,
+> {
+    pub solvency: SolvencyCircuit<L, N_ASSETS, N_BYTES>,
+    pub ratios: [Fp; N_ASSETS],
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize, const SCALE: u64> CircuitExt<Fp>
+    for ReservesRatioCircuit<L, N_ASSETS, N_BYTES, SCALE>
+{
+    /// Returns the number of public inputs of the circuit. It is the public inputs of the solvency circuit followed by the N_ASSETS ratios
+    fn num_instance(&self) -> Vec<usize> {
+        vec![self.solvency.num_instance()[0] + N_ASSETS]
+    }
+
+    /// Returns the values of the public inputs of the circuit. The public inputs of the solvency circuit are followed by the reserves ratio for each asset
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        let mut instances = self.solvency.instances();
+        instances[0].extend(self.ratios);
+        instances
+    }
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize, const SCALE: u64>
+    ReservesRatioCircuit<L, N_ASSETS, N_BYTES, SCALE>
+{
+    /// Returns the row of the reserves ratio of `asset` in the instance column, after the public inputs of the solvency circuit
+    pub const fn ratio_row(asset: usize) -> usize {
+        SolvencyCircuit::<L, N_ASSETS, N_BYTES>::ASSET_COUNT_ROW + 1 + asset
+    }
+
+    pub fn init_empty() -> Self {
+        assert_ratio_range::<N_BYTES>();
+
+        Self {
+            solvency: SolvencyCircuit::init_empty(),
+            ratios: [Fp::zero(); N_ASSETS],
+        }
+    }
+
+    /// Initializes the circuit with the merkle sum tree and the assets sum. The ratios are computed from the root balances of the tree.
+    /// Returns an error if the liabilities of an asset are zero, since its ratio is undefined
+    pub fn init(
+        merkle_sum_tree: MerkleSumTree<N_ASSETS>,
+        assets_sum: [Fp; N_ASSETS],
+    ) -> Result<Self, &'static str> {
+        assert_ratio_range::<N_BYTES>();
+
+        let liabilities = merkle_sum_tree.root().balances;
+
+        let mut ratios = [Fp::zero(); N_ASSETS];
+        for asset in 0..N_ASSETS {
+            ratios[asset] = reserves_ratio(&assets_sum[asset], &liabilities[asset], SCALE)
+                .ok_or("The reserves ratio is undefined for zero liabilities")?;
+        }
+
+        Ok(Self {
+            solvency: SolvencyCircuit::init(merkle_sum_tree, assets_sum),
+            ratios,
+        })
+    }
+}
+
+/// Number of bits of the range in which the reserves ratio, scaled by SCALE, must lie
+pub const RATIO_BITS: usize = 64;
+
+/// Computes natively `floor(assets * scale / liabilities)`. Returns None if `liabilities` is zero.
+pub fn reserves_ratio(assets: &Fp, liabilities: &Fp, scale: u64) -> Option<Fp> {
+    if *liabilities == Fp::zero() {
+        return None;
+    }
+
+    let ratio = fp_to_big_int(assets) * scale / fp_to_big_int(liabilities);
+    Some(big_int_to_fp(&ratio))
+}
+
+/// The liabilities are range checked to `8 * N_BYTES - RATIO_BITS` bits, which must leave room for a positive value
+fn assert_ratio_range<const N_BYTES: usize>() {
+    assert!(
+        8 * N_BYTES > RATIO_BITS,
+        "The range of the less than chip must be wider than RATIO_BITS"
+    );
+}
+
+/// Configuration for the reserves ratio circuit
+///
+/// # Fields
+///
+/// * `solvency_config`: Configuration for the solvency circuit. Its instance column is used to store the public inputs
+/// * `ratio_selector`: Selector to activate the reserves ratio constraints
+///
+/// The circuit performs the following additional constraints on the 3 advice columns of the merkle sum tree chip (if `ratio_selector` is toggled):
+///
+/// | a                        | b               | c             |
+/// | ------------------------ | --------------- | ------------- |
+/// | `liabilities`            | `ratio`         | `assets`      |
+/// | `lower`                  | `scaled_assets` | `upper`       |
+/// | `scaled_assets_plus_one` | -               | -             |
+///
+/// * `lower = liabilities * ratio`
+/// * `scaled_assets = assets * SCALE`
+/// * `upper = lower + liabilities`
+/// * `scaled_assets_plus_one = scaled_assets + 1`
+#[derive(Debug, Clone)]
+pub struct ReservesRatioConfig<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> {
+    pub solvency_config: SolvencyConfig<L, N_ASSETS, N_BYTES>,
+    pub ratio_selector: Selector,
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize, const SCALE: u64> Circuit<Fp>
+    for ReservesRatioCircuit<L, N_ASSETS, N_BYTES, SCALE>
+{
+    type Config = ReservesRatioConfig<L, N_ASSETS, N_BYTES>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        assert_ratio_range::<N_BYTES>();
+
+        let solvency_config = SolvencyConfig::<L, N_ASSETS, N_BYTES>::configure(meta);
+
+        let ratio_selector = meta.selector();
+        let [col_a, col_b, col_c] = solvency_config.merkle_sum_tree_config.advice;
+
+        meta.create_gate("reserves ratio constraint", |meta| {
+            let s = meta.query_selector(ratio_selector);
+
+            let liabilities = meta.query_advice(col_a, Rotation::cur());
+            let ratio = meta.query_advice(col_b, Rotation::cur());
+            let assets = meta.query_advice(col_c, Rotation::cur());
+            let lower = meta.query_advice(col_a, Rotation::next());
+            let scaled_assets = meta.query_advice(col_b, Rotation::next());
+            let upper = meta.query_advice(col_c, Rotation::next());
+            let scaled_assets_plus_one = meta.query_advice(col_a, Rotation(2));
+
+            vec![
+                s.clone() * (lower.clone() - liabilities.clone() * ratio),
+                s.clone()
+                    * (scaled_assets.clone() - assets * Expression::Constant(Fp::from(SCALE))),
+                s.clone() * (upper - lower - liabilities),
+                s * (scaled_assets_plus_one - scaled_assets - Expression::Constant(Fp::from(1))),
+            ]
+        });
+
+        ReservesRatioConfig {
+            solvency_config,
+            ratio_selector,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let root_balances = self
+            .solvency
+            .assign_solvency(&config.solvency_config, &mut layouter)?;
+
+        // the lookup table of the lt chip has already been loaded by the solvency circuit
+        let lt_chip = LtVerticalChip::<N_BYTES>::construct(config.solvency_config.lt_config);
+        let [col_a, col_b, col_c] = config.solvency_config.merkle_sum_tree_config.advice;
+
+        for asset in 0..N_ASSETS {
+            let (liabilities, ratio, lower, scaled_assets, upper, scaled_assets_plus_one) =
+                layouter.assign_region(
+                    || format!("asset {}: assign reserves ratio", asset),
+                    |mut region| {
+                        config.ratio_selector.enable(&mut region, 0)?;
+
+                        let liabilities = root_balances[asset].copy_advice(
+                            || "copy liabilities",
+                            &mut region,
+                            col_a,
+                            0,
+                        )?;
+
+                        let ratio = region.assign_advice(
+                            || "reserves ratio",
+                            col_b,
+                            0,
+                            || Value::known(self.ratios[asset]),
+                        )?;
+
+                        // the assets sum is the one exposed as public input by the solvency circuit
+                        let assets = region.assign_advice_from_instance(
+                            || "copy assets sum from instance column",
+                            config.solvency_config.instance,
+                            SolvencyCircuit::<L, N_ASSETS, N_BYTES>::assets_sum_row(asset),
+                            col_c,
+                            0,
+                        )?;
+
+                        let lower_val = liabilities.value().copied() * ratio.value().copied();
+                        let scaled_assets_val =
+                            assets.value().copied() * Value::known(Fp::from(SCALE));
+                        let upper_val = lower_val + liabilities.value().copied();
+
+                        let lower = region.assign_advice(|| "lower", col_a, 1, || lower_val)?;
+                        let scaled_assets = region.assign_advice(
+                            || "scaled assets",
+                            col_b,
+                            1,
+                            || scaled_assets_val,
+                        )?;
+                        let upper = region.assign_advice(|| "upper", col_c, 1, || upper_val)?;
+                        let scaled_assets_plus_one = region.assign_advice(
+                            || "scaled assets plus one",
+                            col_a,
+                            2,
+                            || scaled_assets_val + Value::known(Fp::from(1)),
+                        )?;
+
+                        Ok((
+                            liabilities,
+                            ratio,
+                            lower,
+                            scaled_assets,
+                            upper,
+                            scaled_assets_plus_one,
+                        ))
+                    },
+                )?;
+
+            // the ratio and the liabilities are range checked, so that liabilities * (ratio + 1) is less than 2^(8 * N_BYTES) as an integer
+            config.solvency_config.enforce_range(
+                layouter.namespace(|| "range check the ratio"),
+                &ratio,
+                RATIO_BITS,
+                &lt_chip,
+            )?;
+
+            config.solvency_config.enforce_range(
+                layouter.namespace(|| "range check the liabilities"),
+                &liabilities,
+                8 * N_BYTES - RATIO_BITS,
+                &lt_chip,
+            )?;
+
+            // both sides of the inequalities are bounded below 2^(8 * N_BYTES) before being compared
+            config.solvency_config.enforce_range(
+                layouter.namespace(|| "bound the scaled assets"),
+                &scaled_assets,
+                8 * N_BYTES,
+                &lt_chip,
+            )?;
+
+            config.solvency_config.enforce_range(
+                layouter.namespace(|| "bound the upper bound of the ratio"),
+                &upper,
+                8 * N_BYTES,
+                &lt_chip,
+            )?;
+
+            // liabilities * ratio <= assets * SCALE
+            config.solvency_config.enforce_cell_less_than(
+                layouter.namespace(|| "enforce lower bound of the ratio"),
+                &lower,
+                &scaled_assets_plus_one,
+                &lt_chip,
+            )?;
+
+            // assets * SCALE < liabilities * (ratio + 1)
+            config.solvency_config.enforce_cell_less_than(
+                layouter.namespace(|| "enforce upper bound of the ratio"),
+                &scaled_assets,
+                &upper,
+                &lt_chip,
+            )?;
+
+            config.solvency_config.expose_public(
+                layouter.namespace(|| "public reserves ratio"),
+                &ratio,
+                Self::ratio_row(asset),
+            )?;
+        }
+
+        Ok(())
+    }
+}
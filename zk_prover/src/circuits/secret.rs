@@ -0,0 +1,48 @@
+use crate::merkle_sum_tree::big_int_to_fp;
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use num_bigint::BigInt;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// The balances of a user, zeroized when dropped so that they don't linger in memory once the proof is generated.
+/// They are not printed by `Debug`.
+///
+/// [Fp] doesn't implement [Zeroize], so the balances are held as an owned buffer of their 32 bytes representations, which is zeroized with the [zeroize] crate.
+/// Only this buffer is covered: the field elements returned by [SecretBalances::expose] are copies, which are not zeroized.
+pub struct SecretBalances<const N_ASSETS: usize>([[u8; 32]; N_ASSETS]);
+
+impl<const N_ASSETS: usize> SecretBalances<N_ASSETS> {
+    pub fn new(balances: [Fp; N_ASSETS]) -> Self {
+        Self(balances.map(|balance| balance.to_bytes()))
+    }
+
+    /// Converts the balances of an [crate::merkle_sum_tree::Entry] to field elements. The [BigInt] balances are not zeroized, so they should be dropped by the caller as soon as possible
+    pub fn from_big_ints(balances: &[BigInt; N_ASSETS]) -> Self {
+        Self::new(std::array::from_fn(|asset| big_int_to_fp(&balances[asset])))
+    }
+
+    /// Returns a copy of the balances as field elements, which should be dropped by the caller as soon as possible
+    pub fn expose(&self) -> [Fp; N_ASSETS] {
+        // the bytes are the representation of a field element, so they are always decoded
+        self.0.map(|bytes| Fp::from_bytes(&bytes).unwrap())
+    }
+}
+
+impl<const N_ASSETS: usize> std::fmt::Debug for SecretBalances<N_ASSETS> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretBalances(..)")
+    }
+}
+
+impl<const N_ASSETS: usize> Zeroize for SecretBalances<N_ASSETS> {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<const N_ASSETS: usize> Drop for SecretBalances<N_ASSETS> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<const N_ASSETS: usize> ZeroizeOnDrop for SecretBalances<N_ASSETS> {}
@@ -0,0 +1,138 @@
+use crate::circuits::merkle_sum_tree::PublicInputs;
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use serde::{Deserialize, Serialize};
+
+/// Serializable representation of the [PublicInputs] of a proof. Field elements are stored as `0x`-prefixed big endian hex strings.
+///
+/// # Fields
+///
+/// * `leaf_hash`: The leaf hash to be verified inclusion of
+/// * `root_hash`: The root hash of the merkle sum tree
+/// * `root_balances`: The balances of the root, namely the total liabilities of the CEX per asset, if they are public
+/// * `extra_public_inputs`: The application-specific public inputs bound into the proof
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SerializedPublicInputs {
+    pub leaf_hash: String,
+    pub root_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root_balances: Option<Vec<String>>,
+    #[serde(default)]
+    pub extra_public_inputs: Vec<String>,
+}
+
+/// Serializable representation of a proof of the [crate::circuits::merkle_sum_tree::MstInclusionCircuit] together with its public inputs, so that it can be exchanged as JSON with web backends.
+/// The proof bytes are stored as a `0x`-prefixed hex string.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SerializedProof {
+    pub proof: String,
+    pub public_inputs: SerializedPublicInputs,
+}
+
+impl SerializedPublicInputs {
+    pub fn from_public_inputs<const N_ASSETS: usize>(
+        public_inputs: &PublicInputs<N_ASSETS>,
+    ) -> Self {
+        Self {
+            leaf_hash: fp_to_hex(&public_inputs.leaf_hash()),
+            root_hash: fp_to_hex(&public_inputs.root_hash()),
+            root_balances: public_inputs
+                .root_balances()
+                .map(|balances| balances.iter().map(fp_to_hex).collect()),
+            extra_public_inputs: public_inputs
+                .extra_public_inputs()
+                .iter()
+                .map(fp_to_hex)
+                .collect(),
+        }
+    }
+
+    pub fn to_public_inputs<const N_ASSETS: usize>(
+        &self,
+    ) -> Result<PublicInputs<N_ASSETS>, Box<dyn std::error::Error>> {
+        let root_balances: Option<[Fp; N_ASSETS]> = match &self.root_balances {
+            Some(balances) => Some(
+                balances
+                    .iter()
+                    .map(|balance| hex_to_fp(balance))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .try_into()
+                    .map_err(|_| {
+                        "The number of root balances doesn't match the number of assets"
+                    })?,
+            ),
+            None => None,
+        };
+
+        let extra_public_inputs = self
+            .extra_public_inputs
+            .iter()
+            .map(|value| hex_to_fp(value))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(PublicInputs::new(
+            hex_to_fp(&self.leaf_hash)?,
+            hex_to_fp(&self.root_hash)?,
+            root_balances,
+            extra_public_inputs,
+        ))
+    }
+}
+
+impl<const N_ASSETS: usize> PublicInputs<N_ASSETS> {
+    /// Serializes the public inputs as a JSON string, see [SerializedPublicInputs]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&SerializedPublicInputs::from_public_inputs(self))
+    }
+
+    /// Parses the public inputs from a JSON string, as written by [PublicInputs::to_json]
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        serde_json::from_str::<SerializedPublicInputs>(json)?.to_public_inputs()
+    }
+}
+
+/// Serializes a proof and its public inputs as a JSON string, see [SerializedProof]
+pub fn proof_to_json<const N_ASSETS: usize>(
+    proof: &[u8],
+    public_inputs: &PublicInputs<N_ASSETS>,
+) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&SerializedProof {
+        proof: format!("0x{}", hex::encode(proof)),
+        public_inputs: SerializedPublicInputs::from_public_inputs(public_inputs),
+    })
+}
+
+/// Parses a proof and its public inputs from a JSON string, as written by [proof_to_json]
+pub fn proof_from_json<const N_ASSETS: usize>(
+    json: &str,
+) -> Result<(Vec<u8>, PublicInputs<N_ASSETS>), Box<dyn std::error::Error>> {
+    let serialized: SerializedProof = serde_json::from_str(json)?;
+
+    Ok((
+        hex::decode(strip_hex_prefix(&serialized.proof)?)?,
+        serialized.public_inputs.to_public_inputs()?,
+    ))
+}
+
+/// Encodes a field element as a `0x`-prefixed big endian hex string
+fn fp_to_hex(value: &Fp) -> String {
+    let mut bytes = value.to_bytes();
+    bytes.reverse();
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Decodes a field element from a `0x`-prefixed big endian hex string of 32 bytes, rejecting values that are not less than the modulus
+fn hex_to_fp(value: &str) -> Result<Fp, Box<dyn std::error::Error>> {
+    let mut bytes: [u8; 32] = hex::decode(strip_hex_prefix(value)?)?
+        .try_into()
+        .map_err(|_| format!("The field element {:?} is not 32 bytes long", value))?;
+    bytes.reverse();
+
+    Option::<Fp>::from(Fp::from_bytes(&bytes))
+        .ok_or_else(|| format!("The field element {:?} is not less than the modulus", value).into())
+}
+
+fn strip_hex_prefix(value: &str) -> Result<&str, Box<dyn std::error::Error>> {
+    value
+        .strip_prefix("0x")
+        .ok_or_else(|| format!("The hex string {:?} must start with 0x", value).into())
+}
@@ -5,7 +5,9 @@ use crate::chips::merkle_sum_tree::{MerkleSumTreeChip, MerkleSumTreeConfig};
 use crate::chips::poseidon::hash::{PoseidonChip, PoseidonConfig};
 use crate::chips::poseidon::poseidon_spec::PoseidonSpec;
 use crate::merkle_sum_tree::MerkleSumTree;
-use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner};
+use crate::timing;
+use gadgets::util::pow_of_two;
+use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value};
 use halo2_proofs::halo2curves::bn256::Fr as Fp;
 use halo2_proofs::plonk::{
     Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, Instance, Selector,
@@ -15,11 +17,13 @@ use snark_verifier_sdk::CircuitExt;
 
 /// Circuit for verifying solvency, namely that the assets_sum is greater than the sum of the liabilities stored in the merkle sum tree
 ///
+/// The number of assets covered by the proof is exposed as public input, so that a verifier can check that no asset has been left out of the summation.
+///
 /// # Type Parameters
 ///
 /// * `L`: The length of the hasher input, namely 2 + (2 * N_ASSETS)
 /// * `N_ASSETS`: The number of assets for which the solvency is verified.
-/// * `N_BYTES`: Range in which the balances should lie
+/// * `N_BYTES`: Range in which the balances should lie, namely the number of bytes of the less than comparison between the liabilities and the assets. Liabilities denominated in small units, such as satoshis or wei, can exceed 64 bits, in which case a wider comparison, for example 11 bytes, is needed
 ///
 /// # Fields
 ///
@@ -29,6 +33,7 @@ use snark_verifier_sdk::CircuitExt;
 /// * `right_node_balances`: The balances of the penultimate right node of the merkle sum tree
 /// * `assets_sum`: The sum of the assets of the CEX for each asset
 /// * `root_hash`: The root hash of the merkle sum tree
+/// * `inclusive_comparison`: Whether the root balances are enforced to be less than or equal to the assets sum, rather than strictly less than it, so that a CEX holding exactly its liabilities can prove solvency
 #[derive(Clone)]
 pub struct SolvencyCircuit<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> {
     pub left_node_hash: Fp,
@@ -37,20 +42,25 @@ pub struct SolvencyCircuit<const L: usize, const N_ASSETS: usize, const N_BYTES:
     pub right_node_balances: [Fp; N_ASSETS],
     pub assets_sum: [Fp; N_ASSETS],
     pub root_hash: Fp,
+    pub inclusive_comparison: bool,
 }
 
 impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> CircuitExt<Fp>
     for SolvencyCircuit<L, N_ASSETS, N_BYTES>
 {
-    /// Returns the number of public inputs of the circuit. It is 1 + N_ASSETS, namely the root hash of the merkle sum tree and the sum of the assets of the CEX for each asset
+    /// Returns the number of public inputs of the circuit. It is 2 + N_ASSETS, namely the root hash of the merkle sum tree, the sum of the assets of the CEX for each asset and the number of assets
     fn num_instance(&self) -> Vec<usize> {
-        vec![1 + N_ASSETS]
+        vec![Self::ASSET_COUNT_ROW + 1]
     }
 
-    /// Returns the values of the public inputs of the circuit. The first value is the root hash of the merkle sum tree and the remaining values are the sum of the assets of the CEX for each asset
+    /// Returns the values of the public inputs of the circuit. The first value is the root hash of the merkle sum tree, followed by the sum of the assets of the CEX for each asset. The last value is the number of assets
     fn instances(&self) -> Vec<Vec<Fp>> {
-        let mut instances = vec![self.root_hash];
-        instances.extend(self.assets_sum);
+        let mut instances = vec![Fp::zero(); Self::ASSET_COUNT_ROW + 1];
+        instances[Self::ROOT_HASH_ROW] = self.root_hash;
+        for asset in 0..N_ASSETS {
+            instances[Self::assets_sum_row(asset)] = self.assets_sum[asset];
+        }
+        instances[Self::ASSET_COUNT_ROW] = Fp::from(N_ASSETS as u64);
         vec![instances]
     }
 }
@@ -58,6 +68,17 @@ impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> CircuitExt<Fp>
 impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
     SolvencyCircuit<L, N_ASSETS, N_BYTES>
 {
+    /// Row of the root hash of the merkle sum tree in the instance column
+    pub const ROOT_HASH_ROW: usize = 0;
+
+    /// Row of the number of assets in the instance column, after the assets sums
+    pub const ASSET_COUNT_ROW: usize = 1 + N_ASSETS;
+
+    /// Returns the row of the sum of the assets of the CEX for `asset` in the instance column
+    pub const fn assets_sum_row(asset: usize) -> usize {
+        1 + asset
+    }
+
     pub fn init_empty() -> Self {
         assert_eq!((N_ASSETS * 2) + 2, L);
         Self {
@@ -67,6 +88,7 @@ impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
             right_node_balances: [Fp::zero(); N_ASSETS],
             assets_sum: [Fp::zero(); N_ASSETS],
             root_hash: Fp::zero(),
+            inclusive_comparison: false,
         }
     }
 
@@ -87,8 +109,184 @@ impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
             right_node_balances: penultimate_node_right.balances,
             assets_sum,
             root_hash,
+            inclusive_comparison: false,
         }
     }
+
+    /// Enforces the root balances to be less than or equal to the assets sum, see the `inclusive_comparison` field
+    pub fn with_inclusive_comparison(mut self) -> Self {
+        self.inclusive_comparison = true;
+        self
+    }
+
+    /// Computes the root from the penultimate nodes, checks the root balances against the assets sum and exposes the public inputs of the solvency circuit.
+    /// Returns the assigned root balances so that further constraints can be built on top of them.
+    pub fn assign_solvency(
+        &self,
+        config: &SolvencyConfig<L, N_ASSETS, N_BYTES>,
+        layouter: &mut impl Layouter<Fp>,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let lt_chip = LtVerticalChip::<N_BYTES>::construct(config.lt_config);
+
+        let (root_hash, root_balances) = assign_root_from_penultimate_nodes(
+            &config.merkle_sum_tree_config,
+            &config.poseidon_config,
+            layouter,
+            self.left_node_hash,
+            &self.left_node_balances,
+            self.right_node_hash,
+            &self.right_node_balances,
+        )?;
+
+        // expose the root hash, as public input
+        config.expose_public(
+            layouter.namespace(|| "public root hash"),
+            &root_hash,
+            Self::ROOT_HASH_ROW,
+        )?;
+
+        // load lookup table for lt chip
+        lt_chip.load(layouter)?;
+
+        // enforce root balances to be less than (or equal to) the assets sum
+        for asset in 0..N_ASSETS {
+            if self.inclusive_comparison {
+                config.enforce_less_than_or_equal(
+                    layouter.namespace(|| "enforce less than or equal"),
+                    &root_balances[asset],
+                    Self::assets_sum_row(asset),
+                    &lt_chip,
+                )?;
+            } else {
+                config.enforce_less_than(
+                    layouter.namespace(|| "enforce less than"),
+                    &root_balances[asset],
+                    Self::assets_sum_row(asset),
+                    &lt_chip,
+                )?;
+            }
+        }
+
+        // expose the number of assets for which the root balances have been checked, as public input
+        let asset_count = config.assign_asset_count(layouter.namespace(|| "assign asset count"))?;
+        config.expose_public(
+            layouter.namespace(|| "public asset count"),
+            &asset_count,
+            Self::ASSET_COUNT_ROW,
+        )?;
+
+        Ok(root_balances)
+    }
+}
+
+/// Assigns the penultimate nodes of a merkle sum tree and computes the root from them, namely the root hash and the root balances.
+/// Returns the assigned root hash and root balances so that the caller can decide what to expose as public input.
+#[allow(clippy::type_complexity)]
+pub fn assign_root_from_penultimate_nodes<const L: usize, const N_ASSETS: usize>(
+    merkle_sum_tree_config: &MerkleSumTreeConfig,
+    poseidon_config: &PoseidonConfig<3, 2, L>,
+    layouter: &mut impl Layouter<Fp>,
+    left_node_hash: Fp,
+    left_node_balances: &[Fp; N_ASSETS],
+    right_node_hash: Fp,
+    right_node_balances: &[Fp; N_ASSETS],
+) -> Result<(AssignedCell<Fp, Fp>, Vec<AssignedCell<Fp, Fp>>), Error> {
+    let (root_hash, _, root_balances) = assign_penultimate_nodes(
+        merkle_sum_tree_config,
+        poseidon_config,
+        layouter,
+        left_node_hash,
+        left_node_balances,
+        right_node_hash,
+        right_node_balances,
+    )?;
+
+    Ok((root_hash, root_balances))
+}
+
+/// Assigns the penultimate nodes of a merkle sum tree and computes the root from them, as [assign_root_from_penultimate_nodes] does.
+/// Returns the assigned root hash, the assigned balances of the left and of the right penultimate node, which are hashed into the root, and the assigned root balances.
+#[allow(clippy::type_complexity)]
+pub fn assign_penultimate_nodes<const L: usize, const N_ASSETS: usize>(
+    merkle_sum_tree_config: &MerkleSumTreeConfig,
+    poseidon_config: &PoseidonConfig<3, 2, L>,
+    layouter: &mut impl Layouter<Fp>,
+    left_node_hash: Fp,
+    left_node_balances: &[Fp; N_ASSETS],
+    right_node_hash: Fp,
+    right_node_balances: &[Fp; N_ASSETS],
+) -> Result<
+    (
+        AssignedCell<Fp, Fp>,
+        [Vec<AssignedCell<Fp, Fp>>; 2],
+        Vec<AssignedCell<Fp, Fp>>,
+    ),
+    Error,
+> {
+    // build auxiliary chips
+    let merkle_sum_tree_chip =
+        MerkleSumTreeChip::<N_ASSETS>::construct(merkle_sum_tree_config.clone());
+    let poseidon_chip = PoseidonChip::<PoseidonSpec, 3, 2, L>::construct(poseidon_config.clone());
+
+    // Assign the left penultimate hash and the left penultimate balances
+    let (left_node_hash, left_node_balances) = merkle_sum_tree_chip
+        .assign_entry_hash_and_balances(
+            layouter.namespace(|| "assign leaf hash and balances"),
+            left_node_hash,
+            left_node_balances,
+        )?;
+
+    let swap_bit =
+        merkle_sum_tree_chip.assing_swap_bit(layouter.namespace(|| "assign swap bit"), false)?;
+
+    // assign penultimate nodes hashes according to the swap bit
+    let (left_hash, right_hash) = merkle_sum_tree_chip.assign_nodes_hashes_per_level(
+        layouter.namespace(|| "assign penultimate nodes hashes"),
+        &left_node_hash,
+        right_node_hash,
+        swap_bit.clone(),
+    )?;
+
+    let mut root_balances = vec![];
+    let mut left_balances = vec![];
+    let mut right_balances = vec![];
+
+    // assign penultimate nodes balances per each asset according to the swap bit
+    for asset in 0..N_ASSETS {
+        let (left_balance, right_balance, next_balance) = merkle_sum_tree_chip
+            .assign_nodes_balance_per_asset(
+                layouter.namespace(|| format!("asset {}: assign nodes balances", asset)),
+                &left_node_balances[asset],
+                right_node_balances[asset],
+                swap_bit.clone(),
+            )?;
+
+        root_balances.push(next_balance);
+        left_balances.push(left_balance);
+        right_balances.push(right_balance);
+    }
+
+    // create an hash_input array of length L that contains the left hash, the left balances, the right hash and the right balances
+    let hash_input_vec: Vec<AssignedCell<Fp, Fp>> = [left_hash]
+        .iter()
+        .chain(left_balances.iter())
+        .chain([right_hash].iter())
+        .chain(right_balances.iter())
+        .map(|x| x.to_owned())
+        .collect();
+
+    let hash_input: [AssignedCell<Fp, Fp>; L] = match hash_input_vec.try_into() {
+        Ok(arr) => arr,
+        Err(_) => panic!("Failed to convert Vec to Array"),
+    };
+
+    // compute the root hash
+    let root_hash = poseidon_chip.hash(
+        layouter.namespace(|| format!("perform root hash")),
+        hash_input,
+    )?;
+
+    Ok((root_hash, [left_balances, right_balances], root_balances))
 }
 
 /// Configuration for the solvency circuit
@@ -105,6 +303,7 @@ impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
 /// * `instance`: Instance column used to store the public inputs
 /// * `lt_selector`: Selector to activate the less than constraint
 /// * `lt_config`: Configuration for the less than chip
+/// * `constants`: Fixed column used to store the constants of the circuit, such as the number of assets
 ///
 /// The circuit performs an additional constraint:
 /// * `lt_enable * (lt_config.is_lt - 1) = 0` (if `lt_enable` is toggled). It basically enforces the result of the less than chip to be 1.
@@ -115,6 +314,7 @@ pub struct SolvencyConfig<const L: usize, const N_ASSETS: usize, const N_BYTES:
     pub instance: Column<Instance>,
     pub lt_selector: Selector,
     pub lt_config: LtVerticalConfig<N_BYTES>,
+    pub constants: Column<Fixed>,
 }
 
 impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
@@ -122,6 +322,17 @@ impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
 {
     /// Configures the circuit
     pub fn configure(meta: &mut ConstraintSystem<Fp>) -> Self {
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        Self::configure_with_instance(meta, instance)
+    }
+
+    /// Configures the circuit on top of an existing instance column, so that a circuit that embeds this configuration can keep all its public inputs in a single column
+    pub fn configure_with_instance(
+        meta: &mut ConstraintSystem<Fp>,
+        instance: Column<Instance>,
+    ) -> Self {
         // the max number of advices columns needed is WIDTH + 1 given requirement of the poseidon config with WIDTH 3
         let advices: [Column<Advice>; 4] = std::array::from_fn(|_| meta.advice_column());
 
@@ -173,8 +384,8 @@ impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
             vec![lt_enable * (lt_config.is_lt(meta, None) - Expression::Constant(Fp::from(1)))]
         });
 
-        let instance = meta.instance_column();
-        meta.enable_equality(instance);
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
 
         Self {
             merkle_sum_tree_config,
@@ -182,10 +393,13 @@ impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
             lt_config,
             lt_selector,
             instance,
+            constants,
         }
     }
 
     /// Enforces value in the cell passed as input to be less than the value in the instance column at row `index`.
+    /// The lookup table of `lt_chip` is not loaded here: it must be loaded once per circuit with [LtVerticalInstruction::load] before the first call, so that all the checks share it.
+    /// An error of the assignment, either of the copied cells or of the less than chip, is returned to the caller rather than leaving the region partially assigned, for example [Error::NotEnoughRowsAvailable] if `index` lies outside the usable rows of the instance column.
     pub fn enforce_less_than(
         &self,
         mut layouter: impl Layouter<Fp>,
@@ -193,6 +407,9 @@ impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
         index: usize,
         lt_chip: &LtVerticalChip<N_BYTES>,
     ) -> Result<(), Error> {
+        let _timer = timing::start(timing::ENFORCE_LESS_THAN);
+        let _span = tracing::debug_span!("enforce less than", index).entered();
+
         layouter.assign_region(
             || "enforce input cell to be less than value in instance column at row `index`",
             |mut region| {
@@ -217,6 +434,10 @@ impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
                 self.lt_selector.enable(&mut region, 0)?;
 
                 lt_chip.assign(&mut region, 0, lhs.value().copied(), rhs.value().copied())?;
+                tracing::debug!(
+                    is_lt = ?lhs.value().zip(rhs.value()).map(|(lhs, rhs)| lhs < rhs),
+                    "assigned less than comparison"
+                );
 
                 Ok(())
             },
@@ -225,6 +446,185 @@ impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
         Ok(())
     }
 
+    /// Enforces value in the cell passed as input to be less than or equal to the value in the instance column at row `index`, with [Self::enforce_cell_less_than_or_equal].
+    /// As for [Self::enforce_less_than], the lookup table of `lt_chip` must have been loaded once per circuit before.
+    pub fn enforce_less_than_or_equal(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        input_cell: &AssignedCell<Fp, Fp>,
+        index: usize,
+        lt_chip: &LtVerticalChip<N_BYTES>,
+    ) -> Result<(), Error> {
+        let _span = tracing::debug_span!("enforce less than or equal", index).entered();
+
+        let value = layouter.assign_region(
+            || "copy value from instance column at row `index`",
+            |mut region| {
+                region.assign_advice_from_instance(
+                    || "copy value from instance column",
+                    self.instance,
+                    index,
+                    self.merkle_sum_tree_config.advice[0],
+                    0,
+                )
+            },
+        )?;
+
+        self.enforce_cell_less_than_or_equal(
+            layouter.namespace(|| "enforce less than or equal to value"),
+            input_cell,
+            &value,
+            lt_chip,
+        )
+    }
+
+    /// Enforces the value in the `lhs` cell to be less than or equal to the value in the `rhs` cell.
+    /// The value in the `rhs` cell is incremented by one with the sum constraint of the merkle sum tree chip, then the `lhs` cell is enforced to be less than the incremented value with [Self::enforce_cell_less_than].
+    /// The incremented value reaches 2^(8 * N_BYTES) when the `rhs` value is the largest one in range, which the lt chip still handles: the difference with any input in range fits in N_BYTES bytes.
+    pub fn enforce_cell_less_than_or_equal(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        lhs: &AssignedCell<Fp, Fp>,
+        rhs: &AssignedCell<Fp, Fp>,
+        lt_chip: &LtVerticalChip<N_BYTES>,
+    ) -> Result<(), Error> {
+        let incremented = layouter.assign_region(
+            || "increment rhs cell",
+            |mut region| {
+                let value = rhs.copy_advice(
+                    || "copy rhs",
+                    &mut region,
+                    self.merkle_sum_tree_config.advice[0],
+                    0,
+                )?;
+
+                region.assign_advice_from_constant(
+                    || "one",
+                    self.merkle_sum_tree_config.advice[1],
+                    0,
+                    Fp::one(),
+                )?;
+
+                // enable the sum selector, so that the incremented value is constrained to be the value plus one
+                self.merkle_sum_tree_config
+                    .sum_selector
+                    .enable(&mut region, 0)?;
+
+                region.assign_advice(
+                    || "incremented value",
+                    self.merkle_sum_tree_config.advice[2],
+                    0,
+                    || value.value().copied() + Value::known(Fp::one()),
+                )
+            },
+        )?;
+
+        self.enforce_cell_less_than(
+            layouter.namespace(|| "enforce less than incremented value"),
+            lhs,
+            &incremented,
+            lt_chip,
+        )
+    }
+
+    /// Enforces the value in the `lhs` cell to be less than the value in the `rhs` cell.
+    pub fn enforce_cell_less_than(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        lhs: &AssignedCell<Fp, Fp>,
+        rhs: &AssignedCell<Fp, Fp>,
+        lt_chip: &LtVerticalChip<N_BYTES>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "enforce lhs cell to be less than rhs cell",
+            |mut region| {
+                let lhs = lhs.copy_advice(
+                    || "copy lhs",
+                    &mut region,
+                    self.merkle_sum_tree_config.advice[0],
+                    0,
+                )?;
+
+                let rhs = rhs.copy_advice(
+                    || "copy rhs",
+                    &mut region,
+                    self.merkle_sum_tree_config.advice[1],
+                    0,
+                )?;
+
+                // enable lt seletor
+                self.lt_selector.enable(&mut region, 0)?;
+
+                lt_chip.assign(&mut region, 0, lhs.value().copied(), rhs.value().copied())?;
+
+                Ok(())
+            },
+        )
+    }
+
+    /// Enforces the value in the cell passed as input to lie in the range `[0, 2^bits)`, with `bits` at most 8 * N_BYTES.
+    /// The lt chip only bounds the difference between its inputs, so the cell is first enforced to be less than 2^(8 * N_BYTES), which also rules out the values wrapped around the modulus, and then less than 2^bits if the range is narrower.
+    /// As for [Self::enforce_less_than], the lookup table of `lt_chip` must have been loaded once per circuit before.
+    pub fn enforce_range(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        cell: &AssignedCell<Fp, Fp>,
+        bits: usize,
+        lt_chip: &LtVerticalChip<N_BYTES>,
+    ) -> Result<(), Error> {
+        assert!(
+            bits <= 8 * N_BYTES,
+            "The range can't be wider than the less than chip"
+        );
+
+        let mut bounds = vec![8 * N_BYTES];
+        if bits < 8 * N_BYTES {
+            bounds.push(bits);
+        }
+
+        for bound_bits in bounds {
+            let bound = layouter.assign_region(
+                || format!("assign 2^{}", bound_bits),
+                |mut region| {
+                    region.assign_advice_from_constant(
+                        || "bound",
+                        self.merkle_sum_tree_config.advice[1],
+                        0,
+                        pow_of_two::<Fp>(bound_bits),
+                    )
+                },
+            )?;
+
+            self.enforce_cell_less_than(
+                layouter.namespace(|| format!("enforce cell to be less than 2^{}", bound_bits)),
+                cell,
+                &bound,
+                lt_chip,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Assigns the number of assets as a constant of the circuit and returns the assigned cell.
+    /// Since the value is fixed at key generation, a proof can only be generated for the number of assets actually summed by the circuit.
+    pub fn assign_asset_count(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        layouter.assign_region(
+            || "assign asset count",
+            |mut region| {
+                region.assign_advice_from_constant(
+                    || "asset count",
+                    self.merkle_sum_tree_config.advice[0],
+                    0,
+                    Fp::from(N_ASSETS as u64),
+                )
+            },
+        )
+    }
+
     /// Enforces copy constraint check between input cell and instance column at row passed as input
     pub fn expose_public(
         &self,
@@ -243,7 +643,10 @@ impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> Circuit<Fp>
     type FloorPlanner = SimpleFloorPlanner;
 
     fn without_witnesses(&self) -> Self {
-        Self::init_empty()
+        // the inclusive comparison determines the layout of the circuit, so it is preserved
+        let mut circuit = Self::init_empty();
+        circuit.inclusive_comparison = self.inclusive_comparison;
+        circuit
     }
 
     fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
@@ -255,86 +658,7 @@ impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> Circuit<Fp>
         config: Self::Config,
         mut layouter: impl Layouter<Fp>,
     ) -> Result<(), Error> {
-        // build auxiliary chips
-        let merkle_sum_tree_chip =
-            MerkleSumTreeChip::<N_ASSETS>::construct(config.merkle_sum_tree_config.clone());
-        let poseidon_chip =
-            PoseidonChip::<PoseidonSpec, 3, 2, L>::construct(config.poseidon_config.clone());
-        let lt_chip = LtVerticalChip::<N_BYTES>::construct(config.lt_config);
-
-        // Assign the left penultimate hash and the left penultimate balances
-        let (left_node_hash, left_node_balances) = merkle_sum_tree_chip
-            .assign_entry_hash_and_balances(
-                layouter.namespace(|| "assign leaf hash and balances"),
-                self.left_node_hash,
-                &self.left_node_balances,
-            )?;
-
-        let swap_bit = merkle_sum_tree_chip
-            .assing_swap_bit(layouter.namespace(|| "assign swap bit"), Fp::from(0))?;
-
-        // assign penultimate nodes hashes according to the swap bit
-        let (left_hash, right_hash) = merkle_sum_tree_chip.assign_nodes_hashes_per_level(
-            layouter.namespace(|| "assign penultimate nodes hashes"),
-            &left_node_hash,
-            self.right_node_hash,
-            swap_bit.clone(),
-        )?;
-
-        let mut root_balances = vec![];
-        let mut left_balances = vec![];
-        let mut right_balances = vec![];
-
-        // assign penultimate nodes balances per each asset according to the swap bit
-        for asset in 0..N_ASSETS {
-            let (left_balance, right_balance, next_balance) = merkle_sum_tree_chip
-                .assign_nodes_balance_per_asset(
-                    layouter.namespace(|| format!("asset {}: assign nodes balances", asset)),
-                    &left_node_balances[asset],
-                    self.right_node_balances[asset],
-                    swap_bit.clone(),
-                )?;
-
-            root_balances.push(next_balance);
-            left_balances.push(left_balance);
-            right_balances.push(right_balance);
-        }
-
-        // create an hash_input array of length L that contains the left hash, the left balances, the right hash and the right balances
-        let hash_input_vec: Vec<AssignedCell<Fp, Fp>> = [left_hash]
-            .iter()
-            .chain(left_balances.iter())
-            .chain([right_hash].iter())
-            .chain(right_balances.iter())
-            .map(|x| x.to_owned())
-            .collect();
-
-        let hash_input: [AssignedCell<Fp, Fp>; L] = match hash_input_vec.try_into() {
-            Ok(arr) => arr,
-            Err(_) => panic!("Failed to convert Vec to Array"),
-        };
-
-        // compute the root hash
-        let root_hash = poseidon_chip.hash(
-            layouter.namespace(|| format!("perform root hash")),
-            hash_input,
-        )?;
-
-        // expose the root hash, as public input
-        config.expose_public(layouter.namespace(|| "public root hash"), &root_hash, 0)?;
-
-        // load lookup table for lt chip
-        lt_chip.load(&mut layouter)?;
-
-        // enforce root balances to be less than the assets sum
-        for asset in 0..N_ASSETS {
-            config.enforce_less_than(
-                layouter.namespace(|| "enforce less than"),
-                &root_balances[asset],
-                asset + 1,
-                &lt_chip,
-            )?;
-        }
+        self.assign_solvency(&config, &mut layouter)?;
 
         Ok(())
     }
@@ -0,0 +1,224 @@
+use crate::chips::less_than::less_than_vertical::{LtVerticalChip, LtVerticalInstruction};
+use crate::chips::poseidon::poseidon_spec::PoseidonSpec;
+use crate::chips::poseidon::sponge::{PoseidonSpongeChip, PoseidonSpongeConfig};
+use crate::circuits::solvency::{assign_root_from_penultimate_nodes, SolvencyConfig};
+use crate::merkle_sum_tree::{poseidon_solvency_commitment, MerkleSumTree};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed};
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for verifying the solvency of a CEX across all its assets at once, namely that the liabilities stored in the merkle sum tree are less than the assets sum for each asset, as in [crate::circuits::solvency::SolvencyCircuit].
+///
+/// Instead of exposing the assets sum of each asset as a public input, the circuit exposes a commitment to the pairs of the liabilities and the assets sum of each asset, see [poseidon_solvency_commitment], so that the public inputs are the root hash and the commitment no matter the number of assets.
+/// The verifier recomputes the commitment from the liabilities and the assets sum published by the CEX.
+///
+/// # Type Parameters
+///
+/// * `L`: The length of the hasher input, namely 2 + (2 * N_ASSETS)
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+/// * `N_BYTES`: Range in which the balances should lie, namely the number of bytes of the less than comparison between the liabilities and the assets
+///
+/// # Fields
+///
+/// * `left_node_hash`: The hash of the penultimate left node of the merkle sum tree
+/// * `left_node_balances`: The balances of the penultimate left node of the merkle sum tree
+/// * `right_node_hash`: The hash of the penultimate right node of the merkle sum tree
+/// * `right_node_balances`: The balances of the penultimate right node of the merkle sum tree
+/// * `assets_sum`: The sum of the assets of the CEX for each asset, private
+/// * `root_hash`: The root hash of the merkle sum tree
+#[derive(Clone)]
+pub struct SolvencyCommitmentCircuit<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> {
+    pub left_node_hash: Fp,
+    pub left_node_balances: [Fp; N_ASSETS],
+    pub right_node_hash: Fp,
+    pub right_node_balances: [Fp; N_ASSETS],
+    pub assets_sum: [Fp; N_ASSETS],
+    pub root_hash: Fp,
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> CircuitExt<Fp>
+    for SolvencyCommitmentCircuit<L, N_ASSETS, N_BYTES>
+{
+    /// Returns the number of public inputs of the circuit. It is 2, namely the root hash of the merkle sum tree and the solvency commitment
+    fn num_instance(&self) -> Vec<usize> {
+        vec![2]
+    }
+
+    /// Returns the values of the public inputs of the circuit, namely the root hash of the merkle sum tree and the solvency commitment
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        vec![vec![self.root_hash, self.commitment()]]
+    }
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
+    SolvencyCommitmentCircuit<L, N_ASSETS, N_BYTES>
+{
+    pub fn init_empty() -> Self {
+        assert_eq!((N_ASSETS * 2) + 2, L);
+        Self {
+            left_node_hash: Fp::zero(),
+            left_node_balances: [Fp::zero(); N_ASSETS],
+            right_node_hash: Fp::zero(),
+            right_node_balances: [Fp::zero(); N_ASSETS],
+            assets_sum: [Fp::zero(); N_ASSETS],
+            root_hash: Fp::zero(),
+        }
+    }
+
+    /// Initializes the circuit with the merkle sum tree and the assets sum
+    pub fn init(merkle_sum_tree: MerkleSumTree<N_ASSETS>, assets_sum: [Fp; N_ASSETS]) -> Self {
+        assert_eq!((N_ASSETS * 2) + 2, L);
+
+        let (penultimate_node_left, penultimate_node_right) = merkle_sum_tree
+            .penultimate_level_data()
+            .expect("Failed to retrieve penultimate level data");
+
+        Self {
+            left_node_hash: penultimate_node_left.hash,
+            left_node_balances: penultimate_node_left.balances,
+            right_node_hash: penultimate_node_right.hash,
+            right_node_balances: penultimate_node_right.balances,
+            assets_sum,
+            root_hash: merkle_sum_tree.root().hash,
+        }
+    }
+
+    /// Returns the liabilities of the CEX for each asset, namely the balances of the root computed off-circuit from the penultimate nodes
+    pub fn liabilities(&self) -> [Fp; N_ASSETS] {
+        std::array::from_fn(|asset| {
+            self.left_node_balances[asset] + self.right_node_balances[asset]
+        })
+    }
+
+    /// Returns the solvency commitment computed off-circuit, see [poseidon_solvency_commitment]
+    pub fn commitment(&self) -> Fp {
+        poseidon_solvency_commitment(&self.liabilities(), &self.assets_sum)
+    }
+}
+
+/// Configuration for the Solvency Commitment circuit
+///
+/// # Fields
+///
+/// * `solvency_config`: Configuration for the root computation and the less than checks, see [SolvencyConfig]
+/// * `sponge_config`: Configuration for the poseidon sponge used to compute the solvency commitment, with WIDTH = 3 and RATE = 2
+/// * `advice`: Advice columns used by the sponge
+#[derive(Debug, Clone)]
+pub struct SolvencyCommitmentConfig<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> {
+    pub solvency_config: SolvencyConfig<L, N_ASSETS, N_BYTES>,
+    pub sponge_config: PoseidonSpongeConfig<3, 2>,
+    pub advice: [Column<Advice>; 4],
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> Circuit<Fp>
+    for SolvencyCommitmentCircuit<L, N_ASSETS, N_BYTES>
+{
+    type Config = SolvencyCommitmentConfig<L, N_ASSETS, N_BYTES>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    /// Configures the circuit
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let solvency_config = SolvencyConfig::<L, N_ASSETS, N_BYTES>::configure(meta);
+
+        // the sponge requires #WIDTH advice columns for state and 1 for partial_sbox, 3 fixed columns for rc_a and 3 for rc_b
+        let advice: [Column<Advice>; 4] = std::array::from_fn(|_| meta.advice_column());
+        let fixed_columns: [Column<Fixed>; 6] = std::array::from_fn(|_| meta.fixed_column());
+
+        for col in &advice {
+            meta.enable_equality(*col);
+        }
+
+        let sponge_config = PoseidonSpongeChip::<PoseidonSpec, 3, 2>::configure(
+            meta,
+            advice[0..3].try_into().unwrap(),
+            advice[3],
+            fixed_columns[0..3].try_into().unwrap(),
+            fixed_columns[3..6].try_into().unwrap(),
+        );
+
+        SolvencyCommitmentConfig {
+            solvency_config,
+            sponge_config,
+            advice,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let solvency_config = &config.solvency_config;
+
+        let (root_hash, root_balances) = assign_root_from_penultimate_nodes(
+            &solvency_config.merkle_sum_tree_config,
+            &solvency_config.poseidon_config,
+            &mut layouter,
+            self.left_node_hash,
+            &self.left_node_balances,
+            self.right_node_hash,
+            &self.right_node_balances,
+        )?;
+
+        // the assets sum is private, it is only exposed through the commitment
+        let assets_sum = layouter.assign_region(
+            || "assign assets sum",
+            |mut region| {
+                self.assets_sum
+                    .iter()
+                    .enumerate()
+                    .map(|(asset, assets_sum)| {
+                        region.assign_advice(
+                            || format!("asset {}: assets sum", asset),
+                            config.advice[0],
+                            asset,
+                            || Value::known(*assets_sum),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )?;
+
+        // load lookup table for lt chip
+        let lt_chip = LtVerticalChip::<N_BYTES>::construct(solvency_config.lt_config);
+        lt_chip.load(&mut layouter)?;
+
+        // enforce root balances to be less than the assets sum
+        for asset in 0..N_ASSETS {
+            solvency_config.enforce_cell_less_than(
+                layouter.namespace(|| format!("asset {}: enforce less than", asset)),
+                &root_balances[asset],
+                &assets_sum[asset],
+                &lt_chip,
+            )?;
+        }
+
+        // hash the pairs of the liabilities and the assets sum of each asset
+        let commitment_inputs = root_balances
+            .iter()
+            .zip(assets_sum.iter())
+            .flat_map(|(liabilities, assets_sum)| [liabilities.clone(), assets_sum.clone()])
+            .collect::<Vec<_>>();
+
+        let sponge_chip = PoseidonSpongeChip::<PoseidonSpec, 3, 2>::construct(config.sponge_config);
+        let commitment = sponge_chip.hash_variable(
+            layouter.namespace(|| "solvency commitment"),
+            &commitment_inputs,
+        )?;
+
+        // expose the root hash and the solvency commitment as public inputs
+        solvency_config.expose_public(layouter.namespace(|| "public root hash"), &root_hash, 0)?;
+        solvency_config.expose_public(
+            layouter.namespace(|| "public solvency commitment"),
+            &commitment,
+            1,
+        )?;
+
+        Ok(())
+    }
+}
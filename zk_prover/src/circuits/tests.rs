@@ -1,23 +1,70 @@
 #[cfg(test)]
 mod test {
 
+    use std::collections::HashSet;
+    use std::io::Cursor;
     use std::path::Path;
+    use zeroize::Zeroize;
 
+    use crate::chips::less_than::less_than_vertical::{LtVerticalChip, LtVerticalInstruction};
+    use crate::chips::merkle_sum_tree::{MerkleSumTreeChip, MerkleSumTreeConfig};
     use crate::circuits::{
-        aggregation::WrappedAggregationCircuit,
-        merkle_sum_tree::MstInclusionCircuit,
-        solvency::SolvencyCircuit,
-        utils::{full_prover, full_verifier, generate_setup_params},
+        aggregation::{aggregate, InclusionAggregationCircuit, WrappedAggregationCircuit},
+        balance_allowlist::BalanceAllowlistCircuit,
+        balance_increase::BalanceIncreaseCircuit,
+        batch_inclusion::BatchMstInclusionCircuit,
+        evm_verifier::gen_solvency_evm_verifier,
+        kzg_balances::{verify_balance_opening, KzgBalanceCommitment},
+        merkle_sum_tree::{MstInclusionCircuit, PublicInputs},
+        merkle_sum_tree_any_root::MstInclusionAnyRootCircuit,
+        merkle_sum_tree_committed_leaf::MstInclusionCommittedLeafCircuit,
+        merkle_sum_tree_exclusion::MstExclusionCircuit,
+        merkle_sum_tree_limbs::MstLimbInclusionCircuit,
+        merkle_sum_tree_sha256_leaf::MstInclusionSha256LeafCircuit,
+        merkle_sum_tree_snapshot::MstInclusionSnapshotCircuit,
+        merkle_sum_tree_sorted::MstSortedInclusionCircuit,
+        metadata::export_circuit_metadata,
+        minimum_reserve::MinimumReserveCircuit,
+        nary_merkle_sum_tree::NaryMstInclusionCircuit,
+        partition::PartitionCircuit,
+        proof_bundle::{BundleError, SignedProofBundle},
+        reserves_ratio::{reserves_ratio, ReservesRatioCircuit},
+        secret::SecretBalances,
+        serialization::{proof_from_json, proof_to_json},
+        solvency::{SolvencyCircuit, SolvencyConfig},
+        solvency_commitment::SolvencyCommitmentCircuit,
+        utils::{
+            assert_mock_satisfied, estimated_verify_gas, full_prover, full_prover_to_writer,
+            full_prover_with_peak_memory, full_prover_with_self_check, full_verifier,
+            generate_setup_params, min_k, probe_free_rows, proof_filename, prove_inclusion,
+            prove_user_inclusion, read_params, read_verifying_key, reprove_inclusion_after_update,
+            verify_any, verify_batch, verify_fresh, verify_inclusion, verifying_key_fingerprint,
+            write_params, write_verifying_key, TimestampedProof, VerifyError,
+        },
+        weighted_solvency::{balance_bits, WeightedSolvencyCircuit},
     };
-    use crate::merkle_sum_tree::{MerkleSumTree, MOD_BITS, N_ASSETS};
+    use crate::merkle_sum_tree::{
+        big_int_to_fp, create_middle_node, fp_to_big_int, limbs_to_big_uint,
+        poseidon_solvency_commitment, AttestationScalar, BalanceAttestation, Entry,
+        HierarchicalMerkleSumTree, KeyedMerkleSumTree, LeafHasher, LimbMerkleSumTree,
+        MerkleSumTree, MerkleSumTreeError, NaryMerkleSumTree, L_ENTRY, MOD_BITS, N_ASSETS,
+    };
+    use crate::timing;
     use ark_std::{end_timer, start_timer};
+    use gadgets::util::pow_of_two;
     use halo2_proofs::{
+        arithmetic::Field,
+        circuit::{Layouter, SimpleFloorPlanner, Value},
         dev::{FailureLocation, MockProver, VerifyFailure},
         halo2curves::bn256::Fr as Fp,
-        plonk::{keygen_pk, keygen_vk, Any, Circuit},
-        poly::commitment::Params,
+        plonk::{keygen_pk, keygen_vk, Any, Circuit, ConstraintSystem, Error as PlonkError},
+        poly::commitment::{Blind, Params},
+    };
+    use num_bigint::{BigInt, BigUint};
+    use rand::{
+        rngs::{OsRng, StdRng},
+        SeedableRng,
     };
-    use rand::rngs::OsRng;
     use snark_verifier_sdk::{
         evm::{evm_verify, gen_evm_proof_shplonk, gen_evm_verifier_shplonk},
         gen_pk,
@@ -50,236 +97,2202 @@ mod test {
     }
 
     #[test]
-    fn test_valid_merkle_sum_tree_with_full_prover() {
-        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
-
-        // we generate a universal trusted setup of our own for testing
-        let params = generate_setup_params(K);
+    fn test_valid_merkle_sum_tree_of_different_depths() {
+        let entries = MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv")
+            .unwrap()
+            .entries()
+            .to_vec();
+
+        // the depth of the tree is the LEVELS const generic of the circuit, so the same circuit proves a tree of 4 users
+        let shallow_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries[..4].to_vec()).unwrap();
+        assert_eq!(*shallow_tree.depth(), 2);
+
+        for user_index in [0, 3] {
+            let circuit =
+                MstInclusionCircuit::<2, L, N_ASSETS>::init(shallow_tree.clone(), user_index);
+            let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+            valid_prover.assert_satisfied();
+        }
 
-        // we generate the verification key and the proving key
-        // we use an empty circuit just to enphasize that the circuit input are not relevant when generating the keys
-        // Note: the dimension of the circuit used to generate the keys must be the same as the dimension of the circuit used to generate the proof
-        // In this case, the dimension are represented by the heigth of the merkle tree
-        let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
-        let pk = keygen_pk(&params, vk.clone(), &circuit).expect("pk generation should not fail");
+        // and a tree of 32 users, which needs twice the rows for the extra levels
+        let deep_entries = entries
+            .iter()
+            .chain(entries.iter())
+            .enumerate()
+            .map(|(i, entry)| {
+                Entry::new(
+                    format!("{}_{}", entry.username(), i),
+                    entry.balances().clone(),
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let deep_tree = MerkleSumTree::<N_ASSETS>::from_entries(deep_entries).unwrap();
+        assert_eq!(*deep_tree.depth(), 5);
+
+        for user_index in [0, 31] {
+            let circuit =
+                MstInclusionCircuit::<5, L, N_ASSETS>::init(deep_tree.clone(), user_index);
+            let valid_prover = MockProver::run(K + 1, &circuit, circuit.instances()).unwrap();
+            valid_prover.assert_satisfied();
+        }
+    }
 
+    #[test]
+    fn test_timing_summary() {
         let merkle_sum_tree =
             MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        // Only now we can instantiate the circuit with the actual inputs
         let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
 
-        // Generate the proof
-        let proof = full_prover(&params, &pk, circuit.clone(), circuit.instances());
+        let summary = timing::summary();
+        timing::print_summary();
+
+        // the timings are only recorded with the timing feature, other tests may record timings concurrently
+        if cfg!(feature = "timing") {
+            for label in [timing::MERKLE_LEVEL, timing::POSEIDON_HASH] {
+                let section = summary
+                    .iter()
+                    .find(|section| section.label == label)
+                    .unwrap();
+                assert!(section.count >= LEVELS);
+            }
+        } else {
+            assert!(summary.is_empty());
+        }
+    }
 
-        // verify the proof to be true
-        assert!(full_verifier(&params, &vk, proof, circuit.instances()));
+    #[test]
+    fn test_valid_nary_merkle_sum_tree() {
+        const ARITY: usize = 4;
+        const NARY_LEVELS: usize = 2;
+        const NARY_L: usize = ARITY * (1 + N_ASSETS);
+
+        let entries = MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv")
+            .unwrap()
+            .entries()
+            .to_vec();
+        let merkle_sum_tree = NaryMerkleSumTree::<N_ASSETS, ARITY>::from_entries(entries).unwrap();
+
+        for user_index in [0, 5, 10, 15] {
+            let circuit = NaryMstInclusionCircuit::<NARY_LEVELS, NARY_L, N_ASSETS, ARITY>::init(
+                merkle_sum_tree.clone(),
+                user_index,
+            );
+
+            let valid_prover = MockProver::run(12, &circuit, circuit.instances()).unwrap();
+            valid_prover.assert_satisfied();
+        }
+
+        // a wrong root hash should invalidate the proof
+        let circuit = NaryMstInclusionCircuit::<NARY_LEVELS, NARY_L, N_ASSETS, ARITY>::init(
+            merkle_sum_tree,
+            6,
+        );
+        let invalid_instances = vec![vec![circuit.leaf_hash, Fp::from(1000u64)]];
+
+        let invalid_prover = MockProver::run(12, &circuit, invalid_instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
     }
 
     #[test]
-    #[ignore]
-    fn test_valid_merkle_sum_tree_with_full_recursive_prover() {
-        // params for the aggregation circuit
-        let params_agg = generate_setup_params(21);
+    fn test_valid_limb_merkle_sum_tree() {
+        const LIMB_LEVELS: usize = 2;
+        const LIMB_L: usize = 2 + (N_ASSETS * 4);
+        const N_LIMBS: usize = 2 * N_ASSETS;
+
+        // the balances of the first asset are near 2^128, so the low limbs carry into the high limbs up to the root.
+        // The total of the second asset exceeds 2^MOD_BITS, so it couldn't be held in a single field element
+        let near_2_128 = (BigInt::from(1u8) << 128) - 1u8;
+        let balances = [
+            [near_2_128.clone(), BigInt::from(1u8)],
+            [near_2_128, (BigInt::from(1u8) << 255) - 1u8],
+            [BigInt::from(5u8), BigInt::from(0u8)],
+            [(BigInt::from(1u8) << 128) + 1u8, BigInt::from(3u8)],
+        ];
+        let entries = balances
+            .iter()
+            .enumerate()
+            .map(|(i, balances)| Entry::new(format!("user{}", i), balances.clone()).unwrap())
+            .collect::<Vec<_>>();
 
-        // downsize params for our application specific snark
-        let mut params_app = params_agg.clone();
-        params_app.downsize(K);
+        let merkle_sum_tree =
+            LimbMerkleSumTree::<N_ASSETS, N_LIMBS>::from_entries(entries).unwrap();
 
-        // generate the verification key and the proving key for the application circuit, using an empty circuit
-        let circuit_app = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+        // the total of the first asset is 3 * 2^128 + 4
+        let root_limbs = merkle_sum_tree.root().balances;
+        assert_eq!([root_limbs[0], root_limbs[1]], [Fp::from(4), Fp::from(3)]);
+        assert_eq!(
+            limbs_to_big_uint(&[root_limbs[2], root_limbs[3]]),
+            (BigUint::from(1u8) << 255) + 4u8
+        );
 
-        let vk_app = keygen_vk(&params_app, &circuit_app).expect("vk generation should not fail");
-        let pk_app =
-            keygen_pk(&params_app, vk_app, &circuit_app).expect("pk generation should not fail");
+        for user_index in 0..4 {
+            let circuit = MstLimbInclusionCircuit::<LIMB_LEVELS, LIMB_L, N_ASSETS>::init(
+                merkle_sum_tree.clone(),
+                user_index,
+            );
+
+            let valid_prover = MockProver::run(12, &circuit, circuit.instances()).unwrap();
+            valid_prover.assert_satisfied();
+        }
 
+        // the root of the same leaves summed without propagating the carry should invalidate the proof
+        let leaves = merkle_sum_tree.leaves();
+        let root_without_carry = create_middle_node(
+            &create_middle_node(&leaves[0], &leaves[1]),
+            &create_middle_node(&leaves[2], &leaves[3]),
+        );
+        assert_ne!(root_without_carry.hash, merkle_sum_tree.root().hash);
+
+        let circuit =
+            MstLimbInclusionCircuit::<LIMB_LEVELS, LIMB_L, N_ASSETS>::init(merkle_sum_tree, 0);
+        let invalid_instances = vec![vec![circuit.leaf_hash, root_without_carry.hash]];
+
+        let invalid_prover = MockProver::run(12, &circuit, invalid_instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_min_k() {
         let merkle_sum_tree =
             MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        // Only now we can instantiate the circuit with the actual inputs
-        let circuit_app = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+        // the layout doesn't depend on the witness, so the empty circuit is enough to compute k
+        let k = min_k(&MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty()).unwrap();
+        assert!(k <= K);
 
-        let snark_app = [(); 1]
-            .map(|_| gen_snark_shplonk(&params_app, &pk_app, circuit_app.clone(), None::<&str>));
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree.clone(), 0);
+        let valid_prover = MockProver::run(k, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
 
-        const N_SNARK: usize = 1;
+        assert!(matches!(
+            MockProver::run(k - 1, &circuit, circuit.instances()),
+            Err(PlonkError::NotEnoughRowsAvailable { .. })
+        ));
 
-        // create aggregation circuit
-        let agg_circuit = WrappedAggregationCircuit::<N_SNARK>::new(&params_agg, snark_app);
+        // a deeper tree needs at least as many rows
+        assert!(min_k(&MstInclusionCircuit::<8, L, N_ASSETS>::init_empty()).unwrap() >= k);
 
-        assert_eq!(agg_circuit.instances()[0], circuit_app.instances()[0]);
+        // the arity of the tree is accounted for as well
+        const ARITY: usize = 4;
+        const NARY_LEVELS: usize = 2;
+        const NARY_L: usize = ARITY * (1 + N_ASSETS);
 
-        let start0 = start_timer!(|| "gen vk & pk");
-        // generate proving key for the aggregation circuit
-        let pk_agg = gen_pk(&params_agg, &agg_circuit.without_witnesses(), None);
-        end_timer!(start0);
+        let nary_tree =
+            NaryMerkleSumTree::<N_ASSETS, ARITY>::from_entries(merkle_sum_tree.entries().to_vec())
+                .unwrap();
+        let nary_circuit =
+            NaryMstInclusionCircuit::<NARY_LEVELS, NARY_L, N_ASSETS, ARITY>::init(nary_tree, 5);
 
-        let num_instances = agg_circuit.num_instance();
-        let instances = agg_circuit.instances();
+        let nary_k = min_k(&nary_circuit).unwrap();
+        let valid_prover =
+            MockProver::run(nary_k, &nary_circuit, nary_circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+    }
 
-        let proof_calldata =
-            gen_evm_proof_shplonk(&params_agg, &pk_agg, agg_circuit, instances.clone());
+    #[test]
+    fn test_valid_merkle_sum_tree_with_sha256_leaves() {
+        let entries = MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv")
+            .unwrap()
+            .entries()
+            .to_vec();
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::from_entries_with_leaf_hasher(entries, LeafHasher::Sha256)
+                .unwrap();
 
-        let deployment_code = gen_evm_verifier_shplonk::<WrappedAggregationCircuit<N_SNARK>>(
-            &params_agg,
-            pk_agg.get_vk(),
-            num_instances,
-            None,
-        );
-        let gas_cost = evm_verify(deployment_code, instances, proof_calldata);
+        for user_index in [0, 7, 15] {
+            let circuit = MstInclusionSha256LeafCircuit::<LEVELS, L, N_ASSETS>::init(
+                merkle_sum_tree.clone(),
+                user_index,
+            );
 
-        // assert gas_cost to verify the proof on chain to be between 575000 and 590000
-        assert!(
-            (575000..=590000).contains(&gas_cost),
-            "gas_cost is not within the expected range"
-        );
+            let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+            valid_prover.assert_satisfied();
+        }
+
+        // the poseidon hash of the entry doesn't match the sha256 hash recomputed in-circuit
+        let circuit =
+            MstInclusionSha256LeafCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree.clone(), 0);
+        let poseidon_leaf_hash = merkle_sum_tree.entries()[0].compute_leaf().hash;
+        let invalid_instances = vec![vec![poseidon_leaf_hash, circuit.inclusion.root_hash]];
+
+        let invalid_prover = MockProver::run(K, &circuit, invalid_instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
     }
 
     #[test]
-    #[ignore]
-    fn test_invalid_merkle_sum_tree_with_full_recursive_prover() {
-        // params for the aggregation circuit
-        let params_agg = generate_setup_params(21);
+    fn test_committed_leaf_rejects_tampered_balance() {
+        let entries = MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv")
+            .unwrap()
+            .entries()
+            .to_vec();
+        let merkle_sum_tree = MerkleSumTree::<N_ASSETS>::from_entries_with_leaf_hasher(
+            entries.clone(),
+            LeafHasher::PoseidonUsernameHash,
+        )
+        .unwrap();
 
-        // downsize params for our application specific snark
-        let mut params_app = params_agg.clone();
-        params_app.downsize(K);
+        let circuit = MstInclusionCommittedLeafCircuit::<LEVELS, L, L_ENTRY, N_ASSETS>::init(
+            merkle_sum_tree,
+            3,
+        );
 
-        // generate the verification key and the proving key for the application circuit, using an empty circuit
-        let circuit_app = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
 
-        let vk_app = keygen_vk(&params_app, &circuit_app).expect("vk generation should not fail");
-        let pk_app =
-            keygen_pk(&params_app, vk_app, &circuit_app).expect("pk generation should not fail");
+        // in a tree of a single entry the root is the leaf itself, so the balances of the leaf don't enter any hash of the path
+        let single_entry_tree = MerkleSumTree::<N_ASSETS>::from_entries_with_leaf_hasher(
+            vec![entries[0].clone()],
+            LeafHasher::PoseidonUsernameHash,
+        )
+        .unwrap();
+
+        // the plain inclusion circuit accepts a tampered balance, as it isn't bound to the leaf hash
+        let mut unbound_circuit =
+            MstInclusionCircuit::<0, L, N_ASSETS>::init(single_entry_tree.clone(), 0);
+        unbound_circuit.leaf_balances[0] += Fp::one();
+
+        let unbound_prover =
+            MockProver::run(K, &unbound_circuit, unbound_circuit.instances()).unwrap();
+        unbound_prover.assert_satisfied();
+
+        // the committed leaf circuit rejects it, as the recomputed leaf hash doesn't match the committed one
+        let mut tampered_circuit =
+            MstInclusionCommittedLeafCircuit::<0, L, L_ENTRY, N_ASSETS>::init(single_entry_tree, 0);
+        tampered_circuit.inclusion.leaf_balances[0] += Fp::one();
 
+        let invalid_prover =
+            MockProver::run(K, &tampered_circuit, tampered_circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_snapshot_roots_of_different_timestamps() {
         let merkle_sum_tree =
             MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        // Only now we can instantiate the circuit with the actual inputs
-        let circuit_app = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+        // the same balances captured at two different timestamps result in two different snapshot roots
+        let (timestamp, later_timestamp) = (1_690_000_000, 1_690_086_400);
+        let snapshot_root = merkle_sum_tree.snapshot_root(timestamp);
+        assert_ne!(
+            snapshot_root,
+            merkle_sum_tree.snapshot_root(later_timestamp)
+        );
+        assert_ne!(snapshot_root, merkle_sum_tree.root().hash);
 
-        let snark_app = [(); 1]
-            .map(|_| gen_snark_shplonk(&params_app, &pk_app, circuit_app.clone(), None::<&str>));
+        let circuit =
+            MstInclusionSnapshotCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0, timestamp);
+        assert_eq!(circuit.instances()[0][1], snapshot_root);
 
-        const N_SNARK: usize = 1;
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
 
-        // create aggregation circuit
-        let agg_circuit = WrappedAggregationCircuit::<N_SNARK>::new(&params_agg, snark_app);
+        // the proof can't be passed off as a proof of the snapshot taken at another timestamp
+        let mut invalid_instances = circuit.instances();
+        invalid_instances[0][2] = Fp::from(later_timestamp);
+        let invalid_prover = MockProver::run(K, &circuit, invalid_instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
 
-        let invalid_root_hash = Fp::from(1000u64);
+        let mut invalid_instances = circuit.instances();
+        invalid_instances[0][1] = circuit.inclusion.root_hash;
+        let invalid_prover = MockProver::run(K, &circuit, invalid_instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
 
-        let mut agg_circuit_invalid_instances = agg_circuit.instances();
-        agg_circuit_invalid_instances[0][1] = invalid_root_hash;
+    #[test]
+    fn test_valid_merkle_sum_tree_with_extra_public_inputs() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        let invalid_prover =
-            MockProver::run(21, &agg_circuit, agg_circuit_invalid_instances).unwrap();
+        // bind a request nonce into the proof
+        let nonce = Fp::from(123456789u64);
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0)
+            .with_extra_public_inputs(vec![nonce]);
 
+        let instances = circuit.instances();
         assert_eq!(
-            invalid_prover.verify(),
-            Err(vec![
-                VerifyFailure::Permutation {
-                    column: (Any::Instance, 0).into(),
-                    location: FailureLocation::OutsideRegion { row: 1 }
-                },
-                VerifyFailure::Permutation {
-                    column: (Any::advice(), 0).into(),
-                    location: FailureLocation::InRegion {
-                        region: (1, "").into(),
-                        offset: 1
-                    }
-                }
-            ])
+            instances[0],
+            vec![circuit.leaf_hash, circuit.root_hash, nonce]
         );
+        assert_eq!(circuit.num_instance(), vec![3]);
+
+        let valid_prover = MockProver::run(K, &circuit, instances.clone()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // tampering with the nonce should invalidate the proof
+        let mut invalid_instances = instances;
+        invalid_instances[0][2] = Fp::from(987654321u64);
+
+        let invalid_prover = MockProver::run(K, &circuit, invalid_instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
     }
 
-    // Passing an invalid root hash in the instance column should fail the permutation check between the computed root hash and the instance column root hash
     #[test]
-    fn test_invalid_root_hash() {
+    fn test_valid_merkle_sum_tree_with_public_root_balances() {
         let merkle_sum_tree =
             MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
-
-        let mut instances = circuit.instances();
-        let invalid_root_hash = Fp::from(1000u64);
-        instances[0][1] = invalid_root_hash;
-
-        let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
+        let nonce = Fp::from(123456789u64);
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree.clone(), 0)
+            .with_public_root_balances()
+            .with_extra_public_inputs(vec![nonce]);
 
+        // the root balances, namely the total liabilities, are exposed right after the root hash
+        let instances = circuit.instances();
+        let public_inputs = PublicInputs::<N_ASSETS>::from_instances(&instances, true).unwrap();
+        assert_eq!(public_inputs, circuit.public_inputs());
+        assert_eq!(public_inputs.root_hash(), merkle_sum_tree.root().hash);
         assert_eq!(
-            invalid_prover.verify(),
-            Err(vec![
-                VerifyFailure::Permutation {
-                    column: (Any::advice(), 0).into(),
-                    location: FailureLocation::InRegion {
-                        region: (61, "permute state").into(),
-                        offset: 38
-                    }
-                },
-                VerifyFailure::Permutation {
-                    column: (Any::Instance, 0).into(),
-                    location: FailureLocation::OutsideRegion { row: 1 }
-                },
-            ])
+            public_inputs.root_balances(),
+            Some(&merkle_sum_tree.root().balances)
         );
-    }
+        assert_eq!(public_inputs.extra_public_inputs(), &[nonce]);
+        assert_eq!(public_inputs.root_balance_index(1), Some(3));
+        assert_eq!(public_inputs.extra_public_input_index(0), 2 + N_ASSETS);
 
-    #[test]
-    fn test_invalid_root_hash_as_instance_with_full_prover() {
-        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+        let valid_prover = MockProver::run(K, &circuit, instances.clone()).unwrap();
+        valid_prover.assert_satisfied();
 
-        // we generate a universal trusted setup of our own for testing
-        let params = generate_setup_params(K);
+        // tampering with the total liabilities should invalidate the proof
+        let mut invalid_instances = instances;
+        invalid_instances[0][public_inputs.root_balance_index(0).unwrap()] += Fp::one();
 
-        // we generate the verification key and the proving key
-        // we use an empty circuit just to enphasize that the circuit input are not relevant when generating the keys
-        let vk = keygen_vk(&params, &circuit).expect("vk should not fail");
-        let pk = keygen_pk(&params, vk.clone(), &circuit).expect("pk should not fail");
+        let invalid_prover = MockProver::run(K, &circuit, invalid_instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // the root balances are not public by default
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+        assert_eq!(circuit.public_inputs().root_balances(), None);
+        assert_eq!(circuit.num_instance(), vec![2]);
+    }
 
+    #[test]
+    fn test_near_modulus_leaf_balance() {
         let merkle_sum_tree =
             MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        // Only now we can instantiate the circuit with the actual inputs
-        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+        // a valid tree passes the validation and the range check of the leaf balances
+        assert_eq!(merkle_sum_tree.validate(MOD_BITS), Ok(()));
 
-        let invalid_root_hash = Fp::from(1000u64);
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree.clone(), 3)
+            .with_leaf_range_check();
 
-        let mut instances = circuit.instances();
-        instances[0][1] = invalid_root_hash;
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
 
-        // Generate the proof
-        let proof = full_prover(&params, &pk, circuit, instances.clone());
+        // replace the first balance of the fourth user with p - 1, which acts as a balance of -1
+        let mut entries = merkle_sum_tree.entries().to_vec();
+        let mut balances = entries[3].balances().clone();
+        balances[0] = fp_to_big_int(&-Fp::one());
+        entries[3] = Entry::new(entries[3].username().to_string(), balances).unwrap();
 
-        // verify the proof to be false
-        assert!(!full_verifier(&params, &vk, proof, instances));
+        let invalid_merkle_sum_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries).unwrap();
+
+        assert_eq!(
+            invalid_merkle_sum_tree.validate(MOD_BITS),
+            Err(MerkleSumTreeError::BalanceOutOfRange {
+                username: invalid_merkle_sum_tree.entries()[3].username().to_string(),
+                asset: 0
+            })
+        );
+
+        let invalid_circuit =
+            MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(invalid_merkle_sum_tree, 3)
+                .with_leaf_range_check();
+
+        let invalid_prover =
+            MockProver::run(K, &invalid_circuit, invalid_circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
     }
 
-    // Passing an invalid leaf hash as input for the witness generation should fail:
-    // - the permutation check between the leaf hash and the instance column leaf hash
-    // - the permutation check between the computed root hash and the instance column root hash
     #[test]
-    fn test_invalid_leaf_hash_as_witness() {
+    fn test_leaf_range_check_of_custom_width() {
+        const LEAF_BITS: usize = 64;
+
         let merkle_sum_tree =
             MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        let mut circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+        // the balances of the leaves of the tree fit in 64 bits
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS, 1, MOD_BITS, LEAF_BITS>::init(
+            merkle_sum_tree.clone(),
+            3,
+        )
+        .with_leaf_range_check();
 
-        let instances = circuit.instances();
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
 
-        // invalidate leaf hash
-        circuit.leaf_hash = Fp::from(1000u64);
+        // replace the first balance of the fourth user with 2^64, which is less than 2^MOD_BITS but doesn't fit in 64 bits
+        let mut entries = merkle_sum_tree.entries().to_vec();
+        let mut balances = entries[3].balances().clone();
+        balances[0] = BigInt::from(1) << LEAF_BITS;
+        entries[3] = Entry::new(entries[3].username().to_string(), balances).unwrap();
 
-        let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
-        assert_eq!(
-            invalid_prover.verify(),
-            Err(vec![
+        let invalid_merkle_sum_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries).unwrap();
+
+        // without the leaf range check the circuit is satisfied, as the balance is less than 2^MOD_BITS
+        let unchecked_circuit =
+            MstInclusionCircuit::<LEVELS, L, N_ASSETS, 1, MOD_BITS, LEAF_BITS>::init(
+                invalid_merkle_sum_tree.clone(),
+                3,
+            );
+
+        let unchecked_prover =
+            MockProver::run(K, &unchecked_circuit, unchecked_circuit.instances()).unwrap();
+        unchecked_prover.assert_satisfied();
+
+        let invalid_circuit =
+            MstInclusionCircuit::<LEVELS, L, N_ASSETS, 1, MOD_BITS, LEAF_BITS>::init(
+                invalid_merkle_sum_tree,
+                3,
+            )
+            .with_leaf_range_check();
+
+        let invalid_prover =
+            MockProver::run(K, &invalid_circuit, invalid_circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_sum_range_check() {
+        const SUM_BITS: usize = 64;
+
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // the computed sums of the tree fit in 64 bits, the sum range check is enabled by default
+        let circuit =
+            MstInclusionCircuit::<LEVELS, L, N_ASSETS, 1, SUM_BITS>::init(merkle_sum_tree, 0);
+
+        assert!(circuit.sum_range_check);
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // the first balance of the first user is 2^64, so the computed sums exceed the 64 bits range
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16_bigints.csv").unwrap();
+
+        let invalid_circuit =
+            MstInclusionCircuit::<LEVELS, L, N_ASSETS, 1, SUM_BITS>::init(merkle_sum_tree, 0);
+
+        let invalid_prover =
+            MockProver::run(K, &invalid_circuit, invalid_circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // the opt-out is explicit: without the sum range check the sums only need to fit in MOD_BITS
+        let circuit = invalid_circuit.without_sum_range_check();
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_valid_hierarchical_merkle_sum_tree() {
+        let sub_tree_1 =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let sub_tree_2 =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16_switched_order.csv")
+                .unwrap();
+
+        let tree = HierarchicalMerkleSumTree::new(vec![sub_tree_1, sub_tree_2]).unwrap();
+
+        // the path goes through the 4 levels of the sub-tree and the single level of the top-level tree
+        let proof = tree.generate_proof(1, 7).unwrap();
+        let circuit = MstInclusionCircuit::<{ LEVELS + 1 }, L, N_ASSETS>::init_from_proof(proof);
+
+        assert_eq!(circuit.instances()[0][1], tree.root().hash);
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_valid_merkle_sum_tree_with_any_of_the_roots() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let merkle_sum_tree_2 =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16_switched_order.csv")
+                .unwrap();
+        let merkle_sum_tree_3 =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16_bigints.csv").unwrap();
+
+        let candidate_roots = [
+            merkle_sum_tree.root().hash,
+            merkle_sum_tree_2.root().hash,
+            merkle_sum_tree_3.root().hash,
+        ];
+
+        // prove the inclusion of the same user against the second root
+        let user_index = merkle_sum_tree_2
+            .index_of(
+                merkle_sum_tree.entries()[0].username(),
+                merkle_sum_tree.entries()[0].balances().clone(),
+            )
+            .unwrap();
+
+        let circuit = MstInclusionAnyRootCircuit::<LEVELS, L, L_ENTRY, N_ASSETS, 3>::init(
+            merkle_sum_tree_2,
+            user_index,
+            candidate_roots,
+        );
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // the instance is exactly the candidate roots, neither the leaf hash nor the root used are public
+        assert_eq!(circuit.instances(), vec![candidate_roots.to_vec()]);
+
+        // the public inputs don't leak which root has been used: they are the same whichever root the proof is generated against
+        for (tree, index) in [(&merkle_sum_tree, 0), (&merkle_sum_tree_3, 5)] {
+            let other_circuit = MstInclusionAnyRootCircuit::<LEVELS, L, L_ENTRY, N_ASSETS, 3>::init(
+                tree.clone(),
+                index,
+                candidate_roots,
+            );
+            assert_eq!(circuit.instances(), other_circuit.instances());
+
+            let valid_prover =
+                MockProver::run(K, &other_circuit, other_circuit.instances()).unwrap();
+            valid_prover.assert_satisfied();
+        }
+
+        // the proof fails when the root used is not among the candidates
+        let mut instances = circuit.instances();
+        instances[0][1] = Fp::from(1000u64);
+
+        let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // the private leaf hash must be the hash of the private username and balances
+        let mut forged_circuit = circuit.clone();
+        forged_circuit.username = Fp::one();
+
+        let invalid_prover =
+            MockProver::run(K, &forged_circuit, forged_circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_balance_in_allowlist() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // the balance of the first asset of the first user is 11888
+        let allowed_values = [Fp::from(1000u64), Fp::from(11888u64), Fp::from(50000u64)];
+
+        let circuit = BalanceAllowlistCircuit::<LEVELS, L, N_ASSETS, 3>::init(
+            merkle_sum_tree.clone(),
+            0,
+            0,
+            allowed_values,
+        );
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // the balance of the second asset, namely 41163, is not in the allowlist
+        let circuit = BalanceAllowlistCircuit::<LEVELS, L, N_ASSETS, 3>::init(
+            merkle_sum_tree,
+            0,
+            1,
+            allowed_values,
+        );
+
+        let invalid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_balance_increase_between_snapshots() {
+        let snapshot_a =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // build the later snapshots by updating the balances of the first user
+        let snapshot_with_balances = |delta: i64| {
+            let mut entries = snapshot_a.entries().to_vec();
+            let balances = entries[0].balances().clone().map(|balance| balance + delta);
+            entries[0] = Entry::new(entries[0].username().to_string(), balances).unwrap();
+            MerkleSumTree::<N_ASSETS>::from_entries(entries).unwrap()
+        };
+
+        // the balances of the user increased
+        let circuit = BalanceIncreaseCircuit::<LEVELS, L, L_ENTRY, N_ASSETS, N_BYTES>::init(
+            snapshot_a.clone(),
+            0,
+            snapshot_with_balances(1),
+            0,
+        );
+
+        let valid_prover = MockProver::run(12, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // the balances of the user didn't change
+        let circuit = BalanceIncreaseCircuit::<LEVELS, L, L_ENTRY, N_ASSETS, N_BYTES>::init(
+            snapshot_a.clone(),
+            0,
+            snapshot_a.clone(),
+            0,
+        );
+
+        let valid_prover = MockProver::run(12, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // the proof fails when the username is not the one of the leaves
+        let mut instances = circuit.instances();
+        instances[0][0] = Fp::from(1000u64);
+
+        let invalid_prover = MockProver::run(12, &circuit, instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // the balances of the user decreased
+        let circuit = BalanceIncreaseCircuit::<LEVELS, L, L_ENTRY, N_ASSETS, N_BYTES>::init(
+            snapshot_a.clone(),
+            0,
+            snapshot_with_balances(-1),
+            0,
+        );
+
+        let invalid_prover = MockProver::run(12, &circuit, circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_exclusion_proof() {
+        // the 16 entries and the two sentinels of the sorted tree need 5 levels
+        const LEVELS: usize = 5;
+
+        let entries = MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv")
+            .unwrap()
+            .entries()
+            .to_vec();
+        let merkle_sum_tree = MerkleSumTree::<N_ASSETS>::from_entries_sorted(entries).unwrap();
+
+        let circuit =
+            MstExclusionCircuit::<LEVELS, L, L_ENTRY, N_ASSETS>::init(&merkle_sum_tree, "AAAAAAAA")
+                .unwrap();
+
+        assert_eq!(circuit.instances()[0][1], merkle_sum_tree.root().hash);
+
+        let valid_prover = MockProver::run(13, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // there is no exclusion witness for an included username
+        let included_username = merkle_sum_tree.entries()[3].username().to_string();
+        assert!(MstExclusionCircuit::<LEVELS, L, L_ENTRY, N_ASSETS>::init(
+            &merkle_sum_tree,
+            &included_username
+        )
+        .is_err());
+
+        // the proof fails when the public username is claimed to be an included one
+        let mut instances = circuit.instances();
+        instances[0][0] = circuit.left_username;
+
+        let invalid_prover = MockProver::run(13, &circuit, instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // a forged proof that encloses an included username between two non adjacent leaves fails
+        let forged_circuit = MstExclusionCircuit::<LEVELS, L, L_ENTRY, N_ASSETS> {
+            username: big_int_to_fp(merkle_sum_tree.entries()[3].username_to_big_int()),
+            left_username: big_int_to_fp(merkle_sum_tree.entries()[2].username_to_big_int()),
+            right_username: big_int_to_fp(merkle_sum_tree.entries()[4].username_to_big_int()),
+            left: MstInclusionCircuit::init(merkle_sum_tree.clone(), 2),
+            right: MstInclusionCircuit::init(merkle_sum_tree.clone(), 4),
+        };
+
+        let invalid_prover =
+            MockProver::run(13, &forged_circuit, forged_circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_sorted_inclusion_proof() {
+        // the 16 entries and the two sentinels of the sorted tree need 5 levels
+        const LEVELS: usize = 5;
+        // the middle nodes also hash the least and the greatest username of each child
+        const L_KEYED: usize = 2 * (3 + N_ASSETS);
+
+        let entries = MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv")
+            .unwrap()
+            .entries()
+            .to_vec();
+        let merkle_sum_tree = KeyedMerkleSumTree::<N_ASSETS>::from_entries(entries).unwrap();
+
+        let username = merkle_sum_tree.entries()[3].username().to_string();
+        let circuit = MstSortedInclusionCircuit::<LEVELS, L_KEYED, L_ENTRY, N_ASSETS>::init(
+            &merkle_sum_tree,
+            &username,
+        )
+        .unwrap();
+
+        assert_eq!(circuit.instances()[0][1], merkle_sum_tree.root().hash);
+
+        let valid_prover = MockProver::run(13, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // there is no inclusion witness for a username that is not included
+        assert!(
+            MstSortedInclusionCircuit::<LEVELS, L_KEYED, L_ENTRY, N_ASSETS>::init(
+                &merkle_sum_tree,
+                "AAAAAAAA"
+            )
+            .is_err()
+        );
+
+        // a supplied direction that contradicts the committed keys of the sibling fails
+        let mut forged_circuit = circuit.clone();
+        forged_circuit.path_indices[0] = !forged_circuit.path_indices[0];
+
+        let invalid_prover =
+            MockProver::run(13, &forged_circuit, forged_circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // forged sibling keys flipping the direction consistently with the comparison don't rebuild the root
+        let mut forged_circuit = circuit.clone();
+        let sibling_on_left = forged_circuit.path_indices[1];
+        forged_circuit.sibling_keys[1] = if sibling_on_left {
+            [pow_of_two::<Fp>(248) - Fp::one(); 2]
+        } else {
+            [Fp::zero(); 2]
+        };
+        forged_circuit.path_indices[1] = !sibling_on_left;
+
+        let invalid_prover =
+            MockProver::run(13, &forged_circuit, forged_circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_minimum_reserve_of_sampled_users() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let minimum_reserve = [Fp::from(10000u64), Fp::from(15000u64)];
+
+        // the sampled users hold [11888, 41163], [67823, 18651] and [34897, 83296]
+        let circuit = MinimumReserveCircuit::<LEVELS, L, N_ASSETS, N_BYTES, 3>::init(
+            merkle_sum_tree.clone(),
+            [0, 1, 4],
+            minimum_reserve,
+        );
+
+        let valid_prover = MockProver::run(12, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // the second sampled user holds [18651, 2087], below the minimum reserve of the second asset
+        let circuit = MinimumReserveCircuit::<LEVELS, L, N_ASSETS, N_BYTES, 3>::init(
+            merkle_sum_tree,
+            [0, 2, 4],
+            minimum_reserve,
+        );
+
+        let invalid_prover = MockProver::run(12, &circuit, circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_batch_inclusion_of_three_users() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let circuit = BatchMstInclusionCircuit::<LEVELS, L, N_ASSETS, 3>::init(
+            merkle_sum_tree.clone(),
+            [0, 7, 15],
+        );
+
+        // each user has a leaf hash and a root hash in the instance column
+        let instances = circuit.instances();
+        assert_eq!(instances[0].len(), circuit.num_instance()[0]);
+        for (proof_index, user_index) in [0, 7, 15].into_iter().enumerate() {
+            assert_eq!(
+                instances[0][2 * proof_index],
+                merkle_sum_tree.leaves()[user_index].hash
+            );
+            assert_eq!(
+                instances[0][2 * proof_index + 1],
+                merkle_sum_tree.root().hash
+            );
+        }
+
+        let valid_prover = MockProver::run(12, &circuit, instances.clone()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // swapping the leaf hashes of two users should invalidate the proof
+        let mut invalid_instances = instances;
+        invalid_instances[0].swap(0, 2);
+
+        let invalid_prover = MockProver::run(12, &circuit, invalid_instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_kzg_balance_commitment_opening() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // commit to the balances of the first asset of the users
+        let balances = merkle_sum_tree
+            .leaves()
+            .iter()
+            .map(|leaf| leaf.balances[0])
+            .collect::<Vec<_>>();
+
+        let params = generate_setup_params(K);
+
+        // the constant default blind should be rejected
+        assert!(KzgBalanceCommitment::commit(&params, &balances, Blind::default()).is_err());
+
+        let commitment =
+            KzgBalanceCommitment::commit(&params, &balances, Blind::new(&mut OsRng)).unwrap();
+
+        let index = 3;
+        let (balance, proof) = commitment.open(&params, index);
+        assert_eq!(balance, balances[index]);
+
+        assert!(verify_balance_opening(
+            &params,
+            commitment.commitment(),
+            commitment.point(index),
+            balance,
+            &proof
+        ));
+
+        // a mismatched balance should fail
+        assert!(!verify_balance_opening(
+            &params,
+            commitment.commitment(),
+            commitment.point(index),
+            balance + Fp::one(),
+            &proof
+        ));
+
+        // the opening shouldn't be valid for another index
+        assert!(!verify_balance_opening(
+            &params,
+            commitment.commitment(),
+            commitment.point(index + 1),
+            balance,
+            &proof
+        ));
+    }
+
+    #[test]
+    fn test_balance_attestation_bound_to_leaf_hash() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let entry = merkle_sum_tree.entries()[0].clone();
+
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // the public leaf hash of the inclusion proof
+        let leaf_hash = circuit.instances()[0][0];
+
+        let sk = AttestationScalar::random(OsRng);
+        let user_key = (Secp256k1::generator() * sk).to_affine();
+
+        // the user signs the balances actually included in the tree
+        let attestation =
+            BalanceAttestation::sign(entry.username().to_string(), entry.balances().clone(), sk)
+                .unwrap();
+        assert!(attestation.verify(leaf_hash, &user_key));
+
+        // the user signs a higher balance than the one included in the tree
+        let mut signed_balances = entry.balances().clone();
+        signed_balances[0] += 1;
+        let mismatched_attestation =
+            BalanceAttestation::sign(entry.username().to_string(), signed_balances, sk).unwrap();
+        assert!(!mismatched_attestation.verify(leaf_hash, &user_key));
+
+        // an attestation of the included balances signed by a key other than the one of the user is rejected
+        let exchange_sk = AttestationScalar::random(OsRng);
+        let forged_attestation = BalanceAttestation::sign(
+            entry.username().to_string(),
+            entry.balances().clone(),
+            exchange_sk,
+        )
+        .unwrap();
+        assert!(!forged_attestation.verify(leaf_hash, &user_key));
+
+        // a tampered signature is rejected
+        let mut tampered_attestation = attestation;
+        tampered_attestation.signature.1 += AttestationScalar::from(1);
+        assert!(!tampered_attestation.verify(leaf_hash, &user_key));
+
+        // an invalid entry can't be signed
+        let mut negative_balances = entry.balances().clone();
+        negative_balances[0] = BigInt::from(-1);
+        assert!(
+            BalanceAttestation::sign(entry.username().to_string(), negative_balances, sk).is_err()
+        );
+    }
+
+    #[test]
+    fn test_signed_proof_bundle() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let params = generate_setup_params(K);
+        let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
+        let pk = keygen_pk(&params, vk.clone(), &circuit).expect("pk generation should not fail");
+
+        let server_sk = AttestationScalar::random(OsRng);
+        let timestamp = 1_700_000_000;
+        let max_age = 60;
+
+        let bundle = SignedProofBundle::generate(&params, &pk, circuit, 42, timestamp, server_sk);
+        let server_key = bundle.public_key;
+
+        let mut seen_nonces = HashSet::new();
+
+        // an expired bundle is rejected and doesn't burn its nonce
+        assert_eq!(
+            bundle.verify(
+                &params,
+                &vk,
+                &server_key,
+                timestamp + max_age + 1,
+                max_age,
+                &mut seen_nonces
+            ),
+            Err(BundleError::Expired {
+                age: max_age + 1,
+                max_age
+            })
+        );
+        assert!(seen_nonces.is_empty());
+
+        // a fresh bundle is accepted and its nonce is recorded
+        assert_eq!(
+            bundle.verify(
+                &params,
+                &vk,
+                &server_key,
+                timestamp + 10,
+                max_age,
+                &mut seen_nonces
+            ),
+            Ok(())
+        );
+        assert!(seen_nonces.contains(&42));
+
+        // the same bundle replayed within the freshness window is rejected
+        assert_eq!(
+            bundle.verify(
+                &params,
+                &vk,
+                &server_key,
+                timestamp + 20,
+                max_age,
+                &mut seen_nonces
+            ),
+            Err(BundleError::ReplayedNonce(42))
+        );
+
+        // changing the nonce of the bundle invalidates its signature
+        let mut renonced_bundle = bundle.clone();
+        renonced_bundle.nonce = 43;
+        assert_eq!(
+            renonced_bundle.verify(
+                &params,
+                &vk,
+                &server_key,
+                timestamp + 20,
+                max_age,
+                &mut seen_nonces
+            ),
+            Err(BundleError::InvalidSignature)
+        );
+
+        // a bundle signed by another key is rejected
+        let forged_bundle = SignedProofBundle::sign(
+            bundle.proof.clone(),
+            bundle.public_inputs.clone(),
+            44,
+            timestamp,
+            AttestationScalar::random(OsRng),
+        );
+        assert_eq!(
+            forged_bundle.verify(
+                &params,
+                &vk,
+                &server_key,
+                timestamp + 20,
+                max_age,
+                &mut seen_nonces
+            ),
+            Err(BundleError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_valid_single_entry_merkle_sum_tree() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let single_entry_tree =
+            MerkleSumTree::<N_ASSETS>::from_entries(merkle_sum_tree.entries()[..1].to_vec())
+                .unwrap();
+
+        // the inclusion path is empty, so the root is the leaf hash itself
+        let circuit = MstInclusionCircuit::<0, L, N_ASSETS>::init(single_entry_tree, 0);
+        assert_eq!(circuit.instances()[0][0], circuit.instances()[0][1]);
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_valid_merkle_sum_tree_with_full_prover() {
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+
+        // we generate a universal trusted setup of our own for testing
+        let params = generate_setup_params(K);
+
+        // we generate the verification key and the proving key
+        // we use an empty circuit just to enphasize that the circuit input are not relevant when generating the keys
+        // Note: the dimension of the circuit used to generate the keys must be the same as the dimension of the circuit used to generate the proof
+        // In this case, the dimension are represented by the heigth of the merkle tree
+        let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
+        let pk = keygen_pk(&params, vk.clone(), &circuit).expect("pk generation should not fail");
+
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // Only now we can instantiate the circuit with the actual inputs
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        // Generate the proof
+        let proof = full_prover(&params, &pk, circuit.clone(), circuit.instances());
+
+        // verify the proof to be true
+        assert!(full_verifier(&params, &vk, proof, circuit.instances()));
+    }
+
+    // The dummy prover skips the real proving, its proofs are accepted by the dummy verifier only
+    #[cfg(feature = "dummy-prover")]
+    #[test]
+    fn test_dummy_prover() {
+        use crate::circuits::dummy;
+
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+
+        let params = generate_setup_params(K);
+        let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
+        let pk = keygen_pk(&params, vk.clone(), &circuit).expect("pk generation should not fail");
+
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let proof = dummy::full_prover(&params, &pk, circuit.clone(), circuit.instances());
+        assert!(proof.starts_with(dummy::DUMMY_PROOF_TAG));
+        assert!(dummy::full_verifier(
+            &params,
+            &vk,
+            proof.clone(),
+            circuit.instances()
+        ));
+
+        // the proof is bound to its public inputs
+        let mut invalid_instances = circuit.instances();
+        invalid_instances[0][0] = Fp::from(1000u64);
+        assert!(!dummy::full_verifier(
+            &params,
+            &vk,
+            proof.clone(),
+            invalid_instances.clone()
+        ));
+
+        // a dummy proof is never accepted by the real verifier
+        assert!(!full_verifier(&params, &vk, proof, circuit.instances()));
+
+        // the proof of an unsatisfied circuit is rejected
+        let proof = dummy::full_prover(&params, &pk, circuit, invalid_instances.clone());
+        assert!(!dummy::full_verifier(
+            &params,
+            &vk,
+            proof,
+            invalid_instances
+        ));
+    }
+
+    #[test]
+    fn test_full_prover_to_writer() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let params = generate_setup_params(K);
+        let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
+        let pk = keygen_pk(&params, vk.clone(), &circuit).expect("pk generation should not fail");
+
+        // stream the proof to an in-memory buffer
+        let buffer = full_prover_to_writer(
+            &params,
+            &pk,
+            circuit.clone(),
+            circuit.instances(),
+            Cursor::new(vec![]),
+            OsRng,
+        )
+        .unwrap();
+
+        // the proof read back from the buffer should be valid
+        let proof = buffer.into_inner();
+        assert!(!proof.is_empty());
+        assert!(full_verifier(&params, &vk, proof, circuit.instances()));
+
+        // a number of public input vectors different from the number of instance columns is returned as an error
+        let mut extra_instances = circuit.instances();
+        extra_instances.push(vec![]);
+        assert!(matches!(
+            full_prover_to_writer(
+                &params,
+                &pk,
+                circuit.clone(),
+                extra_instances,
+                Cursor::new(vec![]),
+                OsRng,
+            ),
+            Err(PlonkError::InvalidInstances)
+        ));
+    }
+
+    #[test]
+    fn test_full_prover_with_seeded_rng() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let params = generate_setup_params(K);
+        let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
+        let pk = keygen_pk(&params, vk.clone(), &circuit).expect("pk generation should not fail");
+
+        // a seeded rng is only meant for testing, it makes the blinding factors reproducible
+        let proof = full_prover_with_rng(
+            &params,
+            &pk,
+            circuit.clone(),
+            circuit.instances(),
+            StdRng::seed_from_u64(42),
+        );
+        assert!(full_verifier(
+            &params,
+            &vk,
+            proof.clone(),
+            circuit.instances()
+        ));
+
+        // the same seed produces the same proof
+        let same_seed_proof = full_prover_with_rng(
+            &params,
+            &pk,
+            circuit.clone(),
+            circuit.instances(),
+            StdRng::seed_from_u64(42),
+        );
+        assert_eq!(proof, same_seed_proof);
+    }
+
+    #[test]
+    fn test_reprove_inclusion_after_update() {
+        let mut merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let mut circuit =
+            MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree.clone(), 0);
+
+        let params = generate_setup_params(K);
+        let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
+        let pk = keygen_pk(&params, vk.clone(), &circuit).expect("pk generation should not fail");
+
+        let proof = full_prover(&params, &pk, circuit.clone(), circuit.instances());
+        let old_instances = circuit.instances();
+
+        // update the balances of another user, which changes the path of the first user
+        let (_, new_root) = merkle_sum_tree
+            .update_leaf(5, [BigInt::from(1000), BigInt::from(2000)])
+            .unwrap();
+
+        // the updated tree matches the one rebuilt from scratch
+        let mut entries = merkle_sum_tree.entries().to_vec();
+        entries[5] = Entry::new(
+            entries[5].username().to_string(),
+            [1000.into(), 2000.into()],
+        )
+        .unwrap();
+        let rebuilt_merkle_sum_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries).unwrap();
+        assert_eq!(new_root.hash, rebuilt_merkle_sum_tree.root().hash);
+        assert_eq!(new_root.balances, rebuilt_merkle_sum_tree.root().balances);
+
+        // the same params and proving key are reused to re-prove the inclusion
+        let new_proof =
+            reprove_inclusion_after_update(&params, &pk, &mut circuit, &merkle_sum_tree, 0)
+                .unwrap();
+
+        assert_eq!(circuit.root_hash, new_root.hash);
+        assert!(full_verifier(&params, &vk, new_proof, circuit.instances()));
+
+        // the old proof doesn't verify against the updated root
+        assert_ne!(old_instances, circuit.instances());
+        assert!(!full_verifier(&params, &vk, proof, circuit.instances()));
+    }
+
+    #[test]
+    fn test_prove_and_verify_inclusion() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let empty_circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+
+        let params = generate_setup_params(K);
+        let vk = keygen_vk(&params, &empty_circuit).expect("vk generation should not fail");
+        let pk =
+            keygen_pk(&params, vk.clone(), &empty_circuit).expect("pk generation should not fail");
+
+        let entry = merkle_sum_tree.entries()[5].clone();
+        let (proof, public_inputs) =
+            prove_inclusion::<LEVELS, L, N_ASSETS>(&params, &pk, &entry, &merkle_sum_tree).unwrap();
+
+        assert_eq!(public_inputs.leaf_hash(), entry.compute_leaf().hash);
+        assert_eq!(public_inputs.root_hash(), merkle_sum_tree.root().hash);
+        assert!(verify_inclusion(&params, &vk, &proof, &public_inputs));
+
+        // the proof doesn't verify against the public inputs of another entry
+        let other_entry = merkle_sum_tree.entries()[6].clone();
+        let other_public_inputs = PublicInputs::new(
+            other_entry.compute_leaf().hash,
+            public_inputs.root_hash(),
+            None,
+            vec![],
+        );
+        assert!(!verify_inclusion(
+            &params,
+            &vk,
+            &proof,
+            &other_public_inputs
+        ));
+
+        // an entry that is not in the tree can't be proven
+        let missing_entry =
+            Entry::new("missing_user".to_string(), entry.balances().clone()).unwrap();
+        assert!(prove_inclusion::<LEVELS, L, N_ASSETS>(
+            &params,
+            &pk,
+            &missing_entry,
+            &merkle_sum_tree
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_prove_user_inclusion() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let empty_circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+
+        let params = generate_setup_params(K);
+        let vk = keygen_vk(&params, &empty_circuit).expect("vk generation should not fail");
+        let pk =
+            keygen_pk(&params, vk.clone(), &empty_circuit).expect("pk generation should not fail");
+
+        // the user only knows their username and balances
+        let entry = merkle_sum_tree.entries()[9].clone();
+        let (proof, public_inputs) = prove_user_inclusion::<LEVELS, L, N_ASSETS>(
+            &params,
+            &pk,
+            &merkle_sum_tree,
+            entry.username(),
+            SecretBalances::from_big_ints(entry.balances()),
+        )
+        .unwrap();
+
+        assert_eq!(public_inputs.leaf_hash(), entry.compute_leaf().hash);
+        assert!(verify_inclusion(&params, &vk, &proof, &public_inputs));
+
+        assert_eq!(
+            prove_user_inclusion::<LEVELS, L, N_ASSETS>(
+                &params,
+                &pk,
+                &merkle_sum_tree,
+                "missing_user",
+                SecretBalances::from_big_ints(entry.balances()),
+            )
+            .unwrap_err(),
+            "The username is not included in the merkle sum tree"
+        );
+
+        let mut wrong_balances = entry.balances().clone();
+        wrong_balances[0] += 1;
+        assert_eq!(
+            prove_user_inclusion::<LEVELS, L, N_ASSETS>(
+                &params,
+                &pk,
+                &merkle_sum_tree,
+                entry.username(),
+                SecretBalances::from_big_ints(&wrong_balances),
+            )
+            .unwrap_err(),
+            "The balances don't match the ones committed in the leaf of the username"
+        );
+    }
+
+    #[test]
+    fn test_zeroize_balances() {
+        let mut balances = SecretBalances::new([Fp::from(5), Fp::from(7)]);
+        assert_eq!(format!("{:?}", balances), "SecretBalances(..)");
+
+        balances.zeroize();
+        assert_eq!(balances.expose(), [Fp::zero(); N_ASSETS]);
+
+        // the balances of the witness of the circuit are zeroized, while the hashes are kept
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let mut circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+        assert_ne!(circuit.leaf_balances, vec![Fp::zero(); N_ASSETS]);
+
+        circuit.zeroize();
+        assert_eq!(circuit.leaf_balances, vec![Fp::zero(); N_ASSETS]);
+        assert_eq!(
+            circuit.path_element_balances,
+            vec![[Fp::zero(); N_ASSETS]; LEVELS]
+        );
+        assert_ne!(circuit.leaf_hash, Fp::zero());
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let empty_circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+
+        let params = generate_setup_params(K);
+        let vk = keygen_vk(&params, &empty_circuit).expect("vk generation should not fail");
+        let pk =
+            keygen_pk(&params, vk.clone(), &empty_circuit).expect("pk generation should not fail");
+
+        let root_hash = merkle_sum_tree.root().hash;
+        let proofs = [2, 9]
+            .iter()
+            .map(|&index| {
+                let entry = merkle_sum_tree.entries()[index].clone();
+                prove_inclusion::<LEVELS, L, N_ASSETS>(&params, &pk, &entry, &merkle_sum_tree)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            verify_batch(&params, &vk, root_hash, &proofs, false),
+            vec![true, true]
+        );
+
+        // a tampered proof is reported at its position
+        let mut tampered_proof = proofs[0].0.clone();
+        tampered_proof[0] ^= 1;
+        let batch = vec![
+            proofs[0].clone(),
+            (tampered_proof, proofs[0].1.clone()),
+            proofs[1].clone(),
+        ];
+
+        assert_eq!(
+            verify_batch(&params, &vk, root_hash, &batch, false),
+            vec![true, false, true]
+        );
+
+        // the verification stops at the first failure
+        assert_eq!(
+            verify_batch(&params, &vk, root_hash, &batch, true),
+            vec![true, false]
+        );
+
+        // the proofs are invalid against another root
+        assert_eq!(
+            verify_batch(&params, &vk, Fp::from(1u64), &proofs, false),
+            vec![false, false]
+        );
+    }
+
+    #[test]
+    fn test_json_serialization() {
+        let public_inputs = PublicInputs::<N_ASSETS>::new(
+            Fp::from(1u64),
+            -Fp::one(),
+            Some([Fp::from(556862u64), Fp::from(556863u64)]),
+            vec![Fp::from(1700000000u64)],
+        );
+
+        // the field elements are encoded as big endian hex strings
+        let json = public_inputs.to_json().unwrap();
+        assert!(json.contains(
+            "\"leaf_hash\":\"0x0000000000000000000000000000000000000000000000000000000000000001\""
+        ));
+        assert_eq!(
+            PublicInputs::<N_ASSETS>::from_json(&json).unwrap(),
+            public_inputs
+        );
+
+        // the root balances are omitted when they are not public
+        let private_root_balances =
+            PublicInputs::<N_ASSETS>::new(Fp::from(1u64), Fp::from(2u64), None, vec![]);
+        let json = private_root_balances.to_json().unwrap();
+        assert!(!json.contains("root_balances"));
+        assert_eq!(
+            PublicInputs::<N_ASSETS>::from_json(&json).unwrap(),
+            private_root_balances
+        );
+
+        let proof = vec![0u8, 1, 2, 253, 254, 255];
+        let json = proof_to_json(&proof, &public_inputs).unwrap();
+        assert_eq!(
+            proof_from_json::<N_ASSETS>(&json).unwrap(),
+            (proof, public_inputs)
+        );
+
+        // a field element that is not less than the modulus is rejected
+        let json = format!(
+            "{{\"leaf_hash\":\"0x{}\",\"root_hash\":\"0x{}\"}}",
+            "ff".repeat(32),
+            "00".repeat(32)
+        );
+        assert!(PublicInputs::<N_ASSETS>::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn test_write_and_read_params() {
+        let params = generate_setup_params(K);
+
+        let mut bytes = vec![];
+        write_params(&params, &mut bytes).unwrap();
+
+        let loaded_params = read_params(&mut Cursor::new(&bytes), K).unwrap();
+        assert_eq!(loaded_params.k(), K);
+
+        let mut loaded_bytes = vec![];
+        write_params(&loaded_params, &mut loaded_bytes).unwrap();
+        assert_eq!(loaded_bytes, bytes);
+
+        // params of the wrong size are rejected at load time
+        let error = read_params(&mut Cursor::new(&bytes), K + 1).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            format!("The params have k = {}, but k = {} is expected", K, K + 1)
+        );
+    }
+
+    #[test]
+    fn test_full_prover_with_self_check() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let params = generate_setup_params(K);
+        let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
+        let pk = keygen_pk(&params, vk.clone(), &circuit).expect("pk generation should not fail");
+
+        // a valid witness passes the self-check
+        let proof = full_prover_with_self_check(&params, &pk, circuit.clone(), circuit.instances())
+            .unwrap();
+        assert!(full_verifier(&params, &vk, proof, circuit.instances()));
+
+        // corrupt the first sibling hash of the path, so that the computed root doesn't match the public one
+        let mut invalid_circuit = circuit.clone();
+        invalid_circuit.path_element_hashes[0] = Fp::from(1000u64);
+
+        assert!(full_prover_with_self_check(
+            &params,
+            &pk,
+            invalid_circuit.clone(),
+            invalid_circuit.instances()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_verify_fresh() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // bind the timestamp at which the proof is generated as extra public input
+        let timestamp = 1_700_000_000u64;
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0)
+            .with_extra_public_inputs(vec![Fp::from(timestamp)]);
+
+        let params = generate_setup_params(K);
+        let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
+        let pk = keygen_pk(&params, vk.clone(), &circuit).expect("pk generation should not fail");
+
+        let artifact = TimestampedProof {
+            proof: full_prover(&params, &pk, circuit.clone(), circuit.instances()),
+            public_inputs: circuit.instances(),
+            timestamp,
+        };
+
+        // a proof generated 10 minutes ago is fresh within a window of 1 hour
+        assert_eq!(
+            verify_fresh(&params, &vk, &artifact, timestamp + 600, 3600, 60),
+            Ok(())
+        );
+
+        // a proof generated 2 hours ago is stale
+        assert_eq!(
+            verify_fresh(&params, &vk, &artifact, timestamp + 7200, 3600, 60),
+            Err(VerifyError::Stale {
+                age: 7200,
+                max_age: 3600
+            })
+        );
+
+        // a proof issued within the clock skew after the current time is accepted, but not beyond it
+        assert_eq!(
+            verify_fresh(&params, &vk, &artifact, timestamp - 30, 3600, 60),
+            Ok(())
+        );
+        assert_eq!(
+            verify_fresh(&params, &vk, &artifact, timestamp - 600, 3600, 60),
+            Err(VerifyError::FutureTimestamp {
+                timestamp,
+                now: timestamp - 600
+            })
+        );
+
+        // the timestamp can't be changed without invalidating the proof
+        let mut forged_artifact = artifact.clone();
+        forged_artifact.timestamp = timestamp + 7000;
+        assert_eq!(
+            verify_fresh(&params, &vk, &forged_artifact, timestamp + 7200, 3600, 60),
+            Err(VerifyError::UnboundTimestamp)
+        );
+
+        forged_artifact.public_inputs[0][TimestampedProof::TIMESTAMP_ROW] =
+            Fp::from(timestamp + 7000);
+        assert_eq!(
+            verify_fresh(&params, &vk, &forged_artifact, timestamp + 7200, 3600, 60),
+            Err(VerifyError::InvalidProof)
+        );
+    }
+
+    #[test]
+    fn test_proof_filename() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let root = merkle_sum_tree.root().hash;
+        let timestamp = 1_700_000_000u64;
+
+        // the name is stable for the same inputs
+        let filename = proof_filename(&root, timestamp);
+        assert_eq!(filename, proof_filename(&root, timestamp));
+        assert!(filename.starts_with("proof_"));
+        assert!(filename.ends_with("_1700000000.bin"));
+
+        // the name is distinct for different roots and different timestamps
+        assert_ne!(filename, proof_filename(&Fp::from(1000u64), timestamp));
+        assert_ne!(filename, proof_filename(&root, timestamp + 1));
+    }
+
+    #[test]
+    fn test_full_prover_reports_peak_memory() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let params = generate_setup_params(K);
+        let empty_circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+        let vk = keygen_vk(&params, &empty_circuit).expect("vk generation should not fail");
+        let pk =
+            keygen_pk(&params, vk.clone(), &empty_circuit).expect("pk generation should not fail");
+
+        let (proof, peak_memory) =
+            full_prover_with_peak_memory(&params, &pk, circuit.clone(), circuit.instances());
+
+        assert!(full_verifier(&params, &vk, proof, circuit.instances()));
+
+        // the memory query is only supported on linux
+        if cfg!(target_os = "linux") {
+            let peak_memory = peak_memory.unwrap();
+            println!(
+                "peak memory for a tree of {} levels: {} bytes",
+                LEVELS, peak_memory
+            );
+            assert!(peak_memory > 0);
+        }
+    }
+
+    #[test]
+    fn test_export_circuit_metadata() {
+        let params = generate_setup_params(K);
+
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+        let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
+
+        let metadata = export_circuit_metadata(&circuit, &vk);
+
+        assert_eq!(metadata.k, K);
+        assert_eq!(metadata.num_instance_columns, 1);
+        assert_eq!(metadata.num_instance, vec![2]);
+
+        let json: serde_json::Value = serde_json::from_str(&metadata.to_json().unwrap()).unwrap();
+
+        assert_eq!(json["name"], "MstInclusionCircuit");
+        assert_eq!(json["instance_slots"][0]["description"], "leaf hash");
+        assert_eq!(json["instance_slots"][0]["row"], 0);
+        assert_eq!(json["instance_slots"][1]["description"], "root hash");
+        assert_eq!(json["instance_slots"][1]["row"], 1);
+        assert_eq!(
+            json["vk_fingerprint"],
+            format!("{:?}", vk.transcript_repr())
+        );
+
+        // the fingerprint changes with the circuit
+        let circuit_2 = MstInclusionCircuit::<{ LEVELS + 1 }, L, N_ASSETS>::init_empty();
+        let vk_2 = keygen_vk(&params, &circuit_2).expect("vk generation should not fail");
+        assert_ne!(
+            export_circuit_metadata(&circuit_2, &vk_2).vk_fingerprint,
+            metadata.vk_fingerprint
+        );
+    }
+
+    #[test]
+    fn test_verify_any_verifying_key() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let params = generate_setup_params(K);
+
+        let empty_circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+        let vk = keygen_vk(&params, &empty_circuit).expect("vk generation should not fail");
+        let pk =
+            keygen_pk(&params, vk.clone(), &empty_circuit).expect("pk generation should not fail");
+
+        // the verifying key of a different circuit, for example the one being rotated out
+        let other_circuit = MstInclusionCircuit::<{ LEVELS + 1 }, L, N_ASSETS>::init_empty();
+        let other_vk = keygen_vk(&params, &other_circuit).expect("vk generation should not fail");
+
+        let proof = full_prover(&params, &pk, circuit.clone(), circuit.instances());
+
+        assert_eq!(
+            verify_any(
+                &params,
+                &[other_vk.clone(), vk],
+                proof.clone(),
+                circuit.instances()
+            ),
+            Some(1)
+        );
+
+        assert_eq!(
+            verify_any(&params, &[other_vk], proof, circuit.instances()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_verifying_key_fingerprint() {
+        let params = generate_setup_params(K);
+
+        let empty_circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+        let vk = keygen_vk(&params, &empty_circuit).expect("vk generation should not fail");
+
+        let fingerprint = verifying_key_fingerprint(&vk);
+        assert_eq!(fingerprint.len(), 32);
+        assert!(fingerprint.chars().all(|c| c.is_ascii_hexdigit()));
+
+        // the fingerprint is stable across key generations of the same circuit
+        let same_vk = keygen_vk(&params, &empty_circuit).expect("vk generation should not fail");
+        assert_eq!(verifying_key_fingerprint(&same_vk), fingerprint);
+
+        // a different circuit version has a different fingerprint
+        let other_circuit = MstInclusionCircuit::<{ LEVELS + 1 }, L, N_ASSETS>::init_empty();
+        let other_vk = keygen_vk(&params, &other_circuit).expect("vk generation should not fail");
+        assert_ne!(verifying_key_fingerprint(&other_vk), fingerprint);
+    }
+
+    // Laying out the public inputs across two instance columns should bind the leaf hash to the first column and the root hash to the second one
+    #[test]
+    fn test_valid_merkle_sum_tree_with_two_instance_columns() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS, 2>::init(merkle_sum_tree, 0);
+
+        let instances = circuit.instances();
+        assert_eq!(circuit.num_instance(), vec![1, 1]);
+        assert_eq!(instances[0], vec![circuit.leaf_hash]);
+        assert_eq!(instances[1], vec![circuit.root_hash]);
+
+        let valid_prover = MockProver::run(K, &circuit, instances.clone()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // swapping the values between the two instance columns should fail the permutation checks
+        let swapped_instances = vec![instances[1].clone(), instances[0].clone()];
+        let invalid_prover = MockProver::run(K, &circuit, swapped_instances.clone()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // the same holds for the full prover
+        let params = generate_setup_params(K);
+        let empty_circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS, 2>::init_empty();
+        let vk = keygen_vk(&params, &empty_circuit).expect("vk generation should not fail");
+        let pk =
+            keygen_pk(&params, vk.clone(), &empty_circuit).expect("pk generation should not fail");
+
+        let proof = full_prover(&params, &pk, circuit.clone(), instances.clone());
+        assert!(full_verifier(&params, &vk, proof.clone(), instances));
+        assert!(!full_verifier(&params, &vk, proof, swapped_instances));
+    }
+
+    // Requires solc to compile the generated verifier
+    #[test]
+    #[ignore]
+    fn test_solvency_evm_verifier() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let assets_sum = [Fp::from(556863u64), Fp::from(556863u64)];
+        let circuit = SolvencyCircuit::<L, N_ASSETS, N_BYTES>::init(merkle_sum_tree, assets_sum);
+
+        let params = generate_setup_params(K);
+        let pk = gen_pk(&params, &circuit.without_witnesses(), None);
+
+        let yul_path = std::env::temp_dir().join("summa_solvency_verifier.yul");
+        let deployment_code = gen_solvency_evm_verifier::<L, N_ASSETS, N_BYTES>(
+            &params,
+            pk.get_vk(),
+            Some(&yul_path),
+        );
+        assert!(!std::fs::read_to_string(&yul_path).unwrap().is_empty());
+
+        // the public inputs are the root hash, the assets sum for each asset and the number of assets
+        let instances = circuit.instances();
+        assert_eq!(instances[0].len(), 2 + N_ASSETS);
+
+        let proof_calldata = gen_evm_proof_shplonk(&params, &pk, circuit, instances.clone());
+
+        // evm_verify panics if the verifier rejects the proof
+        let gas_cost = evm_verify(deployment_code, instances, proof_calldata);
+        assert!(gas_cost > 0);
+
+        std::fs::remove_file(yul_path).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_estimated_verify_gas() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let params = generate_setup_params(K);
+        let pk = gen_pk(&params, &circuit.without_witnesses(), None);
+
+        let num_instances = circuit.num_instance();
+        let instances = circuit.instances();
+
+        let proof_calldata =
+            gen_evm_proof_shplonk(&params, &pk, circuit.clone(), instances.clone());
+
+        let deployment_code = gen_evm_verifier_shplonk::<MstInclusionCircuit<LEVELS, L, N_ASSETS>>(
+            &params,
+            pk.get_vk(),
+            num_instances.clone(),
+            None,
+        );
+        let gas_cost = evm_verify(deployment_code, instances, proof_calldata);
+
+        let estimated_gas = estimated_verify_gas(pk.get_vk(), num_instances[0]);
+
+        // the estimate should be within a factor of 2 of the gas measured on the EVM
+        assert!(
+            (gas_cost / 2..=gas_cost * 2).contains(&estimated_gas),
+            "estimated gas {} is not within the expected range of the actual gas {}",
+            estimated_gas,
+            gas_cost
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_valid_merkle_sum_tree_with_full_recursive_prover() {
+        // params for the aggregation circuit
+        let params_agg = generate_setup_params(21);
+
+        // downsize params for our application specific snark
+        let mut params_app = params_agg.clone();
+        params_app.downsize(K);
+
+        // generate the verification key and the proving key for the application circuit, using an empty circuit
+        let circuit_app = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+
+        let vk_app = keygen_vk(&params_app, &circuit_app).expect("vk generation should not fail");
+        let pk_app =
+            keygen_pk(&params_app, vk_app, &circuit_app).expect("pk generation should not fail");
+
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // Only now we can instantiate the circuit with the actual inputs
+        let circuit_app = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let snark_app = [(); 1]
+            .map(|_| gen_snark_shplonk(&params_app, &pk_app, circuit_app.clone(), None::<&str>));
+
+        const N_SNARK: usize = 1;
+
+        // create aggregation circuit
+        let agg_circuit = WrappedAggregationCircuit::<N_SNARK>::new(&params_agg, snark_app);
+
+        assert_eq!(agg_circuit.instances()[0], circuit_app.instances()[0]);
+
+        let start0 = start_timer!(|| "gen vk & pk");
+        // generate proving key for the aggregation circuit
+        let pk_agg = gen_pk(&params_agg, &agg_circuit.without_witnesses(), None);
+        end_timer!(start0);
+
+        let num_instances = agg_circuit.num_instance();
+        let instances = agg_circuit.instances();
+
+        let proof_calldata =
+            gen_evm_proof_shplonk(&params_agg, &pk_agg, agg_circuit, instances.clone());
+
+        let deployment_code = gen_evm_verifier_shplonk::<WrappedAggregationCircuit<N_SNARK>>(
+            &params_agg,
+            pk_agg.get_vk(),
+            num_instances,
+            None,
+        );
+        let gas_cost = evm_verify(deployment_code, instances, proof_calldata);
+
+        // assert gas_cost to verify the proof on chain to be between 575000 and 590000
+        assert!(
+            (575000..=590000).contains(&gas_cost),
+            "gas_cost is not within the expected range"
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_invalid_merkle_sum_tree_with_full_recursive_prover() {
+        // params for the aggregation circuit
+        let params_agg = generate_setup_params(21);
+
+        // downsize params for our application specific snark
+        let mut params_app = params_agg.clone();
+        params_app.downsize(K);
+
+        // generate the verification key and the proving key for the application circuit, using an empty circuit
+        let circuit_app = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+
+        let vk_app = keygen_vk(&params_app, &circuit_app).expect("vk generation should not fail");
+        let pk_app =
+            keygen_pk(&params_app, vk_app, &circuit_app).expect("pk generation should not fail");
+
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // Only now we can instantiate the circuit with the actual inputs
+        let circuit_app = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let snark_app = [(); 1]
+            .map(|_| gen_snark_shplonk(&params_app, &pk_app, circuit_app.clone(), None::<&str>));
+
+        const N_SNARK: usize = 1;
+
+        // create aggregation circuit
+        let agg_circuit = WrappedAggregationCircuit::<N_SNARK>::new(&params_agg, snark_app);
+
+        let invalid_root_hash = Fp::from(1000u64);
+
+        let mut agg_circuit_invalid_instances = agg_circuit.instances();
+        agg_circuit_invalid_instances[0][1] = invalid_root_hash;
+
+        let invalid_prover =
+            MockProver::run(21, &agg_circuit, agg_circuit_invalid_instances).unwrap();
+
+        assert_eq!(
+            invalid_prover.verify(),
+            Err(vec![
+                VerifyFailure::Permutation {
+                    column: (Any::Instance, 0).into(),
+                    location: FailureLocation::OutsideRegion { row: 1 }
+                },
+                VerifyFailure::Permutation {
+                    column: (Any::advice(), 0).into(),
+                    location: FailureLocation::InRegion {
+                        region: (1, "").into(),
+                        offset: 1
+                    }
+                }
+            ])
+        );
+    }
+
+    // Inclusion proofs of users of the same tree should be aggregated into a single proof, while proofs of different trees shouldn't
+    #[test]
+    #[ignore]
+    fn test_aggregate_inclusion_proofs() {
+        // params for the aggregation circuit
+        let params_agg = generate_setup_params(21);
+
+        // downsize params for our application specific snark
+        let mut params_app = params_agg.clone();
+        params_app.downsize(K);
+
+        let circuit_app = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+
+        let vk_app = keygen_vk(&params_app, &circuit_app).expect("vk generation should not fail");
+        let pk_app =
+            keygen_pk(&params_app, vk_app, &circuit_app).expect("pk generation should not fail");
+
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let snarks = [0, 1]
+            .map(|user_index| {
+                let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(
+                    merkle_sum_tree.clone(),
+                    user_index,
+                );
+                gen_snark_shplonk(&params_app, &pk_app, circuit, None::<&str>)
+            })
+            .to_vec();
+
+        // the proving key of the aggregation circuit is generated once and reused for every aggregation
+        let circuit_agg = InclusionAggregationCircuit::<2>::new(&params_agg, snarks.clone());
+        let pk_agg = gen_pk(&params_agg, &circuit_agg.without_witnesses(), None);
+
+        let aggregated_proof = aggregate::<2>(&params_agg, &pk_agg, snarks.clone()).unwrap();
+        assert_eq!(aggregated_proof.root_hash, merkle_sum_tree.root().hash);
+        assert!(aggregated_proof.verify(&params_agg, pk_agg.get_vk()));
+
+        // the proof of a user of another tree can't be aggregated
+        let other_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16_switched_order.csv")
+                .unwrap();
+        let other_snark = gen_snark_shplonk(
+            &params_app,
+            &pk_app,
+            MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(other_tree, 0),
+            None::<&str>,
+        );
+        let mixed_snarks = vec![snarks[0].clone(), other_snark];
+        assert!(aggregate::<2>(&params_agg, &pk_agg, mixed_snarks.clone()).is_err());
+
+        // an empty set of proofs can't be aggregated, even if no snark is expected
+        assert!(aggregate::<0>(&params_agg, &pk_agg, vec![]).is_err());
+
+        // the common root hash is enforced by the aggregation circuit as well
+        let circuit = InclusionAggregationCircuit::<2>::new(&params_agg, mixed_snarks);
+        let invalid_prover = MockProver::run(21, &circuit, circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    // Passing an invalid root hash in the instance column should fail the permutation check between the computed root hash and the instance column root hash
+    #[test]
+    fn test_invalid_root_hash() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let mut instances = circuit.instances();
+        let invalid_root_hash = Fp::from(1000u64);
+        instances[0][1] = invalid_root_hash;
+
+        let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
+
+        assert_eq!(
+            invalid_prover.verify(),
+            Err(vec![
+                VerifyFailure::Permutation {
+                    column: (Any::advice(), 0).into(),
+                    location: FailureLocation::InRegion {
+                        region: (61, "permute state").into(),
+                        offset: 38
+                    }
+                },
+                VerifyFailure::Permutation {
+                    column: (Any::Instance, 0).into(),
+                    location: FailureLocation::OutsideRegion { row: 1 }
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_invalid_root_hash_as_instance_with_full_prover() {
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty();
+
+        // we generate a universal trusted setup of our own for testing
+        let params = generate_setup_params(K);
+
+        // we generate the verification key and the proving key
+        // we use an empty circuit just to enphasize that the circuit input are not relevant when generating the keys
+        let vk = keygen_vk(&params, &circuit).expect("vk should not fail");
+        let pk = keygen_pk(&params, vk.clone(), &circuit).expect("pk should not fail");
+
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // Only now we can instantiate the circuit with the actual inputs
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let invalid_root_hash = Fp::from(1000u64);
+
+        let mut instances = circuit.instances();
+        instances[0][1] = invalid_root_hash;
+
+        // Generate the proof
+        let proof = full_prover(&params, &pk, circuit, instances.clone());
+
+        // verify the proof to be false
+        assert!(!full_verifier(&params, &vk, proof, instances));
+    }
+
+    // Passing an invalid leaf hash as input for the witness generation should fail:
+    // - the permutation check between the leaf hash and the instance column leaf hash
+    // - the permutation check between the computed root hash and the instance column root hash
+    #[test]
+    fn test_invalid_leaf_hash_as_witness() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let mut circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let instances = circuit.instances();
+
+        // invalidate leaf hash
+        circuit.leaf_hash = Fp::from(1000u64);
+
+        let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
+        assert_eq!(
+            invalid_prover.verify(),
+            Err(vec![
+                VerifyFailure::Permutation {
+                    column: (Any::advice(), 0).into(),
+                    location: FailureLocation::InRegion {
+                        region: (3, "assign nodes hashes per merkle tree level").into(),
+                        offset: 0
+                    }
+                },
+                VerifyFailure::Permutation {
+                    column: (Any::advice(), 0).into(),
+                    location: FailureLocation::InRegion {
+                        region: (61, "permute state").into(),
+                        offset: 38
+                    }
+                },
+                VerifyFailure::Permutation {
+                    column: (Any::Instance, 0).into(),
+                    location: FailureLocation::OutsideRegion { row: 0 }
+                },
+                VerifyFailure::Permutation {
+                    column: (Any::Instance, 0).into(),
+                    location: FailureLocation::OutsideRegion { row: 1 }
+                },
+            ])
+        );
+    }
+
+    // Passing an invalid leaf hash in the instance column should fail the permutation check between the (valid) leaf hash added as part of the witness and the instance column leaf hash
+    #[test]
+    fn test_invalid_leaf_hash_as_instance() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        let mut instances = circuit.instances();
+        let invalid_leaf_hash = Fp::from(1000u64);
+        instances[0][0] = invalid_leaf_hash;
+
+        let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
+
+        assert_eq!(
+            invalid_prover.verify(),
+            Err(vec![
+                VerifyFailure::Permutation {
+                    column: (Any::advice(), 0).into(),
+                    location: FailureLocation::InRegion {
+                        region: (3, "assign nodes hashes per merkle tree level").into(),
+                        offset: 0
+                    }
+                },
+                VerifyFailure::Permutation {
+                    column: (Any::Instance, 0).into(),
+                    location: FailureLocation::OutsideRegion { row: 0 }
+                },
+            ])
+        );
+    }
+
+    // Passing an invalid leaf balance as input for the witness generation.
+    // Invalid leaf balance means: leaf_hash = H(user_id, valid_balance), while the leaf balance passed as witness is invalid.
+    // The following permutation check should fail:
+    // - The root hash that doesn't match the expected one.
+    #[test]
+    fn test_invalid_leaf_balance_as_witness() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let mut circuit =
+            MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree.clone(), 0);
+
+        // We need to extract the valid instances before invalidating the circuit
+        let instances = circuit.instances();
+
+        // invalid leaf balance for the first asset
+        circuit.leaf_balances = vec![Fp::from(1000u64), circuit.leaf_balances[1]];
+
+        let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
+
+        assert_eq!(
+            invalid_prover.verify(),
+            Err(vec![
                 VerifyFailure::Permutation {
                     column: (Any::advice(), 0).into(),
                     location: FailureLocation::InRegion {
-                        region: (3, "assign nodes hashes per merkle tree level").into(),
-                        offset: 0
+                        region: (61, "permute state").into(),
+                        offset: 38
                     }
                 },
+                VerifyFailure::Permutation {
+                    column: (Any::Instance, 0).into(),
+                    location: FailureLocation::OutsideRegion { row: 1 }
+                }
+            ])
+        );
+
+        let mut circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+
+        // We need to extract the valid instances before invalidating the circuit
+        let instances = circuit.instances();
+
+        // invalid leaf balance for the second asset
+        circuit.leaf_balances = vec![circuit.leaf_balances[0], Fp::from(1000u64)];
+
+        let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
+
+        assert_eq!(
+            invalid_prover.verify(),
+            Err(vec![
                 VerifyFailure::Permutation {
                     column: (Any::advice(), 0).into(),
                     location: FailureLocation::InRegion {
@@ -287,10 +2300,6 @@ mod test {
                         offset: 38
                     }
                 },
-                VerifyFailure::Permutation {
-                    column: (Any::Instance, 0).into(),
-                    location: FailureLocation::OutsideRegion { row: 0 }
-                },
                 VerifyFailure::Permutation {
                     column: (Any::Instance, 0).into(),
                     location: FailureLocation::OutsideRegion { row: 1 }
@@ -298,18 +2307,194 @@ mod test {
             ])
         );
     }
+    /// Circuit that assigns an arbitrary swap bit, bypassing the bool type of [MerkleSumTreeChip::assing_swap_bit], to exercise the bool constraint that remains as defense in depth
+    #[derive(Clone)]
+    struct SwapBitCircuit {
+        swap_bit: Fp,
+    }
 
-    // Passing an invalid leaf hash in the instance column should fail the permutation check between the (valid) leaf hash added as part of the witness and the instance column leaf hash
+    impl Circuit<Fp> for SwapBitCircuit {
+        type Config = MerkleSumTreeConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advice = [(); 3].map(|_| meta.advice_column());
+            for column in advice.iter() {
+                meta.enable_equality(*column);
+            }
+            let selectors = [(); 2].map(|_| meta.selector());
+
+            MerkleSumTreeChip::<N_ASSETS>::configure(meta, advice, selectors)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), PlonkError> {
+            let chip = MerkleSumTreeChip::<N_ASSETS>::construct(config);
+
+            let (current_hash, _) = chip.assign_entry_hash_and_balances(
+                layouter.namespace(|| "assign leaf hash and balances"),
+                Fp::from(1),
+                &[Fp::zero(); N_ASSETS],
+            )?;
+
+            let swap_bit = chip
+                .assign_swap_bit_value(layouter.namespace(|| "assign swap bit"), self.swap_bit)?;
+
+            chip.assign_nodes_hashes_per_level(
+                layouter.namespace(|| "assign nodes hashes"),
+                &current_hash,
+                Fp::from(2),
+                swap_bit,
+            )?;
+
+            Ok(())
+        }
+    }
+
+    // The swap bit is a bool at the API boundary, but passing a non binary swap bit to the chip should still fail the bool constraint inside the "assign nodes hashes per merkle tree level" region
     #[test]
-    fn test_invalid_leaf_hash_as_instance() {
+    fn test_non_binary_index() {
+        for swap_bit in [Fp::zero(), Fp::one()] {
+            let valid_prover = MockProver::run(K, &SwapBitCircuit { swap_bit }, vec![]).unwrap();
+            valid_prover.assert_satisfied();
+        }
+
+        let invalid_prover = MockProver::run(
+            K,
+            &SwapBitCircuit {
+                swap_bit: Fp::from(2),
+            },
+            vec![],
+        )
+        .unwrap();
+
+        assert_eq!(
+            invalid_prover.verify(),
+            Err(vec![VerifyFailure::ConstraintNotSatisfied {
+                constraint: ((0, "bool constraint").into(), 0, "").into(),
+                location: FailureLocation::InRegion {
+                    region: (2, "assign nodes hashes per merkle tree level").into(),
+                    offset: 0
+                },
+                cell_values: vec![(((Any::advice(), 2).into(), 0).into(), "0x2".to_string()),]
+            },])
+        );
+    }
+
+    /// Circuit that assigns the first `steps` operations of a level of the merkle path with [MerkleSumTreeChip], namely the entry, the swap bit, the nodes hashes and the nodes balances, then assigns `probe_rows` rows on the first advice column, to measure the rows consumed by each operation
+    #[derive(Clone)]
+    struct ChipRowsCircuit {
+        steps: usize,
+        probe_rows: usize,
+    }
+
+    impl Circuit<Fp> for ChipRowsCircuit {
+        type Config = MerkleSumTreeConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            SwapBitCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), PlonkError> {
+            let chip = MerkleSumTreeChip::<N_ASSETS>::construct(config.clone());
+
+            if self.steps >= 1 {
+                let (current_hash, current_balances) = chip.assign_entry_hash_and_balances(
+                    layouter.namespace(|| "assign leaf hash and balances"),
+                    Fp::from(1),
+                    &[Fp::zero(); N_ASSETS],
+                )?;
+
+                if self.steps >= 2 {
+                    let swap_bit =
+                        chip.assing_swap_bit(layouter.namespace(|| "assign swap bit"), false)?;
+
+                    if self.steps >= 3 {
+                        chip.assign_nodes_hashes_per_level(
+                            layouter.namespace(|| "assign nodes hashes"),
+                            &current_hash,
+                            Fp::from(2),
+                            swap_bit.clone(),
+                        )?;
+                    }
+
+                    if self.steps >= 4 {
+                        for balance in current_balances.iter() {
+                            chip.assign_nodes_balance_per_asset(
+                                layouter.namespace(|| "assign nodes balance"),
+                                balance,
+                                Fp::zero(),
+                                swap_bit.clone(),
+                            )?;
+                        }
+                    }
+                }
+            }
+
+            layouter.assign_region(
+                || "probe",
+                |mut region| {
+                    for row in 0..self.probe_rows {
+                        region.assign_advice(
+                            || "probe",
+                            config.advice[0],
+                            row,
+                            || Value::known(Fp::zero()),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    // The rows consumed by each operation of the chip, measured with the mock prover, match the constants used to choose k
+    #[test]
+    fn test_merkle_sum_tree_chip_rows() {
+        let k = 6;
+        let free_rows: Vec<usize> = (0..=4)
+            .map(|steps| probe_free_rows(k, |probe_rows| ChipRowsCircuit { steps, probe_rows }))
+            .collect();
+
+        type Chip = MerkleSumTreeChip<N_ASSETS>;
+        assert_eq!(free_rows[0] - free_rows[1], Chip::ENTRY_ROWS);
+        assert_eq!(free_rows[1] - free_rows[2], Chip::SWAP_BIT_ROWS);
+        assert_eq!(free_rows[2] - free_rows[3], Chip::NODES_HASHES_ROWS);
+        assert_eq!(
+            free_rows[3] - free_rows[4],
+            N_ASSETS * Chip::NODES_BALANCE_ROWS
+        );
+        assert_eq!(free_rows[1] - free_rows[4], Chip::LEVEL_ROWS);
+    }
+
+    // Swapping the indices should fail the permutation check between the computed root hash and the instance column root hash
+    #[test]
+    fn test_swapping_index() {
         let merkle_sum_tree =
             MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+        let mut circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
 
-        let mut instances = circuit.instances();
-        let invalid_leaf_hash = Fp::from(1000u64);
-        instances[0][0] = invalid_leaf_hash;
+        let instances = circuit.instances();
+
+        // swap indices
+        circuit.path_indices[0] = true;
 
         let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
 
@@ -319,186 +2504,563 @@ mod test {
                 VerifyFailure::Permutation {
                     column: (Any::advice(), 0).into(),
                     location: FailureLocation::InRegion {
-                        region: (3, "assign nodes hashes per merkle tree level").into(),
-                        offset: 0
+                        region: (61, "permute state").into(),
+                        offset: 38
                     }
                 },
                 VerifyFailure::Permutation {
                     column: (Any::Instance, 0).into(),
-                    location: FailureLocation::OutsideRegion { row: 0 }
+                    location: FailureLocation::OutsideRegion { row: 1 }
                 },
             ])
         );
     }
 
-    // Passing an invalid leaf balance as input for the witness generation.
-    // Invalid leaf balance means: leaf_hash = H(user_id, valid_balance), while the leaf balance passed as witness is invalid.
-    // The following permutation check should fail:
-    // - The root hash that doesn't match the expected one.
+    // Passing assets_sum that are less than the liabilities sum should not fail the solvency circuit
     #[test]
-    fn test_invalid_leaf_balance_as_witness() {
+    fn test_valid_liabilities_less_than_assets() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // Make the first asset sum more than liabilities sum (556862)
+        let assets_sum = [Fp::from(556863u64), Fp::from(556863u64)];
+
+        let circuit = SolvencyCircuit::<L, N_ASSETS, N_BYTES>::init(merkle_sum_tree, assets_sum);
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+
+        valid_prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_solvency_instance_rows() {
+        type Solvency = SolvencyCircuit<L, N_ASSETS, N_BYTES>;
+
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let root_hash = merkle_sum_tree.root().hash;
+
+        let assets_sum = [Fp::from(556863u64), Fp::from(556864u64)];
+        let circuit = Solvency::init(merkle_sum_tree, assets_sum);
+
+        // each named row holds the value it stands for
+        let instances = circuit.instances();
+        assert_eq!(instances[0].len(), Solvency::ASSET_COUNT_ROW + 1);
+        assert_eq!(instances[0][Solvency::ROOT_HASH_ROW], root_hash);
+        for (asset, assets_sum) in assets_sum.iter().enumerate() {
+            assert_eq!(instances[0][Solvency::assets_sum_row(asset)], *assets_sum);
+        }
+        assert_eq!(
+            instances[0][Solvency::ASSET_COUNT_ROW],
+            Fp::from(N_ASSETS as u64)
+        );
+
+        let valid_prover = MockProver::run(K, &circuit, instances.clone()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // the less than check reads the assets sum of the second asset from its row, 556862 is not more than the liabilities
+        let mut invalid_instances = instances;
+        invalid_instances[0][Solvency::assets_sum_row(1)] = Fp::from(556862u64);
+        let invalid_prover = MockProver::run(K, &circuit, invalid_instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    // A tree with a single asset is the special case N_ASSETS = 1 of the multi-asset circuits
+    #[test]
+    fn test_single_asset_tree() {
+        const SINGLE_ASSET: usize = 1;
+        const SINGLE_ASSET_L: usize = 2 + (SINGLE_ASSET * 2);
+
+        let merkle_sum_tree =
+            MerkleSumTree::<SINGLE_ASSET>::new("src/merkle_sum_tree/csv/entry_16_single_asset.csv")
+                .unwrap();
+
+        // the liabilities sum is 556862
+        assert_eq!(merkle_sum_tree.root().balances, [Fp::from(556862u64)]);
+
+        let circuit = MstInclusionCircuit::<LEVELS, SINGLE_ASSET_L, SINGLE_ASSET>::init(
+            merkle_sum_tree.clone(),
+            0,
+        );
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        let circuit = SolvencyCircuit::<SINGLE_ASSET_L, SINGLE_ASSET, N_BYTES>::init(
+            merkle_sum_tree.clone(),
+            [Fp::from(556863u64)],
+        );
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // the assets sum must still be greater than the liabilities sum
+        let circuit = SolvencyCircuit::<SINGLE_ASSET_L, SINGLE_ASSET, N_BYTES>::init(
+            merkle_sum_tree,
+            [Fp::from(556862u64)],
+        );
+
+        let invalid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    // Holding exactly the required assets should be just enough to pass the solvency circuit
+    #[test]
+    fn test_required_assets() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let required_assets = merkle_sum_tree.required_assets();
+
+        // the liabilities sum is 556862 for both assets
+        assert_eq!(
+            required_assets,
+            vec![BigUint::from(556863u64), BigUint::from(556863u64)]
+        );
+
+        let assets_sum: [Fp; N_ASSETS] = required_assets
+            .iter()
+            .map(|assets| big_int_to_fp(&BigInt::from(assets.clone())))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+
+        let circuit =
+            SolvencyCircuit::<L, N_ASSETS, N_BYTES>::init(merkle_sum_tree.clone(), assets_sum);
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // holding one unit less of the first asset makes the CEX insolvent
+        let mut insufficient_assets_sum = assets_sum;
+        insufficient_assets_sum[0] -= Fp::one();
+
+        let circuit =
+            SolvencyCircuit::<L, N_ASSETS, N_BYTES>::init(merkle_sum_tree, insufficient_assets_sum);
+
+        let invalid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    // Holding exactly the liabilities is enough to pass the solvency circuit with the inclusive comparison, but not with the strict one
+    #[test]
+    fn test_inclusive_solvency() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // the liabilities sum is 556862 for both assets
+        let assets_sum = merkle_sum_tree.root().balances;
+        assert_eq!(assets_sum, [Fp::from(556862u64); N_ASSETS]);
+        assert_eq!(
+            merkle_sum_tree.required_assets_inclusive(),
+            vec![BigUint::from(556862u64), BigUint::from(556862u64)]
+        );
+
+        let circuit =
+            SolvencyCircuit::<L, N_ASSETS, N_BYTES>::init(merkle_sum_tree.clone(), assets_sum)
+                .with_inclusive_comparison();
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        let circuit =
+            SolvencyCircuit::<L, N_ASSETS, N_BYTES>::init(merkle_sum_tree.clone(), assets_sum);
+
+        let invalid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // holding one unit less of the first asset makes the CEX insolvent with both comparisons
+        let mut insufficient_assets_sum = assets_sum;
+        insufficient_assets_sum[0] -= Fp::one();
+
+        let circuit =
+            SolvencyCircuit::<L, N_ASSETS, N_BYTES>::init(merkle_sum_tree, insufficient_assets_sum)
+                .with_inclusive_comparison();
+
+        let invalid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    // The solvency across all the assets is exposed as a single commitment to the pairs of liabilities and assets sum
+    #[test]
+    fn test_solvency_commitment() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let liabilities = merkle_sum_tree.root().balances;
+        let assets_sum = [Fp::from(556863u64), Fp::from(556864u64)];
+
+        let circuit = SolvencyCommitmentCircuit::<L, N_ASSETS, N_BYTES>::init(
+            merkle_sum_tree.clone(),
+            assets_sum,
+        );
+
+        // the in-circuit commitment matches the native one
+        let commitment = poseidon_solvency_commitment(&liabilities, &assets_sum);
+        assert_eq!(circuit.liabilities(), liabilities);
+        assert_eq!(
+            circuit.instances(),
+            vec![vec![merkle_sum_tree.root().hash, commitment]]
+        );
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // changing the liabilities or the assets sum of any asset changes the commitment
+        for asset in 0..N_ASSETS {
+            let mut other_liabilities = liabilities;
+            other_liabilities[asset] += Fp::one();
+            assert_ne!(
+                poseidon_solvency_commitment(&other_liabilities, &assets_sum),
+                commitment
+            );
+
+            let mut other_assets_sum = assets_sum;
+            other_assets_sum[asset] += Fp::one();
+            assert_ne!(
+                poseidon_solvency_commitment(&liabilities, &other_assets_sum),
+                commitment
+            );
+
+            let mut invalid_instances = circuit.instances();
+            invalid_instances[0][1] = poseidon_solvency_commitment(&liabilities, &other_assets_sum);
+            let invalid_prover = MockProver::run(K, &circuit, invalid_instances).unwrap();
+            assert!(invalid_prover.verify().is_err());
+        }
+
+        // the assets sum must still be greater than the liabilities sum
+        let circuit = SolvencyCommitmentCircuit::<L, N_ASSETS, N_BYTES>::init(
+            merkle_sum_tree,
+            [Fp::from(556863u64), Fp::from(556862u64)],
+        );
+        let invalid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    // The number of assets covered by the solvency proof is exposed as public input and can't be altered
+    #[test]
+    fn test_solvency_lt_bytes() {
+        // the liabilities of the first asset exceed 2^64
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16_bigints.csv").unwrap();
+        let liabilities = merkle_sum_tree.root().balances;
+        assert!(fp_to_big_int(&liabilities[0]) > BigInt::from(1) << 64);
+
+        // the assets exceed the liabilities by more than 2^64
+        let margin = big_int_to_fp(&((BigInt::from(1) << 64) + 1));
+        let assets_sum = [liabilities[0] + margin, liabilities[1] + margin];
+
+        // a 11 bytes comparison fits the difference between the liabilities and the assets
+        let circuit = SolvencyCircuit::<L, N_ASSETS, 11>::init(merkle_sum_tree.clone(), assets_sum);
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // a 8 bytes comparison doesn't
+        let circuit = SolvencyCircuit::<L, N_ASSETS, 8>::init(merkle_sum_tree, assets_sum);
+        let invalid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "('is_lt is 1') is not satisfied")]
+    fn test_assert_mock_satisfied_names_the_failing_gate() {
         let merkle_sum_tree =
             MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        let mut circuit =
-            MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree.clone(), 0);
+        let insolvent_assets_sum = [Fp::from(556861u64), Fp::from(556863u64)];
+        let circuit =
+            SolvencyCircuit::<L, N_ASSETS, N_BYTES>::init(merkle_sum_tree, insolvent_assets_sum)
+                .with_inclusive_comparison();
+        assert_mock_satisfied(&circuit, circuit.instances(), K);
+    }
 
-        // We need to extract the valid instances before invalidating the circuit
-        let instances = circuit.instances();
+    #[test]
+    fn test_inclusive_solvency_full_prover() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        // invalid leaf balance for the first asset
-        circuit.leaf_balances = vec![Fp::from(1000u64), circuit.leaf_balances[1]];
+        // the assets can be equal to the liabilities
+        let assets_sum = merkle_sum_tree.root().balances;
 
-        let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
+        let circuit = SolvencyCircuit::<L, N_ASSETS, N_BYTES>::init(merkle_sum_tree, assets_sum)
+            .with_inclusive_comparison();
 
+        let params = generate_setup_params(K);
+        let vk = keygen_vk(&params, &circuit.without_witnesses()).unwrap();
+        let pk = keygen_pk(&params, vk.clone(), &circuit.without_witnesses()).unwrap();
+
+        // export the verifying key and read it back as a verifier would do
+        let vk_path = std::env::temp_dir().join("summa_inclusive_solvency.vk");
+        write_verifying_key(&vk, &vk_path).unwrap();
+        let exported_vk =
+            read_verifying_key::<SolvencyCircuit<L, N_ASSETS, N_BYTES>>(&vk_path).unwrap();
         assert_eq!(
-            invalid_prover.verify(),
-            Err(vec![
-                VerifyFailure::Permutation {
-                    column: (Any::advice(), 0).into(),
-                    location: FailureLocation::InRegion {
-                        region: (61, "permute state").into(),
-                        offset: 38
-                    }
-                },
-                VerifyFailure::Permutation {
-                    column: (Any::Instance, 0).into(),
-                    location: FailureLocation::OutsideRegion { row: 1 }
-                }
-            ])
+            format!("{:?}", exported_vk.transcript_repr()),
+            format!("{:?}", vk.transcript_repr())
         );
 
-        let mut circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
-
-        // We need to extract the valid instances before invalidating the circuit
         let instances = circuit.instances();
+        let proof = full_prover(&params, &pk, circuit, instances.clone());
+        assert!(full_verifier(
+            &params,
+            &exported_vk,
+            proof.clone(),
+            instances.clone()
+        ));
+
+        // the proof doesn't verify for different assets
+        let mut other_instances = instances;
+        other_instances[0][SolvencyCircuit::<L, N_ASSETS, N_BYTES>::assets_sum_row(0)] += Fp::one();
+        assert!(!full_verifier(
+            &params,
+            &exported_vk,
+            proof,
+            other_instances
+        ));
+
+        std::fs::remove_file(vk_path).unwrap();
+    }
 
-        // invalid leaf balance for the second asset
-        circuit.leaf_balances = vec![circuit.leaf_balances[0], Fp::from(1000u64)];
+    #[test]
+    fn test_solvency_asset_count() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
+        let assets_sum = [Fp::from(556863u64), Fp::from(556863u64)];
 
-        assert_eq!(
-            invalid_prover.verify(),
-            Err(vec![
-                VerifyFailure::Permutation {
-                    column: (Any::advice(), 0).into(),
-                    location: FailureLocation::InRegion {
-                        region: (61, "permute state").into(),
-                        offset: 38
-                    }
-                },
-                VerifyFailure::Permutation {
-                    column: (Any::Instance, 0).into(),
-                    location: FailureLocation::OutsideRegion { row: 1 }
-                },
-            ])
-        );
+        let circuit = SolvencyCircuit::<L, N_ASSETS, N_BYTES>::init(merkle_sum_tree, assets_sum);
+
+        let instances = circuit.instances();
+        assert_eq!(instances[0][1 + N_ASSETS], Fp::from(N_ASSETS as u64));
+
+        let valid_prover = MockProver::run(K, &circuit, instances.clone()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // claim that the proof covers one more asset than the ones actually summed
+        let mut invalid_instances = instances;
+        invalid_instances[0][1 + N_ASSETS] = Fp::from(N_ASSETS as u64 + 1);
+
+        let invalid_prover = MockProver::run(K, &circuit, invalid_instances).unwrap();
+        assert!(invalid_prover
+            .verify()
+            .unwrap_err()
+            .contains(&VerifyFailure::Permutation {
+                column: (Any::Instance, 0).into(),
+                location: FailureLocation::OutsideRegion { row: 1 + N_ASSETS }
+            }));
     }
-    // Passing a non binary index should fail the bool constraint inside "assign nodes hashes per merkle tree level" and "assign nodes balances per asset" region and the permutation check between the computed root hash and the instance column root hash
+
+    // Two disjoint sets of users covering the whole tree should add up to the root balances, while a double counted user should fail the partition circuit
     #[test]
-    fn test_non_binary_index() {
+    fn test_partition_of_users() {
         let merkle_sum_tree =
             MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        let mut circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
+        // split the users in two halves
+        let set_a = (0..8).collect::<Vec<_>>();
+        let set_b = (8..16).collect::<Vec<_>>();
 
-        let instances = circuit.instances();
+        let (sums_a, sums_b) = merkle_sum_tree.partition_sums(&set_a, &set_b).unwrap();
 
-        // invalidate path index inside the circuit
-        circuit.path_indices[0] = Fp::from(2);
+        let circuit = PartitionCircuit::<L, N_ASSETS, N_BYTES>::init(
+            merkle_sum_tree.clone(),
+            [sums_a, sums_b],
+        );
 
-        let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
 
-        assert_eq!(
-            invalid_prover.verify(),
-            Err(vec![
-                VerifyFailure::ConstraintNotSatisfied {
-                    constraint: ((3, "bool constraint").into(), 0, "").into(),
-                    location: FailureLocation::InRegion {
-                        region: (3, "assign nodes hashes per merkle tree level").into(),
-                        offset: 0
-                    },
-                    cell_values: vec![(((Any::advice(), 2).into(), 0).into(), "0x2".to_string()),]
-                },
-                VerifyFailure::ConstraintNotSatisfied {
-                    constraint: ((3, "bool constraint").into(), 0, "").into(),
-                    location: FailureLocation::InRegion {
-                        region: (4, "assign nodes balances per asset").into(),
-                        offset: 0
-                    },
-                    cell_values: vec![(((Any::advice(), 2).into(), 0).into(), "0x2".to_string()),]
-                },
-                VerifyFailure::ConstraintNotSatisfied {
-                    constraint: ((3, "bool constraint").into(), 0, "").into(),
-                    location: FailureLocation::InRegion {
-                        region: (7, "assign nodes balances per asset").into(),
-                        offset: 0
-                    },
-                    cell_values: vec![(((Any::advice(), 2).into(), 0).into(), "0x2".to_string()),]
-                },
-                VerifyFailure::Permutation {
-                    column: (Any::advice(), 0).into(),
-                    location: FailureLocation::InRegion {
-                        region: (61, "permute state").into(),
-                        offset: 38
-                    }
-                },
-                VerifyFailure::Permutation {
-                    column: (Any::Instance, 0).into(),
-                    location: FailureLocation::OutsideRegion { row: 1 }
-                },
-            ])
+        valid_prover.assert_satisfied();
+
+        // count the first user of the second set in the first set too
+        let double_counted_balances = merkle_sum_tree.leaves()[8].balances;
+        let mut invalid_sums_a = sums_a;
+        for asset in 0..N_ASSETS {
+            invalid_sums_a[asset] += double_counted_balances[asset];
+        }
+
+        let invalid_circuit = PartitionCircuit::<L, N_ASSETS, N_BYTES>::init(
+            merkle_sum_tree.clone(),
+            [invalid_sums_a, sums_b],
+        );
+
+        let invalid_prover =
+            MockProver::run(K, &invalid_circuit, invalid_circuit.instances()).unwrap();
+
+        assert!(invalid_prover.verify().is_err());
+
+        // moving the user to the first set still adds up to the root balances, but the sums don't match the halves of the tree
+        let mut invalid_sums_b = sums_b;
+        for asset in 0..N_ASSETS {
+            invalid_sums_b[asset] -= double_counted_balances[asset];
+        }
+
+        let invalid_circuit = PartitionCircuit::<L, N_ASSETS, N_BYTES>::init(
+            merkle_sum_tree,
+            [invalid_sums_a, invalid_sums_b],
         );
+
+        let invalid_prover =
+            MockProver::run(K, &invalid_circuit, invalid_circuit.instances()).unwrap();
+
+        assert!(invalid_prover.verify().is_err());
     }
 
-    // Swapping the indices should fail the permutation check between the computed root hash and the instance column root hash
+    // The reserves ratio exposed as public input should match the one computed off-circuit and can't be altered
     #[test]
-    fn test_swapping_index() {
+    fn test_reserves_ratio() {
+        const SCALE: u64 = 10000;
+
         let merkle_sum_tree =
             MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        let mut circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init(merkle_sum_tree, 0);
-
-        let instances = circuit.instances();
+        // the liabilities sum is 556862 for both assets
+        let assets_sum = [Fp::from(1000000u64), Fp::from(556863u64)];
 
-        // swap indices
-        circuit.path_indices[0] = Fp::from(1);
+        let circuit =
+            ReservesRatioCircuit::<L, N_ASSETS, N_BYTES, SCALE>::init(merkle_sum_tree, assets_sum)
+                .unwrap();
 
-        let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
+        let instances = circuit.instances();
+        assert_eq!(instances[0].len(), circuit.num_instance()[0]);
 
+        // floor(1000000 * 10000 / 556862) and floor(556863 * 10000 / 556862)
+        assert_eq!(instances[0][2 + N_ASSETS], Fp::from(17957u64));
+        assert_eq!(instances[0][3 + N_ASSETS], Fp::from(10000u64));
         assert_eq!(
-            invalid_prover.verify(),
-            Err(vec![
-                VerifyFailure::Permutation {
-                    column: (Any::advice(), 0).into(),
-                    location: FailureLocation::InRegion {
-                        region: (61, "permute state").into(),
-                        offset: 38
-                    }
-                },
-                VerifyFailure::Permutation {
-                    column: (Any::Instance, 0).into(),
-                    location: FailureLocation::OutsideRegion { row: 1 }
-                },
-            ])
+            instances[0][2 + N_ASSETS],
+            reserves_ratio(&assets_sum[0], &Fp::from(556862u64), SCALE).unwrap()
         );
+
+        let valid_prover = MockProver::run(K, &circuit, instances.clone()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // claiming a higher ratio should fail the public input check
+        let mut invalid_instances = instances;
+        invalid_instances[0][2 + N_ASSETS] = Fp::from(17958u64);
+
+        let invalid_prover = MockProver::run(K, &circuit, invalid_instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // witnessing a higher ratio should fail the lower bound check
+        let mut invalid_circuit = circuit.clone();
+        invalid_circuit.ratios[0] = Fp::from(17958u64);
+
+        let invalid_prover =
+            MockProver::run(K, &invalid_circuit, invalid_circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // witnessing a lower ratio should fail the upper bound check
+        let mut invalid_circuit = circuit.clone();
+        invalid_circuit.ratios[0] = Fp::from(17956u64);
+
+        let invalid_prover =
+            MockProver::run(K, &invalid_circuit, invalid_circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // a ratio wrapped around the modulus, such that liabilities * ratio is exactly assets * SCALE in the field, satisfies both inequalities but should fail the range check of the ratio
+        let scaled_assets = assets_sum[0] * Fp::from(SCALE);
+        let wrapped_ratio = scaled_assets * Fp::from(556862u64).invert().unwrap();
+        assert_eq!(wrapped_ratio * Fp::from(556862u64), scaled_assets);
+
+        let mut invalid_circuit = circuit;
+        invalid_circuit.ratios[0] = wrapped_ratio;
+
+        let invalid_prover =
+            MockProver::run(K, &invalid_circuit, invalid_circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // the ratio is undefined for zero liabilities
+        assert!(reserves_ratio(&assets_sum[0], &Fp::zero(), SCALE).is_none());
+
+        let zero_entries = (0..4)
+            .map(|i| Entry::new(format!("user{}", i), [BigInt::from(0), BigInt::from(1)]).unwrap())
+            .collect::<Vec<_>>();
+        let zero_liabilities_tree = MerkleSumTree::<N_ASSETS>::from_entries(zero_entries).unwrap();
+        assert!(ReservesRatioCircuit::<L, N_ASSETS, N_BYTES, SCALE>::init(
+            zero_liabilities_tree,
+            assets_sum
+        )
+        .is_err());
     }
 
-    // Passing assets_sum that are less than the liabilities sum should not fail the solvency circuit
+    // The weighted collateral, namely 0.8 * assets_0 + 0.5 * assets_1, must be greater than the total liabilities
     #[test]
-    fn test_valid_liabilities_less_than_assets() {
+    fn test_weighted_solvency() {
+        const SCALE: u64 = 10000;
+
         let merkle_sum_tree =
             MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
 
-        // Make the first asset sum more than liabilities sum (556862)
-        let assets_sum = [Fp::from(556863u64), Fp::from(556863u64)];
+        let collateral_factors = [Fp::from(8000u64), Fp::from(5000u64)];
 
-        let circuit = SolvencyCircuit::<L, N_ASSETS, N_BYTES>::init(merkle_sum_tree, assets_sum);
+        // total liabilities are 556862 * 2 = 1113724, while the weighted collateral is 0.8 * 1000000 + 0.5 * 1000000 = 1300000
+        let assets_sum = [Fp::from(1000000u64), Fp::from(1000000u64)];
+
+        let circuit = WeightedSolvencyCircuit::<L, N_ASSETS, N_BYTES, SCALE>::init(
+            merkle_sum_tree.clone(),
+            assets_sum,
+            collateral_factors,
+        );
+
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // each asset covers its liabilities, but the weighted collateral 0.8 * 600000 + 0.5 * 600000 = 780000 falls short
+        let assets_sum = [Fp::from(600000u64), Fp::from(600000u64)];
+
+        let circuit = WeightedSolvencyCircuit::<L, N_ASSETS, N_BYTES, SCALE>::init(
+            merkle_sum_tree,
+            assets_sum,
+            collateral_factors,
+        );
+
+        let invalid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // with factors of 1, namely SCALE, the weighted collateral 600000 + 600000 = 1200000 covers the liabilities
+        let circuit = WeightedSolvencyCircuit::<L, N_ASSETS, N_BYTES, SCALE>::init(
+            merkle_sum_tree.clone(),
+            assets_sum,
+            [Fp::from(SCALE), Fp::from(SCALE)],
+        );
 
         let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // inflating the first factor above SCALE would cover the liabilities with a single asset, 2 * 600000 = 1200000, but should fail the factor check
+        let circuit = WeightedSolvencyCircuit::<L, N_ASSETS, N_BYTES, SCALE>::init(
+            merkle_sum_tree.clone(),
+            assets_sum,
+            [Fp::from(2 * SCALE), Fp::zero()],
+        );
+
+        let invalid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // the assets sums are range checked to balance_bits, namely 248 - 15 = 233 bits with N_ASSETS * SCALE = 20000
+        let balance_bits = balance_bits::<N_ASSETS, N_BYTES, SCALE>();
+        assert_eq!(balance_bits, 233);
+
+        // assets sums right below the bound are accepted, the weighted collateral 0.8 * (2^233 - 1) + 0.5 * (2^233 - 1) being less than 2^248
+        let max_assets_sum = pow_of_two::<Fp>(balance_bits) - Fp::one();
+        let circuit = WeightedSolvencyCircuit::<L, N_ASSETS, N_BYTES, SCALE>::init(
+            merkle_sum_tree.clone(),
+            [max_assets_sum, max_assets_sum],
+            collateral_factors,
+        );
 
+        let valid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
         valid_prover.assert_satisfied();
+
+        // an assets sum at the bound is rejected
+        let circuit = WeightedSolvencyCircuit::<L, N_ASSETS, N_BYTES, SCALE>::init(
+            merkle_sum_tree,
+            [pow_of_two::<Fp>(balance_bits), max_assets_sum],
+            collateral_factors,
+        );
+
+        let invalid_prover = MockProver::run(K, &circuit, circuit.instances()).unwrap();
+        assert!(invalid_prover.verify().is_err());
     }
 
     // Passing assets sum that is less than the liabilities sum should fail the solvency circuit
@@ -698,7 +3260,157 @@ mod test {
         );
     }
 
+    /// Circuit that enforces each witness value of `checks` to be less than (or equal to, if `inclusive`) the public input at the row paired with it, used to exercise the failure path of [SolvencyConfig::enforce_less_than]
+    #[derive(Clone)]
+    struct EnforceLessThanCircuit {
+        checks: Vec<(Fp, usize)>,
+        inclusive: bool,
+    }
+
+    impl Circuit<Fp> for EnforceLessThanCircuit {
+        type Config = SolvencyConfig<L, N_ASSETS, N_BYTES>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            SolvencyConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), PlonkError> {
+            // the lookup table is loaded once and shared by all the checks
+            let lt_chip = LtVerticalChip::<N_BYTES>::construct(config.lt_config);
+            lt_chip.load(&mut layouter)?;
+
+            for (value, index) in self.checks.iter() {
+                let value = layouter.assign_region(
+                    || "assign value",
+                    |mut region| {
+                        region.assign_advice(
+                            || "value",
+                            config.merkle_sum_tree_config.advice[0],
+                            0,
+                            || Value::known(*value),
+                        )
+                    },
+                )?;
+
+                if self.inclusive {
+                    config.enforce_less_than_or_equal(
+                        layouter.namespace(|| "enforce less than or equal"),
+                        &value,
+                        *index,
+                        &lt_chip,
+                    )?;
+                } else {
+                    config.enforce_less_than(
+                        layouter.namespace(|| "enforce less than"),
+                        &value,
+                        *index,
+                        &lt_chip,
+                    )?;
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_enforce_less_than_error() {
+        let instances = vec![vec![Fp::from(1), Fp::from(2)]];
+
+        let circuit = EnforceLessThanCircuit {
+            checks: vec![(Fp::from(1), 1)],
+            inclusive: false,
+        };
+        let valid_prover = MockProver::run(K, &circuit, instances.clone()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // the instance row is out of the usable rows, the assignment error is returned by synthesize instead of being swallowed
+        let circuit = EnforceLessThanCircuit {
+            checks: vec![(Fp::from(1), 1 << K)],
+            inclusive: false,
+        };
+        assert!(matches!(
+            MockProver::run(K, &circuit, instances),
+            Err(PlonkError::NotEnoughRowsAvailable { .. })
+        ));
+    }
+
+    // Several less than checks share the lookup table of the lt chip, which is loaded once per circuit
+    #[test]
+    fn test_enforce_less_than_twice() {
+        // the u8 table takes 256 of the 512 rows, a second copy of it wouldn't fit
+        let k = 9;
+        let instances = vec![vec![Fp::from(1), Fp::from(2)]];
+
+        let circuit = EnforceLessThanCircuit {
+            checks: vec![(Fp::from(1), 1)],
+            inclusive: false,
+        };
+        let valid_prover = MockProver::run(k, &circuit, instances.clone()).unwrap();
+        valid_prover.assert_satisfied();
+
+        let circuit = EnforceLessThanCircuit {
+            checks: vec![(Fp::from(0), 0), (Fp::from(1), 1)],
+            inclusive: false,
+        };
+        let valid_prover = MockProver::run(k, &circuit, instances.clone()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // each check is still enforced
+        let circuit = EnforceLessThanCircuit {
+            checks: vec![(Fp::from(0), 0), (Fp::from(2), 1)],
+            inclusive: false,
+        };
+        let invalid_prover = MockProver::run(k, &circuit, instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    // With the inclusive comparison, the value may be equal to the public input, including at the largest value of the range of the lt chip
+    #[test]
+    fn test_enforce_less_than_or_equal() {
+        let max = pow_of_two::<Fp>(8 * N_BYTES) - Fp::one();
+        let instances = vec![vec![Fp::from(2), max]];
+
+        for (value, index) in [(Fp::from(2), 0), (max, 1)] {
+            let circuit = EnforceLessThanCircuit {
+                checks: vec![(value, index)],
+                inclusive: true,
+            };
+            let valid_prover = MockProver::run(K, &circuit, instances.clone()).unwrap();
+            valid_prover.assert_satisfied();
+
+            // the strict comparison rejects the equality
+            let circuit = EnforceLessThanCircuit {
+                checks: vec![(value, index)],
+                inclusive: false,
+            };
+            let invalid_prover = MockProver::run(K, &circuit, instances.clone()).unwrap();
+            assert!(invalid_prover.verify().is_err());
+        }
+
+        // a value greater than the public input is still rejected
+        let circuit = EnforceLessThanCircuit {
+            checks: vec![(Fp::from(3), 0)],
+            inclusive: true,
+        };
+        let invalid_prover = MockProver::run(K, &circuit, instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
+    use crate::chips::pedersen::pedersen_commitment;
     use crate::circuits::ecdsa::EcdsaVerifyCircuit;
+    use crate::circuits::merkle_sum_tree_commitment::{
+        commitment_limbs, MstInclusionCommitmentCircuit,
+    };
     use ecc::maingate::{big_to_fe, decompose, fe_to_big};
     use halo2_proofs::arithmetic::{CurveAffine, Field};
     use halo2_proofs::halo2curves::{
@@ -956,6 +3668,73 @@ mod test {
         );
     }
 
+    // The commitment circuit should expose the root hash and the commitment to the leaf balances only, and the commitment should open to the real balances
+    #[test]
+    fn test_valid_merkle_sum_tree_with_balance_commitment() {
+        let merkle_sum_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let blinding = <Secp256k1 as CurveAffine>::ScalarExt::random(OsRng);
+
+        let circuit = MstInclusionCommitmentCircuit::<LEVELS, L, L_ENTRY, N_ASSETS>::init(
+            merkle_sum_tree.clone(),
+            0,
+            blinding,
+            OsRng,
+        );
+
+        let instances = circuit.instances();
+        assert_eq!(instances[0].len(), circuit.num_instance()[0]);
+
+        // the instance is exactly the root hash followed by the commitment limbs, neither the leaf hash nor the balances are public
+        let real_balances: [Fp; N_ASSETS] =
+            circuit.inclusion.leaf_balances.clone().try_into().unwrap();
+        let commitment = pedersen_commitment(&real_balances, blinding);
+        let mut expected_instance = vec![merkle_sum_tree.root().hash];
+        expected_instance.extend(commitment_limbs(&commitment));
+        assert_eq!(instances, vec![expected_instance]);
+        assert!(!instances[0].contains(&circuit.inclusion.leaf_hash));
+
+        // the circuit is built deterministically from a seeded rng
+        let seeded_circuit = |seed| {
+            MstInclusionCommitmentCircuit::<LEVELS, L, L_ENTRY, N_ASSETS>::init(
+                merkle_sum_tree.clone(),
+                0,
+                blinding,
+                StdRng::seed_from_u64(seed),
+            )
+        };
+        assert_eq!(
+            seeded_circuit(7).aux_generator,
+            seeded_circuit(7).aux_generator
+        );
+
+        // the committed balances are range checked
+        assert!(circuit.inclusion.leaf_range_check);
+
+        // the commitment doesn't open to a different balance
+        let wrong_balances = [real_balances[0] + Fp::one(), real_balances[1]];
+        assert_ne!(pedersen_commitment(&wrong_balances, blinding), commitment);
+
+        let valid_prover = MockProver::run(19, &circuit, instances.clone()).unwrap();
+        valid_prover.assert_satisfied();
+
+        // proving a commitment to a balance different from the one used in the sum constraints should fail
+        let mut invalid_instances = instances.clone();
+        invalid_instances[0].splice(
+            1..,
+            commitment_limbs(&pedersen_commitment(&wrong_balances, blinding)),
+        );
+        let invalid_prover = MockProver::run(19, &circuit, invalid_instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
+
+        // the private leaf hash must be the hash of the private username and balances
+        let mut forged_circuit = circuit.clone();
+        forged_circuit.username = Fp::one();
+        let invalid_prover = MockProver::run(19, &forged_circuit, instances).unwrap();
+        assert!(invalid_prover.verify().is_err());
+    }
+
     #[cfg(feature = "dev-graph")]
     #[test]
     fn print_mst_inclusion() {
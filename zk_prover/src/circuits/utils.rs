@@ -1,7 +1,10 @@
+use crate::circuits::merkle_sum_tree::{MstInclusionCircuit, PublicInputs};
+use crate::circuits::secret::SecretBalances;
+use crate::merkle_sum_tree::{Entry, MerkleSumTree};
 use ark_std::{end_timer, start_timer};
 use halo2_proofs::{
     halo2curves::bn256::{Bn256, Fr as Fp, G1Affine},
-    plonk::{create_proof, verify_proof, Circuit, ProvingKey, VerifyingKey},
+    plonk::{create_proof, verify_proof, Circuit, Error, ProvingKey, VerifyingKey},
     poly::{
         commitment::{Params, ParamsProver},
         kzg::{
@@ -13,10 +16,15 @@ use halo2_proofs::{
     transcript::{
         Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
     },
+    SerdeFormat,
 };
-use rand::rngs::OsRng;
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
 use snark_verifier_sdk::CircuitExt;
+use std::fmt;
 use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
 
 /// Generate setup parameters for a circuit of size `k` where 2^k is the number of rows in the circuit.
 /// First checks if the trusted setup parameters are already generated and saved in the `ptau` folder with the name `hermez-raw-k`, if so, it loads them.
@@ -44,6 +52,7 @@ pub fn generate_setup_params(k: u32) -> ParamsKZG<Bn256> {
 }
 
 /// Generates a proof given the public setup, the proving key, the initiated circuit and its public inputs.
+/// `public_inputs` contains one vector of values per instance column of the circuit, so its length must match the number of instance columns declared by the circuit.
 pub fn full_prover<C: Circuit<Fp> + CircuitExt<Fp>>(
     params: &ParamsKZG<Bn256>,
     pk: &ProvingKey<G1Affine>,
@@ -51,26 +60,231 @@ pub fn full_prover<C: Circuit<Fp> + CircuitExt<Fp>>(
     public_inputs: Vec<Vec<Fp>>,
 ) -> Vec<u8> {
     let pf_time = start_timer!(|| "Creating proof");
+    let proof = full_prover_to_writer(params, pk, circuit, public_inputs, vec![], OsRng)
+        .expect("prover should not fail");
+    end_timer!(pf_time);
+
+    proof
+}
+
+/// Same as [full_prover], but also returns the peak resident memory of the process in bytes while proving, see [peak_memory_bytes].
+/// The high water mark is reset with [reset_peak_memory] right before `create_proof`, so that it doesn't account for the memory used before proving, for example to generate the proving key.
+/// The high water mark is process-wide: the memory allocated by other threads while proving is counted as well, and if the reset is not supported the peak also covers the lifetime of the process before proving.
+/// Returns `None` as peak memory if the platform doesn't support the query.
+pub fn full_prover_with_peak_memory<C: Circuit<Fp> + CircuitExt<Fp>>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    public_inputs: Vec<Vec<Fp>>,
+) -> (Vec<u8>, Option<u64>) {
+    reset_peak_memory();
+
+    let proof = full_prover(params, pk, circuit, public_inputs);
+
+    (proof, peak_memory_bytes())
+}
+
+/// Same as [full_prover], but the blinding factors of the proof are sampled from `rng` instead of [OsRng], so that operators can inject a vetted CSPRNG.
+/// `rng` must be cryptographically secure for real use, as predictable blinding factors leak information about the witness. A seeded RNG is only meant for testing.
+pub fn full_prover_with_rng<C: Circuit<Fp> + CircuitExt<Fp>>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    public_inputs: Vec<Vec<Fp>>,
+    rng: impl RngCore + CryptoRng,
+) -> Vec<u8> {
+    full_prover_to_writer(params, pk, circuit, public_inputs, vec![], rng)
+        .expect("prover should not fail")
+}
+
+/// Same as [full_prover_with_rng], but the transcript of the proof is written to `writer` as it is generated instead of being buffered in memory, for example to stream many proofs to disk or to the network.
+/// Returns the writer once the proof is complete. The bytes written are the same returned by [full_prover], so they can be verified with [full_verifier].
+/// Returns [Error::InvalidInstances] if the number of public input vectors doesn't match the number of instance columns of the circuit.
+pub fn full_prover_to_writer<C: Circuit<Fp> + CircuitExt<Fp>, W: Write>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    public_inputs: Vec<Vec<Fp>>,
+    writer: W,
+    rng: impl RngCore + CryptoRng,
+) -> Result<W, Error> {
+    if public_inputs.len() != circuit.num_instance().len() {
+        return Err(Error::InvalidInstances);
+    }
 
     let instance: Vec<&[Fp]> = public_inputs.iter().map(|input| &input[..]).collect();
     let instances = &[&instance[..]];
 
-    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(writer);
     create_proof::<
         KZGCommitmentScheme<Bn256>,
         ProverSHPLONK<'_, Bn256>,
         Challenge255<G1Affine>,
         _,
-        Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+        Blake2bWrite<W, G1Affine, Challenge255<G1Affine>>,
         _,
-    >(params, pk, &[circuit], instances, OsRng, &mut transcript)
-    .expect("prover should not fail");
-    let proof = transcript.finalize();
-    end_timer!(pf_time);
-    proof
+    >(params, pk, &[circuit], instances, rng, &mut transcript)?;
+
+    Ok(transcript.finalize())
+}
+
+/// Re-proves the inclusion of the user at `user_index` after `merkle_sum_tree` has been updated, for example with [MerkleSumTree::update_leaf].
+/// Halo2 doesn't support incremental proving, so the proof is generated from scratch, but everything that doesn't depend on the witness is reused:
+/// * `params` and `pk` are reused as they are, since the layout of the circuit doesn't change with the witness
+/// * the merkle proof of the user is regenerated from the nodes already stored in the tree, without rebuilding it
+/// * only the witness of `circuit`, namely the leaf, the path and the root, is replaced, while its extra public inputs are kept
+pub fn reprove_inclusion_after_update<
+    const LEVELS: usize,
+    const L: usize,
+    const N_ASSETS: usize,
+>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: &mut MstInclusionCircuit<LEVELS, L, N_ASSETS>,
+    merkle_sum_tree: &MerkleSumTree<N_ASSETS>,
+    user_index: usize,
+) -> Result<Vec<u8>, &'static str> {
+    let proof = merkle_sum_tree.generate_proof(user_index)?;
+    circuit.update_witness(proof);
+
+    Ok(full_prover(
+        params,
+        pk,
+        circuit.clone(),
+        circuit.instances(),
+    ))
+}
+
+/// Proves the inclusion of `entry` in `merkle_sum_tree` with the [MstInclusionCircuit], without the caller having to build the circuit nor its public inputs.
+/// `pk` must be generated from an [MstInclusionCircuit] with the same type parameters, for example from `MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_empty()`.
+/// Returns the proof together with its public inputs, to be passed to [verify_inclusion]. Returns an error if `entry` is not in the tree or if the depth of the tree is not `LEVELS`.
+pub fn prove_inclusion<const LEVELS: usize, const L: usize, const N_ASSETS: usize>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    entry: &Entry<N_ASSETS>,
+    merkle_sum_tree: &MerkleSumTree<N_ASSETS>,
+) -> Result<(Vec<u8>, PublicInputs<N_ASSETS>), &'static str> {
+    if *merkle_sum_tree.depth() != LEVELS {
+        return Err("The depth of the merkle sum tree doesn't match the levels of the circuit");
+    }
+
+    let user_index = merkle_sum_tree
+        .index_of(entry.username(), entry.balances().clone())
+        .ok_or("The entry is not included in the merkle sum tree")?;
+    let proof = merkle_sum_tree.generate_proof(user_index)?;
+
+    let circuit = MstInclusionCircuit::<LEVELS, L, N_ASSETS>::init_from_proof(proof);
+    let public_inputs = circuit.public_inputs();
+
+    Ok((
+        full_prover(params, pk, circuit, public_inputs.to_instances(1)),
+        public_inputs,
+    ))
+}
+
+/// Proves the inclusion of the user with the given `username` and `balances` in `merkle_sum_tree`, for example on behalf of a user who only knows their username and balances but not the index of their leaf.
+/// The `balances` are zeroized when they are dropped at the end of the call, see [SecretBalances] for what is covered.
+/// Returns an error if no entry of the tree has `username` or if `balances` don't match the balances committed in its leaf, see [prove_inclusion] for the other errors.
+pub fn prove_user_inclusion<const LEVELS: usize, const L: usize, const N_ASSETS: usize>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    merkle_sum_tree: &MerkleSumTree<N_ASSETS>,
+    username: &str,
+    balances: SecretBalances<N_ASSETS>,
+) -> Result<(Vec<u8>, PublicInputs<N_ASSETS>), &'static str> {
+    let (index, committed_balances) = merkle_sum_tree
+        .contains(username)
+        .ok_or("The username is not included in the merkle sum tree")?;
+
+    if SecretBalances::from_big_ints(committed_balances).expose() != balances.expose() {
+        return Err("The balances don't match the ones committed in the leaf of the username");
+    }
+
+    prove_inclusion::<LEVELS, L, N_ASSETS>(
+        params,
+        pk,
+        &merkle_sum_tree.entries()[index],
+        merkle_sum_tree,
+    )
+}
+
+/// Verifies a proof generated by [prove_inclusion] given the public setup, the verification key and the public inputs of the proof.
+pub fn verify_inclusion<const N_ASSETS: usize>(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    proof: &[u8],
+    public_inputs: &PublicInputs<N_ASSETS>,
+) -> bool {
+    full_verifier(params, vk, proof.to_vec(), public_inputs.to_instances(1))
+}
+
+/// Verifies many proofs generated by [prove_inclusion] against the same published `root_hash`, reusing the same public setup and verification key for all of them.
+/// Returns the outcome of each proof in the same order, so that the caller can identify the users whose proof failed. A proof whose public inputs commit to another root is invalid.
+/// If `stop_on_failure` is set, the proofs following the first invalid one are not verified, namely the returned outcomes end with the first failure.
+pub fn verify_batch<const N_ASSETS: usize>(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    root_hash: Fp,
+    proofs: &[(Vec<u8>, PublicInputs<N_ASSETS>)],
+    stop_on_failure: bool,
+) -> Vec<bool> {
+    let mut outcomes = Vec::with_capacity(proofs.len());
+
+    for (proof, public_inputs) in proofs {
+        let is_valid = public_inputs.root_hash() == root_hash
+            && verify_inclusion(params, vk, proof, public_inputs);
+        outcomes.push(is_valid);
+
+        if stop_on_failure && !is_valid {
+            break;
+        }
+    }
+
+    outcomes
+}
+
+/// Same as [full_prover], but verifies the freshly generated proof against the verifying key of `pk` before returning it.
+/// An invalid proof at this point means that the witness doesn't satisfy the constraints of the circuit, so an error is returned instead of the proof.
+/// The self-check costs a full verification, so it is opt-in.
+pub fn full_prover_with_self_check<C: Circuit<Fp> + CircuitExt<Fp>>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    public_inputs: Vec<Vec<Fp>>,
+) -> Result<Vec<u8>, &'static str> {
+    let proof = full_prover(params, pk, circuit, public_inputs.clone());
+
+    if !full_verifier(params, pk.get_vk(), proof.clone(), public_inputs) {
+        return Err(
+            "The generated proof is invalid, the witness doesn't satisfy the circuit constraints",
+        );
+    }
+
+    Ok(proof)
+}
+
+/// Returns the peak resident memory (high water mark) of the current process in bytes, namely `VmHWM`.
+/// It is read from `/proc/self/status`, so it is only available on Linux. Returns `None` on other platforms.
+/// The high water mark covers the whole process, namely all its threads, since the last [reset_peak_memory] or since the process started.
+pub fn peak_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+
+    status
+        .lines()
+        .find(|line| line.starts_with("VmHWM:"))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+}
+
+/// Resets the peak resident memory of the current process, see [peak_memory_bytes], to its current resident memory by writing `5` to `/proc/self/clear_refs`.
+/// It is only available on Linux 4.0 or later. Returns `false` if the reset is not supported, in which case the high water mark keeps covering the whole lifetime of the process.
+pub fn reset_peak_memory() -> bool {
+    std::fs::write("/proc/self/clear_refs", "5").is_ok()
 }
 
 /// Verifies a proof given the public setup, the verification key, the proof and the public inputs of the circuit.
+/// `public_inputs` contains one vector of values per instance column of the circuit.
 pub fn full_verifier(
     params: &ParamsKZG<Bn256>,
     vk: &VerifyingKey<G1Affine>,
@@ -93,3 +307,293 @@ pub fn full_verifier(
     >(verifier_params, vk, strategy, instances, &mut transcript)
     .is_ok()
 }
+
+/// Writes the verifying key `vk` to the file stored at `path`, so that it can be shipped to the verifiers of the circuit independently of the proving key
+pub fn write_verifying_key(vk: &VerifyingKey<G1Affine>, path: &Path) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    vk.write(&mut writer, SerdeFormat::RawBytes)?;
+    writer.flush()
+}
+
+/// Reads a verifying key of the circuit `C` from the file stored at `path`, as written by [write_verifying_key]
+pub fn read_verifying_key<C: Circuit<Fp>>(path: &Path) -> std::io::Result<VerifyingKey<G1Affine>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    VerifyingKey::read::<_, C>(&mut reader, SerdeFormat::RawBytes)
+}
+
+// number of bytes of the SHA-256 digest of a verifying key kept in its fingerprint
+const VK_FINGERPRINT_BYTES: usize = 16;
+
+/// Returns the fingerprint of the verifying key `vk`, namely the hex encoding of the first bytes of the SHA-256 hash of `vk` serialized as by [write_verifying_key].
+/// The fingerprint is stable for the same circuit and setup params, so it can be published to let the users confirm that a proof was produced for the expected circuit version before trusting it.
+pub fn verifying_key_fingerprint(vk: &VerifyingKey<G1Affine>) -> String {
+    let mut bytes = vec![];
+    vk.write(&mut bytes, SerdeFormat::RawBytes)
+        .expect("writing to a vector should not fail");
+
+    Sha256::digest(&bytes)
+        .iter()
+        .take(VK_FINGERPRINT_BYTES)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Writes `params` to `writer` in the format read by [read_params]. The serialized params already start with their `k`, so no extra header is needed
+pub fn write_params<W: Write>(params: &ParamsKZG<Bn256>, writer: &mut W) -> std::io::Result<()> {
+    params.write(writer)
+}
+
+/// Reads params written by [write_params] from `reader`, checking that their `k` is the expected `k`, namely the degree of the circuit they are meant for.
+/// Returns an [std::io::ErrorKind::InvalidData] error on mismatch instead of returning params that would produce a bad proof.
+pub fn read_params<R: Read>(reader: &mut R, k: u32) -> std::io::Result<ParamsKZG<Bn256>> {
+    let params = ParamsKZG::<Bn256>::read(reader)?;
+    if params.k() != k {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "The params have k = {}, but k = {} is expected",
+                params.k(),
+                k
+            ),
+        ));
+    }
+
+    Ok(params)
+}
+
+// Gas costs of the EVM operations performed by the verifier
+const TX_BASE_GAS: u64 = 21000;
+const CALLDATA_GAS_PER_BYTE: u64 = 16;
+const EC_MUL_GAS: u64 = 6000;
+const EC_ADD_GAS: u64 = 150;
+const PAIRING_BASE_GAS: u64 = 45000;
+const PAIRING_PER_PAIR_GAS: u64 = 34000;
+// rough cost of the field arithmetic performed by the verifier for each evaluation and each public input
+const EVALUATION_GAS: u64 = 500;
+const PUBLIC_INPUT_GAS: u64 = 500;
+
+/// Returns a rough estimate of the gas needed to verify on the EVM a SHPLONK proof generated with `vk` and with `num_instance` public inputs, for example by the verifier generated by [snark_verifier_sdk::evm::gen_evm_verifier_shplonk].
+/// The estimate accounts for the size of the multi-scalar multiplication over the commitments of the proof and of the verifying key, the final pairing check, the evaluations and the public inputs sent as calldata.
+/// It doesn't account for the gas of the deployment of the verifier.
+pub fn estimated_verify_gas(vk: &VerifyingKey<G1Affine>, num_instance: usize) -> u64 {
+    let cs = vk.cs();
+
+    let permutation_columns = cs.permutation().get_columns().len();
+    let lookups = cs.lookups().len();
+    // the permutation product is split in chunks of `degree - 2` columns
+    let permutation_chunks = (permutation_columns + cs.degree() - 3) / (cs.degree() - 2);
+    let quotient_chunks = cs.degree() - 1;
+
+    // advice, lookup (permuted input, permuted table, product), permutation product, vanishing (random and quotient) and SHPLONK commitments
+    let proof_commitments =
+        cs.num_advice_columns() + 3 * lookups + permutation_chunks + 1 + quotient_chunks + 2;
+    // fixed and permutation commitments
+    let vk_commitments = cs.num_fixed_columns() + permutation_columns;
+
+    let evaluations = cs.advice_queries().len()
+        + cs.fixed_queries().len()
+        + permutation_columns
+        + 3 * permutation_chunks
+        + 5 * lookups
+        + 1;
+
+    // each commitment is 64 bytes and each evaluation 32 bytes
+    let calldata_bytes = 64 * proof_commitments + 32 * (evaluations + num_instance);
+
+    TX_BASE_GAS
+        + CALLDATA_GAS_PER_BYTE * calldata_bytes as u64
+        + (EC_MUL_GAS + EC_ADD_GAS) * (proof_commitments + vk_commitments) as u64
+        + PAIRING_BASE_GAS
+        + 2 * PAIRING_PER_PAIR_GAS
+        + EVALUATION_GAS * evaluations as u64
+        + PUBLIC_INPUT_GAS * num_instance as u64
+}
+
+/// Verifies a proof against each of the verifying keys in `vks`, for example the old and the new keys during a key rotation window.
+/// Returns the index of the first verifying key under which the proof is valid, or `None` if the proof is not valid under any of them.
+pub fn verify_any(
+    params: &ParamsKZG<Bn256>,
+    vks: &[VerifyingKey<G1Affine>],
+    proof: Vec<u8>,
+    public_inputs: Vec<Vec<Fp>>,
+) -> Option<usize> {
+    vks.iter()
+        .position(|vk| full_verifier(params, vk, proof.clone(), public_inputs.clone()))
+}
+
+/// A proof together with the public inputs it was generated with and the timestamp at which it was generated.
+/// The timestamp is bound to the proof as the first extra public input, see [crate::circuits::merkle_sum_tree::MstInclusionCircuit::with_extra_public_inputs], at row [TimestampedProof::TIMESTAMP_ROW].
+#[derive(Debug, Clone)]
+pub struct TimestampedProof {
+    pub proof: Vec<u8>,
+    pub public_inputs: Vec<Vec<Fp>>,
+    pub timestamp: u64,
+}
+
+impl TimestampedProof {
+    /// Row of the timestamp in the instance column, namely the first extra public input of a [crate::circuits::merkle_sum_tree::MstInclusionCircuit] with a single instance column and private root balances, right after the leaf hash and the root hash
+    pub const TIMESTAMP_ROW: usize = 2;
+}
+
+/// Errors that can occur while verifying a [TimestampedProof]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The proof is not valid for the public inputs
+    InvalidProof,
+    /// The timestamp is not the public input of the proof at row [TimestampedProof::TIMESTAMP_ROW]
+    UnboundTimestamp,
+    /// The proof is older than the allowed maximum age
+    Stale { age: u64, max_age: u64 },
+    /// The proof is issued after the current time, beyond the allowed clock skew
+    FutureTimestamp { timestamp: u64, now: u64 },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::InvalidProof => write!(f, "The proof is invalid"),
+            VerifyError::UnboundTimestamp => {
+                write!(f, "The timestamp is not bound to the proof")
+            }
+            VerifyError::Stale { age, max_age } => write!(
+                f,
+                "The proof is {} seconds old but at most {} seconds are allowed",
+                age, max_age
+            ),
+            VerifyError::FutureTimestamp { timestamp, now } => write!(
+                f,
+                "The proof is issued at {} which is after the current time {}",
+                timestamp, now
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Verifies the proof of `artifact` and checks that its timestamp is at most `max_age` seconds older than `now` and at most `max_skew` seconds newer than it, to tolerate the clock drift between the prover and the verifier.
+/// The timestamp must be bound to the proof as its public input at row [TimestampedProof::TIMESTAMP_ROW], otherwise it could be changed without invalidating the proof.
+pub fn verify_fresh(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    artifact: &TimestampedProof,
+    now: u64,
+    max_age: u64,
+    max_skew: u64,
+) -> Result<(), VerifyError> {
+    let bound_timestamp = artifact
+        .public_inputs
+        .first()
+        .and_then(|column| column.get(TimestampedProof::TIMESTAMP_ROW));
+    if bound_timestamp != Some(&Fp::from(artifact.timestamp)) {
+        return Err(VerifyError::UnboundTimestamp);
+    }
+
+    if !full_verifier(
+        params,
+        vk,
+        artifact.proof.clone(),
+        artifact.public_inputs.clone(),
+    ) {
+        return Err(VerifyError::InvalidProof);
+    }
+
+    if artifact.timestamp > now.saturating_add(max_skew) {
+        return Err(VerifyError::FutureTimestamp {
+            timestamp: artifact.timestamp,
+            now,
+        });
+    }
+
+    let age = now.saturating_sub(artifact.timestamp);
+    if age > max_age {
+        return Err(VerifyError::Stale { age, max_age });
+    }
+
+    Ok(())
+}
+
+// number of bytes of the root hash used in the name of a proof file, 128 bits are enough to avoid collisions among proofs of different trees
+const PROOF_FILENAME_ROOT_BYTES: usize = 16;
+
+/// Returns the name of the file in which to store a proof of the tree with the given `root` generated at `timestamp`, namely `proof_<root prefix>_<timestamp>.bin`.
+/// The root prefix is the hex encoding of the most significant bytes of the root hash, so the name is stable for the same inputs and distinct for different trees.
+pub fn proof_filename(root: &Fp, timestamp: u64) -> String {
+    let root_prefix: String = root
+        .to_bytes()
+        .iter()
+        .rev()
+        .take(PROOF_FILENAME_ROOT_BYTES)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    format!("proof_{}_{}.bin", root_prefix, timestamp)
+}
+
+// 2^28 is the largest power of two domain supported by the BN256 scalar field
+const MAX_K: u32 = 28;
+
+/// Returns the minimum `k` such that `circuit` fits in `2^k` rows, namely the rows assigned by all its chips, such as the Poseidon permutations and the levels of the merkle path, the rows of the lookup tables of the range checks and the blinding rows.
+/// The rows depend on the depth of the tree and, for [crate::circuits::nary_merkle_sum_tree::NaryMstInclusionCircuit], on its arity, which are const generics of the circuit, but not on the witness, so `circuit` can be built with `init_empty`.
+/// The layout is synthesized with the [halo2_proofs::dev::MockProver] for increasing values of `k` until it fits, so the result accounts for the floor planner instead of estimating the rows of each chip by hand. The constraints are not checked.
+pub fn min_k<C: Circuit<Fp> + CircuitExt<Fp>>(circuit: &C) -> Result<u32, Error> {
+    for k in 1..=MAX_K {
+        match halo2_proofs::dev::MockProver::run(k, circuit, circuit.instances()) {
+            Ok(_) => return Ok(k),
+            Err(Error::NotEnoughRowsAvailable { .. }) | Err(Error::InstanceTooLarge) => continue,
+            Err(error) => return Err(error),
+        }
+    }
+
+    Err(Error::NotEnoughRowsAvailable { current_k: MAX_K })
+}
+
+/// Runs the [halo2_proofs::dev::MockProver] on `circuit` with its public inputs `instances` and `2^k` rows, and panics if the circuit is not satisfied.
+/// Unlike [halo2_proofs::dev::MockProver::assert_satisfied], the panic message lists every failure on its own line, each naming the gate, the lookup or the column involved, for example `Constraint 0 in gate 2 ('sum constraint') is not satisfied in Region 3 ('merkle prove layer 0') at offset 1`.
+#[cfg(test)]
+pub fn assert_mock_satisfied<C: Circuit<Fp>>(circuit: &C, instances: Vec<Vec<Fp>>, k: u32) {
+    let prover = halo2_proofs::dev::MockProver::run(k, circuit, instances)
+        .unwrap_or_else(|e| panic!("the mock prover failed to synthesize the circuit: {:?}", e));
+
+    if let Err(failures) = prover.verify() {
+        let failures = failures
+            .iter()
+            .map(|failure| format!("- {}", failure))
+            .collect::<Vec<_>>()
+            .join("\n");
+        panic!("the circuit is not satisfied:\n{}", failures);
+    }
+}
+
+/// Returns the number of rows left to the probe region of the circuit returned by `circuit_with_probe` with `2^k` rows, namely the largest `probe_rows` such that `circuit_with_probe(probe_rows)` fits.
+/// The probe region is meant to be assigned after the regions to be measured on a column that they share, so that the rows they consume are the difference with the rows left to the probe without them.
+#[cfg(test)]
+pub fn probe_free_rows<C: Circuit<Fp>>(k: u32, circuit_with_probe: impl Fn(usize) -> C) -> usize {
+    let fits = |probe_rows| match halo2_proofs::dev::MockProver::run(
+        k,
+        &circuit_with_probe(probe_rows),
+        vec![],
+    ) {
+        Ok(_) => true,
+        Err(Error::NotEnoughRowsAvailable { .. }) => false,
+        Err(error) => panic!(
+            "the mock prover failed to synthesize the circuit: {:?}",
+            error
+        ),
+    };
+
+    // binary search of the largest probe that fits, the circuit fits without probe and no probe fits beyond 2^k rows
+    let (mut low, mut high) = (0, 1 << k);
+    assert!(fits(low), "the circuit doesn't fit in 2^{} rows", k);
+
+    while high - low > 1 {
+        let middle = (low + high) / 2;
+        if fits(middle) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    low
+}
@@ -0,0 +1,322 @@
+use crate::chips::less_than::less_than_vertical::LtVerticalChip;
+use crate::circuits::solvency::{SolvencyCircuit, SolvencyConfig};
+use crate::merkle_sum_tree::MerkleSumTree;
+use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error, Selector};
+use halo2_proofs::poly::Rotation;
+use snark_verifier_sdk::CircuitExt;
+
+/// Circuit for verifying solvency in a lending-style setting, where each asset contributes to the collateral at a haircut.
+///
+/// On top of the checks of the [SolvencyCircuit], it enforces `SCALE * sum(liabilities_i) < sum(assets_sum_i * factor_i)`,
+/// where `factor_i` is the collateral factor of the asset `i` expressed as a fixed-point number with scale SCALE, for example 8000 for a factor of 0.8 with a SCALE of 10000.
+/// Each collateral factor is enforced to lie in `[0, SCALE]`, namely a factor of at most 1.
+/// The liabilities and the assets sum of each asset are range checked to [balance_bits] bits, so that both weighted sums, made of N_ASSETS products by at most SCALE, are less than 2^(8 * N_BYTES), the range of the less than chip, and can't wrap around the modulus.
+/// The liabilities of the different assets are expected to be expressed in a common unit.
+///
+/// # Type Parameters
+///
+/// * `L`: The length of the hasher input, namely 2 + (2 * N_ASSETS)
+/// * `N_ASSETS`: The number of assets for which the solvency is verified.
+/// * `N_BYTES`: Range in which the balances should lie
+/// * `SCALE`: The fixed-point scale of the collateral factors
+///
+/// # Fields
+///
+/// * `solvency`: The solvency circuit witness, namely the penultimate nodes, the assets sum and the root hash
+/// * `collateral_factors`: The collateral factor of each asset scaled by SCALE. They are committed as public inputs
+#[derive(Clone)]
+pub struct WeightedSolvencyCircuit<
+    const L: usize,
+    const N_ASSETS: usize,
+    const N_BYTES: usize,
+    const SCALE: u64,
+> {
+    pub solvency: SolvencyCircuit<L, N_ASSETS, N_BYTES>,
+    pub collateral_factors: [Fp; N_ASSETS],
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize, const SCALE: u64> CircuitExt<Fp>
+    for WeightedSolvencyCircuit<L, N_ASSETS, N_BYTES, SCALE>
+{
+    /// Returns the number of public inputs of the circuit. It is the public inputs of the solvency circuit followed by the N_ASSETS collateral factors
+    fn num_instance(&self) -> Vec<usize> {
+        vec![self.solvency.num_instance()[0] + N_ASSETS]
+    }
+
+    /// Returns the values of the public inputs of the circuit. The public inputs of the solvency circuit are followed by the collateral factor of each asset
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        let mut instances = self.solvency.instances();
+        instances[0].extend(self.collateral_factors);
+        instances
+    }
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize, const SCALE: u64>
+    WeightedSolvencyCircuit<L, N_ASSETS, N_BYTES, SCALE>
+{
+    pub fn init_empty() -> Self {
+        balance_bits::<N_ASSETS, N_BYTES, SCALE>();
+
+        Self {
+            solvency: SolvencyCircuit::init_empty(),
+            collateral_factors: [Fp::zero(); N_ASSETS],
+        }
+    }
+
+    /// Initializes the circuit with the merkle sum tree, the assets sum and the collateral factors scaled by SCALE
+    pub fn init(
+        merkle_sum_tree: MerkleSumTree<N_ASSETS>,
+        assets_sum: [Fp; N_ASSETS],
+        collateral_factors: [Fp; N_ASSETS],
+    ) -> Self {
+        Self {
+            solvency: SolvencyCircuit::init(merkle_sum_tree, assets_sum),
+            collateral_factors,
+        }
+    }
+}
+
+/// Returns the number of bits of the range in which the liabilities and the assets sum of each asset must lie, namely `8 * N_BYTES` minus the bit length of `N_ASSETS * SCALE`, so that `N_ASSETS * SCALE * 2^bits < 2^(8 * N_BYTES)`.
+/// Panics if the range of the less than chip leaves no room for the balances
+pub fn balance_bits<const N_ASSETS: usize, const N_BYTES: usize, const SCALE: u64>() -> usize {
+    let weight_bits = (u64::BITS - (N_ASSETS as u64 * SCALE).leading_zeros()) as usize;
+
+    assert!(
+        8 * N_BYTES > weight_bits,
+        "The range of the less than chip must be wider than the bit length of N_ASSETS * SCALE"
+    );
+
+    8 * N_BYTES - weight_bits
+}
+
+/// Configuration for the weighted solvency circuit
+///
+/// # Fields
+///
+/// * `solvency_config`: Configuration for the solvency circuit. Its instance column is used to store the public inputs
+/// * `weighted_sum_selector`: Selector to activate the weighted sum constraint
+///
+/// The weighted sum is computed on the 3 advice columns of the merkle sum tree chip following this layout:
+///
+/// | a         | b          | c       |
+/// | --------- | ---------- | ------- |
+/// | -         | -          | `0`     |
+/// | `value_0` | `weight_0` | `acc_0` |
+/// | ...       | ...        | ...     |
+/// | `value_N` | `weight_N` | `acc_N` |
+///
+/// * `acc_i = acc_(i-1) + value_i * weight_i` (if `weighted_sum_selector` is toggled)
+#[derive(Debug, Clone)]
+pub struct WeightedSolvencyConfig<const L: usize, const N_ASSETS: usize, const N_BYTES: usize> {
+    pub solvency_config: SolvencyConfig<L, N_ASSETS, N_BYTES>,
+    pub weighted_sum_selector: Selector,
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize>
+    WeightedSolvencyConfig<L, N_ASSETS, N_BYTES>
+{
+    /// Computes `sum(values_i * weights_i)` and returns the assigned result
+    pub fn weighted_sum(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        values: &[AssignedCell<Fp, Fp>],
+        weights: &[AssignedCell<Fp, Fp>],
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        assert_eq!(values.len(), weights.len());
+
+        let [col_a, col_b, col_c] = self.solvency_config.merkle_sum_tree_config.advice;
+
+        layouter.assign_region(
+            || "weighted sum",
+            |mut region| {
+                let mut acc =
+                    region.assign_advice_from_constant(|| "initial sum", col_c, 0, Fp::zero())?;
+
+                for (i, (value, weight)) in values.iter().zip(weights.iter()).enumerate() {
+                    let row = i + 1;
+                    self.weighted_sum_selector.enable(&mut region, row)?;
+
+                    let value = value.copy_advice(|| "copy value", &mut region, col_a, row)?;
+                    let weight = weight.copy_advice(|| "copy weight", &mut region, col_b, row)?;
+
+                    let acc_val =
+                        acc.value().copied() + value.value().copied() * weight.value().copied();
+                    acc = region.assign_advice(|| "weighted sum", col_c, row, || acc_val)?;
+                }
+
+                Ok(acc)
+            },
+        )
+    }
+}
+
+impl<const L: usize, const N_ASSETS: usize, const N_BYTES: usize, const SCALE: u64> Circuit<Fp>
+    for WeightedSolvencyCircuit<L, N_ASSETS, N_BYTES, SCALE>
+{
+    type Config = WeightedSolvencyConfig<L, N_ASSETS, N_BYTES>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::init_empty()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        balance_bits::<N_ASSETS, N_BYTES, SCALE>();
+
+        let solvency_config = SolvencyConfig::<L, N_ASSETS, N_BYTES>::configure(meta);
+
+        let weighted_sum_selector = meta.selector();
+        let [col_a, col_b, col_c] = solvency_config.merkle_sum_tree_config.advice;
+
+        meta.create_gate("weighted sum constraint", |meta| {
+            let s = meta.query_selector(weighted_sum_selector);
+            let value = meta.query_advice(col_a, Rotation::cur());
+            let weight = meta.query_advice(col_b, Rotation::cur());
+            let acc = meta.query_advice(col_c, Rotation::cur());
+            let acc_prev = meta.query_advice(col_c, Rotation::prev());
+            vec![s * (acc - acc_prev - value * weight)]
+        });
+
+        WeightedSolvencyConfig {
+            solvency_config,
+            weighted_sum_selector,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let root_balances = self
+            .solvency
+            .assign_solvency(&config.solvency_config, &mut layouter)?;
+
+        // load the assets sum and the collateral factors from the instance column, and the scale as a constant
+        let (assets_sum, collateral_factors, scales) = layouter.assign_region(
+            || "assign collateral factors",
+            |mut region| {
+                let [col_a, col_b, col_c] = config.solvency_config.merkle_sum_tree_config.advice;
+
+                let mut assets_sum = vec![];
+                let mut collateral_factors = vec![];
+                let mut scales = vec![];
+
+                for asset in 0..N_ASSETS {
+                    assets_sum.push(region.assign_advice_from_instance(
+                        || "copy assets sum from instance column",
+                        config.solvency_config.instance,
+                        1 + asset,
+                        col_a,
+                        asset,
+                    )?);
+
+                    collateral_factors.push(region.assign_advice_from_instance(
+                        || "copy collateral factor from instance column",
+                        config.solvency_config.instance,
+                        2 + N_ASSETS + asset,
+                        col_b,
+                        asset,
+                    )?);
+
+                    scales.push(region.assign_advice_from_constant(
+                        || "scale",
+                        col_c,
+                        asset,
+                        Fp::from(SCALE),
+                    )?);
+                }
+
+                Ok((assets_sum, collateral_factors, scales))
+            },
+        )?;
+
+        // the lookup table of the lt chip has already been loaded by the solvency circuit
+        let lt_chip = LtVerticalChip::<N_BYTES>::construct(config.solvency_config.lt_config);
+
+        // each collateral factor must lie in [0, SCALE], so that an asset can't contribute more than its value to the collateral
+        let scale_plus_one = layouter.assign_region(
+            || "assign scale plus one",
+            |mut region| {
+                region.assign_advice_from_constant(
+                    || "scale plus one",
+                    config.solvency_config.merkle_sum_tree_config.advice[1],
+                    0,
+                    Fp::from(SCALE + 1),
+                )
+            },
+        )?;
+
+        for (asset, collateral_factor) in collateral_factors.iter().enumerate() {
+            config.solvency_config.enforce_range(
+                layouter.namespace(|| format!("asset {}: range check collateral factor", asset)),
+                collateral_factor,
+                8 * N_BYTES,
+                &lt_chip,
+            )?;
+
+            config.solvency_config.enforce_cell_less_than(
+                layouter
+                    .namespace(|| format!("asset {}: enforce collateral factor <= SCALE", asset)),
+                collateral_factor,
+                &scale_plus_one,
+                &lt_chip,
+            )?;
+        }
+
+        // the liabilities and the assets sum are bounded, so that the weighted sums are less than 2^(8 * N_BYTES)
+        let balance_bits = balance_bits::<N_ASSETS, N_BYTES, SCALE>();
+
+        for asset in 0..N_ASSETS {
+            config.solvency_config.enforce_range(
+                layouter.namespace(|| format!("asset {}: range check liabilities", asset)),
+                &root_balances[asset],
+                balance_bits,
+                &lt_chip,
+            )?;
+
+            config.solvency_config.enforce_range(
+                layouter.namespace(|| format!("asset {}: range check assets sum", asset)),
+                &assets_sum[asset],
+                balance_bits,
+                &lt_chip,
+            )?;
+        }
+
+        let weighted_collateral = config.weighted_sum(
+            layouter.namespace(|| "weighted collateral"),
+            &assets_sum,
+            &collateral_factors,
+        )?;
+
+        let scaled_liabilities = config.weighted_sum(
+            layouter.namespace(|| "scaled liabilities"),
+            &root_balances,
+            &scales,
+        )?;
+
+        // both sides of the inequality are bounded below 2^(8 * N_BYTES) before being compared
+        for (name, weighted_sum) in [
+            ("weighted collateral", &weighted_collateral),
+            ("scaled liabilities", &scaled_liabilities),
+        ] {
+            config.solvency_config.enforce_range(
+                layouter.namespace(|| format!("bound the {}", name)),
+                weighted_sum,
+                8 * N_BYTES,
+                &lt_chip,
+            )?;
+        }
+
+        config.solvency_config.enforce_cell_less_than(
+            layouter.namespace(|| "enforce liabilities to be less than weighted collateral"),
+            &scaled_liabilities,
+            &weighted_collateral,
+            &lt_chip,
+        )?;
+
+        Ok(())
+    }
+}
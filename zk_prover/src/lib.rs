@@ -7,3 +7,11 @@ pub mod chips;
 pub mod circuits;
 /// Utilities to build the merkle sum tree data structure. No zk proof in here.
 pub mod merkle_sum_tree;
+/// Bindings of the prover and the verifier for Python, compiled only with the `python` feature.
+#[cfg(feature = "python")]
+pub mod python;
+/// Wall-clock timing of the witness assignment, recorded only with the `timing` feature.
+pub mod timing;
+/// Bindings of the native merkle sum tree for the browser, compiled only for `wasm32` targets.
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
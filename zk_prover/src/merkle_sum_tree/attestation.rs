@@ -0,0 +1,113 @@
+use crate::merkle_sum_tree::Entry;
+use ecc::maingate::{big_to_fe, fe_to_big};
+use halo2_proofs::halo2curves::{
+    bn256::Fr as Fp,
+    ff::Field,
+    group::{Curve, Group},
+    secp256k1::Secp256k1Affine as Secp256k1,
+    CurveAffine,
+};
+use num_bigint::BigInt;
+use rand::rngs::OsRng;
+
+/// Scalar field of the curve on which the attestations are signed
+pub type AttestationScalar = <Secp256k1 as CurveAffine>::ScalarExt;
+
+/// A balance attestation signed by a user with an ECDSA key on secp256k1.
+///
+/// The signed message is the leaf hash `H(username, balance[0], ..., balance[N_ASSETS])` of the user entry, namely the same value that is exposed as public input by the [crate::circuits::merkle_sum_tree::MstInclusionCircuit].
+/// Checking the attestation against the public leaf hash of an inclusion proof binds the balances included in the tree to the ones claimed by the user, preventing the exchange from understating them.
+/// The signature check is performed off-circuit, against the public key registered by the user rather than the one carried by the attestation, so that an exchange can't sign an understated balance with a key of its own.
+///
+/// # Fields
+///
+/// * `entry`: The username and the balances claimed by the user
+/// * `public_key`: The public key of the user
+/// * `signature`: The ECDSA signature (r, s) over the leaf hash of `entry`
+#[derive(Clone, Debug)]
+pub struct BalanceAttestation<const N_ASSETS: usize> {
+    pub entry: Entry<N_ASSETS>,
+    pub public_key: Secp256k1,
+    pub signature: (AttestationScalar, AttestationScalar),
+}
+
+impl<const N_ASSETS: usize> BalanceAttestation<N_ASSETS> {
+    /// Signs the leaf hash of the entry made of `username` and `balances` with the secret key `sk`.
+    /// Returns an error if the entry is invalid, see [Entry::new]
+    pub fn sign(
+        username: String,
+        balances: [BigInt; N_ASSETS],
+        sk: AttestationScalar,
+    ) -> Result<Self, &'static str> {
+        let entry = Entry::new(username, balances)?;
+        let (public_key, signature) = ecdsa_sign(entry.compute_leaf().hash, sk);
+
+        Ok(Self {
+            entry,
+            public_key,
+            signature,
+        })
+    }
+
+    /// Returns true if the attestation is signed by `expected_public_key`, namely the key registered by the user, and the signed balances are the ones committed in `leaf_hash`, namely the public leaf hash of an inclusion proof
+    pub fn verify(&self, leaf_hash: Fp, expected_public_key: &Secp256k1) -> bool {
+        if self.public_key != *expected_public_key {
+            return false;
+        }
+
+        let signed_leaf_hash = self.entry.compute_leaf().hash;
+        if signed_leaf_hash != leaf_hash {
+            return false;
+        }
+
+        ecdsa_verify(signed_leaf_hash, &self.public_key, &self.signature)
+    }
+}
+
+/// Signs `msg` with the secret key `sk`, returning the public key of `sk` together with the ECDSA signature (r, s)
+pub(crate) fn ecdsa_sign(
+    msg: Fp,
+    sk: AttestationScalar,
+) -> (Secp256k1, (AttestationScalar, AttestationScalar)) {
+    let msg_hash = msg_to_scalar(msg);
+
+    let g = Secp256k1::generator();
+    let public_key = (g * sk).to_affine();
+
+    let k = AttestationScalar::random(OsRng);
+    let k_inv = k.invert().unwrap();
+    let r = x_mod_n(&(g * k).to_affine());
+    let s = k_inv * (msg_hash + (r * sk));
+
+    (public_key, (r, s))
+}
+
+/// Returns true if `signature` is a valid ECDSA signature of `msg` under `public_key`
+pub(crate) fn ecdsa_verify(
+    msg: Fp,
+    public_key: &Secp256k1,
+    signature: &(AttestationScalar, AttestationScalar),
+) -> bool {
+    let (r, s) = *signature;
+    let s_inv = match Option::<AttestationScalar>::from(s.invert()) {
+        Some(s_inv) => s_inv,
+        None => return false,
+    };
+
+    let msg_hash = msg_to_scalar(msg);
+    let g = Secp256k1::generator();
+    let r_point = ((g * (msg_hash * s_inv)) + (*public_key * (r * s_inv))).to_affine();
+
+    bool::from(!r_point.is_identity()) && x_mod_n(&r_point) == r
+}
+
+/// Maps a message of the BN256 scalar field, such as the leaf hash, to the scalar field of secp256k1. The map is injective as the former is smaller than the latter.
+fn msg_to_scalar(msg: Fp) -> AttestationScalar {
+    big_to_fe(fe_to_big(msg))
+}
+
+/// Returns the x coordinate of the point reduced modulo the order of secp256k1
+fn x_mod_n(point: &Secp256k1) -> AttestationScalar {
+    let x = *point.coordinates().unwrap().x();
+    big_to_fe(fe_to_big(x))
+}
@@ -1,7 +1,25 @@
-use crate::merkle_sum_tree::utils::{big_int_to_fp, big_intify_username, poseidon_entry};
-use crate::merkle_sum_tree::Node;
+use crate::merkle_sum_tree::utils::{
+    big_int_to_fp, big_intify_username, poseidon_entry, poseidon_username, sha256_entry,
+};
+use crate::merkle_sum_tree::{MerkleSumTreeError, Node};
 use halo2_proofs::halo2curves::bn256::Fr as Fp;
-use num_bigint::BigInt;
+use num_bigint::{BigInt, Sign};
+use serde::{Deserialize, Serialize};
+
+/// Hash function used to compute the hash of the leaves of a Merkle Sum Tree from the entries.
+/// The middle nodes are always hashed with Poseidon.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LeafHasher {
+    /// `H(username, balance[0], ..., balance[N_ASSETS - 1])` with Poseidon, see [poseidon_entry]
+    #[default]
+    Poseidon,
+    /// The same inputs hashed with SHA-256 and truncated to 248 bits, see [sha256_entry], so that the leaves can be recomputed by verifiers with standard tooling.
+    /// It is proven in-circuit by [crate::circuits::merkle_sum_tree_sha256_leaf::MstInclusionSha256LeafCircuit]
+    Sha256,
+    /// `H(H(username), balance[0], ..., balance[N_ASSETS - 1])` with Poseidon, namely the balances committed together with the hash of the username, see [poseidon_username].
+    /// It is proven in-circuit by [crate::circuits::merkle_sum_tree_committed_leaf::MstInclusionCommittedLeafCircuit], which recomputes the leaf so that the balances can't be swapped with the ones of another leaf
+    PoseidonUsernameHash,
+}
 
 /// An entry in the Merkle Sum Tree from the database of the CEX.
 /// It contains the username and the balances of the user.
@@ -13,7 +31,12 @@ pub struct Entry<const N_ASSETS: usize> {
 }
 
 impl<const N_ASSETS: usize> Entry<N_ASSETS> {
+    /// Creates an entry. A negative balance is rejected, since it has no field representation and would make the computation of the leaf fail
     pub fn new(username: String, balances: [BigInt; N_ASSETS]) -> Result<Self, &'static str> {
+        if balances.iter().any(|balance| balance.sign() == Sign::Minus) {
+            return Err("The balances of an entry must be non-negative");
+        }
+
         Ok(Entry {
             username_to_big_int: big_intify_username(&username),
             balances,
@@ -21,26 +44,56 @@ impl<const N_ASSETS: usize> Entry<N_ASSETS> {
         })
     }
 
-    pub fn compute_leaf(&self) -> Node<N_ASSETS> {
-        Node {
-            hash: poseidon_entry::<N_ASSETS>(
-                big_int_to_fp(&self.username_to_big_int),
-                self.balances
-                    .iter()
-                    .map(big_int_to_fp)
-                    .collect::<Vec<Fp>>()
-                    .try_into()
-                    .unwrap(),
-            ),
-            //Map the array of balances using big_int_to_fp:
-            balances: self
-                .balances
-                .iter()
-                .map(big_int_to_fp)
-                .collect::<Vec<Fp>>()
-                .try_into()
-                .unwrap(),
+    /// Creates an entry, rejecting the balances that would corrupt the sums of the tree.
+    /// Balances are mapped to field elements, so a negative balance or a balance that doesn't fit in `range_bits` bits, such as `-1` wrapped to `p - 1`, would silently reduce the liabilities.
+    /// Returns [MerkleSumTreeError::NegativeBalance] or [MerkleSumTreeError::BalanceOutOfRange] for the first invalid balance.
+    pub fn new_checked(
+        username: String,
+        balances: [BigInt; N_ASSETS],
+        range_bits: usize,
+    ) -> Result<Self, MerkleSumTreeError> {
+        for (asset, balance) in balances.iter().enumerate() {
+            if balance.sign() == Sign::Minus {
+                return Err(MerkleSumTreeError::NegativeBalance { username, asset });
+            }
+
+            if balance.bits() > range_bits as u64 {
+                return Err(MerkleSumTreeError::BalanceOutOfRange { username, asset });
+            }
         }
+
+        Ok(Entry {
+            username_to_big_int: big_intify_username(&username),
+            balances,
+            username,
+        })
+    }
+
+    pub fn compute_leaf(&self) -> Node<N_ASSETS> {
+        self.compute_leaf_with(LeafHasher::Poseidon)
+    }
+
+    /// Computes the leaf of the entry, hashing the username and the balances with `leaf_hasher`
+    pub fn compute_leaf_with(&self, leaf_hasher: LeafHasher) -> Node<N_ASSETS> {
+        let username = big_int_to_fp(&self.username_to_big_int);
+        //Map the array of balances using big_int_to_fp:
+        let balances: [Fp; N_ASSETS] = self
+            .balances
+            .iter()
+            .map(big_int_to_fp)
+            .collect::<Vec<Fp>>()
+            .try_into()
+            .unwrap();
+
+        let hash = match leaf_hasher {
+            LeafHasher::Poseidon => poseidon_entry::<N_ASSETS>(username, balances),
+            LeafHasher::Sha256 => sha256_entry::<N_ASSETS>(username, balances),
+            LeafHasher::PoseidonUsernameHash => {
+                poseidon_entry::<N_ASSETS>(poseidon_username(username), balances)
+            }
+        };
+
+        Node { hash, balances }
     }
 
     pub fn balances(&self) -> &[BigInt; N_ASSETS] {
@@ -0,0 +1,160 @@
+use num_bigint::BigInt;
+use std::fmt;
+
+/// Errors that can occur while building or handling a Merkle Sum Tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MerkleSumTreeError {
+    /// The balance string is not a valid non-negative decimal number
+    InvalidBalance(String),
+    /// The balance string has more fractional digits than the allowed decimals
+    TooManyDecimals { allowed: u32, found: u32 },
+    /// The entry of the given username has all balances equal to zero
+    ZeroBalance(String),
+    /// The number of entries differs from the expected one
+    CountMismatch { expected: usize, actual: usize },
+    /// The tree was built with a Poseidon spec different from the compiled-in one
+    SpecMismatch { expected: String, found: String },
+    /// The balance of the entry of the given username for the given asset doesn't fit in the allowed number of bits, for example a near-modulus value acting as a negative balance
+    BalanceOutOfRange { username: String, asset: usize },
+    /// The row of the CSV file at the given position, header excluded, can't be parsed
+    MalformedRow { row: usize, reason: String },
+    /// The balance of the entry of the given username for the given asset is negative
+    NegativeBalance { username: String, asset: usize },
+    /// The tree stored in the binary format is corrupted or doesn't match the expected tree type
+    CorruptedTree(String),
+    /// The root balance of the given asset differs from the sum of the balances of all the entries
+    RootSumMismatch {
+        asset: usize,
+        expected: BigInt,
+        found: BigInt,
+    },
+    /// The total liabilities of the given asset, namely the root balance, don't fit in a u128
+    LiabilitiesOutOfRange { asset: usize },
+    /// The hash of the node at the given level and index differs from the hash re-derived from its children, or from its entry for a leaf
+    NodeHashMismatch { level: usize, index: usize },
+    /// The balance of the given asset of the node at the given level and index differs from the sum of the balances of its children, or from the balance of its entry for a leaf
+    NodeBalanceMismatch {
+        level: usize,
+        index: usize,
+        asset: usize,
+    },
+    /// The number of values passed per asset differs from the number of assets of the tree
+    AssetCountMismatch { expected: usize, actual: usize },
+    /// The tree has no entries. An empty tree has no well-defined root, so it can't be built rather than committing to a sentinel root
+    EmptyTree,
+    /// The trees to merge have different depths
+    MergeDepthMismatch { left: usize, right: usize },
+    /// The trees to merge hash their leaves differently
+    MergeLeafHasherMismatch,
+    /// The left tree to merge has padding leaves, namely fewer entries than its capacity
+    MergeLeftTreeNotFull { entries: usize, capacity: usize },
+    /// The depth of the tree exceeds the maximum one
+    DepthOutOfRange { depth: usize, max_depth: usize },
+    /// The given username appears in both trees to merge
+    DuplicateUsername(String),
+}
+
+impl fmt::Display for MerkleSumTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MerkleSumTreeError::InvalidBalance(balance) => {
+                write!(f, "Invalid balance: {:?}", balance)
+            }
+            MerkleSumTreeError::TooManyDecimals { allowed, found } => write!(
+                f,
+                "Balance has {} fractional digits but at most {} are allowed",
+                found, allowed
+            ),
+            MerkleSumTreeError::ZeroBalance(username) => {
+                write!(f, "Entry {} has all balances equal to zero", username)
+            }
+            MerkleSumTreeError::CountMismatch { expected, actual } => write!(
+                f,
+                "Expected {} entries but {} were delivered",
+                expected, actual
+            ),
+            MerkleSumTreeError::SpecMismatch { expected, found } => write!(
+                f,
+                "The tree was built with Poseidon spec {} but the expected one is {}",
+                found, expected
+            ),
+            MerkleSumTreeError::BalanceOutOfRange { username, asset } => write!(
+                f,
+                "Entry {} has an out of range balance for asset {}",
+                username, asset
+            ),
+            MerkleSumTreeError::MalformedRow { row, reason } => {
+                write!(f, "Malformed row {}: {}", row, reason)
+            }
+            MerkleSumTreeError::NegativeBalance { username, asset } => write!(
+                f,
+                "Entry {} has a negative balance for asset {}",
+                username, asset
+            ),
+            MerkleSumTreeError::CorruptedTree(reason) => {
+                write!(f, "Corrupted tree file: {}", reason)
+            }
+            MerkleSumTreeError::RootSumMismatch {
+                asset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "The root balance of asset {} is {} but the sum of the entries is {}",
+                asset, found, expected
+            ),
+            MerkleSumTreeError::LiabilitiesOutOfRange { asset } => write!(
+                f,
+                "The total liabilities of asset {} don't fit in a u128",
+                asset
+            ),
+            MerkleSumTreeError::NodeHashMismatch { level, index } => write!(
+                f,
+                "The hash of node {} at level {} doesn't match its children",
+                index, level
+            ),
+            MerkleSumTreeError::NodeBalanceMismatch {
+                level,
+                index,
+                asset,
+            } => write!(
+                f,
+                "The balance of asset {} of node {} at level {} doesn't match its children",
+                asset, index, level
+            ),
+            MerkleSumTreeError::AssetCountMismatch { expected, actual } => write!(
+                f,
+                "Expected a value for each of the {} assets but {} were delivered",
+                expected, actual
+            ),
+            MerkleSumTreeError::EmptyTree => write!(f, "The tree must contain at least one entry"),
+            MerkleSumTreeError::MergeDepthMismatch { left, right } => write!(
+                f,
+                "The trees to merge must have the same depth, but they have depths {} and {}",
+                left, right
+            ),
+            MerkleSumTreeError::MergeLeafHasherMismatch => {
+                write!(f, "The trees to merge must have the same leaf hasher")
+            }
+            MerkleSumTreeError::MergeLeftTreeNotFull { entries, capacity } => write!(
+                f,
+                "The left tree to merge must be full, but it has {} entries out of {}",
+                entries, capacity
+            ),
+            MerkleSumTreeError::DepthOutOfRange { depth, max_depth } => write!(
+                f,
+                "The tree depth is {} but it must be between 0 and {}",
+                depth, max_depth
+            ),
+            MerkleSumTreeError::DuplicateUsername(username) => {
+                write!(
+                    f,
+                    "The username {} appears in both trees to merge",
+                    username
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MerkleSumTreeError {}
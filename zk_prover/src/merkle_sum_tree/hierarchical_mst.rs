@@ -0,0 +1,107 @@
+use crate::merkle_sum_tree::utils::{build_merkle_tree_from_leaves, create_path, verify_proof};
+use crate::merkle_sum_tree::{MerkleProof, MerkleSumTree, Node};
+
+/// Two-level Merkle Sum Tree Data Structure.
+///
+/// Each leaf of the top-level tree is the root node of a sub-tree, namely its hash and its aggregated balances.
+/// For example, each sub-custodian of a CEX can commit to its own Merkle Sum Tree and the CEX commits to the roots of these trees.
+///
+/// Since a top-level leaf is a node of the same shape as the middle nodes of the sub-trees, an inclusion proof through both levels is a regular [MerkleProof]
+/// whose path is the path inside the sub-tree followed by the path inside the top-level tree.
+///
+/// # Type Parameters
+///
+/// * `N_ASSETS`: The number of assets for each user account
+#[derive(Debug, Clone)]
+pub struct HierarchicalMerkleSumTree<const N_ASSETS: usize> {
+    root: Node<N_ASSETS>,
+    top_nodes: Vec<Vec<Node<N_ASSETS>>>,
+    top_depth: usize,
+    sub_trees: Vec<MerkleSumTree<N_ASSETS>>,
+}
+
+impl<const N_ASSETS: usize> HierarchicalMerkleSumTree<N_ASSETS> {
+    /// Builds the top-level tree committing to the roots of `sub_trees`.
+    /// The number of sub-trees must be a power of two and all the sub-trees must have the same depth, so that every proof has the same length
+    pub fn new(
+        sub_trees: Vec<MerkleSumTree<N_ASSETS>>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if sub_trees.len() < 2 {
+            return Err("The top-level tree must commit to at least 2 sub-trees".into());
+        }
+
+        if !sub_trees.len().is_power_of_two() {
+            return Err("The number of sub-trees must be a power of two".into());
+        }
+
+        let top_depth = sub_trees.len().trailing_zeros() as usize;
+
+        if top_depth > MerkleSumTree::<N_ASSETS>::MAX_DEPTH {
+            return Err("The top-level tree commits to too many sub-trees".into());
+        }
+
+        if sub_trees
+            .iter()
+            .any(|sub_tree| sub_tree.depth() != sub_trees[0].depth())
+        {
+            return Err("All the sub-trees must have the same depth".into());
+        }
+
+        let leaves = sub_trees
+            .iter()
+            .map(|sub_tree| sub_tree.root().clone())
+            .collect::<Vec<_>>();
+
+        let mut top_nodes = vec![];
+        let root = build_merkle_tree_from_leaves(leaves, top_depth, &mut top_nodes)?;
+
+        Ok(HierarchicalMerkleSumTree {
+            root,
+            top_nodes,
+            top_depth,
+            sub_trees,
+        })
+    }
+
+    pub fn root(&self) -> &Node<N_ASSETS> {
+        &self.root
+    }
+
+    pub fn top_depth(&self) -> &usize {
+        &self.top_depth
+    }
+
+    pub fn sub_trees(&self) -> &[MerkleSumTree<N_ASSETS>] {
+        &self.sub_trees
+    }
+
+    /// Generates a MerkleProof for the user with index `user_index` inside the sub-tree with index `sub_tree_index`.
+    /// The proof goes from the user leaf up to the root of the top-level tree.
+    pub fn generate_proof(
+        &self,
+        sub_tree_index: usize,
+        user_index: usize,
+    ) -> Result<MerkleProof<N_ASSETS>, &'static str> {
+        let sub_tree = self
+            .sub_trees
+            .get(sub_tree_index)
+            .ok_or("The sub-tree does not exist in this tree")?;
+
+        let mut proof = sub_tree.generate_proof(user_index)?;
+
+        let (sibling_hashes, sibling_sums, path_indices) =
+            create_path(sub_tree_index, self.top_depth, &self.top_nodes);
+
+        proof.sibling_hashes.extend(sibling_hashes);
+        proof.sibling_sums.extend(sibling_sums);
+        proof.path_indices.extend(path_indices);
+        proof.root_hash = self.root.hash;
+
+        Ok(proof)
+    }
+
+    /// Verifies a MerkleProof spanning both levels
+    pub fn verify_proof(&self, proof: &MerkleProof<N_ASSETS>) -> bool {
+        verify_proof(proof)
+    }
+}
@@ -0,0 +1,228 @@
+use crate::merkle_sum_tree::utils::{
+    big_int_to_fp, big_intify_username, fp_to_big_int, poseidon_keyed_node, poseidon_padding_leaf,
+};
+use crate::merkle_sum_tree::{Entry, MerkleSumTree, MerkleSumTreeError, Node};
+use gadgets::util::pow_of_two;
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+
+/// Inclusion proof of an entry in a [KeyedMerkleSumTree].
+///
+/// # Fields
+///
+/// * `root_hash`: The root hash of the tree
+/// * `entry`: The entry of which the inclusion is proven
+/// * `sibling_hashes`: The hashes of the siblings, ordered from the leaf level to the root level
+/// * `sibling_sums`: The balances of the siblings, ordered from the leaf level to the root level
+/// * `sibling_keys`: The least and the greatest username of the subtree of each sibling, converted to field elements and ordered from the leaf level to the root level
+/// * `path_indices`: The direction bits of the path, ordered from the leaf level to the root level. true indicates that the node of the path is the right child, namely that the sibling is on its left
+#[derive(Clone, Debug)]
+pub struct KeyedMerkleProof<const N_ASSETS: usize> {
+    pub root_hash: Fp,
+    pub entry: Entry<N_ASSETS>,
+    pub sibling_hashes: Vec<Fp>,
+    pub sibling_sums: Vec<[Fp; N_ASSETS]>,
+    pub sibling_keys: Vec<[Fp; 2]>,
+    pub path_indices: Vec<bool>,
+}
+
+impl<const N_ASSETS: usize> KeyedMerkleProof<N_ASSETS> {
+    /// Verifies the proof by recomputing the root from the entry and the siblings.
+    /// As in [crate::circuits::merkle_sum_tree_sorted::MstSortedInclusionCircuit], the greatest username of the left child must be less than the least username of the right child at each level
+    pub fn verify(&self) -> bool {
+        let leaf = self.entry.compute_leaf();
+        let username = big_int_to_fp(self.entry.username_to_big_int());
+
+        let mut node = leaf;
+        let mut keys = [username, username];
+
+        for level in 0..self.path_indices.len() {
+            let sibling = Node {
+                hash: self.sibling_hashes[level],
+                balances: self.sibling_sums[level],
+            };
+            let sibling_keys = self.sibling_keys[level];
+
+            let ((left, left_keys), (right, right_keys)) = if self.path_indices[level] {
+                ((&sibling, sibling_keys), (&node, keys))
+            } else {
+                ((&node, keys), (&sibling, sibling_keys))
+            };
+
+            if fp_to_big_int(&left_keys[1]) >= fp_to_big_int(&right_keys[0]) {
+                return false;
+            }
+
+            let parent = create_keyed_middle_node(left, left_keys, right, right_keys);
+            keys = [left_keys[0], right_keys[1]];
+            node = parent;
+        }
+
+        node.hash == self.root_hash
+    }
+}
+
+/// Merkle Sum Tree Data Structure whose entries are sorted by username and whose middle nodes commit to the range of usernames of their children.
+///
+/// The entries are sorted and delimited by the same sentinel entries as [MerkleSumTree::from_entries_sorted], and each node is associated with the least and the greatest username of its subtree, namely its keys.
+/// The hash of a middle node is equal to `H(LeftChild.hash, LeftChild.balances, LeftChild.keys, RightChild.hash, RightChild.balances, RightChild.keys)`, while the leaves are hashed as in [MerkleSumTree].
+/// Since the keys of the siblings are committed in the root, the direction of an inclusion path can be derived from the usernames rather than supplied, see [crate::circuits::merkle_sum_tree_sorted::MstSortedInclusionCircuit].
+/// The leaves are padded up to the next power of two with padding leaves, whose keys are 2^248 - 1, greater than any username of the tree.
+///
+/// # Type Parameters
+///
+/// * `N_ASSETS`: The number of assets for each user account
+#[derive(Debug, Clone)]
+pub struct KeyedMerkleSumTree<const N_ASSETS: usize> {
+    root: Node<N_ASSETS>,
+    nodes: Vec<Vec<Node<N_ASSETS>>>,
+    keys: Vec<Vec<[Fp; 2]>>,
+    depth: usize,
+    entries: Vec<Entry<N_ASSETS>>,
+}
+
+impl<const N_ASSETS: usize> KeyedMerkleSumTree<N_ASSETS> {
+    /// Builds a Keyed Merkle Sum Tree from a vector of entries, which are sorted by username and delimited by the sentinel entries.
+    /// Returns an error under the same conditions as [MerkleSumTree::from_entries_sorted]
+    pub fn from_entries(entries: Vec<Entry<N_ASSETS>>) -> Result<Self, Box<dyn std::error::Error>> {
+        let entries = MerkleSumTree::<N_ASSETS>::sort_entries_with_sentinels(entries)?;
+
+        // the sentinels make at least two entries, so the tree has at least one level above the leaves
+        let depth = (entries.len() as f64).log2().ceil() as usize;
+
+        if depth > MerkleSumTree::<N_ASSETS>::MAX_DEPTH {
+            return Err(MerkleSumTreeError::DepthOutOfRange {
+                depth,
+                max_depth: MerkleSumTree::<N_ASSETS>::MAX_DEPTH,
+            }
+            .into());
+        }
+
+        let padding_key = padding_key::<N_ASSETS>();
+
+        let mut nodes = vec![entries
+            .iter()
+            .map(|entry| entry.compute_leaf())
+            .collect::<Vec<_>>()];
+        let mut keys = vec![entries
+            .iter()
+            .map(|entry| {
+                let username = big_int_to_fp(entry.username_to_big_int());
+                [username, username]
+            })
+            .collect::<Vec<_>>()];
+
+        nodes[0].resize(
+            1 << depth,
+            Node {
+                hash: poseidon_padding_leaf(),
+                balances: [Fp::zero(); N_ASSETS],
+            },
+        );
+        keys[0].resize(1 << depth, [padding_key, padding_key]);
+
+        for level in 1..=depth {
+            let (level_nodes, level_keys) = nodes[level - 1]
+                .chunks(2)
+                .zip(keys[level - 1].chunks(2))
+                .map(|(children, children_keys)| {
+                    (
+                        create_keyed_middle_node(
+                            &children[0],
+                            children_keys[0],
+                            &children[1],
+                            children_keys[1],
+                        ),
+                        [children_keys[0][0], children_keys[1][1]],
+                    )
+                })
+                .unzip();
+            nodes.push(level_nodes);
+            keys.push(level_keys);
+        }
+
+        Ok(KeyedMerkleSumTree {
+            root: nodes[depth][0].clone(),
+            nodes,
+            keys,
+            depth,
+            entries,
+        })
+    }
+
+    pub fn root(&self) -> &Node<N_ASSETS> {
+        &self.root
+    }
+
+    pub fn depth(&self) -> &usize {
+        &self.depth
+    }
+
+    pub fn entries(&self) -> &[Entry<N_ASSETS>] {
+        &self.entries
+    }
+
+    /// Returns the index of the leaf of `username`, if it is included in the tree. The entries are sorted, so the leaf is found with a binary search
+    pub fn index_of(&self, username: &str) -> Option<usize> {
+        let username = big_intify_username(username);
+
+        self.entries
+            .binary_search_by(|entry| entry.username_to_big_int().cmp(&username))
+            .ok()
+    }
+
+    /// Generates a KeyedMerkleProof for the user with the given index
+    pub fn generate_proof(&self, index: usize) -> Result<KeyedMerkleProof<N_ASSETS>, &'static str> {
+        if index >= self.entries.len() {
+            return Err("The leaf does not exist in this tree");
+        }
+
+        let mut sibling_hashes = Vec::with_capacity(self.depth);
+        let mut sibling_sums = Vec::with_capacity(self.depth);
+        let mut sibling_keys = Vec::with_capacity(self.depth);
+        let mut path_indices = Vec::with_capacity(self.depth);
+        let mut current_index = index;
+
+        for level in 0..self.depth {
+            let sibling_index = current_index ^ 1;
+
+            sibling_hashes.push(self.nodes[level][sibling_index].hash);
+            sibling_sums.push(self.nodes[level][sibling_index].balances);
+            sibling_keys.push(self.keys[level][sibling_index]);
+            path_indices.push(current_index % 2 == 1);
+
+            current_index /= 2;
+        }
+
+        Ok(KeyedMerkleProof {
+            root_hash: self.root.hash,
+            entry: self.entries[index].clone(),
+            sibling_hashes,
+            sibling_sums,
+            sibling_keys,
+            path_indices,
+        })
+    }
+}
+
+/// Creates the middle node of `left` and `right` with the keys of their subtrees, see [poseidon_keyed_node]
+fn create_keyed_middle_node<const N_ASSETS: usize>(
+    left: &Node<N_ASSETS>,
+    left_keys: [Fp; 2],
+    right: &Node<N_ASSETS>,
+    right_keys: [Fp; 2],
+) -> Node<N_ASSETS> {
+    let mut balances_sum = [Fp::zero(); N_ASSETS];
+    for (i, balance) in balances_sum.iter_mut().enumerate() {
+        *balance = left.balances[i] + right.balances[i];
+    }
+
+    Node {
+        hash: poseidon_keyed_node(left, left_keys, right, right_keys),
+        balances: balances_sum,
+    }
+}
+
+/// The key of the padding leaves, namely 2^248 - 1, which is greater than any username of at most 31 bytes
+fn padding_key<const N_ASSETS: usize>() -> Fp {
+    pow_of_two::<Fp>(8 * (MerkleSumTree::<N_ASSETS>::SORTED_USERNAME_MAX_BYTES + 1)) - Fp::one()
+}
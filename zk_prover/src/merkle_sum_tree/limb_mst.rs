@@ -0,0 +1,240 @@
+use crate::merkle_sum_tree::utils::{
+    big_int_to_fp, big_uint_to_limbs, limbs_to_big_uint, poseidon_entry, poseidon_node,
+};
+use crate::merkle_sum_tree::{Entry, MerkleSumTree, MerkleSumTreeError, Node};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+
+/// Inclusion proof of an entry in a [LimbMerkleSumTree].
+///
+/// # Fields
+///
+/// * `root_hash`: The root hash of the tree
+/// * `entry`: The entry of which the inclusion is proven
+/// * `sibling_hashes`: The hashes of the siblings, ordered from the leaf level to the root level
+/// * `sibling_limbs`: The limbs of the balances of the siblings, ordered from the leaf level to the root level, laid out as in [LimbMerkleSumTree]
+/// * `path_indices`: The direction bits of the path, ordered from the leaf level to the root level. true indicates that the node of the path is the right child, namely that the sibling is on its left
+#[derive(Clone, Debug)]
+pub struct LimbMerkleProof<const N_ASSETS: usize, const N_LIMBS: usize> {
+    pub root_hash: Fp,
+    pub entry: Entry<N_ASSETS>,
+    pub sibling_hashes: Vec<Fp>,
+    pub sibling_limbs: Vec<[Fp; N_LIMBS]>,
+    pub path_indices: Vec<bool>,
+}
+
+impl<const N_ASSETS: usize, const N_LIMBS: usize> LimbMerkleProof<N_ASSETS, N_LIMBS> {
+    /// Computes the leaf of the entry of the proof, namely its hash and the limbs of its balances.
+    /// Returns an error if a balance of the entry doesn't fit in 256 bits
+    pub fn leaf(&self) -> Result<Node<N_LIMBS>, MerkleSumTreeError> {
+        compute_limb_leaf(&self.entry)
+    }
+}
+
+/// Merkle Sum Tree Data Structure in which each balance is split into a low and a high limb of [crate::merkle_sum_tree::LIMB_BITS] bits, so that balances and totals of up to 256 bits are supported without overflowing the field.
+///
+/// It has the same structure of [MerkleSumTree], but the balances of each node are the limbs of its balances, namely `[balance_0_lo, balance_0_hi, balance_1_lo, balance_1_hi, ...]`:
+/// * The hash of a Leaf Node is equal to `H(username, balance_0_lo, balance_0_hi, ..., balance_N_hi)`.
+/// * The hash of a Middle Node is equal to `H(LeftChild.hash, LeftChild.limbs, RightChild.hash, RightChild.limbs)` and its balances are the sum of the balances of its children per asset, with the carry of the low limbs propagated to the high limbs.
+/// * The leaves are padded up to the next power of two with empty nodes, namely with zero hash and zero balances.
+///
+/// The inclusion of an entry is verified in-circuit by [crate::circuits::merkle_sum_tree_limbs::MstLimbInclusionCircuit].
+///
+/// # Type Parameters
+///
+/// * `N_ASSETS`: The number of assets for each user account
+/// * `N_LIMBS`: The number of limbs of the balances of each node, namely 2 * N_ASSETS
+#[derive(Debug, Clone)]
+pub struct LimbMerkleSumTree<const N_ASSETS: usize, const N_LIMBS: usize> {
+    root: Node<N_LIMBS>,
+    nodes: Vec<Vec<Node<N_LIMBS>>>,
+    depth: usize,
+    entries: Vec<Entry<N_ASSETS>>,
+}
+
+impl<const N_ASSETS: usize, const N_LIMBS: usize> LimbMerkleSumTree<N_ASSETS, N_LIMBS> {
+    /// Builds a Limb Merkle Sum Tree from a vector of entries.
+    /// Returns an error if a balance of an entry or the total of an asset doesn't fit in 256 bits.
+    pub fn from_entries(entries: Vec<Entry<N_ASSETS>>) -> Result<Self, Box<dyn std::error::Error>> {
+        if N_LIMBS != 2 * N_ASSETS {
+            return Err("The number of limbs must be twice the number of assets".into());
+        }
+
+        if entries.is_empty() {
+            return Err(MerkleSumTreeError::EmptyTree.into());
+        }
+
+        let depth = (entries.len() as f64).log2().ceil() as usize;
+
+        if depth > MerkleSumTree::<N_ASSETS>::MAX_DEPTH {
+            return Err(MerkleSumTreeError::DepthOutOfRange {
+                depth,
+                max_depth: MerkleSumTree::<N_ASSETS>::MAX_DEPTH,
+            }
+            .into());
+        }
+
+        let mut leaves = entries
+            .iter()
+            .map(compute_limb_leaf)
+            .collect::<Result<Vec<_>, _>>()?;
+        leaves.resize(1 << depth, empty_node());
+
+        // add levels on top of the leaves until a single node, namely the root, is left
+        let mut nodes = vec![leaves];
+        while nodes.len() <= depth {
+            let next_level = nodes[nodes.len() - 1]
+                .chunks(2)
+                .map(|pair| create_limb_middle_node(&pair[0], &pair[1]))
+                .collect::<Result<Vec<_>, _>>()?;
+            nodes.push(next_level);
+        }
+
+        Ok(LimbMerkleSumTree {
+            root: nodes[depth][0].clone(),
+            nodes,
+            depth,
+            entries,
+        })
+    }
+
+    pub fn root(&self) -> &Node<N_LIMBS> {
+        &self.root
+    }
+
+    pub fn depth(&self) -> &usize {
+        &self.depth
+    }
+
+    pub fn leaves(&self) -> &[Node<N_LIMBS>] {
+        &self.nodes[0]
+    }
+
+    pub fn entries(&self) -> &[Entry<N_ASSETS>] {
+        &self.entries
+    }
+
+    /// Generates a LimbMerkleProof for the user with the given index
+    pub fn generate_proof(
+        &self,
+        index: usize,
+    ) -> Result<LimbMerkleProof<N_ASSETS, N_LIMBS>, &'static str> {
+        if index >= self.entries.len() {
+            return Err("The leaf does not exist in this tree");
+        }
+
+        let mut sibling_hashes = Vec::with_capacity(self.depth);
+        let mut sibling_limbs = Vec::with_capacity(self.depth);
+        let mut path_indices = Vec::with_capacity(self.depth);
+        let mut current_index = index;
+
+        for level in 0..self.depth {
+            let sibling = &self.nodes[level][current_index ^ 1];
+
+            sibling_hashes.push(sibling.hash);
+            sibling_limbs.push(sibling.balances);
+            path_indices.push(current_index % 2 == 1);
+
+            current_index /= 2;
+        }
+
+        Ok(LimbMerkleProof {
+            root_hash: self.root.hash,
+            entry: self.entries[index].clone(),
+            sibling_hashes,
+            sibling_limbs,
+            path_indices,
+        })
+    }
+
+    /// Verifies a LimbMerkleProof by recomputing the root from the entry and the siblings at each level
+    pub fn verify_proof(&self, proof: &LimbMerkleProof<N_ASSETS, N_LIMBS>) -> bool {
+        let mut node = match proof.leaf() {
+            Ok(leaf) => leaf,
+            Err(_) => return false,
+        };
+
+        for ((hash, limbs), is_right) in proof
+            .sibling_hashes
+            .iter()
+            .zip(proof.sibling_limbs.iter())
+            .zip(proof.path_indices.iter())
+        {
+            let sibling = Node {
+                hash: *hash,
+                balances: *limbs,
+            };
+
+            let next_node = if *is_right {
+                create_limb_middle_node(&sibling, &node)
+            } else {
+                create_limb_middle_node(&node, &sibling)
+            };
+
+            match next_node {
+                Ok(next_node) => node = next_node,
+                Err(_) => return false,
+            }
+        }
+
+        proof.root_hash == node.hash
+            && proof.root_hash == self.root.hash
+            && node.balances == self.root.balances
+    }
+}
+
+/// Computes the leaf of `entry`, namely the hash of its username and of the limbs of its balances
+fn compute_limb_leaf<const N_ASSETS: usize, const N_LIMBS: usize>(
+    entry: &Entry<N_ASSETS>,
+) -> Result<Node<N_LIMBS>, MerkleSumTreeError> {
+    let mut limbs = [Fp::zero(); N_LIMBS];
+
+    for (asset, balance) in entry.balances().iter().enumerate() {
+        let balance_limbs = balance
+            .to_biguint()
+            .and_then(|balance| big_uint_to_limbs(&balance).ok())
+            .ok_or_else(|| MerkleSumTreeError::BalanceOutOfRange {
+                username: entry.username().to_string(),
+                asset,
+            })?;
+
+        limbs[2 * asset..2 * asset + 2].copy_from_slice(&balance_limbs);
+    }
+
+    Ok(Node {
+        hash: poseidon_entry::<N_LIMBS>(big_int_to_fp(entry.username_to_big_int()), limbs),
+        balances: limbs,
+    })
+}
+
+/// Creates the middle node of `left` and `right`, adding their balances per asset with the carry of the low limbs propagated to the high limbs.
+/// Returns an error if a sum doesn't fit in 256 bits.
+fn create_limb_middle_node<const N_LIMBS: usize>(
+    left: &Node<N_LIMBS>,
+    right: &Node<N_LIMBS>,
+) -> Result<Node<N_LIMBS>, &'static str> {
+    let mut limbs = [Fp::zero(); N_LIMBS];
+
+    for asset in 0..N_LIMBS / 2 {
+        let limbs_of = |node: &Node<N_LIMBS>| {
+            limbs_to_big_uint(&[node.balances[2 * asset], node.balances[2 * asset + 1]])
+        };
+
+        let sum = big_uint_to_limbs(&(limbs_of(left) + limbs_of(right)))
+            .map_err(|_| "The sum of the balances doesn't fit in 256 bits")?;
+
+        limbs[2 * asset..2 * asset + 2].copy_from_slice(&sum);
+    }
+
+    Ok(Node {
+        hash: poseidon_node(left.hash, left.balances, right.hash, right.balances),
+        balances: limbs,
+    })
+}
+
+/// The node used to pad the leaves up to the next power of two
+fn empty_node<const N_LIMBS: usize>() -> Node<N_LIMBS> {
+    Node {
+        hash: Fp::zero(),
+        balances: [Fp::zero(); N_LIMBS],
+    }
+}
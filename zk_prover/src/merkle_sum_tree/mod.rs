@@ -1,19 +1,61 @@
+mod attestation;
 mod entry;
+mod error;
+mod hierarchical_mst;
+mod keyed_mst;
+mod limb_mst;
 mod mst;
+mod nary_mst;
 mod params;
+mod streaming;
 mod tests;
 mod utils;
 use halo2_proofs::halo2curves::bn256::Fr as Fp;
 
-pub use params::{L_ENTRY, L_NODE, MOD_BITS, MST_WIDTH, N_ASSETS};
+pub use params::{LIMB_BITS, L_ENTRY, L_NODE, MOD_BITS, MST_WIDTH, N_ASSETS};
 
+/// Inclusion proof of an entry in a [MerkleSumTree], namely the plain sibling path from the leaf up to the root, independent of any circuit.
+///
+/// # Fields
+///
+/// * `root_hash`: The root hash of the tree
+/// * `entry`: The entry of which the inclusion is proven
+/// * `sibling_hashes`: The hashes of the siblings, ordered from the leaf level to the root level
+/// * `sibling_sums`: The balances of the siblings, ordered from the leaf level to the root level
+/// * `path_indices`: The direction bits of the path, ordered from the leaf level to the root level. true indicates that the node of the path is the right child, namely that the sibling is on its left
+/// * `leaf_hasher`: The hash function of the leaf of the entry
 #[derive(Clone, Debug)]
 pub struct MerkleProof<const N_ASSETS: usize> {
     pub root_hash: Fp,
     pub entry: Entry<N_ASSETS>,
     pub sibling_hashes: Vec<Fp>,
     pub sibling_sums: Vec<[Fp; N_ASSETS]>,
-    pub path_indices: Vec<Fp>,
+    pub path_indices: Vec<bool>,
+    pub leaf_hasher: LeafHasher,
+}
+
+impl<const N_ASSETS: usize> MerkleProof<N_ASSETS> {
+    /// Verifies the proof off-circuit against `root`, for example to sanity check it before generating the zkSNARK of [crate::circuits::merkle_sum_tree::MstInclusionCircuit].
+    /// The root is recomputed from the entry, hashed with the `leaf_hasher` of the proof, and the siblings with the same Poseidon spec used by the circuit, and it must match `root` both in hash and in balances, as well as the running sum of the balances along the path
+    pub fn verify(&self, root: &Node<N_ASSETS>) -> bool {
+        utils::verify_proof_with_root(self, root)
+    }
+
+    /// Recomputes natively the nodes of the path, from the leaf of the entry up to the root, so that the root computation can be displayed and reproduced before requesting the zkSNARK.
+    /// The first node is the leaf and the last one is the root, whose hash matches `root_hash` for a valid proof
+    pub fn path_nodes(&self) -> Vec<Node<N_ASSETS>> {
+        utils::compute_path_nodes(self)
+    }
+
+    /// Serializes the proof as a JSON string, see [SerializedMerkleProof]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&SerializedMerkleProof::from_proof(self))
+    }
+
+    /// Parses the proof from a JSON string, as written by [MerkleProof::to_json]
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        serde_json::from_str::<SerializedMerkleProof>(json)?.to_proof()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -22,6 +64,20 @@ pub struct Node<const N_ASSETS: usize> {
     pub balances: [Fp; N_ASSETS],
 }
 
-pub use entry::Entry;
-pub use mst::MerkleSumTree;
-pub use utils::{big_int_to_fp, big_intify_username};
+pub(crate) use attestation::{ecdsa_sign, ecdsa_verify};
+pub use attestation::{AttestationScalar, BalanceAttestation};
+pub use entry::{Entry, LeafHasher};
+pub use error::MerkleSumTreeError;
+pub use hierarchical_mst::HierarchicalMerkleSumTree;
+pub use keyed_mst::{KeyedMerkleProof, KeyedMerkleSumTree};
+pub use limb_mst::{LimbMerkleProof, LimbMerkleSumTree};
+pub use mst::{MerkleSumTree, ZeroBalancePolicy};
+pub use nary_mst::{NaryMerkleProof, NaryMerkleSumTree};
+pub use streaming::{build_merkle_sum_tree_streaming, NodeSink, NodeWriter};
+pub use utils::{
+    big_int_to_fp, big_intify_username, big_uint_to_limbs, canonicalize_entries,
+    create_middle_node, create_middle_nodes, fp_to_big_int, fp_to_u128, limbs_to_big_uint,
+    parse_scaled_balance, poseidon_snapshot_root, poseidon_solvency_commitment, poseidon_tagged,
+    poseidon_username, poseidon_variable_length, sha256_entry, sha256_field_elements, verify_proof,
+    HashDomain, SerializedEntry, SerializedMerkleProof,
+};
@@ -1,16 +1,44 @@
+#[cfg(feature = "rayon")]
+use crate::merkle_sum_tree::utils::build_merkle_tree_from_entries_parallel;
 use crate::merkle_sum_tree::utils::{
-    build_merkle_tree_from_entries, create_proof, index_of, parse_csv_to_entries, verify_proof,
+    big_intify_username, build_merkle_tree_from_entries, create_middle_node, create_path,
+    create_proof, decode_tree, encode_tree, fp_to_big_int, fp_to_u128, index_of, padding_nodes,
+    parse_csv_to_entries, parse_csv_to_entries_checked, poseidon_snapshot_root, poseidon_spec_id,
+    update_merkle_path_from_leaf, update_merkle_tree_from_leaf, verify_proof, SerializedEntry,
+    SerializedMerkleSumTree, SerializedNode,
 };
-use crate::merkle_sum_tree::{Entry, MerkleProof, Node};
-use num_bigint::BigInt;
+use crate::merkle_sum_tree::{Entry, LeafHasher, MerkleProof, MerkleSumTreeError, Node, MOD_BITS};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use num_bigint::{BigInt, BigUint};
+use std::collections::HashMap;
+
+/// Signature of the functions computing the nodes of a tree from its entries, see [build_merkle_tree_from_entries]
+type BuildTreeFn<const N_ASSETS: usize> = fn(
+    &[Entry<N_ASSETS>],
+    usize,
+    &mut Vec<Vec<Node<N_ASSETS>>>,
+    LeafHasher,
+) -> Result<Node<N_ASSETS>, Box<dyn std::error::Error>>;
+
+/// Strategy for handling entries whose balances are all exactly zero when building a Merkle Sum Tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZeroBalancePolicy {
+    /// Zero-balance entries are added to the tree as any other entry
+    Include,
+    /// Zero-balance entries are dropped before building the tree, reducing its size
+    Exclude,
+    /// The presence of a zero-balance entry is treated as a data error
+    Error,
+}
 
 /// Merkle Sum Tree Data Structure.
 /// 
 /// A Merkle Sum Tree is a binary Merkle Tree with the following properties:
 /// * Each Entry of a Merkle Sum Tree is a pair of a username and #N_ASSETS balances.
-/// * Each Leaf Node contains a hash and #N_ASSETS balances. The hash is equal to `H(username, balance[0], balance[1], ... balance[N_ASSETS])`.
+/// * Each Leaf Node contains a hash and #N_ASSETS balances. The hash is equal to `H(username, balance[0], balance[1], ... balance[N_ASSETS])`, where `H` is Poseidon or SHA-256 according to the [LeafHasher] of the tree.
 /// * Each Middle Node contains a hash and #N_ASSETS balances. The hash is equal to `H(LeftChild.hash, LeftChild.balance[0], LeftChild.balance[1], LeftChild.balance[N_ASSETS], RightChild.hash, RightChild.balance[0], RightChild.balance[1], RightChild.balance[N_ASSETS])`. The balances are equal to the sum of the balances of the child nodes per each asset.
 /// * The Root Node represents the committed state of the Tree and contains the sum of all the entries' balances per each asset.
+/// * The leaves are padded up to the next power of two with padding leaves, namely the hash of a fixed sentinel with zero balances, see [crate::merkle_sum_tree::utils::padding_nodes]. The padding leaves don't add to the balances of the root and no proof can be generated for them.
 ///
 /// # Type Parameters
 ///
@@ -21,11 +49,16 @@ pub struct MerkleSumTree<const N_ASSETS: usize> {
     nodes: Vec<Vec<Node<N_ASSETS>>>,
     depth: usize,
     entries: Vec<Entry<N_ASSETS>>,
+    leaf_hasher: LeafHasher,
+    username_index: HashMap<String, usize>,
 }
 
 impl<const N_ASSETS: usize> MerkleSumTree<N_ASSETS> {
     pub const MAX_DEPTH: usize = 27;
 
+    /// Maximum length in bytes of the usernames of a tree built with [MerkleSumTree::from_entries_sorted]
+    pub const SORTED_USERNAME_MAX_BYTES: usize = 30;
+
     /// Builds a Merkle Sum Tree from a CSV file stored at `path`. The CSV file must be formatted as follows:
     ///
     /// `username;balances`
@@ -33,23 +66,287 @@ impl<const N_ASSETS: usize> MerkleSumTree<N_ASSETS> {
     /// `dxGaEAii;11888,41163`
     pub fn new(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
         let entries = parse_csv_to_entries(path)?;
-        let depth = (entries.len() as f64).log2().ceil() as usize;
+        Self::from_entries(entries)
+    }
+
+    /// Builds a Merkle Sum Tree from a CSV file stored at `path`, formatted as for [MerkleSumTree::new].
+    /// Unlike [MerkleSumTree::new], it doesn't panic on invalid data: it returns a [MerkleSumTreeError] for a malformed row, a negative balance or a balance that doesn't fit in MOD_BITS bits.
+    pub fn from_csv(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let entries = parse_csv_to_entries_checked(path, MOD_BITS)?;
+        Self::from_entries(entries)
+    }
+
+    /// Builds a Merkle Sum Tree from a vector of entries.
+    /// A tree with a single entry has depth 0, namely its root is the leaf itself and the inclusion proofs have an empty path.
+    /// A tree without entries can't be built: [MerkleSumTreeError::EmptyTree] is returned, so that no inclusion proof can be generated against an undefined root.
+    pub fn from_entries(entries: Vec<Entry<N_ASSETS>>) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_entries_with_leaf_hasher(entries, LeafHasher::Poseidon)
+    }
 
-        if !(1..=Self::MAX_DEPTH).contains(&depth) {
-            return Err(
-                "The tree depth must be between 1 and 27, namely it can support 2^27 users at max"
-                    .into(),
-            );
+    /// Builds a Merkle Sum Tree from a vector of entries, hashing the leaves with `leaf_hasher`. The middle nodes are hashed with Poseidon regardless of it.
+    pub fn from_entries_with_leaf_hasher(
+        entries: Vec<Entry<N_ASSETS>>,
+        leaf_hasher: LeafHasher,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_entries_with_builder(entries, leaf_hasher, build_merkle_tree_from_entries)
+    }
+
+    /// Same as [MerkleSumTree::from_entries], but the leaves and the middle nodes of each level are hashed in parallel with rayon.
+    /// The resulting tree is identical to the one built sequentially.
+    #[cfg(feature = "rayon")]
+    pub fn from_entries_parallel(
+        entries: Vec<Entry<N_ASSETS>>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_entries_with_builder(
+            entries,
+            LeafHasher::Poseidon,
+            build_merkle_tree_from_entries_parallel,
+        )
+    }
+
+    /// Builds a Merkle Sum Tree from a vector of entries, computing its nodes with `build_tree`, namely either the sequential or the parallel builder.
+    /// Returns [MerkleSumTreeError::EmptyTree] if there are no entries and [MerkleSumTreeError::DepthOutOfRange] if they don't fit in a tree of [MerkleSumTree::MAX_DEPTH] levels.
+    fn from_entries_with_builder(
+        entries: Vec<Entry<N_ASSETS>>,
+        leaf_hasher: LeafHasher,
+        build_tree: BuildTreeFn<N_ASSETS>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if entries.is_empty() {
+            return Err(MerkleSumTreeError::EmptyTree.into());
         }
 
+        let depth = Self::depth_for(entries.len())?;
+
         let mut nodes = vec![];
-        let root = build_merkle_tree_from_entries(&entries, depth, &mut nodes)?;
+        let root = build_tree(&entries, depth, &mut nodes, leaf_hasher)?;
+
+        Ok(MerkleSumTree {
+            root,
+            nodes,
+            depth,
+            username_index: Self::index_usernames(&entries),
+            entries,
+            leaf_hasher,
+        })
+    }
+
+    /// Returns the depth of a tree with `n_leaves` leaves, or [MerkleSumTreeError::DepthOutOfRange] if it exceeds [MerkleSumTree::MAX_DEPTH]
+    fn depth_for(n_leaves: usize) -> Result<usize, MerkleSumTreeError> {
+        let depth = (n_leaves as f64).log2().ceil() as usize;
+
+        if depth > Self::MAX_DEPTH {
+            return Err(MerkleSumTreeError::DepthOutOfRange {
+                depth,
+                max_depth: Self::MAX_DEPTH,
+            });
+        }
+
+        Ok(depth)
+    }
+
+    /// Builds a Merkle Sum Tree from a vector of entries, treating the entries with all balances equal to zero according to `zero_balance_policy`.
+    /// If `expected_count` is set, the number of delivered entries must match it, so that entries lost upstream don't silently reduce the liabilities.
+    pub fn build(
+        entries: Vec<Entry<N_ASSETS>>,
+        zero_balance_policy: ZeroBalancePolicy,
+        expected_count: Option<usize>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(expected) = expected_count {
+            if entries.len() != expected {
+                return Err(MerkleSumTreeError::CountMismatch {
+                    expected,
+                    actual: entries.len(),
+                }
+                .into());
+            }
+        }
+
+        let is_zero_balance = |entry: &Entry<N_ASSETS>| {
+            entry
+                .balances()
+                .iter()
+                .all(|balance| *balance == BigInt::from(0))
+        };
+
+        let entries = match zero_balance_policy {
+            ZeroBalancePolicy::Include => entries,
+            ZeroBalancePolicy::Exclude => entries
+                .into_iter()
+                .filter(|entry| !is_zero_balance(entry))
+                .collect(),
+            ZeroBalancePolicy::Error => {
+                if let Some(entry) = entries.iter().find(|entry| is_zero_balance(entry)) {
+                    return Err(
+                        MerkleSumTreeError::ZeroBalance(entry.username().to_string()).into(),
+                    );
+                }
+                entries
+            }
+        };
+
+        Self::from_entries(entries)
+    }
+
+    /// Builds a Merkle Sum Tree placing the entries in ascending order of the key returned by `key_fn`, for example the balance in reverse order for concentration proofs.
+    /// The sort is stable, so entries with the same key keep their relative order.
+    /// Returns the tree together with the permutation applied, namely the index in `entries` of the entry placed at each leaf position
+    pub fn build_with_order<K: Ord>(
+        entries: Vec<Entry<N_ASSETS>>,
+        key_fn: impl Fn(&Entry<N_ASSETS>) -> K,
+    ) -> Result<(Self, Vec<usize>), Box<dyn std::error::Error>> {
+        let mut permutation = (0..entries.len()).collect::<Vec<_>>();
+        permutation.sort_by_key(|&index| key_fn(&entries[index]));
+
+        let ordered_entries = permutation
+            .iter()
+            .map(|&index| entries[index].clone())
+            .collect();
+
+        Ok((Self::from_entries(ordered_entries)?, permutation))
+    }
+
+    /// Builds a Merkle Sum Tree whose entries are sorted in strictly ascending order of the username converted to a field element, so that the absence of a username can be proven with [crate::circuits::merkle_sum_tree_exclusion::MstExclusionCircuit].
+    /// Two sentinel entries with zero balances are placed at both ends, namely an empty username, lower than any other, and a username of 31 bytes, greater than any username of at most [MerkleSumTree::SORTED_USERNAME_MAX_BYTES] bytes, so that any missing username lies strictly between two adjacent entries.
+    /// Returns an error if a username is longer than [MerkleSumTree::SORTED_USERNAME_MAX_BYTES] bytes or if two usernames, including the sentinels, are converted to the same field element.
+    pub fn from_entries_sorted(
+        entries: Vec<Entry<N_ASSETS>>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_entries(Self::sort_entries_with_sentinels(entries)?)
+    }
+
+    /// Sorts the entries in strictly ascending order of the username converted to a field element and places the sentinel entries at both ends, see [MerkleSumTree::from_entries_sorted]
+    pub(crate) fn sort_entries_with_sentinels(
+        entries: Vec<Entry<N_ASSETS>>,
+    ) -> Result<Vec<Entry<N_ASSETS>>, Box<dyn std::error::Error>> {
+        if entries
+            .iter()
+            .any(|entry| entry.username().len() > Self::SORTED_USERNAME_MAX_BYTES)
+        {
+            return Err("The usernames of a sorted tree must be at most 30 bytes long".into());
+        }
+
+        let zero_balances = || std::array::from_fn(|_| BigInt::from(0));
+
+        let mut sorted_entries = vec![Entry::new(String::new(), zero_balances())?];
+        sorted_entries.extend(entries);
+        sorted_entries.push(Entry::new(
+            "~".repeat(Self::SORTED_USERNAME_MAX_BYTES + 1),
+            zero_balances(),
+        )?);
+
+        sorted_entries.sort_by(|a, b| a.username_to_big_int().cmp(b.username_to_big_int()));
+
+        if sorted_entries
+            .windows(2)
+            .any(|pair| pair[0].username_to_big_int() == pair[1].username_to_big_int())
+        {
+            return Err("The usernames of a sorted tree must be distinct".into());
+        }
+
+        Ok(sorted_entries)
+    }
+
+    /// Writes the tree to a JSON file stored at `path`, tagged with the identifier of the Poseidon spec used to compute its hashes
+    pub fn to_file(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let serialized = SerializedMerkleSumTree {
+            spec_id: poseidon_spec_id(),
+            depth: self.depth,
+            entries: self
+                .entries
+                .iter()
+                .map(SerializedEntry::from_entry)
+                .collect(),
+            nodes: self
+                .nodes
+                .iter()
+                .map(|level| level.iter().map(SerializedNode::from_node).collect())
+                .collect(),
+            leaf_hasher: self.leaf_hasher,
+        };
+
+        std::fs::write(path, serde_json::to_string(&serialized)?)?;
+        Ok(())
+    }
+
+    /// Loads a tree from a JSON file stored at `path`, as written by [MerkleSumTree::to_file].
+    /// Returns an error if the tree was built with a Poseidon spec different from the compiled-in one, as the proofs generated from its hashes would fail.
+    /// The serialized nodes are not trusted: every node is re-hashed from the entries with [MerkleSumTree::validate_structure], so that a tampered file is rejected instead of producing proofs against a forged root.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let tree = Self::from_file_unchecked(path)?;
+        tree.validate_structure()?;
+        Ok(tree)
+    }
+
+    /// Loads a tree from a JSON file stored at `path` as [MerkleSumTree::from_file] does, but without re-hashing the serialized nodes.
+    /// It skips the cost of rebuilding the tree and must only be used for files from a trusted source, since a tampered node is loaded as is.
+    pub fn from_file_unchecked(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let serialized: SerializedMerkleSumTree =
+            serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+        let expected_spec_id = poseidon_spec_id();
+        if serialized.spec_id != expected_spec_id {
+            return Err(MerkleSumTreeError::SpecMismatch {
+                expected: expected_spec_id,
+                found: serialized.spec_id,
+            }
+            .into());
+        }
+
+        let entries = serialized
+            .entries
+            .iter()
+            .map(SerializedEntry::to_entry)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let nodes = serialized
+            .nodes
+            .iter()
+            .map(|level| {
+                level
+                    .iter()
+                    .map(SerializedNode::to_node)
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let root = nodes
+            .get(serialized.depth)
+            .and_then(|level| level.first())
+            .ok_or("The serialized tree doesn't contain the root")?
+            .clone();
 
         Ok(MerkleSumTree {
             root,
             nodes,
+            depth: serialized.depth,
+            username_index: Self::index_usernames(&entries),
+            entries,
+            leaf_hasher: serialized.leaf_hasher,
+        })
+    }
+
+    /// Writes the tree to a file stored at `path` in a compact binary format, see [encode_tree]. Field elements are stored as their 32 bytes representation, so they round-trip exactly.
+    /// The binary format only supports Poseidon leaves, use [MerkleSumTree::to_file] for a tree with [LeafHasher::Sha256] leaves
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if self.leaf_hasher != LeafHasher::Poseidon {
+            return Err("The binary format only supports trees with Poseidon leaves".into());
+        }
+        std::fs::write(path, encode_tree(self.depth, &self.entries, &self.nodes))?;
+        Ok(())
+    }
+
+    /// Loads a tree from a binary file stored at `path`, as written by [MerkleSumTree::save].
+    /// Returns a [MerkleSumTreeError::CorruptedTree] if the stored checksum doesn't match the content or the stored root doesn't match the one recomputed from its children, so that a corrupted file is rejected instead of producing wrong proofs
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let (depth, entries, nodes) = decode_tree(&std::fs::read(path)?)?;
+
+        Ok(MerkleSumTree {
+            root: nodes[depth][0].clone(),
+            nodes,
             depth,
+            username_index: Self::index_usernames(&entries),
             entries,
+            leaf_hasher: LeafHasher::Poseidon,
         })
     }
 
@@ -69,30 +366,456 @@ impl<const N_ASSETS: usize> MerkleSumTree<N_ASSETS> {
         &self.entries
     }
 
+    /// Returns the root of the snapshot of the tree taken at `timestamp`, see [poseidon_snapshot_root]
+    pub fn snapshot_root(&self, timestamp: u64) -> Fp {
+        poseidon_snapshot_root(self.root.hash, timestamp)
+    }
+
+    /// Returns the hash function of the leaves of the tree
+    pub fn leaf_hasher(&self) -> LeafHasher {
+        self.leaf_hasher
+    }
+
+    /// Returns the number of real leaves of the tree, namely the number of entries without the padding leaves
+    pub fn leaf_count(&self) -> usize {
+        self.nodes[0].len()
+    }
+
     /// Returns the nodes stored at the penultimate level of the tree, namely the one before the root
     pub fn penultimate_level_data(
         &self,
     ) -> Result<(&Node<N_ASSETS>, &Node<N_ASSETS>), &'static str> {
         let penultimate_level = self
-            .nodes
-            .get(self.depth - 1)
+            .depth
+            .checked_sub(1)
+            .and_then(|level| self.nodes.get(level))
             .ok_or("The tree does not have a penultimate level")?;
 
         Ok((&penultimate_level[0], &penultimate_level[1]))
     }
 
+    /// Returns the index and the balances of the entry with the given username, or `None` if no entry of the tree has it.
+    /// The lookup is backed by a map built with the tree, so it doesn't scan the leaves. If several entries have the same username, the first one is returned
+    pub fn contains(&self, username: &str) -> Option<(usize, &[BigInt; N_ASSETS])> {
+        self.username_index
+            .get(username)
+            .map(|&index| (index, self.entries[index].balances()))
+    }
+
+    fn index_usernames(entries: &[Entry<N_ASSETS>]) -> HashMap<String, usize> {
+        let mut username_index = HashMap::with_capacity(entries.len());
+        for (index, entry) in entries.iter().enumerate() {
+            username_index
+                .entry(entry.username().to_string())
+                .or_insert(index);
+        }
+        username_index
+    }
+
     /// Returns the index of the user with the given username and balances in the tree
     pub fn index_of(&self, username: &str, balances: [BigInt; N_ASSETS]) -> Option<usize> {
-        index_of(username, balances, &self.nodes)
+        index_of(username, balances, &self.nodes, self.leaf_hasher)
     }
 
     /// Generates a MerkleProof for the user with the given index
     pub fn generate_proof(&self, index: usize) -> Result<MerkleProof<N_ASSETS>, &'static str> {
-        create_proof(index, &self.entries, self.depth, &self.nodes, &self.root)
+        create_proof(
+            index,
+            &self.entries,
+            self.depth,
+            &self.nodes,
+            &self.root,
+            self.leaf_hasher,
+        )
+    }
+
+    /// Generates the proofs of the two adjacent entries whose usernames are respectively lower and greater than `username`, namely the witness of the absence of `username` from a tree built with [MerkleSumTree::from_entries_sorted].
+    /// Returns an error if no two adjacent entries enclose `username`, for example because it is included in the tree or because the tree is not sorted
+    pub fn generate_exclusion_proof(
+        &self,
+        username: &str,
+    ) -> Result<(MerkleProof<N_ASSETS>, MerkleProof<N_ASSETS>), &'static str> {
+        let target = big_intify_username(username);
+
+        let index = self
+            .entries
+            .windows(2)
+            .position(|pair| {
+                pair[0].username_to_big_int() < &target && &target < pair[1].username_to_big_int()
+            })
+            .ok_or("The username is not strictly between two adjacent entries of the tree")?;
+
+        Ok((self.generate_proof(index)?, self.generate_proof(index + 1)?))
+    }
+
+    /// Returns the authentication path of the user with the given index, namely for each level from the leaf up to the root the sibling node together with its position, 0 if the sibling is the left child and 1 if it is the right child.
+    /// These are the same siblings carried by the proof returned by [MerkleSumTree::generate_proof]
+    pub fn sibling_path(&self, index: usize) -> Result<Vec<(usize, Node<N_ASSETS>)>, &'static str> {
+        if index >= self.nodes[0].len() {
+            return Err("The leaf does not exist in this tree");
+        }
+
+        let (sibling_hashes, sibling_sums, path_indices) =
+            create_path(index, self.depth, &self.nodes);
+
+        Ok(sibling_hashes
+            .into_iter()
+            .zip(sibling_sums)
+            .zip(path_indices)
+            .map(|((hash, balances), path_index)| {
+                // the path index is the position of the current node, the sibling sits on the other side
+                let position = if path_index { 0 } else { 1 };
+                (position, Node { hash, balances })
+            })
+            .collect())
     }
 
     /// Verifies a MerkleProof
     pub fn verify_proof(&self, proof: &MerkleProof<N_ASSETS>) -> bool {
         verify_proof(proof)
     }
+
+    /// Replaces the balances of the user at `index` with `new_balances`, recomputing only the nodes on the path from its leaf to the root, namely O(log n) hashes.
+    /// Returns the old and the new root, so that the caller can detect an update that didn't change the tree
+    pub fn update_leaf(
+        &mut self,
+        index: usize,
+        new_balances: [BigInt; N_ASSETS],
+    ) -> Result<(Node<N_ASSETS>, Node<N_ASSETS>), &'static str> {
+        let entry = self
+            .entries
+            .get(index)
+            .ok_or("The leaf does not exist in this tree")?;
+
+        let entry = Entry::new(entry.username().to_string(), new_balances)?;
+        self.nodes[0][index] = entry.compute_leaf_with(self.leaf_hasher);
+        self.entries[index] = entry;
+
+        let old_root = std::mem::replace(
+            &mut self.root,
+            update_merkle_path_from_leaf(index, self.depth, &mut self.nodes),
+        );
+
+        Ok((old_root, self.root.clone()))
+    }
+
+    /// Appends a single entry to the tree. Returns the new root
+    pub fn append(
+        &mut self,
+        entry: Entry<N_ASSETS>,
+    ) -> Result<&Node<N_ASSETS>, Box<dyn std::error::Error>> {
+        self.append_batch(vec![entry])
+    }
+
+    /// Appends `entries` to the tree in bulk. The new leaves are computed first, then the middle nodes affected by them are recomputed only once, growing the depth of the tree if needed.
+    /// The resulting tree is the same as the one built from scratch with all the entries. Returns the new root
+    pub fn append_batch(
+        &mut self,
+        entries: Vec<Entry<N_ASSETS>>,
+    ) -> Result<&Node<N_ASSETS>, Box<dyn std::error::Error>> {
+        let first_new_leaf = self.entries.len();
+        let n = first_new_leaf + entries.len();
+
+        let depth = Self::depth_for(n)?;
+
+        self.nodes[0].extend(
+            entries
+                .iter()
+                .map(|entry| entry.compute_leaf_with(self.leaf_hasher)),
+        );
+        for (offset, entry) in entries.iter().enumerate() {
+            self.username_index
+                .entry(entry.username().to_string())
+                .or_insert(first_new_leaf + offset);
+        }
+        self.entries.extend(entries);
+
+        self.root = update_merkle_tree_from_leaf(first_new_leaf, depth, &mut self.nodes);
+        self.depth = depth;
+
+        Ok(&self.root)
+    }
+
+    /// Merges two trees built independently, for example from different shards of the users, into a tree whose root is the parent of the two roots, namely their hash together with the sum of their balances.
+    /// The entries of `right` follow the ones of `left`, so the merged tree is the same as the one built from scratch with all the entries and the inclusion proofs of the leaves of either tree have a path longer by one level.
+    /// Both trees must have the same depth and leaf hasher, `left` must be full, namely have 2^depth entries, so that the leaves of `right` start at the first leaf of the right subtree, and no username can appear in both trees.
+    pub fn merge(left: Self, right: Self) -> Result<Self, MerkleSumTreeError> {
+        if left.depth != right.depth {
+            return Err(MerkleSumTreeError::MergeDepthMismatch {
+                left: left.depth,
+                right: right.depth,
+            });
+        }
+
+        if left.leaf_hasher != right.leaf_hasher {
+            return Err(MerkleSumTreeError::MergeLeafHasherMismatch);
+        }
+
+        if left.entries.len() != 1 << left.depth {
+            return Err(MerkleSumTreeError::MergeLeftTreeNotFull {
+                entries: left.entries.len(),
+                capacity: 1 << left.depth,
+            });
+        }
+
+        let depth = left.depth + 1;
+
+        if depth > Self::MAX_DEPTH {
+            return Err(MerkleSumTreeError::DepthOutOfRange {
+                depth,
+                max_depth: Self::MAX_DEPTH,
+            });
+        }
+
+        // a username in both trees would have two leaves in the merged tree, and only the first one could be found by its username
+        if let Some(entry) = right
+            .entries
+            .iter()
+            .find(|entry| left.username_index.contains_key(entry.username()))
+        {
+            return Err(MerkleSumTreeError::DuplicateUsername(
+                entry.username().to_string(),
+            ));
+        }
+
+        let root = create_middle_node(&left.root, &right.root);
+
+        // the subtrees are aligned, so each level of the merged tree is the level of the left tree followed by the one of the right tree
+        let mut nodes: Vec<Vec<Node<N_ASSETS>>> = left
+            .nodes
+            .into_iter()
+            .zip(right.nodes)
+            .map(|(mut left_level, right_level)| {
+                left_level.extend(right_level);
+                left_level
+            })
+            .collect();
+        nodes.push(vec![root.clone()]);
+
+        let mut entries = left.entries;
+        entries.extend(right.entries);
+
+        Ok(MerkleSumTree {
+            root,
+            nodes,
+            depth,
+            username_index: Self::index_usernames(&entries),
+            entries,
+            leaf_hasher: left.leaf_hasher,
+        })
+    }
+
+    /// Checks that every balance of every leaf lies in `[0, 2^range_bits)`.
+    /// Balances are field elements, so a value close to the modulus would act as a negative balance and reduce the liabilities of the tree.
+    pub fn validate(&self, range_bits: usize) -> Result<(), MerkleSumTreeError> {
+        match self.first_out_of_range_balance(range_bits) {
+            Some((leaf_index, asset)) => Err(MerkleSumTreeError::BalanceOutOfRange {
+                username: self.entries[leaf_index].username().to_string(),
+                asset,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that the balances of the root equal the plain sum of the balances of all the entries, as a self-check of the running sums computed while building the tree.
+    /// The sum is computed over the integers, so a tree whose liabilities overflow the field is also reported.
+    pub fn check_root_sum(&self) -> Result<(), MerkleSumTreeError> {
+        for asset in 0..N_ASSETS {
+            let expected = self
+                .entries
+                .iter()
+                .map(|entry| &entry.balances()[asset])
+                .sum::<BigInt>();
+            let found = fp_to_big_int(&self.root.balances[asset]);
+
+            if expected != found {
+                return Err(MerkleSumTreeError::RootSumMismatch {
+                    asset,
+                    expected,
+                    found,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Re-derives the whole tree from its entries and checks it against the stored nodes, bottom up, so that a tree built with a bug or tampered with after deserialization is detected.
+    /// Each leaf must match the leaf computed from its entry, and each middle node must have hash `H(left_hash, left_balances, right_hash, right_balances)` and balances `left_balances + right_balances`, where a missing right child is the padding node of the level.
+    /// The first mismatching node is reported by its level and its index within the level.
+    pub fn validate_structure(&self) -> Result<(), MerkleSumTreeError> {
+        if self.nodes.len() != self.depth + 1 || self.nodes[0].len() != self.entries.len() {
+            return Err(MerkleSumTreeError::CorruptedTree(
+                "The number of levels or of leaves doesn't match the depth and the entries of the tree"
+                    .to_string(),
+            ));
+        }
+
+        for (index, (entry, leaf)) in self.entries.iter().zip(self.nodes[0].iter()).enumerate() {
+            Self::check_node(&entry.compute_leaf_with(self.leaf_hasher), leaf, 0, index)?;
+        }
+
+        let padding = padding_nodes::<N_ASSETS>(self.depth);
+
+        for level in 1..=self.depth {
+            let children = &self.nodes[level - 1];
+
+            if self.nodes[level].len() != children.len().div_ceil(2) {
+                return Err(MerkleSumTreeError::CorruptedTree(format!(
+                    "The number of nodes at level {} doesn't match the number of their children",
+                    level
+                )));
+            }
+
+            for (index, node) in self.nodes[level].iter().enumerate() {
+                let expected = create_middle_node(
+                    &children[2 * index],
+                    children.get(2 * index + 1).unwrap_or(&padding[level - 1]),
+                );
+                Self::check_node(&expected, node, level, index)?;
+            }
+        }
+
+        Self::check_node(&self.nodes[self.depth][0], &self.root, self.depth, 0)
+    }
+
+    /// Checks that the `found` node at `level` and `index` equals the `expected` one, comparing the balances first so that a tampered balance is reported at its own node
+    fn check_node(
+        expected: &Node<N_ASSETS>,
+        found: &Node<N_ASSETS>,
+        level: usize,
+        index: usize,
+    ) -> Result<(), MerkleSumTreeError> {
+        if let Some(asset) =
+            (0..N_ASSETS).find(|&asset| expected.balances[asset] != found.balances[asset])
+        {
+            return Err(MerkleSumTreeError::NodeBalanceMismatch {
+                level,
+                index,
+                asset,
+            });
+        }
+
+        if expected.hash != found.hash {
+            return Err(MerkleSumTreeError::NodeHashMismatch { level, index });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the leaf index and the asset index of the first balance that doesn't lie in `[0, 2^range_bits)`
+    fn first_out_of_range_balance(&self, range_bits: usize) -> Option<(usize, usize)> {
+        let limit = BigInt::from(1) << range_bits;
+
+        self.nodes[0]
+            .iter()
+            .enumerate()
+            .find_map(|(leaf_index, leaf)| {
+                leaf.balances
+                    .iter()
+                    .position(|balance| fp_to_big_int(balance) >= limit)
+                    .map(|asset_index| (leaf_index, asset_index))
+            })
+    }
+
+    /// Returns the index of the asset with the thinnest solvency margin together with that margin, namely `total_assets[i] - liabilities[i]`.
+    /// `total_assets` contains the assets held by the CEX for each asset. A negative margin means that the CEX is insolvent for that asset.
+    /// Returns [MerkleSumTreeError::AssetCountMismatch] if `total_assets` doesn't contain exactly one value per asset of the tree
+    pub fn tightest_asset(
+        &self,
+        total_assets: &[BigUint],
+    ) -> Result<(usize, BigInt), MerkleSumTreeError> {
+        let count_mismatch = MerkleSumTreeError::AssetCountMismatch {
+            expected: N_ASSETS,
+            actual: total_assets.len(),
+        };
+
+        if total_assets.len() != N_ASSETS {
+            return Err(count_mismatch);
+        }
+
+        self.root
+            .balances
+            .iter()
+            .zip(total_assets.iter())
+            .map(|(liabilities, assets)| BigInt::from(assets.clone()) - fp_to_big_int(liabilities))
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.cmp(b))
+            .ok_or(count_mismatch)
+    }
+
+    /// Returns the total liabilities of the CEX per each asset, namely the balances of the root, as integers.
+    /// Returns [MerkleSumTreeError::LiabilitiesOutOfRange] for the first asset whose total doesn't fit in a u128
+    pub fn total_liabilities(&self) -> Result<[u128; N_ASSETS], MerkleSumTreeError> {
+        let mut total_liabilities = [0u128; N_ASSETS];
+
+        for (asset, (total, balance)) in total_liabilities
+            .iter_mut()
+            .zip(self.root.balances.iter())
+            .enumerate()
+        {
+            *total =
+                fp_to_u128(balance).ok_or(MerkleSumTreeError::LiabilitiesOutOfRange { asset })?;
+        }
+
+        Ok(total_liabilities)
+    }
+
+    /// Returns the minimal assets the CEX must hold per each asset to be solvent, namely the root balance + 1.
+    /// The [crate::circuits::solvency::SolvencyCircuit] enforces the liabilities to be strictly less than the assets, so holding exactly the root balance is not enough, unless the circuit is built with [crate::circuits::solvency::SolvencyCircuit::with_inclusive_comparison], see [MerkleSumTree::required_assets_inclusive].
+    pub fn required_assets(&self) -> Vec<BigUint> {
+        self.required_assets_with_margin(1)
+    }
+
+    /// Returns the minimal assets the CEX must hold per each asset to be solvent under [crate::circuits::solvency::SolvencyCircuit::with_inclusive_comparison], namely the root balance itself.
+    pub fn required_assets_inclusive(&self) -> Vec<BigUint> {
+        self.required_assets_with_margin(0)
+    }
+
+    /// Returns the root balance + `margin` per each asset
+    fn required_assets_with_margin(&self, margin: u64) -> Vec<BigUint> {
+        self.root
+            .balances
+            .iter()
+            .map(|liabilities| {
+                (fp_to_big_int(liabilities) + margin)
+                    .to_biguint()
+                    .expect("the liabilities are non-negative")
+            })
+            .collect()
+    }
+
+    /// Returns the sum of the balances per each asset of the users in `set_a` and of the users in `set_b`, given by their index in the tree.
+    /// The two sets must be disjoint and together cover all the users of the tree, so that the two sums add up to the root balances.
+    pub fn partition_sums(
+        &self,
+        set_a: &[usize],
+        set_b: &[usize],
+    ) -> Result<([Fp; N_ASSETS], [Fp; N_ASSETS]), &'static str> {
+        let mut covered = vec![false; self.entries.len()];
+
+        for &index in set_a.iter().chain(set_b.iter()) {
+            match covered.get_mut(index) {
+                None => return Err("The index of the user is out of range"),
+                Some(true) => return Err("The user sets are not disjoint"),
+                Some(seen) => *seen = true,
+            }
+        }
+
+        if covered.iter().any(|seen| !seen) {
+            return Err("The user sets do not cover all the users of the tree");
+        }
+
+        let sum = |set: &[usize]| {
+            let mut sums = [Fp::zero(); N_ASSETS];
+            for &index in set {
+                for (sum, balance) in sums.iter_mut().zip(self.nodes[0][index].balances.iter()) {
+                    *sum += balance;
+                }
+            }
+            sums
+        };
+
+        Ok((sum(set_a), sum(set_b)))
+    }
 }
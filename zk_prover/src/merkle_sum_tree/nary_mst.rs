@@ -0,0 +1,209 @@
+use crate::merkle_sum_tree::utils::{big_int_to_fp, poseidon_nary_node};
+use crate::merkle_sum_tree::{Entry, MerkleSumTree, MerkleSumTreeError, Node};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+
+/// Inclusion proof of an entry in a [NaryMerkleSumTree].
+///
+/// # Fields
+///
+/// * `root_hash`: The root hash of the tree
+/// * `entry`: The entry of which the inclusion is proven
+/// * `sibling_hashes`: The hashes of the ARITY - 1 siblings at each level from the leaf to the root, in order
+/// * `sibling_sums`: The balances of the ARITY - 1 siblings at each level from the leaf to the root, in order
+/// * `path_indices`: The position of the node of the path among its siblings at each level, in `0..ARITY`
+#[derive(Clone, Debug)]
+pub struct NaryMerkleProof<const N_ASSETS: usize, const ARITY: usize> {
+    pub root_hash: Fp,
+    pub entry: Entry<N_ASSETS>,
+    pub sibling_hashes: Vec<Vec<Fp>>,
+    pub sibling_sums: Vec<Vec<[Fp; N_ASSETS]>>,
+    pub path_indices: Vec<usize>,
+}
+
+/// Merkle Sum Tree Data Structure in which each middle node has `ARITY` children.
+///
+/// It has the same properties of [MerkleSumTree], but the hash of a middle node is equal to `H(Child_0.hash, Child_0.balances, Child_1.hash, Child_1.balances, ..., Child_ARITY-1.hash, Child_ARITY-1.balances)`.
+/// A higher arity makes the tree shallower, and so the inclusion proofs shorter, at the cost of a wider hash per level.
+/// The missing children of the last node of a level are empty nodes, namely with zero hash and zero balances. With `ARITY = 2` and a power of two number of entries the tree is the same as a [MerkleSumTree], which pads its leaves with padding leaves instead.
+///
+/// # Type Parameters
+///
+/// * `N_ASSETS`: The number of assets for each user account
+/// * `ARITY`: The number of children of each middle node
+#[derive(Debug, Clone)]
+pub struct NaryMerkleSumTree<const N_ASSETS: usize, const ARITY: usize> {
+    root: Node<N_ASSETS>,
+    nodes: Vec<Vec<Node<N_ASSETS>>>,
+    depth: usize,
+    entries: Vec<Entry<N_ASSETS>>,
+}
+
+impl<const N_ASSETS: usize, const ARITY: usize> NaryMerkleSumTree<N_ASSETS, ARITY> {
+    /// Builds a N-ary Merkle Sum Tree from a vector of entries.
+    /// The tree can store at most as many entries as a [MerkleSumTree] of maximum depth.
+    pub fn from_entries(entries: Vec<Entry<N_ASSETS>>) -> Result<Self, Box<dyn std::error::Error>> {
+        if ARITY < 2 {
+            return Err("The arity of the tree must be at least 2".into());
+        }
+
+        if entries.is_empty() {
+            return Err(MerkleSumTreeError::EmptyTree.into());
+        }
+
+        if entries.len() > 1 << MerkleSumTree::<N_ASSETS>::MAX_DEPTH {
+            return Err("The tree can support 2^27 users at max".into());
+        }
+
+        let mut nodes = vec![entries
+            .iter()
+            .map(|entry| entry.compute_leaf())
+            .collect::<Vec<_>>()];
+
+        // add levels on top of the leaves until a single node, namely the root, is left
+        let mut depth = 0;
+        while nodes[depth].len() > 1 {
+            let level = nodes[depth]
+                .chunks(ARITY)
+                .map(create_nary_middle_node::<N_ASSETS, ARITY>)
+                .collect::<Vec<_>>();
+            nodes.push(level);
+            depth += 1;
+        }
+
+        Ok(NaryMerkleSumTree {
+            root: nodes[depth][0].clone(),
+            nodes,
+            depth,
+            entries,
+        })
+    }
+
+    pub fn root(&self) -> &Node<N_ASSETS> {
+        &self.root
+    }
+
+    pub fn depth(&self) -> &usize {
+        &self.depth
+    }
+
+    pub fn leaves(&self) -> &[Node<N_ASSETS>] {
+        &self.nodes[0]
+    }
+
+    pub fn entries(&self) -> &[Entry<N_ASSETS>] {
+        &self.entries
+    }
+
+    /// Generates a NaryMerkleProof for the user with the given index
+    pub fn generate_proof(
+        &self,
+        index: usize,
+    ) -> Result<NaryMerkleProof<N_ASSETS, ARITY>, &'static str> {
+        if index >= self.nodes[0].len() {
+            return Err("The leaf does not exist in this tree");
+        }
+
+        let mut sibling_hashes = Vec::with_capacity(self.depth);
+        let mut sibling_sums = Vec::with_capacity(self.depth);
+        let mut path_indices = Vec::with_capacity(self.depth);
+        let mut current_index = index;
+
+        for level in 0..self.depth {
+            let position = current_index % ARITY;
+            let first_child = current_index - position;
+
+            // the missing children of the last node of a level are empty nodes
+            let siblings = (first_child..first_child + ARITY)
+                .filter(|&i| i != current_index)
+                .map(|i| self.nodes[level].get(i).cloned().unwrap_or_else(empty_node))
+                .collect::<Vec<_>>();
+
+            sibling_hashes.push(siblings.iter().map(|node| node.hash).collect());
+            sibling_sums.push(siblings.iter().map(|node| node.balances).collect());
+            path_indices.push(position);
+
+            current_index /= ARITY;
+        }
+
+        Ok(NaryMerkleProof {
+            root_hash: self.root.hash,
+            entry: self.entries[index].clone(),
+            sibling_hashes,
+            sibling_sums,
+            path_indices,
+        })
+    }
+
+    /// Verifies a NaryMerkleProof
+    pub fn verify_proof(&self, proof: &NaryMerkleProof<N_ASSETS, ARITY>) -> bool {
+        verify_nary_proof(proof)
+    }
+}
+
+/// Verifies a NaryMerkleProof by recomputing the root from the entry and the siblings at each level
+fn verify_nary_proof<const N_ASSETS: usize, const ARITY: usize>(
+    proof: &NaryMerkleProof<N_ASSETS, ARITY>,
+) -> bool {
+    let mut node = proof.entry.compute_leaf();
+    let mut balances = proof
+        .entry
+        .balances()
+        .iter()
+        .map(big_int_to_fp)
+        .collect::<Vec<Fp>>();
+
+    for level in 0..proof.path_indices.len() {
+        let position = proof.path_indices[level];
+        if position >= ARITY || proof.sibling_hashes[level].len() != ARITY - 1 {
+            return false;
+        }
+
+        let mut children = proof.sibling_hashes[level]
+            .iter()
+            .zip(proof.sibling_sums[level].iter())
+            .map(|(hash, balances)| Node {
+                hash: *hash,
+                balances: *balances,
+            })
+            .collect::<Vec<_>>();
+
+        for sibling in children.iter() {
+            for (balance, sibling_balance) in balances.iter_mut().zip(sibling.balances.iter()) {
+                *balance += sibling_balance;
+            }
+        }
+
+        children.insert(position, node);
+        node = create_nary_middle_node::<N_ASSETS, ARITY>(&children);
+    }
+
+    proof.root_hash == node.hash && balances == node.balances
+}
+
+/// Creates the middle node of `children`, padding them with empty nodes up to ARITY
+fn create_nary_middle_node<const N_ASSETS: usize, const ARITY: usize>(
+    children: &[Node<N_ASSETS>],
+) -> Node<N_ASSETS> {
+    let mut balances_sum = [Fp::zero(); N_ASSETS];
+    for child in children {
+        for (balance, child_balance) in balances_sum.iter_mut().zip(child.balances.iter()) {
+            *balance += child_balance;
+        }
+    }
+
+    let mut padded_children = children.to_vec();
+    padded_children.resize(ARITY, empty_node());
+
+    Node {
+        hash: poseidon_nary_node(&padded_children),
+        balances: balances_sum,
+    }
+}
+
+/// The node used in place of the missing children of the last node of a level
+fn empty_node<const N_ASSETS: usize>() -> Node<N_ASSETS> {
+    Node {
+        hash: Fp::zero(),
+        balances: [Fp::zero(); N_ASSETS],
+    }
+}
@@ -8,3 +8,5 @@ pub const L_NODE: usize = 2 * (1 + N_ASSETS);
 pub const L_ENTRY: usize = 1 + N_ASSETS;
 /// Overflow limit for the accumulated balances in the proof of solvency
 pub const MOD_BITS: usize = 248;
+/// Number of bits of each of the two limbs in which a 256-bit balance is split by [crate::merkle_sum_tree::big_uint_to_limbs] in a [crate::merkle_sum_tree::LimbMerkleSumTree], to be summed with [crate::chips::limb_sum::limb_sum::LimbSumChip]
+pub const LIMB_BITS: usize = 128;
@@ -0,0 +1,106 @@
+use crate::merkle_sum_tree::utils::{create_middle_node, padding_nodes};
+use crate::merkle_sum_tree::{Entry, MerkleSumTree, MerkleSumTreeError, Node};
+use std::io::Write;
+
+/// Receives the nodes of a tree built by [build_merkle_sum_tree_streaming], in the order in which they are computed.
+/// The nodes of a level are received in ascending index order, but the nodes of different levels are interleaved.
+pub trait NodeSink<const N_ASSETS: usize> {
+    /// Receives the next node of the given level, where the leaves are at level 0
+    fn write_node(&mut self, level: usize, node: &Node<N_ASSETS>) -> std::io::Result<()>;
+}
+
+/// Collects all the nodes in memory, one vector per level, in the same layout as the nodes of [MerkleSumTree]
+impl<const N_ASSETS: usize> NodeSink<N_ASSETS> for Vec<Vec<Node<N_ASSETS>>> {
+    fn write_node(&mut self, level: usize, node: &Node<N_ASSETS>) -> std::io::Result<()> {
+        if self.len() <= level {
+            self.resize(level + 1, vec![]);
+        }
+        self[level].push(node.clone());
+        Ok(())
+    }
+}
+
+/// Writes the nodes to `W`, for example a temporary file, each as the level encoded as a little endian u32 followed by the hash and the balances encoded as 32 bytes field elements
+pub struct NodeWriter<W: Write>(pub W);
+
+impl<const N_ASSETS: usize, W: Write> NodeSink<N_ASSETS> for NodeWriter<W> {
+    fn write_node(&mut self, level: usize, node: &Node<N_ASSETS>) -> std::io::Result<()> {
+        self.0.write_all(&(level as u32).to_le_bytes())?;
+        self.0.write_all(&node.hash.to_bytes())?;
+        for balance in node.balances.iter() {
+            self.0.write_all(&balance.to_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds a Merkle Sum Tree from an iterator of entries without materializing the entries nor the levels of the tree, so that trees of millions of users can be built on memory-constrained hosts.
+///
+/// The entries are hashed one at a time and two nodes are merged as soon as they are siblings, so that at most one node per level, namely O(log n) nodes, is held in memory. Every computed node is passed to `sink`.
+/// The tree is the same as the one built by [MerkleSumTree::from_entries] with the same entries, including the padding of the leaves up to the next power of two.
+/// Returns the root of the tree and its depth, or [MerkleSumTreeError::EmptyTree] if there are no entries.
+pub fn build_merkle_sum_tree_streaming<const N_ASSETS: usize>(
+    entries: impl IntoIterator<Item = Entry<N_ASSETS>>,
+    sink: &mut impl NodeSink<N_ASSETS>,
+) -> Result<(Node<N_ASSETS>, usize), Box<dyn std::error::Error>> {
+    let max_leaves = 1usize << MerkleSumTree::<N_ASSETS>::MAX_DEPTH;
+
+    // the roots of the complete subtrees built so far, together with their level, from the highest to the lowest level
+    let mut stack: Vec<(usize, Node<N_ASSETS>)> = vec![];
+    let mut leaf_count = 0;
+
+    for entry in entries {
+        leaf_count += 1;
+        if leaf_count > max_leaves {
+            return Err(MerkleSumTreeError::DepthOutOfRange {
+                depth: MerkleSumTree::<N_ASSETS>::MAX_DEPTH + 1,
+                max_depth: MerkleSumTree::<N_ASSETS>::MAX_DEPTH,
+            }
+            .into());
+        }
+
+        let mut level = 0;
+        let mut node = entry.compute_leaf();
+        sink.write_node(level, &node)?;
+
+        // merge the new node with its left sibling as long as there is one
+        while let Some(left) = pop_if_level(&mut stack, level) {
+            node = create_middle_node(&left, &node);
+            level += 1;
+            sink.write_node(level, &node)?;
+        }
+
+        stack.push((level, node));
+    }
+
+    if leaf_count == 0 {
+        return Err(MerkleSumTreeError::EmptyTree.into());
+    }
+
+    let depth = (leaf_count as f64).log2().ceil() as usize;
+    let padding = padding_nodes::<N_ASSETS>(depth);
+
+    // complete the tree from the lowest subtree, pairing a node without right sibling with the root of a subtree of padding leaves
+    let (mut level, mut node) = stack.pop().unwrap();
+    while level < depth {
+        node = match pop_if_level(&mut stack, level) {
+            Some(left) => create_middle_node(&left, &node),
+            None => create_middle_node(&node, &padding[level]),
+        };
+        level += 1;
+        sink.write_node(level, &node)?;
+    }
+
+    Ok((node, depth))
+}
+
+/// Pops the last node of `stack` if it is at the given level
+fn pop_if_level<const N_ASSETS: usize>(
+    stack: &mut Vec<(usize, Node<N_ASSETS>)>,
+    level: usize,
+) -> Option<Node<N_ASSETS>> {
+    match stack.last() {
+        Some((last_level, _)) if *last_level == level => stack.pop().map(|(_, node)| node),
+        _ => None,
+    }
+}
@@ -1,9 +1,21 @@
 #[cfg(test)]
 mod test {
 
-    use crate::merkle_sum_tree::utils::{big_int_to_fp, poseidon_node};
-    use crate::merkle_sum_tree::{Entry, MerkleSumTree, N_ASSETS};
-    use num_bigint::{BigInt, ToBigInt};
+    use crate::chips::poseidon::poseidon_spec::PoseidonSpec;
+    use crate::merkle_sum_tree::utils::{
+        big_int_to_fp, build_merkle_tree_from_leaves, padding_nodes, parse_csv_to_entries,
+        poseidon_entry, poseidon_node, poseidon_spec_id,
+    };
+    use crate::merkle_sum_tree::{
+        big_intify_username, build_merkle_sum_tree_streaming, canonicalize_entries,
+        create_middle_node, create_middle_nodes, fp_to_big_int, fp_to_u128, parse_scaled_balance,
+        sha256_field_elements, Entry, HierarchicalMerkleSumTree, KeyedMerkleSumTree, LeafHasher,
+        LimbMerkleSumTree, MerkleProof, MerkleSumTree, MerkleSumTreeError, NaryMerkleSumTree, Node,
+        NodeWriter, ZeroBalancePolicy, MOD_BITS, N_ASSETS,
+    };
+    use halo2_gadgets::poseidon::primitives::{self as poseidon, ConstantLength};
+    use halo2_proofs::halo2curves::bn256::Fr as Fp;
+    use num_bigint::{BigInt, BigUint, ToBigInt};
 
     #[test]
     fn test_mst() {
@@ -181,4 +193,1439 @@ mod test {
             node_left.balances[1] + node_right.balances[1]
         );
     }
+
+    #[test]
+    fn test_tightest_asset() {
+        // build a tree with three assets
+        let entries = vec![
+            Entry::new("alice".to_string(), [100.into(), 200.into(), 300.into()]).unwrap(),
+            Entry::new("bob".to_string(), [100.into(), 200.into(), 300.into()]).unwrap(),
+            Entry::new("carol".to_string(), [100.into(), 200.into(), 300.into()]).unwrap(),
+            Entry::new("dave".to_string(), [100.into(), 200.into(), 300.into()]).unwrap(),
+        ];
+
+        let merkle_tree = MerkleSumTree::<3>::from_entries(entries).unwrap();
+
+        // liabilities are [400, 800, 1200]
+        assert_eq!(
+            merkle_tree.root().balances,
+            [400.into(), 800.into(), 1200.into()]
+        );
+
+        // the second asset has the thinnest margin
+        let total_assets: Vec<BigUint> = vec![1000u32.into(), 850u32.into(), 1500u32.into()];
+        assert_eq!(
+            merkle_tree.tightest_asset(&total_assets).unwrap(),
+            (1, BigInt::from(50))
+        );
+
+        // an insolvent asset is reported with a negative margin
+        let total_assets: Vec<BigUint> = vec![1000u32.into(), 850u32.into(), 1100u32.into()];
+        assert_eq!(
+            merkle_tree.tightest_asset(&total_assets).unwrap(),
+            (2, BigInt::from(-100))
+        );
+
+        // the assets of a missing asset are reported as an error rather than a panic
+        let total_assets: Vec<BigUint> = vec![1000u32.into(), 850u32.into()];
+        assert_eq!(
+            merkle_tree.tightest_asset(&total_assets),
+            Err(MerkleSumTreeError::AssetCountMismatch {
+                expected: 3,
+                actual: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_poseidon_node_above_24_inputs() {
+        // a node of a tree with 15 assets hashes 32 inputs
+        let left = Node::<15> {
+            hash: Fp::from(1u64),
+            balances: core::array::from_fn(|i| Fp::from(i as u64 + 2)),
+        };
+        let right = Node::<15> {
+            hash: Fp::from(100u64),
+            balances: core::array::from_fn(|i| Fp::from(i as u64 + 101)),
+        };
+
+        let hash_inputs: [Fp; 32] = std::iter::once(left.hash)
+            .chain(left.balances)
+            .chain(std::iter::once(right.hash))
+            .chain(right.balances)
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+
+        let expected =
+            poseidon::Hash::<Fp, PoseidonSpec, ConstantLength<32>, 3, 2>::init().hash(hash_inputs);
+
+        assert_eq!(
+            poseidon_node(left.hash, left.balances, right.hash, right.balances),
+            expected
+        );
+
+        // an odd number of inputs is padded as the ConstantLength domain does
+        let hash_inputs: [Fp; 3] = [Fp::from(1u64), Fp::from(2u64), Fp::from(3u64)];
+        let expected =
+            poseidon::Hash::<Fp, PoseidonSpec, ConstantLength<3>, 3, 2>::init().hash(hash_inputs);
+
+        assert_eq!(
+            poseidon_entry(hash_inputs[0], [hash_inputs[1], hash_inputs[2]]),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_parse_scaled_balance() {
+        // fractional balance scaled to 6 decimals
+        assert_eq!(
+            parse_scaled_balance("1.5", 6).unwrap(),
+            BigUint::from(1_500_000u32)
+        );
+
+        // integer balance scaled to 6 decimals
+        assert_eq!(
+            parse_scaled_balance("42", 6).unwrap(),
+            BigUint::from(42_000_000u32)
+        );
+
+        // balance with exactly the allowed decimals
+        assert_eq!(
+            parse_scaled_balance("0.000001", 6).unwrap(),
+            BigUint::from(1u32)
+        );
+
+        // balance with more fractional digits than allowed
+        assert_eq!(
+            parse_scaled_balance("1.0000001", 6),
+            Err(MerkleSumTreeError::TooManyDecimals {
+                allowed: 6,
+                found: 7
+            })
+        );
+
+        // malformed balances
+        for malformed in ["", "1.", ".5", "-1", "1.2.3", "1,5", "abc"] {
+            assert_eq!(
+                parse_scaled_balance(malformed, 6),
+                Err(MerkleSumTreeError::InvalidBalance(malformed.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_hierarchical_mst() {
+        let sub_tree_1 =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let sub_tree_2 =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16_bigints.csv").unwrap();
+
+        let tree =
+            HierarchicalMerkleSumTree::new(vec![sub_tree_1.clone(), sub_tree_2.clone()]).unwrap();
+
+        // the top-level root commits to the aggregated balances of the sub-trees
+        assert_eq!(
+            tree.root().hash,
+            poseidon_node(
+                sub_tree_1.root().hash,
+                sub_tree_1.root().balances,
+                sub_tree_2.root().hash,
+                sub_tree_2.root().balances,
+            )
+        );
+        assert_eq!(
+            tree.root().balances[0],
+            sub_tree_1.root().balances[0] + sub_tree_2.root().balances[0]
+        );
+
+        // a user of the second sub-tree is proven to be included through both levels
+        let proof = tree.generate_proof(1, 3).unwrap();
+        assert_eq!(proof.sibling_hashes.len(), 4 + 1);
+        assert_eq!(proof.root_hash, tree.root().hash);
+        assert_eq!(proof.entry.username(), sub_tree_2.entries()[3].username());
+        assert!(tree.verify_proof(&proof));
+
+        // the proof against the sub-tree alone doesn't verify against the top-level root
+        let mut sub_tree_proof = sub_tree_2.generate_proof(3).unwrap();
+        sub_tree_proof.root_hash = tree.root().hash;
+        assert!(!tree.verify_proof(&sub_tree_proof));
+
+        // a proof claiming the wrong sub-tree position fails
+        let mut invalid_proof = proof;
+        invalid_proof.path_indices[4] = false;
+        assert!(!tree.verify_proof(&invalid_proof));
+
+        // sub-trees that don't exist can't be proven
+        assert!(tree.generate_proof(2, 0).is_err());
+
+        // a number of sub-trees that is not a power of two is rejected
+        assert!(HierarchicalMerkleSumTree::new(vec![
+            sub_tree_1.clone(),
+            sub_tree_2.clone(),
+            sub_tree_1.clone()
+        ])
+        .is_err());
+
+        // a single sub-tree is rejected
+        assert!(HierarchicalMerkleSumTree::new(vec![sub_tree_1.clone()]).is_err());
+
+        // sub-trees of different depths are rejected, since their proofs would have different lengths
+        let shallow_sub_tree =
+            MerkleSumTree::<N_ASSETS>::from_entries(sub_tree_1.entries()[..4].to_vec()).unwrap();
+        assert_eq!(*shallow_sub_tree.depth(), 2);
+        assert!(HierarchicalMerkleSumTree::new(vec![sub_tree_1, shallow_sub_tree]).is_err());
+    }
+
+    #[test]
+    fn test_zero_balance_policy() {
+        // every other entry of the dataset has all balances equal to zero
+        let entries = parse_csv_to_entries::<_, N_ASSETS>(
+            "src/merkle_sum_tree/csv/entry_16_zero_balances.csv",
+        )
+        .unwrap();
+
+        // Include should keep the zero-balance entries in the tree
+        let tree = MerkleSumTree::build(entries.clone(), ZeroBalancePolicy::Include, None).unwrap();
+        assert_eq!(tree.entries().len(), 16);
+        assert_eq!(*tree.depth(), 4);
+
+        // Exclude should drop them without changing the total balances
+        let tree_excluded =
+            MerkleSumTree::build(entries.clone(), ZeroBalancePolicy::Exclude, None).unwrap();
+        assert_eq!(tree_excluded.entries().len(), 8);
+        assert_eq!(*tree_excluded.depth(), 3);
+        assert_eq!(tree_excluded.root().balances, tree.root().balances);
+        assert!(tree_excluded
+            .entries()
+            .iter()
+            .all(|entry| entry.balances().iter().all(|balance| *balance != 0.into())));
+
+        let proof = tree_excluded.generate_proof(7).unwrap();
+        assert!(tree_excluded.verify_proof(&proof));
+
+        // Error should fail on the first zero-balance entry
+        let error = MerkleSumTree::build(entries, ZeroBalancePolicy::Error, None).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<MerkleSumTreeError>(),
+            Some(&MerkleSumTreeError::ZeroBalance("MBlfbBGI".to_string()))
+        );
+
+        // Error should build the tree when no entry has zero balances
+        let entries =
+            parse_csv_to_entries::<_, N_ASSETS>("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let tree = MerkleSumTree::build(entries, ZeroBalancePolicy::Error, None).unwrap();
+        assert_eq!(tree.root().balances, [556862.into(), 556862.into()]);
+    }
+
+    #[test]
+    fn test_expected_entry_count() {
+        let entries =
+            parse_csv_to_entries::<_, N_ASSETS>("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // the tree is built when the delivered count matches the expected one
+        let tree =
+            MerkleSumTree::build(entries.clone(), ZeroBalancePolicy::Include, Some(16)).unwrap();
+        assert_eq!(tree.entries().len(), 16);
+
+        // an entry lost upstream should make the build fail
+        let error =
+            MerkleSumTree::build(entries[..15].to_vec(), ZeroBalancePolicy::Include, Some(16))
+                .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<MerkleSumTreeError>(),
+            Some(&MerkleSumTreeError::CountMismatch {
+                expected: 16,
+                actual: 15
+            })
+        );
+    }
+
+    #[test]
+    fn test_create_middle_nodes() {
+        let merkle_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let padding = padding_nodes::<N_ASSETS>(*merkle_tree.depth());
+
+        // hashing each level in a single batch should result in the same root
+        let mut level = merkle_tree.leaves().to_vec();
+        for padding_node in padding.iter().take(*merkle_tree.depth()) {
+            level = create_middle_nodes(&level, padding_node);
+        }
+        assert_eq!(level.len(), 1);
+        assert_eq!(level[0].hash, merkle_tree.root().hash);
+        assert_eq!(level[0].balances, merkle_tree.root().balances);
+
+        // the last child of an odd level should be paired with the padding node
+        let odd_level = &merkle_tree.leaves()[..5];
+        let nodes = create_middle_nodes(odd_level, &padding[0]);
+        assert_eq!(nodes.len(), 3);
+        for (node, pair) in nodes.iter().zip(odd_level.chunks(2)) {
+            let expected = create_middle_node(&pair[0], pair.get(1).unwrap_or(&padding[0]));
+            assert_eq!(node.hash, expected.hash);
+            assert_eq!(node.balances, expected.balances);
+        }
+    }
+
+    #[test]
+    fn test_canonicalize_entries() {
+        // the same entries in a different order should canonicalize to the same root
+        let entries =
+            parse_csv_to_entries::<_, N_ASSETS>("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let entries_switched = parse_csv_to_entries::<_, N_ASSETS>(
+            "src/merkle_sum_tree/csv/entry_16_switched_order.csv",
+        )
+        .unwrap();
+
+        let tree = MerkleSumTree::from_entries(canonicalize_entries(entries)).unwrap();
+        let tree_switched =
+            MerkleSumTree::from_entries(canonicalize_entries(entries_switched)).unwrap();
+
+        assert_eq!(tree.root().hash, tree_switched.root().hash);
+
+        // usernames are trimmed and duplicates are merged by summing their balances
+        let entry = |username: &str, balances: [u64; N_ASSETS]| {
+            Entry::new(username.to_string(), balances.map(BigInt::from)).unwrap()
+        };
+
+        let entries = vec![
+            entry("alice", [10, 20]),
+            entry("bob", [1, 2]),
+            entry(" alice ", [5, 5]),
+            entry("carol", [3, 4]),
+            entry("dave", [7, 8]),
+        ];
+        let entries_2 = vec![
+            entry("dave", [7, 8]),
+            entry("carol ", [3, 4]),
+            entry("alice", [15, 25]),
+            entry("bob", [1, 2]),
+        ];
+
+        let canonical = canonicalize_entries(entries);
+        assert_eq!(canonical.len(), 4);
+
+        let alice = canonical
+            .iter()
+            .find(|entry| entry.username() == "alice")
+            .unwrap();
+        assert_eq!(alice.balances(), &[BigInt::from(15), BigInt::from(25)]);
+
+        let tree = MerkleSumTree::from_entries(canonical).unwrap();
+        let tree_2 = MerkleSumTree::from_entries(canonicalize_entries(entries_2)).unwrap();
+        assert_eq!(tree.root().hash, tree_2.root().hash);
+    }
+
+    #[test]
+    fn test_single_entry_tree() {
+        let entry = Entry::new("dxGaEAii".to_string(), [11888.into(), 41163.into()]).unwrap();
+
+        let tree = MerkleSumTree::<N_ASSETS>::from_entries(vec![entry.clone()]).unwrap();
+
+        // the root is the leaf itself
+        assert_eq!(*tree.depth(), 0);
+        assert_eq!(tree.root().hash, entry.compute_leaf().hash);
+        assert_eq!(tree.root().balances, [11888.into(), 41163.into()]);
+        assert!(tree.penultimate_level_data().is_err());
+
+        let proof = tree.generate_proof(0).unwrap();
+        assert!(proof.sibling_hashes.is_empty());
+        assert!(tree.verify_proof(&proof));
+        assert!(tree.generate_proof(1).is_err());
+
+        // an empty tree can't be built
+        assert!(MerkleSumTree::<N_ASSETS>::from_entries(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_build_with_order() {
+        let entries =
+            parse_csv_to_entries::<_, N_ASSETS>("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // order the leaves by descending balance of the first asset
+        let (tree, permutation) = MerkleSumTree::build_with_order(entries.clone(), |entry| {
+            std::cmp::Reverse(entry.balances()[0].clone())
+        })
+        .unwrap();
+
+        let largest_balance = entries
+            .iter()
+            .map(|entry| entry.balances()[0].clone())
+            .max()
+            .unwrap();
+        assert_eq!(tree.entries()[0].balances()[0], largest_balance);
+        assert_eq!(
+            tree.leaves()[0].hash,
+            entries[permutation[0]].compute_leaf().hash
+        );
+
+        // the permutation maps every leaf position back to the original entry
+        for (position, &index) in permutation.iter().enumerate() {
+            assert_eq!(
+                tree.entries()[position].username(),
+                entries[index].username()
+            );
+        }
+        assert!(tree
+            .entries()
+            .windows(2)
+            .all(|pair| pair[0].balances()[0] >= pair[1].balances()[0]));
+
+        // the ordering doesn't change the total balances
+        let unordered_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries).unwrap();
+        assert_eq!(tree.root().balances, unordered_tree.root().balances);
+    }
+
+    #[test]
+    fn test_sorted_mst() {
+        let entries =
+            parse_csv_to_entries::<_, N_ASSETS>("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let tree = MerkleSumTree::<N_ASSETS>::from_entries_sorted(entries.clone()).unwrap();
+
+        // the 16 entries are enclosed by the two sentinels, which don't add to the balances of the root
+        assert_eq!(tree.entries().len(), 18);
+        assert_eq!(*tree.depth(), 5);
+        assert_eq!(tree.entries()[0].username(), "");
+        assert!(tree
+            .entries()
+            .windows(2)
+            .all(|pair| pair[0].username_to_big_int() < pair[1].username_to_big_int()));
+
+        let unsorted_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries.clone()).unwrap();
+        assert_eq!(tree.root().balances, unsorted_tree.root().balances);
+
+        // the absence of a missing username is witnessed by the proofs of its two adjacent entries
+        let (left_proof, right_proof) = tree.generate_exclusion_proof("AAAAAAAA").unwrap();
+        let target = big_intify_username("AAAAAAAA");
+        assert!(left_proof.entry.username_to_big_int() < &target);
+        assert!(&target < right_proof.entry.username_to_big_int());
+        assert!(tree.verify_proof(&left_proof));
+        assert!(tree.verify_proof(&right_proof));
+
+        // there is no exclusion proof for an included username
+        assert!(tree
+            .generate_exclusion_proof(entries[0].username())
+            .is_err());
+
+        // the usernames must be distinct and at most 30 bytes long
+        let mut duplicated_entries = entries.clone();
+        duplicated_entries.push(entries[0].clone());
+        assert!(MerkleSumTree::<N_ASSETS>::from_entries_sorted(duplicated_entries).is_err());
+
+        let mut long_entries = entries;
+        long_entries.push(Entry::new("a".repeat(31), [BigInt::from(1), BigInt::from(1)]).unwrap());
+        assert!(MerkleSumTree::<N_ASSETS>::from_entries_sorted(long_entries).is_err());
+    }
+
+    #[test]
+    fn test_keyed_mst() {
+        let entries =
+            parse_csv_to_entries::<_, N_ASSETS>("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let tree = KeyedMerkleSumTree::<N_ASSETS>::from_entries(entries.clone()).unwrap();
+        let sorted_tree = MerkleSumTree::<N_ASSETS>::from_entries_sorted(entries.clone()).unwrap();
+
+        // the entries are the ones of the sorted tree, but the middle nodes also commit to the keys of their children
+        assert!(tree
+            .entries()
+            .iter()
+            .zip(sorted_tree.entries())
+            .all(|(entry, sorted_entry)| entry.username() == sorted_entry.username()));
+        assert_eq!(*tree.depth(), 5);
+        assert_eq!(tree.root().balances, sorted_tree.root().balances);
+        assert_ne!(tree.root().hash, sorted_tree.root().hash);
+
+        for entry in &entries {
+            let index = tree.index_of(entry.username()).unwrap();
+            let proof = tree.generate_proof(index).unwrap();
+            assert_eq!(proof.entry.username(), entry.username());
+            assert!(proof.verify());
+        }
+        assert!(tree.index_of("AAAAAAAA").is_none());
+
+        // keys of a sibling that put it on the other side of the path don't rebuild the root
+        let mut proof = tree.generate_proof(3).unwrap();
+        proof.sibling_keys[1] = [Fp::zero(); 2];
+        assert!(!proof.verify());
+
+        // a flipped direction contradicts the committed keys of the sibling
+        let mut proof = tree.generate_proof(3).unwrap();
+        proof.path_indices[0] = !proof.path_indices[0];
+        assert!(!proof.verify());
+    }
+
+    #[test]
+    fn test_tree_file_poseidon_spec_id() {
+        let tree = MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let path = std::env::temp_dir().join("summa_tree_spec_id.json");
+        let path = path.to_str().unwrap();
+        tree.to_file(path).unwrap();
+
+        // a tree written with the compiled-in spec is loaded back as is
+        let loaded_tree = MerkleSumTree::<N_ASSETS>::from_file(path).unwrap();
+        assert_eq!(loaded_tree.root().hash, tree.root().hash);
+        assert_eq!(loaded_tree.root().balances, tree.root().balances);
+        let proof = loaded_tree.generate_proof(3).unwrap();
+        assert!(loaded_tree.verify_proof(&proof));
+
+        // a tree tagged with a different spec is rejected
+        let mut serialized: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        serialized["spec_id"] = "poseidon-w3-r2-f8-p57-0x00".into();
+        std::fs::write(path, serialized.to_string()).unwrap();
+
+        let error = MerkleSumTree::<N_ASSETS>::from_file(path).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<MerkleSumTreeError>(),
+            Some(&MerkleSumTreeError::SpecMismatch {
+                expected: poseidon_spec_id(),
+                found: "poseidon-w3-r2-f8-p57-0x00".to_string()
+            })
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_validate_structure() {
+        let tree = MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        assert_eq!(tree.validate_structure(), Ok(()));
+
+        // a tree with padding nodes is valid as well
+        let mut entries = tree.entries().to_vec();
+        entries.truncate(11);
+        let padded_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries).unwrap();
+        assert_eq!(padded_tree.validate_structure(), Ok(()));
+
+        let path = std::env::temp_dir().join("summa_tree_structure.json");
+        let path = path.to_str().unwrap();
+        tree.to_file(path).unwrap();
+        let serialized: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+
+        // the corrupted tree is loaded without checks, while the checked loader rejects it with the same error as the validation
+        let load_corrupted = |corrupt: &dyn Fn(&mut serde_json::Value)| {
+            let mut corrupted = serialized.clone();
+            corrupt(&mut corrupted);
+            std::fs::write(path, corrupted.to_string()).unwrap();
+
+            let corrupted_tree = MerkleSumTree::<N_ASSETS>::from_file_unchecked(path).unwrap();
+            let error = MerkleSumTree::<N_ASSETS>::from_file(path).unwrap_err();
+            assert_eq!(
+                error.downcast_ref::<MerkleSumTreeError>(),
+                corrupted_tree.validate_structure().err().as_ref()
+            );
+            corrupted_tree
+        };
+
+        // a middle node whose hash doesn't match its children
+        let corrupted_tree = load_corrupted(&|serialized| {
+            serialized["nodes"][2][1]["hash"] = serialized["nodes"][2][0]["hash"].clone();
+        });
+        assert_eq!(
+            corrupted_tree.validate_structure(),
+            Err(MerkleSumTreeError::NodeHashMismatch { level: 2, index: 1 })
+        );
+
+        // a middle node whose balance doesn't match the sum of its children
+        let corrupted_tree = load_corrupted(&|serialized| {
+            serialized["nodes"][1][3]["balances"][1] = "1".into();
+        });
+        assert_eq!(
+            corrupted_tree.validate_structure(),
+            Err(MerkleSumTreeError::NodeBalanceMismatch {
+                level: 1,
+                index: 3,
+                asset: 1
+            })
+        );
+
+        // a leaf that doesn't match its entry
+        let corrupted_tree = load_corrupted(&|serialized| {
+            serialized["nodes"][0][5]["balances"][0] = "0".into();
+        });
+        assert_eq!(
+            corrupted_tree.validate_structure(),
+            Err(MerkleSumTreeError::NodeBalanceMismatch {
+                level: 0,
+                index: 5,
+                asset: 0
+            })
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_check_root_sum() {
+        let tree = MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        assert_eq!(tree.check_root_sum(), Ok(()));
+
+        // a root whose balance doesn't match the sum of the entries is reported
+        let path = std::env::temp_dir().join("summa_tree_root_sum.json");
+        let path = path.to_str().unwrap();
+        tree.to_file(path).unwrap();
+
+        let mut serialized: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        serialized["nodes"][*tree.depth()][0]["balances"][1] = "556863".into();
+        std::fs::write(path, serialized.to_string()).unwrap();
+
+        let corrupted_tree = MerkleSumTree::<N_ASSETS>::from_file_unchecked(path).unwrap();
+        assert_eq!(
+            corrupted_tree.check_root_sum(),
+            Err(MerkleSumTreeError::RootSumMismatch {
+                asset: 1,
+                expected: BigInt::from(556862),
+                found: BigInt::from(556863)
+            })
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut merkle_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let (index, balances) = merkle_tree.contains("AtwIxZHo").unwrap();
+        assert_eq!(index, 15);
+        assert_eq!(balances, &[BigInt::from(35479), BigInt::from(31699)]);
+        assert_eq!(
+            merkle_tree.index_of("AtwIxZHo", balances.clone()),
+            Some(index)
+        );
+
+        assert!(merkle_tree.contains("AtwHHHHo").is_none());
+
+        // the appended entries can be looked up as well
+        let entry = Entry::new("carol".to_string(), [BigInt::from(1), BigInt::from(2)]).unwrap();
+        merkle_tree.append(entry).unwrap();
+        assert_eq!(
+            merkle_tree.contains("carol"),
+            Some((16, &[BigInt::from(1), BigInt::from(2)]))
+        );
+
+        // the balances follow the updates of the leaf
+        merkle_tree
+            .update_leaf(15, [BigInt::from(7), BigInt::from(8)])
+            .unwrap();
+        assert_eq!(
+            merkle_tree.contains("AtwIxZHo"),
+            Some((15, &[BigInt::from(7), BigInt::from(8)]))
+        );
+    }
+
+    #[test]
+    fn test_append_batch() {
+        let entries =
+            parse_csv_to_entries::<_, N_ASSETS>("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let new_entries = (0..100u64)
+            .map(|i| Entry::new(format!("user{}", i), [i.into(), (2 * i).into()]).unwrap())
+            .collect::<Vec<_>>();
+
+        let mut batch_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries.clone()).unwrap();
+        batch_tree.append_batch(new_entries.clone()).unwrap();
+
+        let mut sequential_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries.clone()).unwrap();
+        for entry in new_entries.clone() {
+            sequential_tree.append(entry).unwrap();
+        }
+
+        let full_tree =
+            MerkleSumTree::<N_ASSETS>::from_entries([entries, new_entries].concat()).unwrap();
+
+        // the bulk append should match both the sequential appends and the full rebuild
+        assert_eq!(batch_tree.root().hash, sequential_tree.root().hash);
+        assert_eq!(batch_tree.root().hash, full_tree.root().hash);
+        assert_eq!(batch_tree.root().balances, full_tree.root().balances);
+        assert_eq!(*batch_tree.depth(), 7);
+        assert_eq!(batch_tree.entries().len(), 116);
+
+        // the proofs of the old and the new leaves should be valid against the new root
+        for index in [0, 15, 16, 115] {
+            let proof = batch_tree.generate_proof(index).unwrap();
+            assert!(batch_tree.verify_proof(&proof));
+            assert_eq!(proof.root_hash, full_tree.root().hash);
+        }
+    }
+
+    #[test]
+    fn test_partition_sums() {
+        let merkle_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // split the users in two halves
+        let set_a = (0..8).collect::<Vec<_>>();
+        let set_b = (8..16).collect::<Vec<_>>();
+
+        let (sums_a, sums_b) = merkle_tree.partition_sums(&set_a, &set_b).unwrap();
+
+        // the sums of the two halves should add up to the root balances
+        for asset in 0..N_ASSETS {
+            assert_eq!(
+                sums_a[asset] + sums_b[asset],
+                merkle_tree.root().balances[asset]
+            );
+        }
+
+        // a user counted in both sets should be rejected
+        let overlapping_set_b = (7..16).collect::<Vec<_>>();
+        assert_eq!(
+            merkle_tree.partition_sums(&set_a, &overlapping_set_b),
+            Err("The user sets are not disjoint")
+        );
+
+        // a user left out of both sets should be rejected
+        assert_eq!(
+            merkle_tree.partition_sums(&set_a, &set_b[1..]),
+            Err("The user sets do not cover all the users of the tree")
+        );
+    }
+
+    #[test]
+    fn test_sibling_path() {
+        let merkle_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        for index in [0, 5, 10, 15] {
+            let sibling_path = merkle_tree.sibling_path(index).unwrap();
+            let proof = merkle_tree.generate_proof(index).unwrap();
+
+            assert_eq!(sibling_path.len(), *merkle_tree.depth());
+
+            // the siblings should match the ones used by verify_proof to reconstruct the root
+            let mut node = merkle_tree.leaves()[index].clone();
+            for (level, (position, sibling)) in sibling_path.iter().enumerate() {
+                assert_eq!(sibling.hash, proof.sibling_hashes[level]);
+                assert_eq!(sibling.balances, proof.sibling_sums[level]);
+                assert_eq!(*position == 0, proof.path_indices[level]);
+
+                let (left, right) = if *position == 0 {
+                    (sibling, &node)
+                } else {
+                    (&node, sibling)
+                };
+
+                let mut balances = [Fp::zero(); N_ASSETS];
+                for asset in 0..N_ASSETS {
+                    balances[asset] = left.balances[asset] + right.balances[asset];
+                }
+
+                node = Node {
+                    hash: poseidon_node(left.hash, left.balances, right.hash, right.balances),
+                    balances,
+                };
+            }
+
+            assert_eq!(node.hash, merkle_tree.root().hash);
+            assert_eq!(node.balances, merkle_tree.root().balances);
+        }
+
+        // a leaf out of the tree has no sibling path
+        assert!(merkle_tree.sibling_path(16).is_err());
+    }
+
+    #[test]
+    fn test_validate_locates_out_of_range_balance() {
+        let entries =
+            parse_csv_to_entries::<_, N_ASSETS>("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let merkle_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries.clone()).unwrap();
+        assert_eq!(merkle_tree.validate(MOD_BITS), Ok(()));
+
+        // set the second asset balance of the leaf at index 6 to 2^MOD_BITS, just out of range
+        let mut invalid_entries = entries;
+        let mut balances = invalid_entries[6].balances().clone();
+        balances[1] = BigInt::from(1) << MOD_BITS;
+        invalid_entries[6] =
+            Entry::new(invalid_entries[6].username().to_string(), balances).unwrap();
+
+        let invalid_merkle_tree = MerkleSumTree::<N_ASSETS>::from_entries(invalid_entries).unwrap();
+        let username = invalid_merkle_tree.entries()[6].username().to_string();
+        assert_eq!(
+            invalid_merkle_tree.validate(MOD_BITS),
+            Err(MerkleSumTreeError::BalanceOutOfRange {
+                username: username.clone(),
+                asset: 1
+            })
+        );
+
+        // the reported username locates the offending leaf
+        assert_eq!(invalid_merkle_tree.contains(&username).unwrap().0, 6);
+    }
+
+    #[test]
+    fn test_limb_mst() {
+        let entries =
+            parse_csv_to_entries::<_, N_ASSETS>("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let merkle_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries.clone()).unwrap();
+        let limb_tree = LimbMerkleSumTree::<N_ASSETS, 4>::from_entries(entries.clone()).unwrap();
+
+        // the balances of the csv fit in the low limbs, so the high limbs of the root are zero
+        assert_eq!(*limb_tree.depth(), *merkle_tree.depth());
+        assert_eq!(limb_tree.root().balances[0], merkle_tree.root().balances[0]);
+        assert_eq!(limb_tree.root().balances[1], Fp::zero());
+        assert_eq!(limb_tree.root().balances[2], merkle_tree.root().balances[1]);
+        assert_eq!(limb_tree.root().balances[3], Fp::zero());
+
+        for index in 0..entries.len() {
+            let proof = limb_tree.generate_proof(index).unwrap();
+            assert!(limb_tree.verify_proof(&proof));
+        }
+
+        // a proof with a tampered sibling limb is invalid
+        let mut invalid_proof = limb_tree.generate_proof(0).unwrap();
+        invalid_proof.sibling_limbs[0][1] += Fp::from(1);
+        assert!(!limb_tree.verify_proof(&invalid_proof));
+
+        assert!(limb_tree.generate_proof(16).is_err());
+
+        let max_balance = (BigInt::from(1u8) << 256) - 1u8;
+        let entry = |username: &str, balance: BigInt| {
+            Entry::new(username.to_string(), [balance, BigInt::from(0u8)]).unwrap()
+        };
+
+        // a balance that doesn't fit in 256 bits can't be split into limbs
+        let error = LimbMerkleSumTree::<N_ASSETS, 4>::from_entries(vec![entry(
+            "alice",
+            max_balance.clone() + 1u8,
+        )])
+        .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<MerkleSumTreeError>(),
+            Some(&MerkleSumTreeError::BalanceOutOfRange {
+                username: "alice".to_string(),
+                asset: 0
+            })
+        );
+
+        // a total that doesn't fit in 256 bits is rejected
+        assert!(LimbMerkleSumTree::<N_ASSETS, 4>::from_entries(vec![
+            entry("alice", max_balance),
+            entry("bob", BigInt::from(1u8)),
+        ])
+        .is_err());
+
+        // the number of limbs must be twice the number of assets
+        assert!(LimbMerkleSumTree::<N_ASSETS, 3>::from_entries(entries).is_err());
+    }
+
+    #[test]
+    fn test_nary_mst() {
+        let entries =
+            parse_csv_to_entries::<_, N_ASSETS>("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let merkle_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries.clone()).unwrap();
+
+        // a binary tree is the same as the MerkleSumTree
+        let binary_tree = NaryMerkleSumTree::<N_ASSETS, 2>::from_entries(entries.clone()).unwrap();
+        assert_eq!(binary_tree.root().hash, merkle_tree.root().hash);
+        assert_eq!(*binary_tree.depth(), *merkle_tree.depth());
+
+        // a 4-ary tree of 16 entries has 2 levels and the same root balances
+        let quaternary_tree =
+            NaryMerkleSumTree::<N_ASSETS, 4>::from_entries(entries.clone()).unwrap();
+        assert_eq!(*quaternary_tree.depth(), 2);
+        assert_eq!(quaternary_tree.root().balances, merkle_tree.root().balances);
+        assert_ne!(quaternary_tree.root().hash, merkle_tree.root().hash);
+
+        for index in 0..entries.len() {
+            let proof = quaternary_tree.generate_proof(index).unwrap();
+            assert_eq!(proof.sibling_hashes[0].len(), 3);
+            assert!(quaternary_tree.verify_proof(&proof));
+        }
+
+        // the missing children of the last node are empty nodes
+        let partial_tree =
+            NaryMerkleSumTree::<N_ASSETS, 8>::from_entries(entries[..5].to_vec()).unwrap();
+        assert_eq!(*partial_tree.depth(), 1);
+        let proof = partial_tree.generate_proof(4).unwrap();
+        assert!(partial_tree.verify_proof(&proof));
+
+        // a proof with a tampered sibling balance is invalid
+        let mut invalid_proof = quaternary_tree.generate_proof(0).unwrap();
+        invalid_proof.sibling_sums[0][0][0] += Fp::from(1);
+        assert!(!quaternary_tree.verify_proof(&invalid_proof));
+
+        assert!(quaternary_tree.generate_proof(16).is_err());
+    }
+
+    #[test]
+    fn test_mst_from_csv() {
+        // a valid CSV file builds the same tree as MerkleSumTree::new
+        let merkle_tree =
+            MerkleSumTree::<N_ASSETS>::from_csv("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let expected_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        assert_eq!(merkle_tree.root().hash, expected_tree.root().hash);
+        assert_eq!(merkle_tree.root().balances, expected_tree.root().balances);
+
+        let error_of = |path: &str| {
+            *MerkleSumTree::<N_ASSETS>::from_csv(path)
+                .unwrap_err()
+                .downcast::<MerkleSumTreeError>()
+                .unwrap()
+        };
+
+        // the second row has a single balance
+        assert!(matches!(
+            error_of("src/merkle_sum_tree/csv/entry_4_malformed.csv"),
+            MerkleSumTreeError::MalformedRow { row: 2, .. }
+        ));
+
+        assert_eq!(
+            error_of("src/merkle_sum_tree/csv/entry_4_negative.csv"),
+            MerkleSumTreeError::NegativeBalance {
+                username: "rWMCcGAl".to_string(),
+                asset: 1
+            }
+        );
+
+        // the first balance of the first user is 2^MOD_BITS
+        assert_eq!(
+            error_of("src/merkle_sum_tree/csv/entry_16_overflow.csv"),
+            MerkleSumTreeError::BalanceOutOfRange {
+                username: "dxGaEAii".to_string(),
+                asset: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_update_leaf() {
+        let mut merkle_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let root = merkle_tree.root().clone();
+
+        let new_balances = [BigInt::from(1000), BigInt::from(2000)];
+        let (old_root, new_root) = merkle_tree.update_leaf(7, new_balances.clone()).unwrap();
+
+        assert_eq!(old_root.hash, root.hash);
+        assert_ne!(new_root.hash, root.hash);
+        assert_eq!(new_root.hash, merkle_tree.root().hash);
+
+        // the updated tree matches the one rebuilt from scratch
+        let rebuilt_tree =
+            MerkleSumTree::<N_ASSETS>::from_entries(merkle_tree.entries().to_vec()).unwrap();
+        assert_eq!(new_root.hash, rebuilt_tree.root().hash);
+        assert_eq!(new_root.balances, rebuilt_tree.root().balances);
+        assert!(merkle_tree.verify_proof(&merkle_tree.generate_proof(7).unwrap()));
+
+        // an update with the same balances doesn't change the root
+        let (old_root, new_root) = merkle_tree.update_leaf(7, new_balances).unwrap();
+        assert_eq!(old_root.hash, new_root.hash);
+
+        // the index must be within the leaves of the tree
+        assert!(merkle_tree
+            .update_leaf(16, [BigInt::from(1), BigInt::from(1)])
+            .is_err());
+    }
+
+    #[test]
+    fn test_tree_save_and_load() {
+        let tree = MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let path = std::env::temp_dir().join("summa_tree.bin");
+        let path = path.to_str().unwrap();
+        tree.save(path).unwrap();
+
+        // the field elements round-trip exactly
+        let loaded_tree = MerkleSumTree::<N_ASSETS>::load(path).unwrap();
+        assert_eq!(loaded_tree.root().hash, tree.root().hash);
+        assert_eq!(loaded_tree.root().balances, tree.root().balances);
+        assert_eq!(*loaded_tree.depth(), *tree.depth());
+        for (loaded_leaf, leaf) in loaded_tree.leaves().iter().zip(tree.leaves()) {
+            assert_eq!(loaded_leaf.hash, leaf.hash);
+            assert_eq!(loaded_leaf.balances, leaf.balances);
+        }
+        for (loaded_entry, entry) in loaded_tree.entries().iter().zip(tree.entries()) {
+            assert_eq!(loaded_entry.username(), entry.username());
+            assert_eq!(loaded_entry.balances(), entry.balances());
+        }
+        let proof = loaded_tree.generate_proof(3).unwrap();
+        assert!(loaded_tree.verify_proof(&proof));
+
+        // a corrupted file is rejected
+        let mut bytes = std::fs::read(path).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 1;
+        std::fs::write(path, &bytes).unwrap();
+
+        let error = MerkleSumTree::<N_ASSETS>::load(path).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<MerkleSumTreeError>(),
+            Some(MerkleSumTreeError::CorruptedTree(_))
+        ));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_non_power_of_two_entries() {
+        for n in [1, 3, 5, 1000] {
+            let entries = (0..n)
+                .map(|i| {
+                    Entry::new(format!("user{}", i), [BigInt::from(i), BigInt::from(2 * i)])
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+
+            let tree = MerkleSumTree::<N_ASSETS>::from_entries(entries).unwrap();
+            let depth = *tree.depth();
+            assert_eq!(1 << depth, n.next_power_of_two());
+            assert_eq!(tree.leaf_count(), n);
+
+            // the padding leaves don't inflate the balances of the root
+            let sum = (0..n as u64).sum::<u64>();
+            assert_eq!(tree.root().balances, [Fp::from(sum), Fp::from(2 * sum)]);
+
+            // the root is the same as the one of the tree built on top of the leaves explicitly padded to the next power of two
+            let mut padded_leaves = tree.leaves().to_vec();
+            padded_leaves.resize(1 << depth, padding_nodes::<N_ASSETS>(0)[0].clone());
+            let mut nodes = vec![];
+            let padded_root =
+                build_merkle_tree_from_leaves(padded_leaves, depth, &mut nodes).unwrap();
+            assert_eq!(padded_root.hash, tree.root().hash);
+            assert_eq!(padded_root.balances, tree.root().balances);
+
+            for index in 0..n {
+                let proof = tree.generate_proof(index).unwrap();
+                assert!(tree.verify_proof(&proof));
+            }
+
+            // the proofs for the padding leaves are rejected
+            for index in n..1 << depth {
+                assert!(tree.generate_proof(index).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_merkle_proof_verify() {
+        let merkle_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let root = merkle_tree.root();
+
+        for index in 0..16 {
+            let proof = merkle_tree.generate_proof(index).unwrap();
+            assert!(proof.verify(root));
+        }
+
+        let proof = merkle_tree.generate_proof(0).unwrap();
+
+        // the proof doesn't verify against the root of another tree
+        let other_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16_switched_order.csv")
+                .unwrap();
+        assert!(!proof.verify(other_tree.root()));
+
+        // the proof doesn't verify against a root with the right hash but different balances
+        let mut inflated_root = root.clone();
+        inflated_root.balances[0] += Fp::from(1);
+        assert!(!proof.verify(&inflated_root));
+
+        // a tampered sibling sum breaks the recomputed root
+        let mut invalid_proof = proof.clone();
+        invalid_proof.sibling_sums[1][1] += Fp::from(1);
+        assert!(!invalid_proof.verify(root));
+    }
+
+    #[test]
+    fn test_total_liabilities() {
+        let merkle_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        assert_eq!(merkle_tree.total_liabilities().unwrap(), [556862, 556862]);
+
+        // the largest u128 is converted exactly
+        let max_balance = BigInt::from(u128::MAX);
+        let entries = vec![
+            Entry::new("alice".to_string(), [BigInt::from(1), max_balance.clone()]).unwrap(),
+            Entry::new("bob".to_string(), [BigInt::from(2), BigInt::from(0)]).unwrap(),
+        ];
+        let merkle_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries).unwrap();
+        assert_eq!(merkle_tree.total_liabilities().unwrap(), [3, u128::MAX]);
+
+        // a total beyond 128 bits is an error rather than being truncated
+        let entries = vec![
+            Entry::new("alice".to_string(), [BigInt::from(1), max_balance]).unwrap(),
+            Entry::new("bob".to_string(), [BigInt::from(2), BigInt::from(1)]).unwrap(),
+        ];
+        let merkle_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries).unwrap();
+        assert_eq!(
+            merkle_tree.total_liabilities().unwrap_err(),
+            MerkleSumTreeError::LiabilitiesOutOfRange { asset: 1 }
+        );
+        assert_eq!(fp_to_u128(&-Fp::one()), None);
+    }
+
+    #[test]
+    fn test_merkle_proof_json() {
+        let merkle_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let proof = merkle_tree.generate_proof(5).unwrap();
+
+        let parsed_proof = MerkleProof::<N_ASSETS>::from_json(&proof.to_json().unwrap()).unwrap();
+        assert_eq!(parsed_proof.root_hash, proof.root_hash);
+        assert_eq!(parsed_proof.entry.username(), proof.entry.username());
+        assert_eq!(parsed_proof.sibling_sums, proof.sibling_sums);
+        assert_eq!(parsed_proof.path_indices, proof.path_indices);
+        assert!(parsed_proof.verify(merkle_tree.root()));
+
+        // a negative field element is rejected instead of failing the conversion
+        let json = proof.to_json().unwrap().replacen(
+            &format!("\"{}\"", fp_to_big_int(&proof.sibling_hashes[0])),
+            "\"-1\"",
+            1,
+        );
+        assert!(MerkleProof::<N_ASSETS>::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let entries =
+            parse_csv_to_entries::<_, N_ASSETS>("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        // the left shard is full, the right one has padding leaves
+        let left = MerkleSumTree::<N_ASSETS>::from_entries(entries[..8].to_vec()).unwrap();
+        let right = MerkleSumTree::<N_ASSETS>::from_entries(entries[8..13].to_vec()).unwrap();
+        let (left_root, right_root) = (left.root().clone(), right.root().clone());
+
+        let merged_tree = MerkleSumTree::merge(left, right).unwrap();
+        assert_eq!(*merged_tree.depth(), 4);
+
+        // the merged root sums the balances of the roots of the shards
+        for asset in 0..N_ASSETS {
+            assert_eq!(
+                merged_tree.root().balances[asset],
+                left_root.balances[asset] + right_root.balances[asset]
+            );
+        }
+
+        // it is the same as the tree built from scratch with the entries of both shards
+        let tree = MerkleSumTree::<N_ASSETS>::from_entries(entries[..13].to_vec()).unwrap();
+        assert_eq!(merged_tree.root().hash, tree.root().hash);
+
+        // the leaves of either shard are proven through the merged root, with a path longer by one level
+        for index in [0, 7, 8, 12] {
+            let proof = merged_tree.generate_proof(index).unwrap();
+            assert_eq!(proof.sibling_hashes.len(), 4);
+            assert!(proof.verify(merged_tree.root()));
+        }
+        let proof = merged_tree.generate_proof(12).unwrap();
+        assert_eq!(proof.sibling_hashes[3], left_root.hash);
+        assert_eq!(proof.sibling_sums[3], left_root.balances);
+        assert_eq!(merged_tree.contains(entries[12].username()).unwrap().0, 12);
+        assert!(merged_tree.generate_proof(13).is_err());
+
+        // the left shard must be full and the shards must have the same depth
+        let partial_left = MerkleSumTree::<N_ASSETS>::from_entries(entries[..7].to_vec()).unwrap();
+        let right = MerkleSumTree::<N_ASSETS>::from_entries(entries[8..16].to_vec()).unwrap();
+        assert_eq!(
+            MerkleSumTree::merge(partial_left, right.clone()).unwrap_err(),
+            MerkleSumTreeError::MergeLeftTreeNotFull {
+                entries: 7,
+                capacity: 8
+            }
+        );
+
+        let shallow_left = MerkleSumTree::<N_ASSETS>::from_entries(entries[..4].to_vec()).unwrap();
+        assert_eq!(
+            MerkleSumTree::merge(shallow_left, right.clone()).unwrap_err(),
+            MerkleSumTreeError::MergeDepthMismatch { left: 2, right: 3 }
+        );
+
+        // a username can't appear in both shards
+        let left = MerkleSumTree::<N_ASSETS>::from_entries(entries[..8].to_vec()).unwrap();
+        let overlapping_right =
+            MerkleSumTree::<N_ASSETS>::from_entries(entries[7..15].to_vec()).unwrap();
+        assert_eq!(
+            MerkleSumTree::merge(left, overlapping_right).unwrap_err(),
+            MerkleSumTreeError::DuplicateUsername(entries[7].username().to_string())
+        );
+    }
+
+    #[test]
+    fn test_adversarial_inputs_dont_panic() {
+        // a negative balance is rejected when creating the entry instead of panicking when computing the leaf
+        assert!(
+            Entry::<N_ASSETS>::new("alice".to_string(), [BigInt::from(1), BigInt::from(-1)])
+                .is_err()
+        );
+
+        let merkle_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let proof = merkle_tree.generate_proof(0).unwrap();
+
+        // a proof with a missing path index is rejected instead of being indexed out of bounds
+        let mut malformed_proof = proof.clone();
+        malformed_proof.path_indices.pop();
+        assert!(!merkle_tree.verify_proof(&malformed_proof));
+        assert!(!malformed_proof.verify(merkle_tree.root()));
+        assert_eq!(
+            malformed_proof.path_nodes().len(),
+            proof.path_nodes().len() - 1
+        );
+
+        // an out of range index is an error
+        assert!(merkle_tree.generate_proof(16).is_err());
+    }
+
+    #[test]
+    fn test_merkle_proof_path_nodes() {
+        let merkle_tree =
+            MerkleSumTree::<N_ASSETS>::new("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+
+        let path_nodes = merkle_tree.generate_proof(5).unwrap().path_nodes();
+
+        // the path goes from the leaf up to the root
+        assert_eq!(path_nodes.len(), merkle_tree.depth() + 1);
+        assert_eq!(path_nodes[0].hash, merkle_tree.leaves()[5].hash);
+        assert_eq!(path_nodes[4].hash, merkle_tree.root().hash);
+        assert_eq!(path_nodes[4].balances, merkle_tree.root().balances);
+
+        // the leaves 4 and 5 are siblings, so their paths share every node above the leaves
+        let sibling_path_nodes = merkle_tree.generate_proof(4).unwrap().path_nodes();
+        assert_ne!(sibling_path_nodes[0].hash, path_nodes[0].hash);
+        for level in 1..=4 {
+            assert_eq!(sibling_path_nodes[level].hash, path_nodes[level].hash);
+            assert_eq!(
+                sibling_path_nodes[level].balances,
+                path_nodes[level].balances
+            );
+        }
+
+        // the parent of the leaves 4 and 5 is the sibling of the first level of the path of the leaf 6
+        let proof = merkle_tree.generate_proof(6).unwrap();
+        assert!(proof.path_indices[1]);
+        assert_eq!(proof.sibling_hashes[1], path_nodes[1].hash);
+    }
+
+    #[test]
+    fn test_entry_new_checked() {
+        let username = "dxGaEAii".to_string();
+
+        let max_balance = (BigInt::from(1) << MOD_BITS) - 1;
+        let entry = Entry::<N_ASSETS>::new_checked(
+            username.clone(),
+            [max_balance.clone(), BigInt::from(0)],
+            MOD_BITS,
+        )
+        .unwrap();
+        assert_eq!(entry.balances()[0], max_balance);
+
+        // a negative balance is rejected instead of being wrapped to p - 1
+        assert_eq!(
+            Entry::<N_ASSETS>::new_checked(
+                username.clone(),
+                [BigInt::from(1), BigInt::from(-1)],
+                MOD_BITS
+            )
+            .unwrap_err(),
+            MerkleSumTreeError::NegativeBalance {
+                username: username.clone(),
+                asset: 1
+            }
+        );
+
+        // p - 1, namely the field representation of -1, exceeds the bit width of the tree
+        assert_eq!(
+            Entry::<N_ASSETS>::new_checked(
+                username.clone(),
+                [fp_to_big_int(&-Fp::one()), BigInt::from(1)],
+                MOD_BITS
+            )
+            .unwrap_err(),
+            MerkleSumTreeError::BalanceOutOfRange {
+                username: username.clone(),
+                asset: 0
+            }
+        );
+
+        assert_eq!(
+            Entry::<N_ASSETS>::new_checked(
+                username.clone(),
+                [BigInt::from(1), BigInt::from(1) << 64],
+                64
+            )
+            .unwrap_err(),
+            MerkleSumTreeError::BalanceOutOfRange { username, asset: 1 }
+        );
+    }
+
+    fn generated_entries(n: usize) -> impl Iterator<Item = Entry<N_ASSETS>> {
+        (0..n).map(|i| {
+            Entry::new(format!("user{}", i), [BigInt::from(i), BigInt::from(2 * i)]).unwrap()
+        })
+    }
+
+    /// Checks that the streaming builder produces the same tree as the eager one for `n` entries
+    fn assert_streaming_tree_matches_eager(n: usize) {
+        let tree = MerkleSumTree::<N_ASSETS>::from_entries(generated_entries(n).collect()).unwrap();
+
+        let mut levels: Vec<Vec<Node<N_ASSETS>>> = vec![];
+        let (root, depth) =
+            build_merkle_sum_tree_streaming(generated_entries(n), &mut levels).unwrap();
+
+        assert_eq!(root.hash, tree.root().hash);
+        assert_eq!(root.balances, tree.root().balances);
+        assert_eq!(depth, *tree.depth());
+
+        assert_eq!(levels.len(), depth + 1);
+        for (level, nodes) in levels.iter().enumerate() {
+            assert_eq!(nodes.len(), (n + (1 << level) - 1) >> level);
+        }
+        for (leaf, expected_leaf) in levels[0].iter().zip(tree.leaves()) {
+            assert_eq!(leaf.hash, expected_leaf.hash);
+        }
+    }
+
+    #[test]
+    fn test_streaming_builder() {
+        for n in [1, 2, 5, 100] {
+            assert_streaming_tree_matches_eager(n);
+        }
+
+        // the nodes can be written to a file instead of being held in memory
+        let mut writer = NodeWriter(vec![]);
+        let (root, _) = build_merkle_sum_tree_streaming(generated_entries(5), &mut writer).unwrap();
+        let tree = MerkleSumTree::<N_ASSETS>::from_entries(generated_entries(5).collect()).unwrap();
+        assert_eq!(root.hash, tree.root().hash);
+        // 5 leaves, 3 + 2 + 1 middle nodes, each with its level, hash and balances
+        assert_eq!(writer.0.len(), 11 * (4 + 32 * (1 + N_ASSETS)));
+
+        assert!(build_merkle_sum_tree_streaming(generated_entries(0), &mut writer).is_err());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_builder() {
+        for n in [1, 2, 5, 16, 100] {
+            let entries = generated_entries(n).collect::<Vec<_>>();
+
+            let tree = MerkleSumTree::<N_ASSETS>::from_entries(entries.clone()).unwrap();
+            let parallel_tree = MerkleSumTree::<N_ASSETS>::from_entries_parallel(entries).unwrap();
+
+            assert_eq!(
+                parallel_tree.root().hash.to_bytes(),
+                tree.root().hash.to_bytes()
+            );
+            assert_eq!(parallel_tree.root().balances, tree.root().balances);
+            assert_eq!(parallel_tree.depth(), tree.depth());
+
+            // the nodes keep their order, so the proofs are the same
+            for index in [0, n - 1] {
+                assert_eq!(
+                    parallel_tree.generate_proof(index).unwrap().sibling_hashes,
+                    tree.generate_proof(index).unwrap().sibling_hashes
+                );
+            }
+        }
+
+        assert!(MerkleSumTree::<N_ASSETS>::from_entries_parallel(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let is_empty_tree_error = |error: Box<dyn std::error::Error>| {
+            error.downcast_ref::<MerkleSumTreeError>() == Some(&MerkleSumTreeError::EmptyTree)
+        };
+
+        // every builder returns an error rather than panicking or committing to an undefined root
+        assert!(is_empty_tree_error(
+            MerkleSumTree::<N_ASSETS>::from_entries(vec![]).unwrap_err()
+        ));
+        assert!(is_empty_tree_error(
+            NaryMerkleSumTree::<N_ASSETS, 4>::from_entries(vec![]).unwrap_err()
+        ));
+
+        let mut levels: Vec<Vec<Node<N_ASSETS>>> = vec![];
+        assert!(is_empty_tree_error(
+            build_merkle_sum_tree_streaming(generated_entries(0), &mut levels).unwrap_err()
+        ));
+
+        let mut nodes = vec![];
+        assert!(is_empty_tree_error(
+            build_merkle_tree_from_leaves::<N_ASSETS>(vec![], 0, &mut nodes).unwrap_err()
+        ));
+
+        // excluding all the entries with a zero balance may leave no entries
+        let zero_entries =
+            vec![Entry::new("alice".to_string(), [BigInt::from(0), BigInt::from(0)]).unwrap()];
+        assert!(is_empty_tree_error(
+            MerkleSumTree::build(zero_entries, ZeroBalancePolicy::Exclude, None).unwrap_err()
+        ));
+    }
+
+    // run with `cargo test --release -- --ignored`, building the trees takes long in debug mode
+    #[test]
+    #[ignore]
+    fn test_streaming_builder_2_16() {
+        assert_streaming_tree_matches_eager(1 << 16);
+    }
+
+    #[test]
+    fn test_sha256_leaf_hasher() {
+        // the digest of the empty message, e3b0c442...b855, without its most significant byte
+        let empty_digest = BigInt::parse_bytes(
+            b"b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            16,
+        )
+        .unwrap();
+        assert_eq!(fp_to_big_int(&sha256_field_elements(&[])), empty_digest);
+
+        let entries =
+            parse_csv_to_entries::<_, N_ASSETS>("src/merkle_sum_tree/csv/entry_16.csv").unwrap();
+        let poseidon_tree = MerkleSumTree::<N_ASSETS>::from_entries(entries.clone()).unwrap();
+        let mut tree = MerkleSumTree::<N_ASSETS>::from_entries_with_leaf_hasher(
+            entries.clone(),
+            LeafHasher::Sha256,
+        )
+        .unwrap();
+        assert_eq!(tree.leaf_hasher(), LeafHasher::Sha256);
+
+        // only the hashes of the leaves change, the middle nodes are still hashed with poseidon
+        assert_eq!(
+            tree.leaves()[0].hash,
+            entries[0].compute_leaf_with(LeafHasher::Sha256).hash
+        );
+        assert_ne!(tree.leaves()[0].hash, poseidon_tree.leaves()[0].hash);
+        assert_ne!(tree.root().hash, poseidon_tree.root().hash);
+        assert_eq!(tree.root().balances, poseidon_tree.root().balances);
+
+        let proof = tree.generate_proof(3).unwrap();
+        assert_eq!(proof.leaf_hasher, LeafHasher::Sha256);
+        assert!(proof.verify(tree.root()));
+        assert_eq!(
+            tree.index_of(entries[3].username(), entries[3].balances().clone()),
+            Some(3)
+        );
+
+        // an updated leaf is hashed with sha256 as well
+        tree.update_leaf(3, [BigInt::from(1), BigInt::from(2)])
+            .unwrap();
+        let rebuilt_tree = MerkleSumTree::<N_ASSETS>::from_entries_with_leaf_hasher(
+            tree.entries().to_vec(),
+            LeafHasher::Sha256,
+        )
+        .unwrap();
+        assert_eq!(tree.root().hash, rebuilt_tree.root().hash);
+
+        // the hasher is preserved by the json format, while the binary format only supports poseidon leaves
+        let path = std::env::temp_dir().join("summa_tree_sha256_leaves.json");
+        let path = path.to_str().unwrap();
+        tree.to_file(path).unwrap();
+        let loaded_tree = MerkleSumTree::<N_ASSETS>::from_file(path).unwrap();
+        assert_eq!(loaded_tree.leaf_hasher(), LeafHasher::Sha256);
+        assert!(loaded_tree.generate_proof(3).unwrap().verify(tree.root()));
+        std::fs::remove_file(path).unwrap();
+
+        let path = std::env::temp_dir().join("summa_tree_sha256_leaves.bin");
+        assert!(tree.save(path.to_str().unwrap()).is_err());
+    }
 }
@@ -1,24 +1,89 @@
-use crate::merkle_sum_tree::utils::create_middle_node::create_middle_node;
-use crate::merkle_sum_tree::{Entry, Node};
+use crate::merkle_sum_tree::utils::create_middle_node::{create_middle_node, create_middle_nodes};
+use crate::merkle_sum_tree::utils::hash::poseidon_padding_leaf;
+use crate::merkle_sum_tree::{Entry, LeafHasher, MerkleSumTreeError, Node};
 use halo2_proofs::halo2curves::bn256::Fr as Fp;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(not(target_arch = "wasm32"))]
 use std::thread;
 
 pub fn build_merkle_tree_from_entries<const N_ASSETS: usize>(
     entries: &[Entry<N_ASSETS>],
     depth: usize,
     nodes: &mut Vec<Vec<Node<N_ASSETS>>>,
+    leaf_hasher: LeafHasher,
 ) -> Result<Node<N_ASSETS>, Box<dyn std::error::Error>> {
     let n = entries.len();
 
-    let mut tree: Vec<Vec<Node<N_ASSETS>>> = Vec::with_capacity(depth + 1);
-
-    tree.push(vec![
+    let mut leaves = vec![
         Node {
             hash: Fp::from(0),
             balances: [Fp::from(0); N_ASSETS]
         };
         n
-    ]);
+    ];
+
+    build_leaves_level(entries, &mut leaves, leaf_hasher);
+
+    build_merkle_tree_from_leaves(leaves, depth, nodes)
+}
+
+/// Same as [build_merkle_tree_from_entries], but the leaves and the nodes of each level are hashed in parallel on the rayon thread pool.
+/// The nodes are collected in the same order as the sequential builder, so the tree is identical.
+#[cfg(feature = "rayon")]
+pub fn build_merkle_tree_from_entries_parallel<const N_ASSETS: usize>(
+    entries: &[Entry<N_ASSETS>],
+    depth: usize,
+    nodes: &mut Vec<Vec<Node<N_ASSETS>>>,
+    leaf_hasher: LeafHasher,
+) -> Result<Node<N_ASSETS>, Box<dyn std::error::Error>> {
+    if entries.is_empty() {
+        return Err(MerkleSumTreeError::EmptyTree.into());
+    }
+
+    let padding = padding_nodes(depth);
+
+    let mut tree: Vec<Vec<Node<N_ASSETS>>> = Vec::with_capacity(depth + 1);
+
+    tree.push(
+        entries
+            .par_iter()
+            .map(|entry| entry.compute_leaf_with(leaf_hasher))
+            .collect(),
+    );
+
+    for level in 1..=depth {
+        let _span = tracing::debug_span!("hash tree level", level).entered();
+
+        let level_nodes = tree[level - 1]
+            .par_chunks(2)
+            .map(|pair| create_middle_node(&pair[0], pair.get(1).unwrap_or(&padding[level - 1])))
+            .collect();
+        tree.push(level_nodes);
+    }
+
+    let root = tree[depth][0].clone();
+    *nodes = tree;
+    Ok(root)
+}
+
+/// Builds the tree on top of leaves that have already been computed, for example the roots of other Merkle Sum Trees.
+/// Returns [MerkleSumTreeError::EmptyTree] if there are no leaves.
+pub fn build_merkle_tree_from_leaves<const N_ASSETS: usize>(
+    leaves: Vec<Node<N_ASSETS>>,
+    depth: usize,
+    nodes: &mut Vec<Vec<Node<N_ASSETS>>>,
+) -> Result<Node<N_ASSETS>, Box<dyn std::error::Error>> {
+    if leaves.is_empty() {
+        return Err(MerkleSumTreeError::EmptyTree.into());
+    }
+
+    let n = leaves.len();
+    let padding = padding_nodes(depth);
+
+    let mut tree: Vec<Vec<Node<N_ASSETS>>> = Vec::with_capacity(depth + 1);
+
+    tree.push(leaves);
 
     for _ in 1..=depth {
         let previous_level = tree.last().unwrap();
@@ -33,10 +98,11 @@ pub fn build_merkle_tree_from_entries<const N_ASSETS: usize>(
         ]);
     }
 
-    build_leaves_level(entries, &mut tree);
-
     for level in 1..=depth {
-        build_middle_level(level, &mut tree, n)
+        let _span = tracing::debug_span!("hash tree level", level).entered();
+
+        build_middle_level(level, &mut tree, n, &padding[level - 1]);
+        tracing::debug!(nodes = tree[level].len(), "hashed tree level");
     }
 
     let root = tree[depth][0].clone();
@@ -44,9 +110,84 @@ pub fn build_merkle_tree_from_entries<const N_ASSETS: usize>(
     Ok(root)
 }
 
+/// Recomputes the middle nodes of `nodes` on top of its leaves, given that only the leaves starting from `first_dirty_leaf` have changed.
+/// The levels are grown to fit the leaves and a new level is added on top when the tree gets deeper, so that the leaves can be appended in bulk and the affected middle nodes are computed only once.
+/// Returns the new root of the tree.
+pub fn update_merkle_tree_from_leaf<const N_ASSETS: usize>(
+    first_dirty_leaf: usize,
+    depth: usize,
+    nodes: &mut Vec<Vec<Node<N_ASSETS>>>,
+) -> Node<N_ASSETS> {
+    nodes.truncate(depth + 1);
+    let padding = padding_nodes(depth);
+
+    for level in 1..=depth {
+        let nodes_in_level = (nodes[level - 1].len() + 1) / 2;
+
+        if nodes.len() == level {
+            nodes.push(vec![]);
+        }
+        nodes[level].resize(nodes_in_level, padding[level].clone());
+
+        // the dirty nodes of the level are hashed in a single batch
+        let first_dirty_node = (first_dirty_leaf >> level).min(nodes_in_level);
+        let children = &nodes[level - 1];
+        let dirty_children = &children[(2 * first_dirty_node).min(children.len())..];
+        let dirty_nodes = create_middle_nodes(dirty_children, &padding[level - 1]);
+        nodes[level].truncate(first_dirty_node);
+        nodes[level].extend(dirty_nodes);
+    }
+
+    nodes[depth][0].clone()
+}
+
+/// Recomputes the middle nodes of `nodes` on the path from the leaf at `index` up to the root, given that only that leaf has changed.
+/// Only one node per level is recomputed, so the update costs O(depth) hashes. Returns the new root of the tree.
+pub fn update_merkle_path_from_leaf<const N_ASSETS: usize>(
+    index: usize,
+    depth: usize,
+    nodes: &mut [Vec<Node<N_ASSETS>>],
+) -> Node<N_ASSETS> {
+    let padding = padding_nodes(depth);
+
+    for level in 1..=depth {
+        let node_index = index >> level;
+        let children = &nodes[level - 1];
+        let node = create_middle_node(
+            &children[2 * node_index],
+            children
+                .get(2 * node_index + 1)
+                .unwrap_or(&padding[level - 1]),
+        );
+        nodes[level][node_index] = node;
+    }
+
+    nodes[depth][0].clone()
+}
+
+/// Returns the roots of the subtrees made only of padding leaves, from a single padding leaf at index 0 up to a subtree of 2^depth padding leaves at index `depth`.
+///
+/// The leaves of a tree are padded up to the next power of two with padding leaves, namely the hash of a fixed sentinel with zero balances, so that the padding doesn't change the balances of the root.
+/// The padding leaves are not stored: the node at index `level` is used as right sibling of the last node of a level with an odd number of nodes, which is the same as building the tree on top of the padded leaves.
+pub fn padding_nodes<const N_ASSETS: usize>(depth: usize) -> Vec<Node<N_ASSETS>> {
+    let mut padding = vec![Node {
+        hash: poseidon_padding_leaf(),
+        balances: [Fp::from(0); N_ASSETS],
+    }];
+
+    for level in 1..=depth {
+        let node = create_middle_node(&padding[level - 1], &padding[level - 1]);
+        padding.push(node);
+    }
+
+    padding
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 fn build_leaves_level<const N_ASSETS: usize>(
     entries: &[Entry<N_ASSETS>],
-    tree: &mut [Vec<Node<N_ASSETS>>],
+    leaves: &mut [Node<N_ASSETS>],
+    leaf_hasher: LeafHasher,
 ) {
     // Compute the leaves in parallel
     let mut handles = vec![];
@@ -56,7 +197,7 @@ fn build_leaves_level<const N_ASSETS: usize>(
         handles.push(thread::spawn(move || {
             chunk
                 .into_iter()
-                .map(|entry| entry.compute_leaf())
+                .map(|entry| entry.compute_leaf_with(leaf_hasher))
                 .collect::<Vec<_>>()
         }));
     }
@@ -65,16 +206,18 @@ fn build_leaves_level<const N_ASSETS: usize>(
     for handle in handles {
         let result = handle.join().unwrap();
         for leaf in result {
-            tree[0][index] = leaf;
+            leaves[index] = leaf;
             index += 1;
         }
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 fn build_middle_level<const N_ASSETS: usize>(
     level: usize,
     tree: &mut [Vec<Node<N_ASSETS>>],
     n: usize,
+    padding: &Node<N_ASSETS>,
 ) {
     let nodes_in_level = (n + (1 << level) - 1) / (1 << level);
 
@@ -83,12 +226,8 @@ fn build_middle_level<const N_ASSETS: usize>(
 
     for chunk in tree[level - 1].chunks(chunk_size * 2) {
         let chunk = chunk.to_vec();
-        handles.push(thread::spawn(move || {
-            chunk
-                .chunks(2)
-                .map(|pair| create_middle_node(&pair[0], &pair[1]))
-                .collect::<Vec<_>>()
-        }));
+        let padding = padding.clone();
+        handles.push(thread::spawn(move || create_middle_nodes(&chunk, &padding)));
     }
 
     let mut index = 0;
@@ -100,3 +239,26 @@ fn build_middle_level<const N_ASSETS: usize>(
         }
     }
 }
+
+/// Same as the threaded [build_leaves_level], as threads can't be spawned on `wasm32`
+#[cfg(target_arch = "wasm32")]
+fn build_leaves_level<const N_ASSETS: usize>(
+    entries: &[Entry<N_ASSETS>],
+    leaves: &mut [Node<N_ASSETS>],
+    leaf_hasher: LeafHasher,
+) {
+    for (leaf, entry) in leaves.iter_mut().zip(entries) {
+        *leaf = entry.compute_leaf_with(leaf_hasher);
+    }
+}
+
+/// Same as the threaded [build_middle_level], as threads can't be spawned on `wasm32`
+#[cfg(target_arch = "wasm32")]
+fn build_middle_level<const N_ASSETS: usize>(
+    level: usize,
+    tree: &mut [Vec<Node<N_ASSETS>>],
+    _n: usize,
+    padding: &Node<N_ASSETS>,
+) {
+    tree[level] = create_middle_nodes(&tree[level - 1], padding);
+}
@@ -0,0 +1,48 @@
+use crate::merkle_sum_tree::utils::{big_int_to_fp, big_intify_username, poseidon_username};
+use crate::merkle_sum_tree::Entry;
+use halo2_proofs::halo2curves::{bn256::Fr as Fp, group::ff::PrimeField};
+use num_bigint::BigInt;
+use std::collections::HashMap;
+
+/// Normalizes a set of entries so that building a tree over them yields the same root no matter how the data has been processed by the operator.
+///
+/// The normalization is applied as follows:
+/// 1. Leading and trailing whitespaces are trimmed from each username.
+/// 2. Entries with the same (trimmed) username are merged into a single entry whose balances are the sum of the balances per each asset.
+/// 3. Entries are sorted by the Poseidon hash of the username, comparing the little-endian byte representation of the hash.
+pub fn canonicalize_entries<const N_ASSETS: usize>(
+    entries: Vec<Entry<N_ASSETS>>,
+) -> Vec<Entry<N_ASSETS>> {
+    let mut merged: HashMap<String, [BigInt; N_ASSETS]> = HashMap::new();
+
+    for entry in entries {
+        let username = entry.username().trim().to_string();
+        let balances = merged
+            .entry(username)
+            .or_insert_with(|| std::array::from_fn(|_| BigInt::from(0)));
+
+        for (acc, balance) in balances.iter_mut().zip(entry.balances().iter()) {
+            *acc += balance;
+        }
+    }
+
+    let mut canonical = merged
+        .into_iter()
+        .map(|(username, balances)| {
+            let hash = username_hash(&username);
+            (hash, Entry::new(username, balances).unwrap())
+        })
+        .collect::<Vec<_>>();
+
+    canonical.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    canonical.into_iter().map(|(_, entry)| entry).collect()
+}
+
+fn username_hash(username: &str) -> Vec<u8> {
+    let hash: Fp = poseidon_username(big_int_to_fp(&big_intify_username(username)));
+    let mut bytes = hash.to_repr().as_ref().to_vec();
+    // compare the most significant byte first
+    bytes.reverse();
+    bytes
+}
@@ -1,6 +1,6 @@
 use halo2_proofs::halo2curves::bn256::Fr as Fp;
 
-use crate::merkle_sum_tree::utils::hash::poseidon_node;
+use crate::merkle_sum_tree::utils::hash::{poseidon_node, poseidon_node_batch};
 use crate::merkle_sum_tree::Node;
 
 pub fn create_middle_node<const N_ASSETS: usize>(
@@ -22,3 +22,33 @@ pub fn create_middle_node<const N_ASSETS: usize>(
         balances: balances_sum,
     }
 }
+
+/// Creates the middle nodes on top of `children`, pairing them two by two and using `padding` as right sibling of the last child if their number is odd.
+/// It is equivalent to calling [create_middle_node] on each pair, but the hashes of the whole level are computed in a single batch, see [poseidon_node_batch].
+pub fn create_middle_nodes<const N_ASSETS: usize>(
+    children: &[Node<N_ASSETS>],
+    padding: &Node<N_ASSETS>,
+) -> Vec<Node<N_ASSETS>> {
+    let pairs = children
+        .chunks(2)
+        .map(|pair| (&pair[0], pair.get(1).unwrap_or(padding)))
+        .collect::<Vec<_>>();
+
+    let hashes = poseidon_node_batch(&pairs);
+
+    pairs
+        .iter()
+        .zip(hashes)
+        .map(|((child_l, child_r), hash)| {
+            let mut balances_sum = [Fp::zero(); N_ASSETS];
+            for (i, balance) in balances_sum.iter_mut().enumerate() {
+                *balance = child_l.balances[i] + child_r.balances[i];
+            }
+
+            Node {
+                hash,
+                balances: balances_sum,
+            }
+        })
+        .collect()
+}
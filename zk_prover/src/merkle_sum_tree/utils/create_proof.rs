@@ -1,4 +1,5 @@
-use crate::merkle_sum_tree::{Entry, MerkleProof, Node};
+use crate::merkle_sum_tree::utils::padding_nodes;
+use crate::merkle_sum_tree::{Entry, LeafHasher, MerkleProof, Node};
 use halo2_proofs::halo2curves::bn256::Fr as Fp;
 
 pub fn create_proof<const N_ASSETS: usize>(
@@ -7,37 +8,57 @@ pub fn create_proof<const N_ASSETS: usize>(
     depth: usize,
     nodes: &[Vec<Node<N_ASSETS>>],
     root: &Node<N_ASSETS>,
+    leaf_hasher: LeafHasher,
 ) -> Result<MerkleProof<N_ASSETS>, &'static str> {
     if index >= nodes[0].len() {
+        if index < 1 << depth {
+            return Err("The leaf is a padding leaf, not an entry of this tree");
+        }
         return Err("The leaf does not exist in this tree");
     }
 
+    let (sibling_hashes, sibling_sums, path_indices) = create_path(index, depth, nodes);
+
+    Ok(MerkleProof {
+        root_hash: root.hash,
+        entry: entries[index].clone(),
+        sibling_hashes,
+        sibling_sums,
+        path_indices,
+        leaf_hasher,
+    })
+}
+
+/// Returns the sibling hashes, the sibling sums and the path indices from the leaf at `index` up to the root. A path index is true if the node of the path is the right child, namely its sibling is on the left
+#[allow(clippy::type_complexity)]
+pub fn create_path<const N_ASSETS: usize>(
+    index: usize,
+    depth: usize,
+    nodes: &[Vec<Node<N_ASSETS>>],
+) -> (Vec<Fp>, Vec<[Fp; N_ASSETS]>, Vec<bool>) {
     let mut sibling_hashes = vec![Fp::from(0); depth];
     let mut sibling_sums = vec![[Fp::from(0); N_ASSETS]; depth];
-    let mut path_indices = vec![Fp::from(0); depth];
+    let mut path_indices = vec![false; depth];
     let mut current_index = index;
+    let padding = padding_nodes::<N_ASSETS>(depth);
 
     for level in 0..depth {
         let position = current_index % 2;
         let level_start_index = current_index - position;
         let level_end_index = level_start_index + 2;
 
-        path_indices[level] = Fp::from(position as u64);
+        path_indices[level] = position == 1;
 
+        // the last node of a level with an odd number of nodes has the root of a subtree of padding leaves as sibling
         for i in level_start_index..level_end_index {
             if i != current_index {
-                sibling_hashes[level] = nodes[level][i].hash;
-                sibling_sums[level] = nodes[level][i].balances;
+                let node = nodes[level].get(i).unwrap_or(&padding[level]);
+                sibling_hashes[level] = node.hash;
+                sibling_sums[level] = node.balances;
             }
         }
         current_index /= 2;
     }
 
-    Ok(MerkleProof {
-        root_hash: root.hash,
-        entry: entries[index].clone(),
-        sibling_hashes,
-        sibling_sums,
-        path_indices,
-    })
+    (sibling_hashes, sibling_sums, path_indices)
 }
@@ -1,4 +1,4 @@
-use crate::merkle_sum_tree::{Entry, MOD_BITS};
+use crate::merkle_sum_tree::{Entry, MerkleSumTreeError, MOD_BITS};
 use num_bigint::BigInt;
 use serde::Deserialize;
 use std::error::Error;
@@ -57,3 +57,56 @@ pub fn parse_csv_to_entries<P: AsRef<Path>, const N_ASSETS: usize>(
 
     Ok(entries)
 }
+
+/// Parses a CSV file stored at path into a vector of Entries, rejecting invalid data instead of panicking.
+/// Returns a [MerkleSumTreeError] for a malformed row, a negative balance or a balance that doesn't fit in `range_bits` bits.
+pub fn parse_csv_to_entries_checked<P: AsRef<Path>, const N_ASSETS: usize>(
+    path: P,
+    range_bits: usize,
+) -> Result<Vec<Entry<N_ASSETS>>, Box<dyn Error>> {
+    let mut entries = Vec::new();
+    let file = File::open(path)?;
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(b';') // The fields are separated by a semicolon
+        .from_reader(file);
+
+    // the rows are numbered from 1, the header excluded
+    for (index, result) in rdr.deserialize().enumerate() {
+        let row = index + 1;
+        let record: CsvEntry = result.map_err(|err| MerkleSumTreeError::MalformedRow {
+            row,
+            reason: err.to_string(),
+        })?;
+
+        let balance_strs: Vec<&str> = record.balances.split(',').collect();
+        if balance_strs.len() != N_ASSETS {
+            return Err(MerkleSumTreeError::MalformedRow {
+                row,
+                reason: format!(
+                    "expected {} balances but found {}",
+                    N_ASSETS,
+                    balance_strs.len()
+                ),
+            }
+            .into());
+        }
+
+        let mut balances: Vec<BigInt> = Vec::with_capacity(N_ASSETS);
+        for balance_str in balance_strs {
+            let balance =
+                BigInt::parse_bytes(balance_str.trim().as_bytes(), 10).ok_or_else(|| {
+                    MerkleSumTreeError::MalformedRow {
+                        row,
+                        reason: format!("invalid balance {:?}", balance_str),
+                    }
+                })?;
+
+            balances.push(balance);
+        }
+
+        let entry = Entry::new_checked(record.username, balances.try_into().unwrap(), range_bits)?;
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
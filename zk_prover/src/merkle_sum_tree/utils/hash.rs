@@ -1,34 +1,318 @@
 use crate::chips::poseidon::poseidon_spec::PoseidonSpec;
-use crate::merkle_sum_tree::{L_ENTRY, L_NODE};
-use halo2_gadgets::poseidon::primitives::{self as poseidon, ConstantLength};
+use crate::chips::poseidon::sponge::VariableLength;
+use crate::merkle_sum_tree::utils::batch_arithmetic::batch_sbox;
+use crate::merkle_sum_tree::Node;
+use halo2_gadgets::poseidon::primitives::{Domain, Spec};
 use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use sha2::{Digest, Sha256};
 
 const WIDTH: usize = 3;
 const RATE: usize = 2;
 
+/// Performs a constant length poseidon hash over `inputs`, equivalent to hashing them with the [halo2_gadgets::poseidon::primitives::ConstantLength] domain of the same length.
+/// The sponge is run by hand rather than through a hasher fixed at compile time, so that the same helper can serve trees with any number of assets and nodes of any arity.
+fn poseidon_constant_length(inputs: &[Fp]) -> Fp {
+    // the capacity element of the ConstantLength<L> domain is L * 2^64, see ConstantLength::initial_capacity_element
+    let mut state = [Fp::zero(); WIDTH];
+    state[RATE] = Fp::from_raw([0, inputs.len() as u64, 0, 0]);
+
+    // the inputs are padded with zeros to a multiple of the rate, at least one chunk is always absorbed
+    let padded_length = std::cmp::max(1, (inputs.len() + RATE - 1) / RATE) * RATE;
+    let padded_inputs = inputs
+        .iter()
+        .copied()
+        .chain(std::iter::repeat(Fp::zero()))
+        .take(padded_length)
+        .collect::<Vec<_>>();
+
+    absorb(&mut state, &padded_inputs);
+
+    state[0]
+}
+
+/// Performs a poseidon hash over `inputs` of any length with the [VariableLength] domain.
+/// It is the off-circuit counterpart of [crate::chips::poseidon::sponge::PoseidonSpongeChip::hash_variable].
+pub fn poseidon_variable_length(inputs: &[Fp]) -> Fp {
+    let mut state = [Fp::zero(); WIDTH];
+    state[RATE] = <VariableLength as Domain<Fp, RATE>>::initial_capacity_element();
+
+    let padded_inputs = inputs
+        .iter()
+        .copied()
+        .chain(<VariableLength as Domain<Fp, RATE>>::padding(inputs.len()))
+        .collect::<Vec<_>>();
+
+    absorb(&mut state, &padded_inputs);
+
+    state[0]
+}
+
+/// Absorbs the padded inputs into `state` one rate-sized chunk at a time, permuting the state after each chunk
+fn absorb(state: &mut [Fp; WIDTH], padded_inputs: &[Fp]) {
+    let (round_constants, mds, _) = PoseidonSpec::constants();
+
+    let r_f = PoseidonSpec::full_rounds() / 2;
+    let r_p = PoseidonSpec::partial_rounds();
+
+    for chunk in padded_inputs.chunks(RATE) {
+        for (word, input) in state.iter_mut().zip(chunk.iter()) {
+            *word += input;
+        }
+
+        for (round, round_constants) in round_constants.iter().enumerate() {
+            let is_full_round = round < r_f || round >= r_f + r_p;
+
+            for (i, (word, constant)) in state.iter_mut().zip(round_constants.iter()).enumerate() {
+                *word += constant;
+                if is_full_round || i == 0 {
+                    *word = PoseidonSpec::sbox(*word);
+                }
+            }
+
+            let mut new_state = [Fp::zero(); WIDTH];
+            for (i, new_word) in new_state.iter_mut().enumerate() {
+                for (j, word) in state.iter().enumerate() {
+                    *new_word += mds[i][j] * word;
+                }
+            }
+            *state = new_state;
+        }
+    }
+}
+
+/// Performs a constant length poseidon hash over each of `inputs`, which must all have the same length, equivalent to calling [poseidon_constant_length] on each of them.
+/// The states of all the hashes are permuted in lock-step, so that each S-box layer is evaluated over the whole batch in a single pass with [batch_sbox].
+fn poseidon_constant_length_batch(inputs: &[Vec<Fp>]) -> Vec<Fp> {
+    let length = inputs.first().map_or(0, Vec::len);
+    assert!(
+        inputs.iter().all(|input| input.len() == length),
+        "The inputs of a batch must have the same length"
+    );
+
+    // words[i][k] is the i-th word of the state of the k-th hash
+    let mut words: [Vec<Fp>; WIDTH] = std::array::from_fn(|_| vec![Fp::zero(); inputs.len()]);
+    words[RATE] = vec![Fp::from_raw([0, length as u64, 0, 0]); inputs.len()];
+
+    let padded_length = std::cmp::max(1, (length + RATE - 1) / RATE) * RATE;
+
+    let (round_constants, mds, _) = PoseidonSpec::constants();
+
+    let r_f = PoseidonSpec::full_rounds() / 2;
+    let r_p = PoseidonSpec::partial_rounds();
+
+    for chunk_start in (0..padded_length).step_by(RATE) {
+        for (i, column) in words.iter_mut().take(RATE).enumerate() {
+            for (word, input) in column.iter_mut().zip(inputs.iter()) {
+                if let Some(input) = input.get(chunk_start + i) {
+                    *word += input;
+                }
+            }
+        }
+
+        for (round, round_constants) in round_constants.iter().enumerate() {
+            let is_full_round = round < r_f || round >= r_f + r_p;
+
+            for (i, (column, constant)) in words.iter_mut().zip(round_constants.iter()).enumerate()
+            {
+                for word in column.iter_mut() {
+                    *word += constant;
+                }
+                if is_full_round || i == 0 {
+                    batch_sbox(column);
+                }
+            }
+
+            for k in 0..inputs.len() {
+                let state: [Fp; WIDTH] = std::array::from_fn(|j| words[j][k]);
+                for (i, column) in words.iter_mut().enumerate() {
+                    column[k] = state
+                        .iter()
+                        .zip(mds[i].iter())
+                        .fold(Fp::zero(), |acc, (word, m)| acc + *m * word);
+                }
+            }
+        }
+    }
+
+    let [hashes, ..] = words;
+    hashes
+}
+
 pub fn poseidon_node<const N_ASSETS: usize>(
     l1: Fp,
     l2: [Fp; N_ASSETS],
     r1: Fp,
     r2: [Fp; N_ASSETS],
 ) -> Fp {
-    let mut hash_inputs: [Fp; L_NODE] = [Fp::zero(); L_NODE];
+    let mut hash_inputs = vec![Fp::zero(); 2 * (1 + N_ASSETS)];
 
     hash_inputs[0] = l1;
     hash_inputs[1..N_ASSETS + 1].copy_from_slice(&l2);
     hash_inputs[N_ASSETS + 1] = r1;
     hash_inputs[N_ASSETS + 2..2 * N_ASSETS + 2].copy_from_slice(&r2);
 
-    poseidon::Hash::<Fp, PoseidonSpec, ConstantLength<L_NODE>, WIDTH, RATE>::init()
-        .hash(hash_inputs)
+    poseidon_constant_length(&hash_inputs)
+}
+
+/// Same as [poseidon_node] over each pair of children of `pairs`, with all the hashes computed in a single batch, see [poseidon_constant_length_batch]
+pub fn poseidon_node_batch<const N_ASSETS: usize>(
+    pairs: &[(&Node<N_ASSETS>, &Node<N_ASSETS>)],
+) -> Vec<Fp> {
+    let hash_inputs = pairs
+        .iter()
+        .map(|(left, right)| {
+            std::iter::once(left.hash)
+                .chain(left.balances)
+                .chain(std::iter::once(right.hash))
+                .chain(right.balances)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    poseidon_constant_length_batch(&hash_inputs)
+}
+
+/// Hashes the children of a middle node of a tree of any arity, namely `H(child_0.hash, child_0.balances, child_1.hash, child_1.balances, ...)`.
+/// With two children it is equivalent to [poseidon_node].
+pub fn poseidon_nary_node<const N_ASSETS: usize>(children: &[Node<N_ASSETS>]) -> Fp {
+    let hash_inputs = children
+        .iter()
+        .flat_map(|child| std::iter::once(child.hash).chain(child.balances))
+        .collect::<Vec<_>>();
+
+    poseidon_constant_length(&hash_inputs)
+}
+
+/// Returns the hash of a middle node of a [crate::merkle_sum_tree::KeyedMerkleSumTree], namely `H(left.hash, left.balances, left_keys, right.hash, right.balances, right_keys)`, where the keys of a child are the least and the greatest username of its subtree
+pub fn poseidon_keyed_node<const N_ASSETS: usize>(
+    left: &Node<N_ASSETS>,
+    left_keys: [Fp; 2],
+    right: &Node<N_ASSETS>,
+    right_keys: [Fp; 2],
+) -> Fp {
+    let hash_inputs = [(left, left_keys), (right, right_keys)]
+        .iter()
+        .flat_map(|(child, keys)| {
+            std::iter::once(child.hash)
+                .chain(child.balances)
+                .chain(*keys)
+        })
+        .collect::<Vec<_>>();
+
+    poseidon_constant_length(&hash_inputs)
 }
 
 pub fn poseidon_entry<const N_ASSETS: usize>(left: Fp, right: [Fp; N_ASSETS]) -> Fp {
-    let mut hash_inputs: [Fp; L_ENTRY] = [Fp::zero(); L_ENTRY];
+    let mut hash_inputs = vec![Fp::zero(); 1 + N_ASSETS];
 
     hash_inputs[0] = left;
     hash_inputs[1..N_ASSETS + 1].copy_from_slice(&right);
 
-    poseidon::Hash::<Fp, PoseidonSpec, ConstantLength<L_ENTRY>, WIDTH, RATE>::init()
-        .hash(hash_inputs)
+    poseidon_constant_length(&hash_inputs)
+}
+
+/// Performs a SHA-256 hash over `inputs`, each encoded as its 32 bytes big endian representation.
+/// The most significant byte of the digest is dropped, so that the remaining 248 bits always fit in a field element.
+/// It is the off-circuit counterpart of [crate::chips::sha256::sha256_chip::Sha256Chip::hash_field_elements].
+pub fn sha256_field_elements(inputs: &[Fp]) -> Fp {
+    let mut hasher = Sha256::new();
+    for input in inputs {
+        let mut bytes = input.to_bytes();
+        bytes.reverse();
+        hasher.update(bytes);
+    }
+    let digest = hasher.finalize();
+
+    let mut bytes = [0u8; 32];
+    for (byte, digest_byte) in bytes.iter_mut().zip(digest.iter().skip(1).rev()) {
+        *byte = *digest_byte;
+    }
+    Fp::from_bytes(&bytes).unwrap()
+}
+
+/// Performs the SHA-256 hash of an entry, namely `SHA256(username, balance[0], ..., balance[N_ASSETS - 1])` truncated to 248 bits, see [sha256_field_elements]
+pub fn sha256_entry<const N_ASSETS: usize>(username: Fp, balances: [Fp; N_ASSETS]) -> Fp {
+    let hash_inputs = std::iter::once(username)
+        .chain(balances)
+        .collect::<Vec<_>>();
+
+    sha256_field_elements(&hash_inputs)
+}
+
+/// The sentinel hashed into the padding leaves of a tree
+const PADDING_SENTINEL: u64 = u64::MAX;
+
+/// Returns the hash of the padding leaves, namely `H(PADDING_SENTINEL)`.
+/// The hash of a padding leaf has a single input while the hash of an entry has 1 + N_ASSETS inputs, so a padding leaf can't be opened as an entry.
+pub fn poseidon_padding_leaf() -> Fp {
+    poseidon_constant_length(&[Fp::from(PADDING_SENTINEL)])
+}
+
+pub fn poseidon_username(username: Fp) -> Fp {
+    poseidon_constant_length(&[username])
+}
+
+/// Returns the root of the snapshot of a tree with root hash `root_hash` taken at `timestamp`, for example a unix timestamp or a block height, namely `H(root_hash, timestamp)`.
+/// The hash has the [VariableLength] domain, which is separated from the one of the leaves and the middle nodes, so that a snapshot root can't be opened as a node of a tree.
+pub fn poseidon_snapshot_root(root_hash: Fp, timestamp: u64) -> Fp {
+    poseidon_variable_length(&[root_hash, Fp::from(timestamp)])
+}
+
+/// Returns the commitment to the solvency of a CEX across all its assets, namely `H(liabilities_0, assets_sum_0, liabilities_1, assets_sum_1, ...)`.
+/// The hash has the [VariableLength] domain, so that the pairs of the liabilities and the assets of each asset can be exposed as a single public input.
+pub fn poseidon_solvency_commitment<const N_ASSETS: usize>(
+    liabilities: &[Fp; N_ASSETS],
+    assets_sum: &[Fp; N_ASSETS],
+) -> Fp {
+    let inputs = liabilities
+        .iter()
+        .zip(assets_sum.iter())
+        .flat_map(|(liabilities, assets_sum)| [*liabilities, *assets_sum])
+        .collect::<Vec<_>>();
+
+    poseidon_variable_length(&inputs)
+}
+
+/// Domain of a hash computed with [poseidon_tagged], whose tag is prepended to the inputs so that a hash computed in one domain can't be reused in another one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashDomain {
+    Leaf,
+    Node,
+    Root,
+}
+
+impl HashDomain {
+    /// Returns the tag of the domain, namely the ASCII encoding of its name
+    pub fn tag(&self) -> Fp {
+        let name: &[u8; 4] = match self {
+            HashDomain::Leaf => b"leaf",
+            HashDomain::Node => b"node",
+            HashDomain::Root => b"root",
+        };
+
+        Fp::from(u32::from_be_bytes(*name) as u64)
+    }
+}
+
+/// Performs a constant length poseidon hash over the tag of `domain` followed by `inputs`, namely `H(tag, inputs[0], ..., inputs[n - 1])`.
+/// It is the off-circuit counterpart of [crate::chips::poseidon::hash::PoseidonChip::hash_with_domain]. The same inputs hashed in different domains, or without domain, result in different hashes.
+pub fn poseidon_tagged(domain: HashDomain, inputs: &[Fp]) -> Fp {
+    let hash_inputs = std::iter::once(domain.tag())
+        .chain(inputs.iter().copied())
+        .collect::<Vec<_>>();
+
+    poseidon_constant_length(&hash_inputs)
+}
+
+/// Returns an identifier of the Poseidon spec compiled in, namely its number of rounds and the hash of a fixed input.
+/// Hashes produced with a spec with different rounds or constants result in a different identifier.
+pub fn poseidon_spec_id() -> String {
+    format!(
+        "poseidon-w{}-r{}-f{}-p{}-{:?}",
+        WIDTH,
+        RATE,
+        <PoseidonSpec as Spec<Fp, WIDTH, RATE>>::full_rounds(),
+        <PoseidonSpec as Spec<Fp, WIDTH, RATE>>::partial_rounds(),
+        poseidon_constant_length(&[Fp::zero(), Fp::one()])
+    )
 }
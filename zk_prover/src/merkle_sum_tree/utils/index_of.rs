@@ -1,13 +1,14 @@
-use crate::merkle_sum_tree::{Entry, Node};
+use crate::merkle_sum_tree::{Entry, LeafHasher, Node};
 use num_bigint::BigInt;
 
 pub fn index_of<const N_ASSETS: usize>(
     username: &str,
     balances: [BigInt; N_ASSETS],
     nodes: &[Vec<Node<N_ASSETS>>],
+    leaf_hasher: LeafHasher,
 ) -> Option<usize> {
     let entry: Entry<N_ASSETS> = Entry::new(username.to_string(), balances).unwrap();
-    let leaf = entry.compute_leaf();
+    let leaf = entry.compute_leaf_with(leaf_hasher);
     let leaf_hash = leaf.hash;
 
     nodes[0].iter().position(|node| node.hash == leaf_hash)
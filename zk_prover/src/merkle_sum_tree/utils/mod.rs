@@ -1,4 +1,6 @@
+mod batch_arithmetic;
 mod build_tree;
+mod canonicalize;
 mod create_middle_node;
 mod create_proof;
 mod csv_parser;
@@ -6,11 +8,27 @@ mod hash;
 mod index_of;
 mod operation_helpers;
 mod proof_verification;
+mod serialization;
 
-pub use build_tree::build_merkle_tree_from_entries;
-pub use create_proof::create_proof;
-pub use csv_parser::parse_csv_to_entries;
-pub use hash::{poseidon_entry, poseidon_node};
+#[cfg(feature = "rayon")]
+pub use build_tree::build_merkle_tree_from_entries_parallel;
+pub use build_tree::{
+    build_merkle_tree_from_entries, build_merkle_tree_from_leaves, padding_nodes,
+    update_merkle_path_from_leaf, update_merkle_tree_from_leaf,
+};
+pub use canonicalize::canonicalize_entries;
+pub use create_middle_node::{create_middle_node, create_middle_nodes};
+pub use create_proof::{create_path, create_proof};
+pub use csv_parser::{parse_csv_to_entries, parse_csv_to_entries_checked};
+pub use hash::{
+    poseidon_entry, poseidon_keyed_node, poseidon_nary_node, poseidon_node, poseidon_padding_leaf,
+    poseidon_snapshot_root, poseidon_solvency_commitment, poseidon_spec_id, poseidon_tagged,
+    poseidon_username, poseidon_variable_length, sha256_entry, sha256_field_elements, HashDomain,
+};
 pub use index_of::index_of;
 pub use operation_helpers::*;
-pub use proof_verification::verify_proof;
+pub use proof_verification::{compute_path_nodes, verify_proof, verify_proof_with_root};
+pub use serialization::{
+    decode_tree, encode_tree, SerializedEntry, SerializedMerkleProof, SerializedMerkleSumTree,
+    SerializedNode,
+};
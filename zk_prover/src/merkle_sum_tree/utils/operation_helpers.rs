@@ -1,5 +1,6 @@
+use crate::merkle_sum_tree::{MerkleSumTreeError, LIMB_BITS};
 use halo2_proofs::halo2curves::{bn256::Fr as Fp, group::ff::PrimeField};
-use num_bigint::{BigInt, Sign};
+use num_bigint::{BigInt, BigUint, Sign};
 
 /// Return a BigInt representation of the username
 pub fn big_intify_username(username: &str) -> BigInt {
@@ -10,3 +11,77 @@ pub fn big_intify_username(username: &str) -> BigInt {
 pub fn big_int_to_fp(big_int: &BigInt) -> Fp {
     Fp::from_str_vartime(&big_int.to_str_radix(10)[..]).unwrap()
 }
+
+/// Converts a Field Element to a BigInt
+pub fn fp_to_big_int(fp: &Fp) -> BigInt {
+    BigInt::from_bytes_le(Sign::Plus, &fp.to_bytes())
+}
+
+/// Converts a Field Element to a u128, returning None if it doesn't fit in 128 bits
+pub fn fp_to_u128(fp: &Fp) -> Option<u128> {
+    let bytes = fp.to_bytes();
+    let (low_bytes, high_bytes) = bytes.split_at(16);
+
+    if high_bytes.iter().any(|byte| *byte != 0) {
+        return None;
+    }
+
+    Some(u128::from_le_bytes(low_bytes.try_into().unwrap()))
+}
+
+/// Splits an unsigned balance of up to 256 bits into its low and high limbs of [LIMB_BITS] bits each, namely `[value mod 2^128, value / 2^128]`.
+/// Returns an error if the balance doesn't fit in 256 bits.
+pub fn big_uint_to_limbs(value: &BigUint) -> Result<[Fp; 2], &'static str> {
+    if value.bits() > 2 * LIMB_BITS as u64 {
+        return Err("The balance doesn't fit in 256 bits");
+    }
+
+    let mask = (BigUint::from(1u8) << LIMB_BITS) - 1u8;
+    let to_fp = |limb: BigUint| big_int_to_fp(&BigInt::from(limb));
+
+    Ok([to_fp(value & &mask), to_fp(value >> LIMB_BITS)])
+}
+
+/// Recomposes an unsigned balance from its low and high limbs, as split by [big_uint_to_limbs]
+pub fn limbs_to_big_uint(limbs: &[Fp; 2]) -> BigUint {
+    let to_big_uint = |limb: &Fp| BigUint::from_bytes_le(&limb.to_bytes());
+
+    to_big_uint(&limbs[0]) + (to_big_uint(&limbs[1]) << LIMB_BITS)
+}
+
+/// Parses a human readable decimal balance such as `"1.5"` into an integer scaled by `10^decimals`, namely `1500000` for 6 decimals.
+/// Returns an error if the string is not a non-negative decimal number or if it has more fractional digits than `decimals`.
+pub fn parse_scaled_balance(s: &str, decimals: u32) -> Result<BigUint, MerkleSumTreeError> {
+    let invalid = || MerkleSumTreeError::InvalidBalance(s.to_string());
+
+    let (integer_part, fractional_part) = match s.split_once('.') {
+        Some((integer_part, fractional_part)) if !fractional_part.is_empty() => {
+            (integer_part, fractional_part)
+        }
+        Some(_) => return Err(invalid()),
+        None => (s, ""),
+    };
+
+    let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    if integer_part.is_empty() || !is_digits(integer_part) || !is_digits(fractional_part) {
+        return Err(invalid());
+    }
+
+    let found = fractional_part.len() as u32;
+    if found > decimals {
+        return Err(MerkleSumTreeError::TooManyDecimals {
+            allowed: decimals,
+            found,
+        });
+    }
+
+    // pad the fractional part with zeros up to the number of decimals and parse the whole as an integer
+    let digits = format!(
+        "{}{}{}",
+        integer_part,
+        fractional_part,
+        "0".repeat((decimals - found) as usize)
+    );
+
+    BigUint::parse_bytes(digits.as_bytes(), 10).ok_or_else(invalid)
+}
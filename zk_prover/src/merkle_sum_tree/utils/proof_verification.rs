@@ -2,8 +2,45 @@ use crate::merkle_sum_tree::utils::{big_int_to_fp, create_middle_node::create_mi
 use crate::merkle_sum_tree::{MerkleProof, Node};
 use halo2_proofs::halo2curves::bn256::Fr as Fp;
 
+/// Verifies `proof` against the root hash it claims. A proof whose sibling hashes, sibling sums and path indices differ in length is malformed and rejected
 pub fn verify_proof<const N_ASSETS: usize>(proof: &MerkleProof<N_ASSETS>) -> bool {
-    let mut node = proof.entry.compute_leaf();
+    if !has_consistent_path(proof) {
+        return false;
+    }
+
+    let (node, balances) = recompute_root(proof);
+
+    proof.root_hash == node.hash && balances == node.balances
+}
+
+/// Verifies `proof` against `root`, namely the root hash claimed by the proof, the root recomputed from the entry and the siblings and the running sum of the balances along the path must all match `root`
+pub fn verify_proof_with_root<const N_ASSETS: usize>(
+    proof: &MerkleProof<N_ASSETS>,
+    root: &Node<N_ASSETS>,
+) -> bool {
+    if !has_consistent_path(proof) {
+        return false;
+    }
+
+    let (node, balances) = recompute_root(proof);
+
+    proof.root_hash == root.hash
+        && node.hash == root.hash
+        && node.balances == root.balances
+        && balances == node.balances
+}
+
+/// Returns true if the proof carries a sibling hash, a sibling sum and a path index for each level
+fn has_consistent_path<const N_ASSETS: usize>(proof: &MerkleProof<N_ASSETS>) -> bool {
+    proof.sibling_hashes.len() == proof.sibling_sums.len()
+        && proof.sibling_hashes.len() == proof.path_indices.len()
+}
+
+/// Returns the root recomputed from the entry and the siblings of `proof`, together with the running sum of the balances of the entry and of the siblings
+fn recompute_root<const N_ASSETS: usize>(
+    proof: &MerkleProof<N_ASSETS>,
+) -> (Node<N_ASSETS>, Vec<Fp>) {
+    let mut node = proof.entry.compute_leaf_with(proof.leaf_hasher);
     let mut balances = proof
         .entry
         .balances()
@@ -17,7 +54,7 @@ pub fn verify_proof<const N_ASSETS: usize>(proof: &MerkleProof<N_ASSETS>) -> boo
             balances: proof.sibling_sums[i],
         };
 
-        if proof.path_indices[i] == 0.into() {
+        if !proof.path_indices[i] {
             node = create_middle_node(&node, &sibling_node);
         } else {
             node = create_middle_node(&sibling_node, &node);
@@ -28,5 +65,35 @@ pub fn verify_proof<const N_ASSETS: usize>(proof: &MerkleProof<N_ASSETS>) -> boo
         }
     }
 
-    proof.root_hash == node.hash && balances == node.balances
+    (node, balances)
+}
+
+/// Returns the nodes recomputed from the entry and the siblings of `proof`, from the leaf up to the root
+pub fn compute_path_nodes<const N_ASSETS: usize>(
+    proof: &MerkleProof<N_ASSETS>,
+) -> Vec<Node<N_ASSETS>> {
+    let mut nodes = vec![proof.entry.compute_leaf_with(proof.leaf_hasher)];
+
+    // a malformed proof is truncated to the shortest of the sibling hashes, sibling sums and path indices
+    for ((hash, balances), is_right) in proof
+        .sibling_hashes
+        .iter()
+        .zip(proof.sibling_sums.iter())
+        .zip(proof.path_indices.iter())
+    {
+        let sibling_node = Node {
+            hash: *hash,
+            balances: *balances,
+        };
+
+        let node = nodes.last().unwrap();
+        let next_node = if !is_right {
+            create_middle_node(node, &sibling_node)
+        } else {
+            create_middle_node(&sibling_node, node)
+        };
+        nodes.push(next_node);
+    }
+
+    nodes
 }
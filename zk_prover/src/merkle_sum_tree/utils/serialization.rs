@@ -0,0 +1,361 @@
+use crate::merkle_sum_tree::utils::create_middle_node::create_middle_node;
+use crate::merkle_sum_tree::utils::{big_int_to_fp, fp_to_big_int};
+use crate::merkle_sum_tree::{Entry, LeafHasher, MerkleProof, MerkleSumTreeError, Node};
+use halo2_proofs::halo2curves::bn256::Fr as Fp;
+use num_bigint::{BigInt, Sign};
+use serde::{Deserialize, Serialize};
+
+/// Magic bytes at the start of a tree stored in the binary format
+const BINARY_MAGIC: &[u8; 4] = b"SMST";
+/// Version of the binary format
+const BINARY_VERSION: u8 = 1;
+/// Number of children of each middle node of a [crate::merkle_sum_tree::MerkleSumTree]
+const BINARY_ARITY: u8 = 2;
+
+/// Serializable representation of a Merkle Sum Tree. Field elements and balances are stored as decimal strings.
+///
+/// # Fields
+///
+/// * `spec_id`: The identifier of the Poseidon spec used to compute the hashes of the nodes, see [crate::merkle_sum_tree::utils::poseidon_spec_id]
+/// * `depth`: The depth of the tree
+/// * `entries`: The entries of the tree, namely the username and the balances
+/// * `nodes`: The nodes of the tree, level by level starting from the leaves
+/// * `leaf_hasher`: The hash function of the leaves. Trees serialized without it have Poseidon leaves
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SerializedMerkleSumTree {
+    pub spec_id: String,
+    pub depth: usize,
+    pub entries: Vec<SerializedEntry>,
+    pub nodes: Vec<Vec<SerializedNode>>,
+    #[serde(default)]
+    pub leaf_hasher: LeafHasher,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SerializedEntry {
+    pub username: String,
+    pub balances: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SerializedNode {
+    pub hash: String,
+    pub balances: Vec<String>,
+}
+
+impl SerializedEntry {
+    pub fn from_entry<const N_ASSETS: usize>(entry: &Entry<N_ASSETS>) -> Self {
+        Self {
+            username: entry.username().to_string(),
+            balances: entry.balances().iter().map(BigInt::to_string).collect(),
+        }
+    }
+
+    pub fn to_entry<const N_ASSETS: usize>(
+        &self,
+    ) -> Result<Entry<N_ASSETS>, Box<dyn std::error::Error>> {
+        let balances = self
+            .balances
+            .iter()
+            .map(|balance| parse_decimal(balance))
+            .collect::<Result<Vec<_>, _>>()?
+            .try_into()
+            .map_err(|_| {
+                "The number of balances of the entry doesn't match the number of assets"
+            })?;
+
+        Ok(Entry::new(self.username.clone(), balances)?)
+    }
+}
+
+impl SerializedNode {
+    pub fn from_node<const N_ASSETS: usize>(node: &Node<N_ASSETS>) -> Self {
+        Self {
+            hash: fp_to_big_int(&node.hash).to_string(),
+            balances: node
+                .balances
+                .iter()
+                .map(|balance| fp_to_big_int(balance).to_string())
+                .collect(),
+        }
+    }
+
+    pub fn to_node<const N_ASSETS: usize>(
+        &self,
+    ) -> Result<Node<N_ASSETS>, Box<dyn std::error::Error>> {
+        let balances = self
+            .balances
+            .iter()
+            .map(|balance| parse_decimal(balance).map(|balance| big_int_to_fp(&balance)))
+            .collect::<Result<Vec<_>, _>>()?
+            .try_into()
+            .map_err(|_| "The number of balances of the node doesn't match the number of assets")?;
+
+        Ok(Node {
+            hash: big_int_to_fp(&parse_decimal(&self.hash)?),
+            balances,
+        })
+    }
+}
+
+/// Serializable representation of a [MerkleProof], so that it can be verified by another client than the one that built the tree, for example in the browser. Field elements and balances are stored as decimal strings.
+///
+/// # Fields
+///
+/// * `root_hash`: The root hash of the tree
+/// * `entry`: The entry of which the inclusion is proven
+/// * `sibling_hashes`: The hashes of the siblings, ordered from the leaf level to the root level
+/// * `sibling_sums`: The balances of the siblings, ordered from the leaf level to the root level
+/// * `path_indices`: The direction bits of the path, ordered from the leaf level to the root level
+/// * `leaf_hasher`: The hash function of the leaf of the entry. Proofs serialized without it have Poseidon leaves
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SerializedMerkleProof {
+    pub root_hash: String,
+    pub entry: SerializedEntry,
+    pub sibling_hashes: Vec<String>,
+    pub sibling_sums: Vec<Vec<String>>,
+    pub path_indices: Vec<bool>,
+    #[serde(default)]
+    pub leaf_hasher: LeafHasher,
+}
+
+impl SerializedMerkleProof {
+    pub fn from_proof<const N_ASSETS: usize>(proof: &MerkleProof<N_ASSETS>) -> Self {
+        let to_decimal = |value: &Fp| fp_to_big_int(value).to_string();
+
+        Self {
+            root_hash: to_decimal(&proof.root_hash),
+            entry: SerializedEntry::from_entry(&proof.entry),
+            sibling_hashes: proof.sibling_hashes.iter().map(to_decimal).collect(),
+            sibling_sums: proof
+                .sibling_sums
+                .iter()
+                .map(|balances| balances.iter().map(to_decimal).collect())
+                .collect(),
+            path_indices: proof.path_indices.clone(),
+            leaf_hasher: proof.leaf_hasher,
+        }
+    }
+
+    /// Parses the proof, rejecting negative field elements and a number of balances that doesn't match N_ASSETS.
+    /// The lengths of the path are not checked, a malformed path is rejected by the verification of the proof
+    pub fn to_proof<const N_ASSETS: usize>(
+        &self,
+    ) -> Result<MerkleProof<N_ASSETS>, Box<dyn std::error::Error>> {
+        let sibling_sums = self
+            .sibling_sums
+            .iter()
+            .map(
+                |balances| -> Result<[Fp; N_ASSETS], Box<dyn std::error::Error>> {
+                    Ok(balances
+                    .iter()
+                    .map(|balance| parse_field_element(balance))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .try_into()
+                    .map_err(|_| {
+                        "The number of balances of the sibling doesn't match the number of assets"
+                    })?)
+                },
+            )
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(MerkleProof {
+            root_hash: parse_field_element(&self.root_hash)?,
+            entry: self.entry.to_entry()?,
+            sibling_hashes: self
+                .sibling_hashes
+                .iter()
+                .map(|hash| parse_field_element(hash))
+                .collect::<Result<Vec<_>, _>>()?,
+            sibling_sums,
+            path_indices: self.path_indices.clone(),
+            leaf_hasher: self.leaf_hasher,
+        })
+    }
+}
+
+/// Parses a field element from a decimal string, rejecting negative values instead of failing the conversion to a field element
+fn parse_field_element(value: &str) -> Result<Fp, Box<dyn std::error::Error>> {
+    let value = parse_decimal(value)?;
+
+    if value.sign() == Sign::Minus {
+        return Err(format!("The field element {} is negative", value).into());
+    }
+
+    Ok(big_int_to_fp(&value))
+}
+
+fn parse_decimal(value: &str) -> Result<BigInt, Box<dyn std::error::Error>> {
+    BigInt::parse_bytes(value.as_bytes(), 10)
+        .ok_or_else(|| format!("Invalid decimal value {:?}", value).into())
+}
+
+/// Encodes a tree in a compact binary format. All the integers are little endian:
+///
+/// | field | encoding |
+/// | --- | --- |
+/// | magic | `SMST` |
+/// | version | u8 |
+/// | arity | u8 |
+/// | number of assets | u32 |
+/// | depth | u32 |
+/// | entries | u64 count, then for each entry a u32 length prefixed username and N_ASSETS u32 length prefixed balances |
+/// | nodes | for each level from the leaves to the root, a u64 count, then for each node the 32 bytes of the hash and of each balance |
+/// | checksum | u64 FNV-1a hash of all the previous bytes |
+pub fn encode_tree<const N_ASSETS: usize>(
+    depth: usize,
+    entries: &[Entry<N_ASSETS>],
+    nodes: &[Vec<Node<N_ASSETS>>],
+) -> Vec<u8> {
+    let mut bytes = BINARY_MAGIC.to_vec();
+    bytes.push(BINARY_VERSION);
+    bytes.push(BINARY_ARITY);
+    bytes.extend((N_ASSETS as u32).to_le_bytes());
+    bytes.extend((depth as u32).to_le_bytes());
+
+    bytes.extend((entries.len() as u64).to_le_bytes());
+    for entry in entries {
+        write_bytes(&mut bytes, entry.username().as_bytes());
+        for balance in entry.balances() {
+            write_bytes(&mut bytes, &balance.to_bytes_le().1);
+        }
+    }
+
+    for level in nodes {
+        bytes.extend((level.len() as u64).to_le_bytes());
+        for node in level {
+            bytes.extend(node.hash.to_bytes());
+            for balance in node.balances.iter() {
+                bytes.extend(balance.to_bytes());
+            }
+        }
+    }
+
+    let checksum = fnv1a_checksum(&bytes);
+    bytes.extend(checksum.to_le_bytes());
+    bytes
+}
+
+/// Decodes a tree encoded by [encode_tree], returning its depth, its entries and its nodes.
+/// Returns a [MerkleSumTreeError::CorruptedTree] if the checksum doesn't match the content, if the tree metadata doesn't match the expected one or if the stored root isn't the middle node of its children.
+pub fn decode_tree<const N_ASSETS: usize>(
+    bytes: &[u8],
+) -> Result<(usize, Vec<Entry<N_ASSETS>>, Vec<Vec<Node<N_ASSETS>>>), Box<dyn std::error::Error>> {
+    let corrupted = |reason: &str| MerkleSumTreeError::CorruptedTree(reason.to_string());
+
+    if bytes.len() < 8 {
+        return Err(corrupted("the file is too short").into());
+    }
+    let (content, checksum) = bytes.split_at(bytes.len() - 8);
+    if fnv1a_checksum(content).to_le_bytes() != checksum {
+        return Err(corrupted("the checksum doesn't match the content").into());
+    }
+
+    let mut reader = ByteReader { bytes: content };
+    if reader.take(4)? != BINARY_MAGIC {
+        return Err(corrupted("the file is not a merkle sum tree").into());
+    }
+    if reader.take(1)?[0] != BINARY_VERSION || reader.take(1)?[0] != BINARY_ARITY {
+        return Err(corrupted("unsupported version or arity").into());
+    }
+    if reader.read_u32()? as usize != N_ASSETS {
+        return Err(corrupted("the number of assets doesn't match").into());
+    }
+    let depth = reader.read_u32()? as usize;
+
+    let entries = (0..reader.read_u64()?)
+        .map(|_| {
+            let username = String::from_utf8(reader.read_bytes()?.to_vec())?;
+            let balances = (0..N_ASSETS)
+                .map(|_| Ok(BigInt::from_bytes_le(Sign::Plus, reader.read_bytes()?)))
+                .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+            Ok(Entry::new(username, balances.try_into().unwrap())?)
+        })
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+
+    let nodes = (0..=depth)
+        .map(|_| {
+            (0..reader.read_u64()?)
+                .map(|_| {
+                    let hash = reader.read_fp()?;
+                    let mut balances = [Fp::zero(); N_ASSETS];
+                    for balance in balances.iter_mut() {
+                        *balance = reader.read_fp()?;
+                    }
+                    Ok(Node { hash, balances })
+                })
+                .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if !reader.bytes.is_empty() {
+        return Err(corrupted("unexpected trailing bytes").into());
+    }
+
+    // the root must be the middle node of its children
+    let root = nodes[depth]
+        .first()
+        .ok_or_else(|| corrupted("the tree doesn't contain the root"))?;
+    let expected_root = match depth {
+        0 => entries
+            .first()
+            .ok_or_else(|| corrupted("the tree doesn't contain any entry"))?
+            .compute_leaf(),
+        _ => match nodes[depth - 1].as_slice() {
+            [left, right, ..] => create_middle_node(left, right),
+            _ => return Err(corrupted("the root doesn't have two children").into()),
+        },
+    };
+    if root.hash != expected_root.hash || root.balances != expected_root.balances {
+        return Err(corrupted("the root doesn't match its children").into());
+    }
+
+    Ok((depth, entries, nodes))
+}
+
+/// 64 bits FNV-1a hash, used to detect the corruption of a tree stored in the binary format
+fn fnv1a_checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn write_bytes(bytes: &mut Vec<u8>, value: &[u8]) {
+    bytes.extend((value.len() as u32).to_le_bytes());
+    bytes.extend(value);
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], MerkleSumTreeError> {
+        if self.bytes.len() < n {
+            return Err(MerkleSumTreeError::CorruptedTree(
+                "unexpected end of file".to_string(),
+            ));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, MerkleSumTreeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, MerkleSumTreeError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8], MerkleSumTreeError> {
+        let len = self.read_u32()? as usize;
+        self.take(len)
+    }
+
+    fn read_fp(&mut self) -> Result<Fp, MerkleSumTreeError> {
+        Option::from(Fp::from_bytes(&self.take(32)?.try_into().unwrap()))
+            .ok_or_else(|| MerkleSumTreeError::CorruptedTree("invalid field element".to_string()))
+    }
+}
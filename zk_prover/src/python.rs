@@ -0,0 +1,174 @@
+//! Bindings of the prover and the verifier of the inclusion proofs for Python, built with [pyo3], so that data teams can generate and check the proofs of the CEX without writing FFI glue.
+//! The number of assets is fixed to [N_ASSETS], since const generics can't cross the Python boundary, while the depth of the tree is picked at runtime among the depths up to [MAX_LEVELS].
+//! Balances are exchanged as Python integers, proofs and field elements as `0x`-prefixed big endian hex strings, and the public inputs as JSON, see [PublicInputs::to_json].
+use crate::circuits::merkle_sum_tree::{MstInclusionCircuit, PublicInputs};
+use crate::circuits::secret::SecretBalances;
+use crate::circuits::utils::{
+    generate_setup_params, min_k, prove_user_inclusion, verify_inclusion,
+};
+use crate::merkle_sum_tree::{fp_to_big_int, Entry, MerkleSumTree, L_NODE, N_ASSETS};
+use halo2_proofs::halo2curves::bn256::{Bn256, Fr as Fp, G1Affine};
+use halo2_proofs::plonk::{keygen_pk, keygen_vk, ProvingKey};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use num_bigint::BigInt;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// The maximum depth of the trees supported by the bindings, namely 2^MAX_LEVELS entries at most
+const MAX_LEVELS: usize = 20;
+
+/// Calls the function `$f` with its `LEVELS` const generic set to `$depth`, so that the [MstInclusionCircuit] matching the depth of the tree is picked at runtime
+macro_rules! with_levels {
+    ($depth:expr, $f:ident($($arg:expr),*)) => {
+        match $depth {
+            0 => $f::<0>($($arg),*),
+            1 => $f::<1>($($arg),*),
+            2 => $f::<2>($($arg),*),
+            3 => $f::<3>($($arg),*),
+            4 => $f::<4>($($arg),*),
+            5 => $f::<5>($($arg),*),
+            6 => $f::<6>($($arg),*),
+            7 => $f::<7>($($arg),*),
+            8 => $f::<8>($($arg),*),
+            9 => $f::<9>($($arg),*),
+            10 => $f::<10>($($arg),*),
+            11 => $f::<11>($($arg),*),
+            12 => $f::<12>($($arg),*),
+            13 => $f::<13>($($arg),*),
+            14 => $f::<14>($($arg),*),
+            15 => $f::<15>($($arg),*),
+            16 => $f::<16>($($arg),*),
+            17 => $f::<17>($($arg),*),
+            18 => $f::<18>($($arg),*),
+            19 => $f::<19>($($arg),*),
+            20 => $f::<20>($($arg),*),
+            depth => Err(value_error(format!(
+                "The tree depth {} is not supported, the bindings support trees of depth {} at most",
+                depth, MAX_LEVELS
+            ))),
+        }
+    };
+}
+
+/// Merkle sum tree of [N_ASSETS] assets handed over to Python, together with the public setup and the proving key of the [MstInclusionCircuit] of its depth
+#[pyclass(name = "MerkleSumTree")]
+pub struct PyMerkleSumTree {
+    tree: MerkleSumTree<N_ASSETS>,
+    params: ParamsKZG<Bn256>,
+    pk: ProvingKey<G1Affine>,
+}
+
+#[pymethods]
+impl PyMerkleSumTree {
+    /// Returns the root hash of the tree as a hex string
+    fn root_hash(&self) -> String {
+        fp_to_hex(&self.tree.root().hash)
+    }
+
+    /// Returns the number of entries of the tree
+    fn leaf_count(&self) -> usize {
+        self.tree.leaf_count()
+    }
+
+    /// Proves the inclusion of the user with the given `username` and `balances` in the tree, see [prove_user_inclusion].
+    /// Returns the proof as a hex string together with its public inputs as JSON, to be passed to [PyMerkleSumTree::verify]
+    fn prove(&self, username: &str, balances: Vec<BigInt>) -> PyResult<(String, String)> {
+        let balances = SecretBalances::from_big_ints(&to_balances(balances)?);
+
+        let (proof, public_inputs) = with_levels!(
+            *self.tree.depth(),
+            prove_with_levels(self, username, balances)
+        )?;
+
+        Ok((
+            format!("0x{}", hex::encode(proof)),
+            public_inputs.to_json().map_err(value_error)?,
+        ))
+    }
+
+    /// Verifies a proof given as a hex string against its public inputs given as JSON, as returned by [PyMerkleSumTree::prove].
+    /// Returns false if the proof doesn't verify or if its public inputs commit to another root than the root of the tree
+    fn verify(&self, proof: &str, public_inputs: &str) -> PyResult<bool> {
+        let proof = hex::decode(
+            proof
+                .strip_prefix("0x")
+                .ok_or_else(|| value_error("The proof must start with 0x"))?,
+        )
+        .map_err(value_error)?;
+        let public_inputs =
+            PublicInputs::<N_ASSETS>::from_json(public_inputs).map_err(value_error)?;
+
+        Ok(public_inputs.root_hash() == self.tree.root().hash
+            && verify_inclusion(&self.params, self.pk.get_vk(), &proof, &public_inputs))
+    }
+}
+
+/// Builds a tree from a list of entries, each a tuple of a username and [N_ASSETS] non-negative balances, for example `[("alice", [100, 20])]`.
+/// The public setup and the proving key are generated for the depth of the tree, so that the returned tree can prove the inclusion of its users right away
+#[pyfunction]
+fn build_tree(entries: Vec<(String, Vec<BigInt>)>) -> PyResult<PyMerkleSumTree> {
+    let entries = entries
+        .into_iter()
+        .map(|(username, balances)| {
+            Entry::new(username, to_balances(balances)?).map_err(value_error)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+
+    let tree = MerkleSumTree::from_entries(entries).map_err(value_error)?;
+    let (params, pk) = with_levels!(*tree.depth(), setup())?;
+
+    Ok(PyMerkleSumTree { tree, params, pk })
+}
+
+#[pymodule]
+fn summa_solvency(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PyMerkleSumTree>()?;
+    module.add_function(wrap_pyfunction!(build_tree, module)?)?;
+
+    Ok(())
+}
+
+/// Generates the public setup and the proving key of the [MstInclusionCircuit] of depth `LEVELS`, with the minimum number of rows that fits the circuit
+fn setup<const LEVELS: usize>() -> PyResult<(ParamsKZG<Bn256>, ProvingKey<G1Affine>)> {
+    let empty_circuit = MstInclusionCircuit::<LEVELS, L_NODE, N_ASSETS>::init_empty();
+
+    let k = min_k(&empty_circuit).map_err(|error| value_error(format!("{:?}", error)))?;
+    let params = generate_setup_params(k);
+
+    let vk =
+        keygen_vk(&params, &empty_circuit).map_err(|error| value_error(format!("{:?}", error)))?;
+    let pk = keygen_pk(&params, vk, &empty_circuit)
+        .map_err(|error| value_error(format!("{:?}", error)))?;
+
+    Ok((params, pk))
+}
+
+fn prove_with_levels<const LEVELS: usize>(
+    tree: &PyMerkleSumTree,
+    username: &str,
+    balances: SecretBalances<N_ASSETS>,
+) -> PyResult<(Vec<u8>, PublicInputs<N_ASSETS>)> {
+    prove_user_inclusion::<LEVELS, L_NODE, N_ASSETS>(
+        &tree.params,
+        &tree.pk,
+        &tree.tree,
+        username,
+        balances,
+    )
+    .map_err(value_error)
+}
+
+fn to_balances(balances: Vec<BigInt>) -> PyResult<[BigInt; N_ASSETS]> {
+    balances
+        .try_into()
+        .map_err(|_| value_error("The number of balances doesn't match the number of assets"))
+}
+
+/// Encodes a field element as a `0x`-prefixed big endian hex string
+fn fp_to_hex(value: &Fp) -> String {
+    format!("{:#066x}", fp_to_big_int(value))
+}
+
+fn value_error(error: impl ToString) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
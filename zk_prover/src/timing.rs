@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+/// Label of the assignment of a level of the merkle path, including its Poseidon hash and its range checks
+pub const MERKLE_LEVEL: &str = "merkle sum tree level";
+/// Label of the assignment of a Poseidon hash
+pub const POSEIDON_HASH: &str = "poseidon hash";
+/// Label of the assignment of a less than comparison against a public input
+pub const ENFORCE_LESS_THAN: &str = "enforce less than";
+
+/// Wall-clock time spent in a labelled section of the witness assignment
+///
+/// # Fields
+///
+/// * `label`: The label of the section, for example [POSEIDON_HASH]
+/// * `total`: The total time spent in the section
+/// * `count`: The number of times the section was entered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionTiming {
+    pub label: &'static str,
+    pub total: Duration,
+    pub count: usize,
+}
+
+#[cfg(feature = "timing")]
+static TIMINGS: std::sync::Mutex<Vec<SectionTiming>> = std::sync::Mutex::new(Vec::new());
+
+/// Measures the wall-clock time from its creation, see [start], to its drop.
+/// Without the `timing` feature it holds nothing and does nothing, so the instrumentation has no overhead.
+pub struct SectionTimer {
+    #[cfg(feature = "timing")]
+    label: &'static str,
+    #[cfg(feature = "timing")]
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "timing")]
+impl Drop for SectionTimer {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let mut timings = TIMINGS.lock().unwrap();
+
+        match timings.iter_mut().find(|timing| timing.label == self.label) {
+            Some(timing) => {
+                timing.total += elapsed;
+                timing.count += 1;
+            }
+            None => timings.push(SectionTiming {
+                label: self.label,
+                total: elapsed,
+                count: 1,
+            }),
+        }
+    }
+}
+
+/// Starts timing the section `label` until the returned timer is dropped. Sections can be nested, for example a [POSEIDON_HASH] inside a [MERKLE_LEVEL], in which case the inner time is counted in both.
+#[inline(always)]
+pub fn start(label: &'static str) -> SectionTimer {
+    #[cfg(feature = "timing")]
+    {
+        SectionTimer {
+            label,
+            start: std::time::Instant::now(),
+        }
+    }
+    #[cfg(not(feature = "timing"))]
+    {
+        let _ = label;
+        SectionTimer {}
+    }
+}
+
+/// Returns the timings recorded so far, in the order in which the sections were first entered.
+/// The witness is assigned every time a circuit is synthesized, namely by the key generation as well as by the prover, and all of them are recorded. Always empty without the `timing` feature
+pub fn summary() -> Vec<SectionTiming> {
+    #[cfg(feature = "timing")]
+    {
+        TIMINGS.lock().unwrap().clone()
+    }
+    #[cfg(not(feature = "timing"))]
+    {
+        vec![]
+    }
+}
+
+/// Clears the timings recorded so far, for example between the key generation and the prover
+pub fn reset() {
+    #[cfg(feature = "timing")]
+    TIMINGS.lock().unwrap().clear();
+}
+
+/// Prints the timings recorded so far, one section per line
+pub fn print_summary() {
+    for timing in summary() {
+        println!(
+            "{}: {:?} in {} calls",
+            timing.label, timing.total, timing.count
+        );
+    }
+}
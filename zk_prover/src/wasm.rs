@@ -0,0 +1,58 @@
+//! Bindings of the native merkle sum tree for the browser, built with [wasm_bindgen], so that users can rebuild the tree and check their inclusion independently of the CEX.
+//! The number of assets is fixed to [N_ASSETS], since const generics can't cross the JavaScript boundary. Entries and proofs are exchanged as JSON strings with balances and field elements as decimal strings.
+use crate::merkle_sum_tree::{
+    fp_to_big_int, verify_proof, MerkleProof, MerkleSumTree, SerializedEntry, N_ASSETS,
+};
+use wasm_bindgen::prelude::*;
+
+/// Merkle sum tree of [N_ASSETS] assets handed over to JavaScript
+#[wasm_bindgen]
+pub struct WasmMerkleSumTree {
+    tree: MerkleSumTree<N_ASSETS>,
+}
+
+#[wasm_bindgen]
+impl WasmMerkleSumTree {
+    /// Returns the root hash of the tree as a decimal string
+    pub fn root_hash(&self) -> String {
+        fp_to_big_int(&self.tree.root().hash).to_string()
+    }
+
+    /// Returns the number of entries of the tree
+    pub fn leaf_count(&self) -> usize {
+        self.tree.leaf_count()
+    }
+}
+
+/// Builds a tree from a JSON array of entries, each with a username and [N_ASSETS] balances, for example `[{"username": "alice", "balances": ["100", "20"]}]`
+#[wasm_bindgen]
+pub fn build_tree(entries_json: &str) -> Result<WasmMerkleSumTree, JsError> {
+    let entries = serde_json::from_str::<Vec<SerializedEntry>>(entries_json)?
+        .iter()
+        .map(SerializedEntry::to_entry)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| JsError::new(&error.to_string()))?;
+
+    let tree =
+        MerkleSumTree::from_entries(entries).map_err(|error| JsError::new(&error.to_string()))?;
+
+    Ok(WasmMerkleSumTree { tree })
+}
+
+/// Returns the inclusion proof of the entry at `index` of `tree` as JSON, see [MerkleProof::to_json]
+#[wasm_bindgen]
+pub fn get_proof(tree: &WasmMerkleSumTree, index: usize) -> Result<String, JsError> {
+    let proof = tree.tree.generate_proof(index).map_err(JsError::new)?;
+
+    Ok(proof.to_json()?)
+}
+
+/// Verifies an inclusion proof given as JSON, as returned by [get_proof], against `root_hash`, namely the root hash published by the CEX as a decimal string.
+/// Returns false if the proof doesn't verify or if it was generated for another root
+#[wasm_bindgen]
+pub fn verify(proof_json: &str, root_hash: &str) -> Result<bool, JsError> {
+    let proof = MerkleProof::<N_ASSETS>::from_json(proof_json)
+        .map_err(|error| JsError::new(&error.to_string()))?;
+
+    Ok(fp_to_big_int(&proof.root_hash).to_string() == root_hash && verify_proof(&proof))
+}